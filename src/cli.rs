@@ -1,9 +1,15 @@
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 
-use clap::{Args, Parser, Subcommand};
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
 
-use crate::tasks::TaskState;
-use crate::timefmt::TimeFormat;
+use crate::tasks::hooks::parse_hook;
+use crate::tasks::transcript::LogCategory;
+use crate::tasks::{HookEvent, SearchField, TaskState, WaitTarget, WebhookEvent, parse_env_var};
+use crate::timefmt::{TimeFormat, parse_duration, parse_time_bound, parse_timestamp};
 
 /// Top-level CLI definition for the `codex-tasks` binary.
 #[derive(Debug, Parser)]
@@ -16,6 +22,10 @@ use crate::timefmt::TimeFormat;
     arg_required_else_help = true
 )]
 pub struct Cli {
+    /// Read codex-tasks settings from this file instead of `~/.codex/tasks/config.toml`
+    /// (also `CODEX_TASKS_CONFIG`).
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
     #[command(subcommand)]
     pub command: Command,
 }
@@ -27,18 +37,58 @@ pub enum Command {
     Start(StartArgs),
     /// Send a prompt to an existing task.
     Send(SendArgs),
+    /// Re-run the last prompt (or a new one) on a task that DIED.
+    Retry(RetryArgs),
+    /// Fork a task into a new, independent thread.
+    Fork(ForkArgs),
     /// Inspect metadata and status for a task.
     Status(StatusArgs),
     /// Stream the transcript log for a task.
     Log(LogArgs),
+    /// Follow a task interactively and send prompts whenever it is idle.
+    Attach(AttachArgs),
+    /// Show the workspace changes made by a task.
+    Diff(DiffArgs),
+    /// Print the final answer of a task.
+    Result(ResultArgs),
+    /// Block until tasks finish, exiting with a code that reflects how they ended.
+    Wait(WaitArgs),
     /// Gracefully stop a running task.
     Stop(StopArgs),
     /// List known tasks, optionally filtered by state.
     Ls(LsArgs),
+    /// Show queued tasks and configure how many tasks may run at once.
+    Queue(QueueArgs),
+    /// Show and configure the lifecycle hooks run for every task.
+    Hooks(HooksArgs),
+    /// Manage URLs notified of task events.
+    Webhook(WebhookArgs),
+    /// Run task pipelines whose steps wait for each other.
+    Pipeline(PipelineArgs),
+    /// Manage recurring prompts.
+    Schedule(ScheduleArgs),
+    /// Run recurring prompts in the foreground.
+    Scheduler(SchedulerArgs),
+    /// Search task transcripts for a pattern.
+    Search(SearchArgs),
+    /// Live dashboard of every task with keyboard actions.
+    #[command(visible_alias = "top")]
+    Watch(WatchArgs),
     /// Archive a completed task.
     Archive(ArchiveArgs),
+    /// Upgrade every stored task to the current on-disk format.
+    Migrate,
+    /// Detect and repair inconsistencies in the task store.
+    Doctor(DoctorArgs),
     /// Run the MCP server over stdio.
     Mcp(McpArgs),
+    /// Inspect the codex-tasks configuration file.
+    Config(ConfigArgs),
+    /// Print a shell completion script.
+    Completions(CompletionsArgs),
+    /// Internal entry-point used by completion scripts to list matching task ids.
+    #[command(name = "complete-task-ids", hide = true)]
+    CompleteTaskIds(CompleteTaskIdsArgs),
     /// Internal entry-point used to run a worker process.
     #[command(hide = true)]
     Worker(WorkerArgs),
@@ -62,8 +112,73 @@ pub struct StartArgs {
     /// Git branch, tag, or commit to check out after cloning the repository.
     #[arg(long = "repo-ref", value_name = "REF")]
     pub repo_ref: Option<String>,
+    /// Parent task to link the new task to (defaults to the task running this command, if any).
+    #[arg(long = "parent", value_name = "TASK_ID")]
+    pub parent: Option<String>,
+    /// Automatically re-run an invocation up to N times when it fails transiently.
+    #[arg(long, value_name = "N")]
+    pub retries: Option<u32>,
+    /// Delay before the first automatic retry, doubling for each further attempt.
+    #[arg(
+        long = "retry-backoff",
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        requires = "retries",
+        default_value = "30s"
+    )]
+    pub retry_backoff: Duration,
+    /// Label to record on the task; repeat to add several.
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// Queue priority used when the concurrency limit is reached; higher starts first.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        allow_negative_numbers = true
+    )]
+    pub priority: i32,
+    /// Run a shell command when the task reaches a lifecycle event (`EVENT=COMMAND`, with
+    /// EVENT one of on_start, on_stopped, on_died, on_archived, on_turn_completed); repeat for
+    /// several events.
+    #[arg(long = "hook", value_name = "EVENT=COMMAND", value_parser = parse_hook)]
+    pub hooks: Vec<(HookEvent, String)>,
+    /// How long each of the task's hooks may run before it is killed.
+    #[arg(
+        long = "hook-timeout",
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        requires = "hooks"
+    )]
+    pub hook_timeout: Option<Duration>,
+    /// Apply a preset from the codex-tasks config; other flags override its values.
+    #[arg(long, value_name = "NAME")]
+    pub preset: Option<String>,
+    /// Set an environment variable for every `codex exec` run of the task; repeat for several.
+    #[arg(long = "env", value_name = "NAME=VALUE", value_parser = parse_env_var)]
+    pub env: Vec<(String, String)>,
+    /// Kill an invocation that runs longer than this; the task then DIES.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+    /// Start every task described by a TOML manifest instead of a single prompt.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = [
+            "prompt", "title", "config_file", "working_dir", "repo", "repo_ref", "parent",
+            "retries", "tags", "priority", "hooks", "preset", "env", "timeout",
+        ]
+    )]
+    pub manifest: Option<PathBuf>,
+    /// Check the manifest and print the tasks it describes without starting them.
+    #[arg(long, requires = "manifest")]
+    pub validate: bool,
+    /// Print the manifest entries and their task ids as JSON.
+    #[arg(long, requires = "manifest")]
+    pub json: bool,
     /// Initial prompt to send immediately after the worker launches.
-    pub prompt: String,
+    #[arg(required_unless_present = "manifest")]
+    pub prompt: Option<String>,
 }
 
 /// Arguments for the `send` subcommand.
@@ -73,6 +188,35 @@ pub struct SendArgs {
     pub task_id: String,
     /// Prompt that will be forwarded to the task worker.
     pub prompt: String,
+    /// Queue priority used when the concurrency limit is reached; higher starts first.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        allow_negative_numbers = true
+    )]
+    pub priority: i32,
+}
+
+/// Arguments for the `retry` subcommand.
+#[derive(Debug, Args)]
+pub struct RetryArgs {
+    /// Identifier of the DIED task to retry.
+    pub task_id: String,
+    /// Prompt to submit instead of the task's last prompt.
+    pub prompt: Option<String>,
+}
+
+/// Arguments for the `fork` subcommand.
+#[derive(Debug, Args)]
+pub struct ForkArgs {
+    /// Optional title for the forked task (defaults to the source task's title).
+    #[arg(short = 't', long)]
+    pub title: Option<String>,
+    /// Identifier of the task to fork.
+    pub task_id: String,
+    /// Prompt to send to the forked task right away.
+    pub prompt: Option<String>,
 }
 
 /// Arguments for the `status` subcommand.
@@ -115,22 +259,200 @@ pub struct LogArgs {
     /// Continue following even after the worker stops running.
     #[arg(short = 'F', long = "forever")]
     pub forever: bool,
-    /// Only print the last N lines before optionally following.
+    /// Only print the last N events before optionally following.
     #[arg(short = 'n', long)]
     pub lines: Option<usize>,
     /// Emit raw JSONL events instead of human-readable output.
     #[arg(long = "json")]
     pub json: bool,
+    /// Only show events logged at or after this time (e.g. `10m` ago, or a timestamp).
+    #[arg(long, value_name = "TIME", value_parser = parse_time_bound, conflicts_with = "export")]
+    pub since: Option<DateTime<Utc>>,
+    /// Only show events logged at or before this time (e.g. `10m` ago, or a timestamp).
+    #[arg(long, value_name = "TIME", value_parser = parse_time_bound, conflicts_with = "export")]
+    pub until: Option<DateTime<Utc>>,
+    /// Only show events of this invocation (1 for the initial prompt, or `last`).
+    #[arg(long, value_name = "N|last", conflicts_with = "export")]
+    pub invocation: Option<InvocationSelector>,
+    /// Only show these event categories (comma-separated).
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "CATEGORIES")]
+    pub only: Vec<LogCategory>,
+    /// Hide these event categories (comma-separated).
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "CATEGORIES")]
+    pub exclude: Vec<LogCategory>,
+    /// Hide reasoning; shorthand for `--exclude reasoning`.
+    #[arg(long)]
+    pub no_reasoning: bool,
+    /// Prefix each event with the time it was logged.
+    #[arg(long, conflicts_with = "json")]
+    pub timestamps: bool,
+    /// Control how `--timestamps` are rendered.
+    #[arg(long = "time-format", value_enum, default_value_t = TimeFormat::Human)]
+    pub time_format: TimeFormat,
+    /// Never use colors (by default they are used only when writing to a terminal).
+    #[arg(long, conflicts_with = "json")]
+    pub no_color: bool,
+    /// Show at most N lines of output per command.
+    #[arg(long, value_name = "N", conflicts_with = "json")]
+    pub max_output_lines: Option<usize>,
+    /// Show events that have no human-readable rendering as raw JSON.
+    #[arg(long, conflicts_with = "json")]
+    pub verbose: bool,
+    /// Write the whole transcript as a Markdown or HTML report instead of printing the log.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        conflicts_with_all = ["follow", "forever", "json", "lines"]
+    )]
+    pub export: Option<ExportFormat>,
+    /// File to write the `--export` report to instead of stdout.
+    #[arg(short = 'o', long, value_name = "FILE", requires = "export")]
+    pub output: Option<PathBuf>,
     /// Identifier of the task whose log should be streamed.
     pub task_id: String,
 }
 
+/// Invocation chosen with `log --invocation`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InvocationSelector {
+    /// A 1-based invocation number.
+    Number(usize),
+    /// The most recent invocation.
+    Last,
+}
+
+impl FromStr for InvocationSelector {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.eq_ignore_ascii_case("last") {
+            return Ok(Self::Last);
+        }
+        match value.parse::<usize>() {
+            Ok(number) if number >= 1 => Ok(Self::Number(number)),
+            _ => Err(format!(
+                "invalid invocation '{value}' (expected a number from 1 or 'last')"
+            )),
+        }
+    }
+}
+
+/// Document format written by `log --export` and the MCP `task_export` tool.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[value(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Markdown, with reasoning in collapsible `<details>` blocks.
+    #[default]
+    Md,
+    /// A standalone HTML page with inline styles.
+    Html,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Md => "md",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+/// Arguments for the `attach` subcommand.
+#[derive(Debug, Args)]
+pub struct AttachArgs {
+    /// Number of transcript events to replay before attaching.
+    #[arg(short = 'n', long = "lines", value_name = "N")]
+    pub lines: Option<usize>,
+    /// Identifier of the task to attach to.
+    pub task_id: String,
+}
+
+/// Arguments for the `diff` subcommand.
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// Only show changes made during this invocation (1 for the initial prompt).
+    #[arg(long, value_name = "N")]
+    pub invocation: Option<usize>,
+    /// Identifier of the task whose changes should be shown.
+    pub task_id: String,
+}
+
+/// Arguments for the `result` subcommand.
+#[derive(Debug, Args)]
+pub struct ResultArgs {
+    /// Print the answer of this invocation (1 for the initial prompt) instead of the last result.
+    #[arg(long, value_name = "N")]
+    pub invocation: Option<usize>,
+    /// Print only the fenced code blocks of the answer.
+    #[arg(long = "code-blocks", conflicts_with = "json")]
+    pub code_blocks: bool,
+    /// Keep only code blocks tagged with this language.
+    #[arg(long, requires = "code_blocks", value_name = "LANG")]
+    pub lang: Option<String>,
+    /// Parse the answer as a JSON structured-output payload and print it.
+    #[arg(long)]
+    pub json: bool,
+    /// Wait for the task to stop running before printing the result.
+    #[arg(long)]
+    pub wait: bool,
+    /// Give up waiting after this long (seconds, or a number suffixed with s, m, h or d).
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, requires = "wait")]
+    pub timeout: Option<Duration>,
+    /// Identifier of the task whose result should be printed.
+    pub task_id: String,
+}
+
+/// Arguments for the `wait` subcommand.
+#[derive(Debug, Args)]
+pub struct WaitArgs {
+    /// State to wait for: STOPPED, DIED or any-terminal.
+    #[arg(long, value_enum, ignore_case = true, default_value_t = WaitTarget::AnyTerminal)]
+    pub until: WaitTarget,
+    /// Give up after this long (seconds, or a number suffixed with s, m, h or d).
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+    /// Fail when a task dies, even when waiting for any terminal state.
+    #[arg(long = "fail-on-died")]
+    pub fail_on_died: bool,
+    /// Return as soon as one task reaches the target state.
+    #[arg(long)]
+    pub any: bool,
+    /// Emit machine-readable JSON output.
+    #[arg(long)]
+    pub json: bool,
+    /// Wait for every currently running or queued task.
+    #[arg(short = 'A', long = "all-running", conflicts_with = "states")]
+    pub all_running: bool,
+    /// Wait for every active task in one of these states.
+    #[arg(long = "state", value_enum, value_delimiter = ',')]
+    pub states: Vec<TaskState>,
+    /// Wait for active tasks carrying this tag (repeatable; tasks must carry every tag).
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// Wait for active tasks whose title contains this text, ignoring case.
+    #[arg(long, value_name = "TEXT")]
+    pub title: Option<String>,
+    /// Identifier(s) of the task(s) to wait for.
+    #[arg(
+        value_name = "TASK_ID",
+        num_args = 1..,
+        required_unless_present_any = ["all_running", "states", "tags", "title"],
+        conflicts_with_all = ["all_running", "states", "tags", "title"]
+    )]
+    pub task_ids: Vec<String>,
+}
+
 /// Arguments for the `stop` subcommand.
 #[derive(Debug, Args)]
 pub struct StopArgs {
     /// Stop every running task instead of targeting a specific identifier.
     #[arg(short = 'a', long = "all")]
     pub all: bool,
+    /// Also stop every descendant of the task.
+    #[arg(short = 'r', long = "recursive", conflicts_with = "all")]
+    pub recursive: bool,
     /// Identifier of the task that should be stopped.
     #[arg(
         conflicts_with = "all",
@@ -140,18 +462,327 @@ pub struct StopArgs {
     pub task_id: Option<String>,
 }
 
+/// Arguments for the `queue` subcommand.
+#[derive(Debug, Args)]
+pub struct QueueArgs {
+    /// Limit how many tasks may run at once; further starts and prompts wait as QUEUED.
+    #[arg(long = "max-running", value_name = "N", conflicts_with = "unlimited")]
+    pub max_running: Option<usize>,
+    /// Remove the concurrency limit and start every queued task.
+    #[arg(long)]
+    pub unlimited: bool,
+    /// Emit the queue as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the `hooks` subcommand.
+#[derive(Debug, Args)]
+pub struct HooksArgs {
+    /// Run COMMAND for every task on EVENT; repeat to set several events.
+    #[arg(long, value_name = "EVENT=COMMAND", value_parser = parse_hook)]
+    pub set: Vec<(HookEvent, String)>,
+    /// Remove the global hook for EVENT; repeat to remove several.
+    #[arg(long, value_name = "EVENT")]
+    pub unset: Vec<HookEvent>,
+    /// How long each global hook may run before it is killed.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+    /// Emit the global hooks as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the `webhook` subcommand.
+#[derive(Debug, Args)]
+pub struct WebhookArgs {
+    #[command(subcommand)]
+    pub command: WebhookCommand,
+}
+
+/// Actions available under `webhook`.
+#[derive(Debug, Subcommand)]
+pub enum WebhookCommand {
+    /// Notify a URL of task events.
+    Add(WebhookAddArgs),
+    /// List configured webhooks.
+    Ls(WebhookLsArgs),
+    /// Remove a webhook.
+    Rm(WebhookRmArgs),
+    /// Show recent deliveries.
+    Log(WebhookLogArgs),
+}
+
+/// Arguments for `webhook add`.
+#[derive(Debug, Args)]
+pub struct WebhookAddArgs {
+    /// URL that receives a JSON POST for every event.
+    pub url: String,
+    /// Only deliver this event; repeat for several (default: every event).
+    #[arg(long = "event", value_enum, value_name = "EVENT")]
+    pub events: Vec<WebhookEvent>,
+    /// Sign payloads with HMAC-SHA256 using this key.
+    #[arg(long, value_name = "KEY")]
+    pub secret: Option<String>,
+    /// Further attempts after a failed delivery.
+    #[arg(long, value_name = "N")]
+    pub retries: Option<u32>,
+    /// Delay before the first retry, doubling for each further attempt.
+    #[arg(long = "retry-backoff", value_name = "DURATION", value_parser = parse_duration)]
+    pub retry_backoff: Option<Duration>,
+}
+
+/// Arguments for `webhook ls`.
+#[derive(Debug, Args)]
+pub struct WebhookLsArgs {
+    /// Emit webhooks as JSON (secrets are masked).
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for `webhook rm`.
+#[derive(Debug, Args)]
+pub struct WebhookRmArgs {
+    /// Identifier (or unique prefix) or URL of the webhook to remove.
+    pub id: String,
+}
+
+/// Arguments for `webhook log`.
+#[derive(Debug, Args)]
+pub struct WebhookLogArgs {
+    /// Number of most recent deliveries to show.
+    #[arg(short = 'n', long, value_name = "N", default_value_t = 20)]
+    pub limit: usize,
+    /// Emit deliveries as JSON.
+    #[arg(long)]
+    pub json: bool,
+    /// Control how timestamps are rendered.
+    #[arg(long = "time-format", value_enum, default_value_t = TimeFormat::Human)]
+    pub time_format: TimeFormat,
+}
+
+/// Arguments for the `pipeline` subcommand.
+#[derive(Debug, Args)]
+pub struct PipelineArgs {
+    #[command(subcommand)]
+    pub command: PipelineCommand,
+}
+
+/// Actions available under `pipeline`.
+#[derive(Debug, Subcommand)]
+pub enum PipelineCommand {
+    /// Start each step of a pipeline once the steps it runs `after` have STOPPED.
+    Run(PipelineRunArgs),
+}
+
+/// Arguments for `pipeline run`.
+#[derive(Debug, Args)]
+pub struct PipelineRunArgs {
+    /// TOML pipeline definition (a manifest whose entries may declare `after`).
+    #[arg(value_name = "FILE")]
+    pub file: PathBuf,
+    /// Check the pipeline and print its steps without starting anything.
+    #[arg(long, conflicts_with = "json")]
+    pub validate: bool,
+    /// Print the final state of every step as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the `schedule` subcommand.
+#[derive(Debug, Args)]
+pub struct ScheduleArgs {
+    #[command(subcommand)]
+    pub command: ScheduleCommand,
+}
+
+/// Actions available under `schedule`.
+#[derive(Debug, Subcommand)]
+pub enum ScheduleCommand {
+    /// Add a recurring prompt for an existing task or for fresh tasks.
+    Add(ScheduleAddArgs),
+    /// List schedules with their next and last runs.
+    Ls(ScheduleLsArgs),
+    /// Remove a schedule.
+    Rm(ScheduleRmArgs),
+}
+
+/// Arguments for `schedule add`.
+#[derive(Debug, Args)]
+pub struct ScheduleAddArgs {
+    /// Five-field cron expression in local time, e.g. "0 2 * * *" for 02:00 every night.
+    #[arg(long, value_name = "EXPR")]
+    pub cron: String,
+    /// Send the prompt to this task at every run (runs are skipped while it is busy).
+    #[arg(long = "task", value_name = "TASK_ID", required_unless_present = "new")]
+    pub task_id: Option<String>,
+    /// Start a fresh task with the prompt at every run.
+    #[arg(long, conflicts_with = "task_id")]
+    pub new: bool,
+    /// Title given to tasks started by the schedule.
+    #[arg(short = 't', long, requires = "new")]
+    pub title: Option<String>,
+    /// Working directory for tasks started by the schedule (defaults to the current one).
+    #[arg(long = "working-dir", value_name = "DIR", requires = "new")]
+    pub working_dir: Option<PathBuf>,
+    /// Label recorded on tasks started by the schedule; repeat to add several.
+    #[arg(long = "tag", value_name = "TAG", requires = "new")]
+    pub tags: Vec<String>,
+    /// Prompt to submit at every run.
+    pub prompt: String,
+}
+
+/// Arguments for `schedule ls`.
+#[derive(Debug, Args)]
+pub struct ScheduleLsArgs {
+    /// Emit schedules, including their run history, as JSON.
+    #[arg(long)]
+    pub json: bool,
+    /// Control how timestamps are rendered.
+    #[arg(long = "time-format", value_enum, default_value_t = TimeFormat::Human)]
+    pub time_format: TimeFormat,
+}
+
+/// Arguments for `schedule rm`.
+#[derive(Debug, Args)]
+pub struct ScheduleRmArgs {
+    /// Identifier (or unique prefix) of the schedule to remove.
+    pub id: String,
+}
+
+/// Arguments for the `scheduler` subcommand.
+#[derive(Debug, Args)]
+pub struct SchedulerArgs {
+    #[command(subcommand)]
+    pub command: SchedulerCommand,
+}
+
+/// Actions available under `scheduler`.
+#[derive(Debug, Subcommand)]
+pub enum SchedulerCommand {
+    /// Fire schedules as they come due until interrupted.
+    Run(SchedulerRunArgs),
+}
+
+/// Arguments for `scheduler run`.
+#[derive(Debug, Args)]
+pub struct SchedulerRunArgs {
+    /// Fire the schedules that are currently due, then exit.
+    #[arg(long)]
+    pub once: bool,
+}
+
 /// Arguments for the `ls` subcommand.
 #[derive(Debug, Args)]
 pub struct LsArgs {
     /// Restrict results to tasks that match the provided states.
     #[arg(long = "state", value_enum, value_delimiter = ',', num_args = 0..)]
     pub states: Vec<TaskState>,
+    /// Restrict results to tasks carrying this tag (repeatable; tasks must carry every tag).
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// Restrict results to tasks whose title contains this text, ignoring case.
+    #[arg(long, value_name = "TEXT")]
+    pub title: Option<String>,
     /// Include archived tasks in the listing.
     #[arg(short = 'a', long = "all")]
     pub include_archived: bool,
     /// Control how timestamps are rendered in the listing.
     #[arg(long = "time-format", value_enum, default_value_t = TimeFormat::Human)]
     pub time_format: TimeFormat,
+    /// Render tasks as a parent/child hierarchy.
+    #[arg(long)]
+    pub tree: bool,
+    /// Output format for the listing.
+    #[arg(long, value_enum, default_value_t = LsFormat::Table)]
+    pub format: LsFormat,
+    /// Comma-separated columns to include (defaults to id,title,state,created,updated,working_dir).
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "COLUMNS")]
+    pub columns: Vec<LsColumn>,
+    /// Sort tasks by the given column instead of most recently updated first.
+    #[arg(long, value_enum, value_name = "COLUMN")]
+    pub sort: Option<LsColumn>,
+    /// Reverse the `--sort` order.
+    #[arg(long, requires = "sort")]
+    pub desc: bool,
+    /// Show at most this many tasks.
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+}
+
+/// Output format supported by the `ls` command.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+#[value(rename_all = "lowercase")]
+pub enum LsFormat {
+    Table,
+    Json,
+    Jsonl,
+    Csv,
+}
+
+/// Column that can be selected, and sorted by, in `ls` output.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum LsColumn {
+    Id,
+    Title,
+    State,
+    Created,
+    Updated,
+    Duration,
+    WorkingDir,
+    LastPrompt,
+    Tokens,
+}
+
+/// Arguments for the `search` subcommand.
+#[derive(Debug, Args)]
+pub struct SearchArgs {
+    /// Regular expression to search for.
+    pub pattern: String,
+    /// Treat the pattern as a literal string instead of a regular expression.
+    #[arg(short = 'F', long = "fixed-strings")]
+    pub fixed_strings: bool,
+    /// Match case-insensitively.
+    #[arg(short = 'i', long = "ignore-case")]
+    pub ignore_case: bool,
+    /// Restrict matching to these parts of the transcript.
+    #[arg(long = "in", value_enum, value_delimiter = ',', value_name = "FIELDS")]
+    pub fields: Vec<SearchField>,
+    /// Only search tasks updated at or after this time (RFC 3339 or YYYY-MM-DD).
+    #[arg(long, value_parser = parse_timestamp, value_name = "TIME")]
+    pub since: Option<DateTime<Utc>>,
+    /// Only search tasks created at or before this time (RFC 3339 or YYYY-MM-DD).
+    #[arg(long, value_parser = parse_timestamp, value_name = "TIME")]
+    pub until: Option<DateTime<Utc>>,
+    /// Include archived tasks in the search.
+    #[arg(short = 'a', long = "all")]
+    pub include_archived: bool,
+    /// Stop after this many matches.
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+    /// Emit matches as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the `watch` subcommand.
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    /// Include archived tasks in the dashboard.
+    #[arg(short = 'a', long = "all")]
+    pub include_archived: bool,
+    /// Refresh interval in milliseconds.
+    #[arg(
+        short = 'n',
+        long = "interval",
+        value_name = "MS",
+        default_value_t = 1000
+    )]
+    pub interval_ms: u64,
+    /// Print a single snapshot of the table and exit.
+    #[arg(long)]
+    pub once: bool,
 }
 
 /// Arguments for the `archive` subcommand.
@@ -160,20 +791,78 @@ pub struct ArchiveArgs {
     /// Archive every STOPPED or DIED task.
     #[arg(short = 'a', long = "all", conflicts_with = "task_id")]
     pub all: bool,
+    /// Also archive every descendant of the task.
+    #[arg(short = 'r', long = "recursive", conflicts_with = "all")]
+    pub recursive: bool,
+    /// Archive STOPPED or DIED tasks idle for longer than `retention.archive_after`.
+    #[arg(long = "expired", conflicts_with_all = ["all", "recursive", "task_id"])]
+    pub expired: bool,
     /// Identifier of the task that should be archived.
-    #[arg(value_name = "TASK_ID", required_unless_present = "all")]
+    #[arg(value_name = "TASK_ID", required_unless_present_any = ["all", "expired"])]
     pub task_id: Option<String>,
 }
 
+/// Arguments for the `doctor` subcommand.
+#[derive(Debug, Args)]
+pub struct DoctorArgs {
+    /// Repair detected problems instead of only reporting them.
+    #[arg(long = "fix")]
+    pub fix: bool,
+}
+
+/// Arguments for the `config` subcommand.
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+/// Actions available under `config`.
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective configuration, defaults and store settings included.
+    Show(ConfigShowArgs),
+}
+
+/// Arguments for `config show`.
+#[derive(Debug, Args)]
+pub struct ConfigShowArgs {
+    /// Emit the configuration as JSON instead of TOML.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the `completions` subcommand.
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    /// Shell to generate the completion script for.
+    #[arg(value_enum)]
+    pub shell: CompletionShell,
+}
+
+/// Shells supported by the completions command.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+#[value(rename_all = "lowercase")]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Arguments for the hidden `complete-task-ids` subcommand.
+#[derive(Debug, Args)]
+pub struct CompleteTaskIdsArgs {
+    /// Command line being completed, ending with the (possibly empty) current word.
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, num_args = 0..)]
+    pub words: Vec<String>,
+}
+
 /// Arguments for the `mcp` subcommand.
 #[derive(Debug, Args)]
 pub struct McpArgs {
     /// Override the store root used for task metadata.
     #[arg(long = "store-root", value_name = "PATH")]
     pub store_root: Option<PathBuf>,
-    /// Path to a configuration file that augments the default settings.
-    #[arg(long = "config", value_name = "PATH")]
-    pub config: Option<PathBuf>,
     /// Allow operations that may modify or stop multiple tasks at once.
     #[arg(long = "allow-unsafe")]
     pub allow_unsafe: bool,
@@ -200,4 +889,46 @@ pub struct WorkerArgs {
     /// Optional working directory for launching `codex exec`.
     #[arg(long = "working-dir")]
     pub working_dir: Option<PathBuf>,
+    /// Task the new task is being forked from.
+    #[arg(long = "forked-from", requires_all = ["fork_invocation", "fork_time"])]
+    pub forked_from: Option<String>,
+    /// Number of prompts the source task had processed at the fork point.
+    #[arg(long = "fork-invocation", requires = "forked_from")]
+    pub fork_invocation: Option<usize>,
+    /// Timestamp (RFC 3339) of the source task at the fork point.
+    #[arg(long = "fork-time", requires = "forked_from")]
+    pub fork_time: Option<DateTime<Utc>>,
+    /// Parent task recorded on the new task.
+    #[arg(long = "parent-id")]
+    pub parent_id: Option<String>,
+    /// Automatic retries recorded on the new task.
+    #[arg(long)]
+    pub retries: Option<u32>,
+    /// Base delay in seconds between automatic retries.
+    #[arg(long = "retry-backoff-secs", requires = "retries", default_value_t = 0)]
+    pub retry_backoff_secs: u64,
+    /// Labels recorded on the new task.
+    #[arg(long = "tag")]
+    pub tags: Vec<String>,
+    /// Launch id to use instead of a fresh one (set when a queued task is admitted).
+    #[arg(long = "launch-id")]
+    pub launch_id: Option<String>,
+    /// Lifecycle hooks (`EVENT=COMMAND`) recorded on the new task.
+    #[arg(long = "hook", value_parser = parse_hook)]
+    pub hooks: Vec<(HookEvent, String)>,
+    /// Timeout in seconds recorded with the task's hooks.
+    #[arg(long = "hook-timeout-secs")]
+    pub hook_timeout_secs: Option<u64>,
+    /// Configuration preset recorded on the new task.
+    #[arg(long)]
+    pub preset: Option<String>,
+    /// Extra `codex exec` argument recorded on the new task.
+    #[arg(long = "exec-arg", allow_hyphen_values = true)]
+    pub exec_args: Vec<String>,
+    /// Environment variable (`NAME=VALUE`) recorded on the new task.
+    #[arg(long = "env", value_parser = parse_env_var)]
+    pub env: Vec<(String, String)>,
+    /// Invocation timeout in seconds recorded on the new task.
+    #[arg(long = "timeout-secs")]
+    pub timeout_secs: Option<u64>,
 }
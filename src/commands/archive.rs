@@ -1,13 +1,23 @@
 use anyhow::{Result, bail};
 
 use crate::cli::ArchiveArgs;
-use crate::tasks::{ArchiveAllSummary, ArchiveTaskOutcome, TaskService};
+use crate::tasks::{ArchiveAllSummary, ArchiveTaskOutcome, TaskService, TasksConfig};
 
-pub fn handle_archive(args: ArchiveArgs) -> Result<()> {
-    let service = TaskService::with_default_store(false)?;
+pub fn handle_archive(args: ArchiveArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
 
-    if args.all {
+    if args.expired {
+        if service.config().archive_after.is_none() {
+            bail!("no retention is configured; set `retention.archive_after` in the config file");
+        }
+        handle_archive_all(service.apply_retention()?)
+    } else if args.all {
         handle_archive_all(service.archive_all()?)
+    } else if args.recursive {
+        let task_id = args
+            .task_id
+            .expect("clap ensures task id is present when --all is absent");
+        handle_archive_all(service.archive_task_recursive(&task_id)?)
     } else {
         let task_id = args
             .task_id
@@ -0,0 +1,229 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+
+use crate::cli::AttachArgs;
+use crate::commands::diff::print_diff;
+use crate::commands::log::{
+    FollowContext, HumanRenderState, LogSlice, follow_log_human, print_initial_log_human,
+};
+use crate::tasks::{
+    LaunchOutcome, SendPromptParams, StopOutcome, TaskService, TaskState, TasksConfig,
+};
+
+/// How long to wait for a submitted prompt's worker to mark the task RUNNING.
+const SEND_START_TIMEOUT: Duration = Duration::from_secs(10);
+const SEND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Delimits a multi-line prompt when entered on a line of its own.
+const BLOCK_DELIMITER: &str = "\"\"\"";
+
+const HELP: &str = "\
+Type a prompt and press Enter to send it once the task is idle.
+End a line with \\ to continue on the next one, or wrap several lines in \"\"\".
+While the transcript streams, press Ctrl-C to return to the prompt.
+Press Enter on an empty line to resume streaming a running task.
+
+  /stop     stop the running invocation
+  /result   print the task's last result
+  /diff     print the workspace changes made by the task
+  /detach   leave the session (the task keeps its state); Ctrl-D also detaches
+  /help     show this help";
+
+/// Set by the SIGINT handler so Ctrl-C pauses streaming instead of exiting.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+enum Input {
+    Prompt(String),
+    Command(String),
+    Empty,
+    Detach,
+}
+
+pub fn handle_attach(args: AttachArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    let task_id = args.task_id;
+    let descriptor = service.prepare_log_descriptor(&task_id, true)?;
+    let file = File::open(&descriptor.path).with_context(|| {
+        format!(
+            "failed to open log for task {task_id} at {}",
+            descriptor.path.display()
+        )
+    })?;
+    let mut reader = BufReader::new(file);
+    let mut render_state = HumanRenderState::new();
+    let mut slice = LogSlice::default();
+    print_initial_log_human(&mut reader, args.lines, &mut render_state, &mut slice)?;
+
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(libc::SIGINT, on_sigint as *const () as libc::sighandler_t);
+    }
+    let mut editor = DefaultEditor::new().context("failed to initialise line editor")?;
+    eprintln!("Attached to task {task_id}. Type /help for commands; Ctrl-D detaches.");
+
+    loop {
+        let mut state = service.get_status(&task_id)?.metadata.state;
+        if state == TaskState::Running {
+            INTERRUPTED.store(false, Ordering::SeqCst);
+            follow_log_human(
+                &mut reader,
+                FollowContext {
+                    task_id: task_id.clone(),
+                    metadata: descriptor.metadata.clone(),
+                    forever: false,
+                    quiet: true,
+                    interrupt: Some(&INTERRUPTED),
+                },
+                &mut render_state,
+                &mut slice,
+            )?;
+            state = service.get_status(&task_id)?.metadata.state;
+        } else {
+            // Catch up on anything written since the last read, e.g. a very short invocation.
+            print_initial_log_human(&mut reader, None, &mut render_state, &mut slice)?;
+        }
+
+        match read_input(&mut editor, &state)? {
+            Input::Detach => break,
+            Input::Empty => {}
+            Input::Command(command) => match command.as_str() {
+                "detach" | "quit" | "exit" => break,
+                "help" => println!("{HELP}"),
+                "stop" => match service.stop_task(&task_id) {
+                    Ok(StopOutcome::Stopped) => eprintln!("Task {task_id} stopped."),
+                    Ok(StopOutcome::AlreadyStopped) => eprintln!("Task {task_id} is not running."),
+                    Ok(StopOutcome::Dequeued) => {
+                        eprintln!("Task {task_id} was queued; its pending prompt was cancelled.")
+                    }
+                    Err(err) => eprintln!("Failed to stop task {task_id}: {err:#}"),
+                },
+                "result" => match service.task_answer(&task_id, None) {
+                    Ok(Some(answer)) => println!("{}", answer.trim_end()),
+                    Ok(None) => eprintln!("Task {task_id} has no result yet."),
+                    Err(err) => eprintln!("Failed to read result: {err:#}"),
+                },
+                "diff" => match service.task_diff(&task_id, None) {
+                    Ok(diff) => print_diff(diff),
+                    Err(err) => eprintln!("Failed to compute diff: {err:#}"),
+                },
+                other => eprintln!("Unknown command /{other}; type /help for commands."),
+            },
+            Input::Prompt(prompt) => {
+                if state == TaskState::Running {
+                    eprintln!(
+                        "Task {task_id} is still running; press Enter to keep watching or /stop it first."
+                    );
+                    continue;
+                }
+                let before = service.get_status(&task_id)?.metadata.updated_at;
+                let sent = service.send_prompt(SendPromptParams {
+                    task_id: task_id.clone(),
+                    prompt,
+                    priority: 0,
+                });
+                match sent {
+                    Ok(LaunchOutcome::Started) => {
+                        wait_for_invocation_start(&service, &task_id, before)?
+                    }
+                    Ok(LaunchOutcome::Queued) => eprintln!(
+                        "Task {task_id} is QUEUED until a slot frees up under the concurrency limit."
+                    ),
+                    Err(err) => eprintln!("Failed to send prompt: {err:#}"),
+                }
+            }
+        }
+    }
+
+    eprintln!("Detached from task {task_id}.");
+    Ok(())
+}
+
+/// Reads one prompt or command, joining continuation lines and `"""` blocks.
+fn read_input(editor: &mut DefaultEditor, state: &TaskState) -> Result<Input> {
+    let prompt = match state {
+        TaskState::Running => "(running) > ".to_string(),
+        TaskState::Stopped => "> ".to_string(),
+        other => format!("({}) > ", other.as_str().to_lowercase()),
+    };
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_block = false;
+    loop {
+        let current = if lines.is_empty() && !in_block {
+            prompt.as_str()
+        } else {
+            "... "
+        };
+        let line = match editor.readline(current) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                lines.clear();
+                in_block = false;
+                continue;
+            }
+            Err(ReadlineError::Eof) => return Ok(Input::Detach),
+            Err(err) => return Err(err).context("failed to read input"),
+        };
+
+        if line.trim() == BLOCK_DELIMITER {
+            if in_block {
+                break;
+            }
+            in_block = true;
+            continue;
+        }
+        if in_block {
+            lines.push(line);
+            continue;
+        }
+        match line.strip_suffix('\\') {
+            Some(head) => lines.push(head.to_string()),
+            None => {
+                lines.push(line);
+                break;
+            }
+        }
+    }
+
+    let text = lines.join("\n");
+    if text.trim().is_empty() {
+        return Ok(Input::Empty);
+    }
+    let _ = editor.add_history_entry(text.as_str());
+    match text.trim().strip_prefix('/') {
+        Some(command) if !command.contains(char::is_whitespace) => {
+            Ok(Input::Command(command.to_string()))
+        }
+        _ => Ok(Input::Prompt(text)),
+    }
+}
+
+/// Waits until the worker launched by `send_prompt` updates the task's metadata, so streaming
+/// does not end before the new invocation starts.
+fn wait_for_invocation_start(
+    service: &TaskService,
+    task_id: &str,
+    before: DateTime<Utc>,
+) -> Result<()> {
+    let deadline = Instant::now() + SEND_START_TIMEOUT;
+    while Instant::now() < deadline {
+        if service.get_status(task_id)?.metadata.updated_at != before {
+            return Ok(());
+        }
+        sleep(SEND_POLL_INTERVAL);
+    }
+    eprintln!("Task {task_id} has not started the new prompt yet; press Enter to check again.");
+    Ok(())
+}
@@ -1,6 +1,7 @@
-use std::io;
+use std::io::{self, Write};
 
 use anyhow::{Context, Result};
+use tabwriter::TabWriter;
 
 pub(crate) fn is_process_running(pid: i32) -> Result<bool> {
     if pid <= 0 {
@@ -19,3 +20,18 @@ pub(crate) fn is_process_running(pid: i32) -> Result<bool> {
         _ => Err(err).with_context(|| format!("failed to query status of process {pid}")),
     }
 }
+
+/// Prints tab-separated rows under a header as aligned columns.
+pub(crate) fn print_table(header: &[&str], rows: &[Vec<String>]) -> Result<()> {
+    let mut buffer = Vec::new();
+    {
+        let mut writer = TabWriter::new(&mut buffer).padding(2);
+        writeln!(&mut writer, "{}", header.join("\t"))?;
+        for row in rows {
+            writeln!(&mut writer, "{}", row.join("\t"))?;
+        }
+        writer.flush()?;
+    }
+    print!("{}", String::from_utf8(buffer)?);
+    Ok(())
+}
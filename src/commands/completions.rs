@@ -0,0 +1,181 @@
+use std::io::{self, Write};
+
+use anyhow::Result;
+use clap::{Arg, CommandFactory};
+use clap_complete::{Shell, generate};
+
+use crate::cli::{Cli, CompleteTaskIdsArgs, CompletionShell, CompletionsArgs};
+use crate::tasks::{ListTasksOptions, TaskService, TaskState, TasksConfig};
+
+const BIN_NAME: &str = "codex-tasks";
+
+/// Bash wrapper that offers task ids before falling back to the generated completions.
+const BASH_DYNAMIC: &str = r#"
+_codex_tasks_dynamic() {
+    local ids
+    ids="$("${COMP_WORDS[0]}" complete-task-ids "${COMP_WORDS[@]:0:COMP_CWORD+1}" 2>/dev/null | cut -f1)"
+    if [[ -n "${ids}" ]]; then
+        COMPREPLY=( $(compgen -W "${ids}" -- "${COMP_WORDS[COMP_CWORD]}") )
+        return 0
+    fi
+    _codex-tasks "$@"
+}
+
+complete -F _codex_tasks_dynamic -o bashdefault -o default codex-tasks
+"#;
+
+/// Zsh wrapper that offers task ids (described by their titles) before falling back to the
+/// generated completions; works both when sourced and when autoloaded from `fpath`.
+const ZSH_DYNAMIC: &str = r#"
+_codex_tasks_dynamic() {
+    local -a ids
+    ids=("${(@f)$("${words[1]}" complete-task-ids "${(@)words[1,CURRENT]}" 2>/dev/null)}")
+    ids=("${(@)ids:#}")
+    if (( ${#ids} )); then
+        _describe -t task-ids 'task id' "${(@)ids/$'\t'/:}"
+        return
+    fi
+    _codex-tasks "$@"
+}
+
+if [ "$funcstack[1]" = "_codex-tasks" ]; then
+    compdef _codex_tasks_dynamic codex-tasks
+    _codex_tasks_dynamic "$@"
+else
+    compdef _codex_tasks_dynamic codex-tasks
+fi
+"#;
+
+/// Fish completions for task ids; descriptions come from the titles.
+const FISH_DYNAMIC: &str = r#"
+function __codex_tasks_ids
+    set -l tokens (commandline -opc) (commandline -ct)
+    $tokens[1] complete-task-ids $tokens 2>/dev/null
+end
+
+complete -c codex-tasks -n 'test -n "$(__codex_tasks_ids)"' -f -a '(__codex_tasks_ids)'
+"#;
+
+/// Marks the start of the trailer clap appends to zsh scripts to register the completion;
+/// it is replaced by [`ZSH_DYNAMIC`].
+const ZSH_REGISTRATION: &str = "if [ \"$funcstack[1]\" = \"_codex-tasks\" ]; then";
+
+pub fn handle_completions(args: CompletionsArgs) -> Result<()> {
+    let shell = match args.shell {
+        CompletionShell::Bash => Shell::Bash,
+        CompletionShell::Zsh => Shell::Zsh,
+        CompletionShell::Fish => Shell::Fish,
+    };
+    let mut script = Vec::new();
+    generate(shell, &mut Cli::command(), BIN_NAME, &mut script);
+    let mut script = String::from_utf8(script)?;
+
+    match args.shell {
+        CompletionShell::Bash => script.push_str(BASH_DYNAMIC),
+        CompletionShell::Zsh => {
+            if let Some(index) = script.find(ZSH_REGISTRATION) {
+                script.truncate(index);
+            }
+            script.push_str(ZSH_DYNAMIC);
+        }
+        CompletionShell::Fish => script.push_str(FISH_DYNAMIC),
+    }
+    print!("{script}");
+    Ok(())
+}
+
+pub fn handle_complete_task_ids(args: CompleteTaskIdsArgs, config: TasksConfig) -> Result<()> {
+    let Some(subcommand) = task_id_subcommand(&args.words) else {
+        return Ok(());
+    };
+    let (states, include_archived) = candidate_states(&subcommand);
+
+    let service = TaskService::with_default_store(config, false)?;
+    let entries = service.list_tasks(ListTasksOptions {
+        include_archived,
+        ..Default::default()
+    })?;
+    let mut out = io::stdout().lock();
+    for entry in entries {
+        let metadata = entry.metadata;
+        if !states.is_empty() && !states.contains(&metadata.state) {
+            continue;
+        }
+        let title = metadata
+            .title
+            .unwrap_or_default()
+            .replace(['\t', '\n'], " ");
+        writeln!(out, "{}\t{}", metadata.id, title)?;
+    }
+    Ok(())
+}
+
+/// Returns the subcommand whose task id argument the last word is completing, if any.
+fn task_id_subcommand(words: &[String]) -> Option<String> {
+    let (current, previous) = words.split_last()?;
+    if current.starts_with('-') {
+        return None;
+    }
+    let mut cli = Cli::command();
+    cli.build();
+    let mut rest = previous.iter().skip(1);
+    let name = rest.by_ref().find(|word| !word.starts_with('-'))?;
+    let subcommand = cli.find_subcommand(name)?;
+
+    let mut positionals = 0;
+    let mut expects_value = false;
+    for word in rest {
+        if expects_value {
+            expects_value = false;
+            continue;
+        }
+        let option = match (word.strip_prefix("--"), word.strip_prefix('-')) {
+            (Some(long), _) if !long.contains('=') => subcommand
+                .get_arguments()
+                .find(|arg| arg.get_long() == Some(long)),
+            (Some(_), _) => None,
+            (None, Some(short)) if short.chars().count() == 1 => subcommand
+                .get_arguments()
+                .find(|arg| arg.get_short().map(String::from).as_deref() == Some(short)),
+            (None, Some(_)) => None,
+            (None, None) => {
+                positionals += 1;
+                continue;
+            }
+        };
+        expects_value = option.is_some_and(|arg| arg.get_action().takes_values());
+    }
+    if expects_value {
+        return None;
+    }
+
+    let arg = positional_at(subcommand.get_positionals(), positionals)?;
+    matches!(arg.get_id().as_str(), "task_id" | "task_ids")
+        .then(|| subcommand.get_name().to_string())
+}
+
+/// Finds the positional argument receiving the value at `index`, letting a trailing
+/// multi-valued argument absorb the rest.
+fn positional_at<'a>(positionals: impl Iterator<Item = &'a Arg>, index: usize) -> Option<&'a Arg> {
+    for (position, arg) in positionals.enumerate() {
+        let multiple = arg
+            .get_num_args()
+            .is_some_and(|range| range.max_values() > 1);
+        if position == index || (multiple && position < index) {
+            return Some(arg);
+        }
+    }
+    None
+}
+
+/// States (empty for any) and archive visibility of the tasks a subcommand can act on.
+fn candidate_states(subcommand: &str) -> (Vec<TaskState>, bool) {
+    match subcommand {
+        "send" => (vec![TaskState::Stopped], false),
+        "retry" => (vec![TaskState::Died], false),
+        "stop" => (vec![TaskState::Running, TaskState::Queued], false),
+        "archive" => (vec![TaskState::Stopped, TaskState::Died], false),
+        "attach" | "fork" => (Vec::new(), false),
+        _ => (Vec::new(), true),
+    }
+}
@@ -0,0 +1,25 @@
+use anyhow::Result;
+
+use crate::cli::{ConfigArgs, ConfigCommand};
+use crate::tasks::{TaskService, TasksConfig};
+
+pub fn handle_config(args: ConfigArgs, config: TasksConfig) -> Result<()> {
+    match args.command {
+        ConfigCommand::Show(args) => {
+            let service = TaskService::with_default_store(config, false)?;
+            let config = service.effective_config()?;
+            if args.json {
+                let mut value = serde_json::to_value(config.to_file())?;
+                value["path"] = serde_json::to_value(&config.path)?;
+                println!("{}", serde_json::to_string_pretty(&value)?);
+                return Ok(());
+            }
+            match &config.path {
+                Some(path) => println!("# Loaded from {}", path.display()),
+                None => println!("# No configuration file; showing built-in defaults."),
+            }
+            print!("{}", toml::to_string_pretty(&config.to_file())?);
+            Ok(())
+        }
+    }
+}
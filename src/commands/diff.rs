@@ -0,0 +1,31 @@
+use anyhow::Result;
+
+use crate::cli::DiffArgs;
+use crate::tasks::{TaskDiff, TaskService, TasksConfig};
+
+pub fn handle_diff(args: DiffArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    print_diff(service.task_diff(&args.task_id, args.invocation)?);
+    Ok(())
+}
+
+/// Prints a patch to stdout, or the transcript's file list when no git snapshot is available.
+pub(crate) fn print_diff(diff: TaskDiff) {
+    match diff {
+        TaskDiff::Patch { patch, .. } if patch.is_empty() => {
+            eprintln!("No workspace changes.");
+        }
+        TaskDiff::Patch { patch, .. } => print!("{patch}"),
+        TaskDiff::Files(files) => {
+            eprintln!(
+                "No git snapshot available; listing files changed according to the transcript."
+            );
+            if files.is_empty() {
+                eprintln!("No file changes recorded.");
+            }
+            for (marker, path) in files {
+                println!("{marker} {path}");
+            }
+        }
+    }
+}
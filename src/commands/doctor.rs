@@ -0,0 +1,48 @@
+use anyhow::{Result, bail};
+
+use crate::cli::DoctorArgs;
+use crate::tasks::{TaskService, TasksConfig};
+
+pub fn handle_doctor(args: DoctorArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    let report = service.diagnose_store(args.fix)?;
+
+    if report.issues.is_empty() {
+        println!("No problems found in {} task(s).", report.scanned);
+        return Ok(());
+    }
+
+    for issue in &report.issues {
+        match &issue.fix {
+            Some(fix) => println!(
+                "[{}] {}: {} ({fix})",
+                issue.kind.as_str(),
+                issue.path.display(),
+                issue.detail
+            ),
+            None => println!(
+                "[{}] {}: {}",
+                issue.kind.as_str(),
+                issue.path.display(),
+                issue.detail
+            ),
+        }
+    }
+
+    let unresolved = report.unresolved();
+    println!(
+        "Found {} problem(s) in {} task(s); fixed {}.",
+        report.issues.len(),
+        report.scanned,
+        report.issues.len() - unresolved
+    );
+
+    if unresolved > 0 {
+        if args.fix {
+            bail!("failed to repair {unresolved} problem(s)");
+        }
+        bail!("found {unresolved} problem(s); rerun with --fix to repair them");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,443 @@
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::cli::ExportFormat;
+use crate::commands::log::{TranscriptEvent, format_tokens};
+use crate::tasks::transcript::{LogCategory, for_each_event, is_plain_user_message};
+use crate::tasks::{TaskMetadata, TaskService, log_event_time};
+use crate::timefmt::{TimeFormat, format_time};
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:60rem;margin:2rem auto;\
+padding:0 1rem;line-height:1.5;color:#1f2328}\
+table{border-collapse:collapse}th,td{border:1px solid #d0d7de;padding:.25rem .75rem;\
+text-align:left;vertical-align:top}\
+section{border-top:2px solid #d0d7de;margin-top:2rem}\
+pre{background:#f6f8fa;padding:.75rem;overflow-x:auto;white-space:pre-wrap}\
+.prompt{border-left:4px solid #0969da;padding-left:1rem}\
+.message{white-space:pre-wrap}.ok{color:#1a7f37}.failed,.error{color:#cf222e}\
+.muted,.time,.tokens{color:#656d76}details{margin:1rem 0}summary{cursor:pointer}\
+ul.plan{list-style:none;padding-left:1rem}";
+
+/// A prompt and everything logged until the next one.
+struct Invocation {
+    started_at: Option<DateTime<Utc>>,
+    prompt: Option<String>,
+    events: Vec<TranscriptEvent>,
+}
+
+/// Everything an exported document shows, independent of its format.
+struct Report {
+    title: String,
+    fields: Vec<(&'static str, String)>,
+    invocations: Vec<Invocation>,
+}
+
+/// Renders the whole transcript of a task as a self-contained document, leaving out events in
+/// the `hidden` categories.
+pub(crate) fn export_transcript(
+    service: &TaskService,
+    task_id: &str,
+    format: ExportFormat,
+    hidden: &[LogCategory],
+) -> Result<String> {
+    let descriptor = service.prepare_log_descriptor(task_id, false)?;
+    let metadata = service.find_status(task_id)?.map(|status| status.metadata);
+    let show_prompts = !hidden.contains(&LogCategory::Messages);
+
+    let mut invocations: Vec<Invocation> = Vec::new();
+    let mut tokens = 0u64;
+    for_each_event(&descriptor.path, |value| {
+        if is_plain_user_message(value) {
+            invocations.push(Invocation {
+                started_at: log_event_time(value),
+                prompt: value
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .filter(|_| show_prompts)
+                    .map(str::to_string),
+                events: Vec::new(),
+            });
+            return;
+        }
+        let event = TranscriptEvent::parse(value);
+        if let TranscriptEvent::TurnCompleted(used) = event {
+            tokens = tokens.saturating_add(used);
+        }
+        // Started commands are repeated with their outcome, and other prompts carry context
+        // that Codex injects rather than anything the user wrote.
+        if hidden.contains(&LogCategory::of(value))
+            || matches!(
+                event,
+                TranscriptEvent::Silent
+                    | TranscriptEvent::Unrecognised
+                    | TranscriptEvent::CommandStarted(_)
+                    | TranscriptEvent::Prompt(_)
+            )
+        {
+            return;
+        }
+        if invocations.is_empty() {
+            invocations.push(Invocation {
+                started_at: log_event_time(value),
+                prompt: None,
+                events: Vec::new(),
+            });
+        }
+        let Some(invocation) = invocations.last_mut() else {
+            return;
+        };
+        // The to-do list is re-sent on every change; keep only its latest version.
+        let previous_plan = match event {
+            TranscriptEvent::TodoList(_) => invocation
+                .events
+                .iter_mut()
+                .find(|existing| matches!(existing, TranscriptEvent::TodoList(_))),
+            _ => None,
+        };
+        match previous_plan {
+            Some(plan) => *plan = event,
+            None => invocation.events.push(event),
+        }
+    })?;
+
+    let report = Report {
+        title: metadata
+            .as_ref()
+            .and_then(|metadata| metadata.title.clone())
+            .unwrap_or_else(|| format!("Task {}", descriptor.task_id)),
+        fields: report_fields(&descriptor.task_id, metadata.as_ref(), &invocations, tokens),
+        invocations,
+    };
+    Ok(match format {
+        ExportFormat::Md => render_markdown(&report),
+        ExportFormat::Html => render_html(&report),
+    })
+}
+
+fn report_fields(
+    task_id: &str,
+    metadata: Option<&TaskMetadata>,
+    invocations: &[Invocation],
+    tokens: u64,
+) -> Vec<(&'static str, String)> {
+    let mut fields = vec![("Task", task_id.to_string())];
+    if let Some(metadata) = metadata {
+        fields.push(("State", metadata.state.as_str().to_string()));
+        fields.push(("Created", format_time(metadata.created_at, TimeFormat::Iso)));
+        fields.push(("Updated", format_time(metadata.updated_at, TimeFormat::Iso)));
+        if let Some(dir) = &metadata.working_dir {
+            fields.push(("Working directory", dir.clone()));
+        }
+        if !metadata.tags.is_empty() {
+            fields.push(("Tags", metadata.tags.join(", ")));
+        }
+        if let Some(preset) = &metadata.preset {
+            fields.push(("Preset", preset.clone()));
+        }
+        if let Some(parent) = &metadata.parent_id {
+            fields.push(("Parent", parent.clone()));
+        }
+        if let Some(source) = &metadata.forked_from {
+            fields.push(("Forked from", source.clone()));
+        }
+        if metadata.retry_count > 0 {
+            fields.push(("Retries", metadata.retry_count.to_string()));
+        }
+    }
+    fields.push(("Invocations", invocations.len().to_string()));
+    fields.push(("Tokens used", format_tokens(tokens)));
+    fields
+}
+
+fn render_markdown(report: &Report) -> String {
+    let mut out = format!("# {}\n\n| Field | Value |\n| --- | --- |\n", report.title);
+    for (name, value) in &report.fields {
+        let value = value.replace('|', "\\|").replace('\n', " ");
+        let _ = writeln!(out, "| {name} | {value} |");
+    }
+
+    for (index, invocation) in report.invocations.iter().enumerate() {
+        let _ = write!(out, "\n## Invocation {}\n\n", index + 1);
+        if let Some(at) = invocation.started_at {
+            let _ = write!(out, "_Started {}_\n\n", format_time(at, TimeFormat::Iso));
+        }
+        if let Some(prompt) = &invocation.prompt {
+            out.push_str("**Prompt**\n\n");
+            for line in prompt.trim_end().lines() {
+                if line.is_empty() {
+                    out.push_str(">\n");
+                } else {
+                    let _ = writeln!(out, "> {line}");
+                }
+            }
+            out.push('\n');
+        }
+        for event in &invocation.events {
+            render_markdown_event(&mut out, event);
+        }
+    }
+    out
+}
+
+fn render_markdown_event(out: &mut String, event: &TranscriptEvent) {
+    match event {
+        TranscriptEvent::AgentMessage(text) => {
+            let _ = write!(out, "**Codex**\n\n{text}\n\n");
+        }
+        TranscriptEvent::Reasoning(text) => {
+            let _ = write!(
+                out,
+                "<details>\n<summary>Reasoning</summary>\n\n{text}\n\n</details>\n\n"
+            );
+        }
+        TranscriptEvent::Command {
+            command,
+            exit_code,
+            status,
+            output,
+        } => {
+            let outcome = if *exit_code == 0 {
+                "exit 0".to_string()
+            } else {
+                format!("exit {exit_code}, {status}")
+            };
+            let _ = write!(out, "**Command** ({outcome})\n\n{}", fenced("sh", command));
+            if !output.is_empty() {
+                out.push_str(&fenced("text", output));
+            }
+        }
+        TranscriptEvent::FileChange { status, changes } => {
+            let _ = write!(out, "**Files changed** ({status})\n\n");
+            for (marker, path) in changes {
+                let _ = writeln!(out, "- {marker} `{path}`");
+            }
+            out.push('\n');
+        }
+        TranscriptEvent::TodoList(entries) => {
+            out.push_str("**Plan**\n\n");
+            for (completed, text) in entries {
+                let mark = if *completed { "x" } else { " " };
+                let _ = writeln!(out, "- [{mark}] {text}");
+            }
+            out.push('\n');
+        }
+        TranscriptEvent::WebSearch(query) => {
+            let _ = write!(out, "**Searched the web:** {query}\n\n");
+        }
+        TranscriptEvent::ToolCall {
+            server,
+            tool,
+            status,
+        } => {
+            let _ = write!(out, "**Tool** `{server}.{tool}` {status}\n\n");
+        }
+        TranscriptEvent::TurnCompleted(tokens) => {
+            let _ = write!(out, "_Tokens used: {}_\n\n", format_tokens(*tokens));
+        }
+        TranscriptEvent::Error(message) => {
+            let _ = write!(out, "> **Error:** {message}\n\n");
+        }
+        TranscriptEvent::Stderr(message) => {
+            let _ = write!(out, "> **stderr:** {message}\n\n");
+        }
+        TranscriptEvent::Retry(line) => {
+            let _ = write!(out, "_{line}_\n\n");
+        }
+        TranscriptEvent::Hook { summary, output } => {
+            let _ = write!(out, "**{summary}**\n\n");
+            if !output.is_empty() {
+                out.push_str(&fenced("text", &output.join("\n")));
+            }
+        }
+        TranscriptEvent::Silent
+        | TranscriptEvent::Unrecognised
+        | TranscriptEvent::Prompt(_)
+        | TranscriptEvent::CommandStarted(_) => {}
+    }
+}
+
+/// Wraps `body` in a code fence longer than any backtick run inside it.
+fn fenced(lang: &str, body: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for ch in body.chars() {
+        run = if ch == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat((longest + 1).max(3));
+    format!(
+        "{fence}{lang}\n{}\n{fence}\n\n",
+        body.trim_end_matches('\n')
+    )
+}
+
+fn render_html(report: &Report) -> String {
+    let title = escape_html(&report.title);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n\
+         <h1>{title}</h1>\n<table>\n"
+    );
+    for (name, value) in &report.fields {
+        let _ = writeln!(
+            out,
+            "<tr><th>{name}</th><td>{}</td></tr>",
+            escape_html(value)
+        );
+    }
+    out.push_str("</table>\n");
+
+    for (index, invocation) in report.invocations.iter().enumerate() {
+        let _ = writeln!(out, "<section>\n<h2>Invocation {}</h2>", index + 1);
+        if let Some(at) = invocation.started_at {
+            let _ = writeln!(
+                out,
+                "<p class=\"time\">Started {}</p>",
+                format_time(at, TimeFormat::Iso)
+            );
+        }
+        if let Some(prompt) = &invocation.prompt {
+            let _ = writeln!(
+                out,
+                "<div class=\"prompt\"><h3>Prompt</h3><div class=\"message\">{}</div></div>",
+                escape_html(prompt.trim_end())
+            );
+        }
+        for event in &invocation.events {
+            render_html_event(&mut out, event);
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn render_html_event(out: &mut String, event: &TranscriptEvent) {
+    match event {
+        TranscriptEvent::AgentMessage(text) => {
+            let _ = writeln!(
+                out,
+                "<h3>Codex</h3>\n<div class=\"message\">{}</div>",
+                escape_html(text)
+            );
+        }
+        TranscriptEvent::Reasoning(text) => {
+            let _ = writeln!(
+                out,
+                "<details><summary>Reasoning</summary><div class=\"message muted\">{}</div></details>",
+                escape_html(text)
+            );
+        }
+        TranscriptEvent::Command {
+            command,
+            exit_code,
+            status,
+            output,
+        } => {
+            let (class, outcome) = if *exit_code == 0 {
+                ("ok", "exit 0".to_string())
+            } else {
+                ("failed", format!("exit {exit_code}, {status}"))
+            };
+            let _ = writeln!(
+                out,
+                "<h3>Command <span class=\"{class}\">({outcome})</span></h3>\n<pre><code>$ {}</code></pre>",
+                escape_html(command)
+            );
+            if !output.is_empty() {
+                let _ = writeln!(out, "<pre>{}</pre>", escape_html(output.trim_end()));
+            }
+        }
+        TranscriptEvent::FileChange { status, changes } => {
+            let _ = writeln!(
+                out,
+                "<h3>Files changed ({})</h3>\n<ul>",
+                escape_html(status)
+            );
+            for (marker, path) in changes {
+                let _ = writeln!(out, "<li>{marker} <code>{}</code></li>", escape_html(path));
+            }
+            out.push_str("</ul>\n");
+        }
+        TranscriptEvent::TodoList(entries) => {
+            out.push_str("<h3>Plan</h3>\n<ul class=\"plan\">\n");
+            for (completed, text) in entries {
+                let mark = if *completed { "&#9745;" } else { "&#9744;" };
+                let _ = writeln!(out, "<li>{mark} {}</li>", escape_html(text));
+            }
+            out.push_str("</ul>\n");
+        }
+        TranscriptEvent::WebSearch(query) => {
+            let _ = writeln!(
+                out,
+                "<p><strong>Searched the web:</strong> {}</p>",
+                escape_html(query)
+            );
+        }
+        TranscriptEvent::ToolCall {
+            server,
+            tool,
+            status,
+        } => {
+            let _ = writeln!(
+                out,
+                "<p><strong>Tool</strong> <code>{}.{}</code> {}</p>",
+                escape_html(server),
+                escape_html(tool),
+                escape_html(status)
+            );
+        }
+        TranscriptEvent::TurnCompleted(tokens) => {
+            let _ = writeln!(
+                out,
+                "<p class=\"tokens\">Tokens used: {}</p>",
+                format_tokens(*tokens)
+            );
+        }
+        TranscriptEvent::Error(message) => {
+            let _ = writeln!(
+                out,
+                "<p class=\"error\"><strong>Error:</strong> {}</p>",
+                escape_html(message)
+            );
+        }
+        TranscriptEvent::Stderr(message) => {
+            let _ = writeln!(
+                out,
+                "<p class=\"error\"><strong>stderr:</strong> {}</p>",
+                escape_html(message)
+            );
+        }
+        TranscriptEvent::Retry(line) => {
+            let _ = writeln!(out, "<p class=\"muted\">{}</p>", escape_html(line));
+        }
+        TranscriptEvent::Hook { summary, output } => {
+            let _ = writeln!(out, "<p><strong>{}</strong></p>", escape_html(summary));
+            if !output.is_empty() {
+                let _ = writeln!(out, "<pre>{}</pre>", escape_html(&output.join("\n")));
+            }
+        }
+        TranscriptEvent::Silent
+        | TranscriptEvent::Unrecognised
+        | TranscriptEvent::Prompt(_)
+        | TranscriptEvent::CommandStarted(_) => {}
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,20 @@
+use anyhow::Result;
+
+use crate::cli::ForkArgs;
+use crate::tasks::{ForkMode, ForkTaskParams, TaskService, TasksConfig};
+
+pub fn handle_fork(args: ForkArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    let result = service.fork_task(ForkTaskParams {
+        task_id: args.task_id,
+        prompt: args.prompt,
+        title: args.title,
+    })?;
+
+    if result.mode == ForkMode::Transcript {
+        eprintln!("No Codex session file found; seeded the fork from the task transcript.");
+    }
+    println!("{}", result.thread_id);
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use anyhow::Result;
+
+use crate::cli::HooksArgs;
+use crate::commands::common::print_table;
+use crate::tasks::{HookEvent, TaskService, TasksConfig};
+
+pub fn handle_hooks(args: HooksArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    let hooks = if args.set.is_empty() && args.unset.is_empty() && args.timeout.is_none() {
+        service.global_hooks()?
+    } else {
+        service.update_global_hooks(|hooks| {
+            for event in args.unset {
+                hooks.set(event, None);
+            }
+            for (event, command) in args.set {
+                hooks.set(event, Some(command));
+            }
+            if let Some(timeout) = args.timeout {
+                hooks.timeout_secs = Some(timeout.as_secs());
+            }
+        })?
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&hooks)?);
+        return Ok(());
+    }
+    if HookEvent::ALL
+        .iter()
+        .all(|event| hooks.command(*event).is_none())
+    {
+        println!("No global hooks.");
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = HookEvent::ALL
+        .into_iter()
+        .filter_map(|event| {
+            let command = hooks.command(event)?;
+            Some(vec![event.as_str().to_string(), command.to_string()])
+        })
+        .collect();
+    print_table(&["EVENT", "COMMAND"], &rows)?;
+    println!(
+        "Timeout: {}s",
+        hooks.timeout_or(service.config().hook_timeout).as_secs()
+    );
+    Ok(())
+}
@@ -1,59 +1,91 @@
 use std::collections::VecDeque;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, ErrorKind, IsTerminal, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, ensure};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
 use codex_protocol::num_format::format_with_separators;
+use owo_colors::Style;
 use serde_json::Value;
 
-use crate::cli::LogArgs;
-use crate::tasks::{FollowMetadata, TaskService, TaskState};
+use crate::cli::{InvocationSelector, LogArgs};
+use crate::commands::export::export_transcript;
+use crate::tasks::hooks::HOOK_EVENT;
+use crate::tasks::retry::RETRY_EVENT;
+use crate::tasks::transcript::{LogCategory, count_invocations, is_plain_user_message};
+use crate::tasks::workspace::WORKSPACE_SNAPSHOT_EVENT;
+use crate::tasks::{FollowMetadata, TaskService, TaskState, TasksConfig, log_event_time};
+use crate::timefmt::{TimeFormat, format_time};
 
-pub fn handle_log(args: LogArgs) -> Result<()> {
-    let service = TaskService::with_default_store(false)?;
+pub fn handle_log(args: LogArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    let options = RenderOptions::from_args(&args);
+    if let Some(format) = args.export {
+        let document = export_transcript(&service, &args.task_id, format, &options.hidden)?;
+        match &args.output {
+            Some(path) => fs::write(path, document)
+                .with_context(|| format!("failed to write {}", path.display()))?,
+            None => print!("{document}"),
+        }
+        return Ok(());
+    }
     let wait_for_log = args.follow || args.forever;
     let descriptor = service.prepare_log_descriptor(&args.task_id, wait_for_log)?;
     let log_path = descriptor.path.clone();
     let file = File::open(&log_path).with_context(|| {
         format!(
             "failed to open log for task {} at {}",
-            args.task_id,
+            descriptor.task_id,
             log_path.display()
         )
     })?;
+    let mut slice = LogSlice::from_args(&args, &descriptor.task_id, &log_path)?;
     let mut reader = BufReader::new(file);
     if args.json {
-        print_initial_log(&mut reader, args.lines)?;
+        print_initial_log(&mut reader, args.lines, &options, &mut slice)?;
 
         let should_follow = args.follow || args.forever;
         if should_follow {
             let context = FollowContext {
-                task_id: args.task_id,
+                task_id: descriptor.task_id,
                 metadata: descriptor.metadata.clone(),
                 forever: args.forever,
+                quiet: false,
+                interrupt: None,
             };
-            follow_log(&mut reader, context)?;
+            follow_log(&mut reader, context, &options, &mut slice)?;
         }
     } else {
-        let mut human_state = HumanRenderState::new();
-        print_initial_log_human(&mut reader, args.lines, &mut human_state)?;
+        let mut human_state = HumanRenderState::with_options(options);
+        print_initial_log_human(&mut reader, args.lines, &mut human_state, &mut slice)?;
 
         let should_follow = args.follow || args.forever;
         if should_follow {
             let context = FollowContext {
-                task_id: args.task_id,
+                task_id: descriptor.task_id,
                 metadata: descriptor.metadata,
                 forever: args.forever,
+                quiet: false,
+                interrupt: None,
             };
-            follow_log_human(&mut reader, context, &mut human_state)?;
+            follow_log_human(&mut reader, context, &mut human_state, &mut slice)?;
         }
     }
     Ok(())
 }
 
-fn print_initial_log(reader: &mut BufReader<File>, limit: Option<usize>) -> Result<()> {
+fn print_initial_log(
+    reader: &mut BufReader<File>,
+    limit: Option<usize>,
+    options: &RenderOptions,
+    slice: &mut LogSlice,
+) -> Result<()> {
     let mut buffer = String::new();
     let mut stdout = io::stdout();
 
@@ -68,7 +100,7 @@ fn print_initial_log(reader: &mut BufReader<File>, limit: Option<usize>) -> Resu
                     break;
                 }
 
-                if limit == 0 {
+                if !slice.admits_line(&buffer) || !options.keeps_line(&buffer) || limit == 0 {
                     continue;
                 }
 
@@ -91,6 +123,9 @@ fn print_initial_log(reader: &mut BufReader<File>, limit: Option<usize>) -> Resu
             if bytes == 0 {
                 break;
             }
+            if !slice.admits_line(&buffer) || !options.keeps_line(&buffer) {
+                continue;
+            }
             stdout
                 .write_all(buffer.as_bytes())
                 .context("failed to write log output")?;
@@ -103,7 +138,12 @@ fn print_initial_log(reader: &mut BufReader<File>, limit: Option<usize>) -> Resu
     Ok(())
 }
 
-fn follow_log(reader: &mut BufReader<File>, context: FollowContext) -> Result<()> {
+fn follow_log(
+    reader: &mut BufReader<File>,
+    context: FollowContext,
+    options: &RenderOptions,
+    slice: &mut LogSlice,
+) -> Result<()> {
     let mut buffer = String::new();
     let mut stdout = io::stdout();
     let mut idle_pending = false;
@@ -122,7 +162,7 @@ fn follow_log(reader: &mut BufReader<File>, context: FollowContext) -> Result<()
                 }
 
                 match context.current_state() {
-                    Ok(Some(TaskState::Running)) => {
+                    Ok(Some(TaskState::Running | TaskState::Queued)) => {
                         idle_pending = false;
                     }
                     Ok(Some(TaskState::Stopped)) => {
@@ -157,6 +197,9 @@ fn follow_log(reader: &mut BufReader<File>, context: FollowContext) -> Result<()
             }
             Ok(_) => {
                 idle_pending = false;
+                if !slice.admits_line(&buffer) || !options.keeps_line(&buffer) {
+                    continue;
+                }
                 stdout
                     .write_all(buffer.as_bytes())
                     .context("failed to write log output")?;
@@ -173,17 +216,21 @@ fn follow_log(reader: &mut BufReader<File>, context: FollowContext) -> Result<()
     Ok(())
 }
 
-fn print_initial_log_human(
+/// Prints the log rendered for humans; `limit` counts rendered events rather than raw lines.
+pub(crate) fn print_initial_log_human(
     reader: &mut BufReader<File>,
     limit: Option<usize>,
     state: &mut HumanRenderState,
+    slice: &mut LogSlice,
 ) -> Result<()> {
     let mut buffer = String::new();
     let mut stdout = io::stdout();
 
     match limit {
         Some(limit) => {
-            let mut lines = VecDeque::new();
+            // Events before the last `limit` are still rendered so state such as pending
+            // commands and token totals stays accurate.
+            let mut events = VecDeque::new();
             loop {
                 buffer.clear();
                 let bytes = read_line_retry(reader, &mut buffer)
@@ -192,18 +239,19 @@ fn print_initial_log_human(
                     break;
                 }
 
-                if limit == 0 {
+                let rendered = render_humanized_line(&buffer, state, slice);
+                if rendered.is_empty() || limit == 0 {
                     continue;
                 }
 
-                if lines.len() == limit {
-                    lines.pop_front();
+                if events.len() == limit {
+                    events.pop_front();
                 }
-                lines.push_back(buffer.clone());
+                events.push_back(rendered);
             }
 
-            for line in lines {
-                write_humanized_line(&line, state, &mut stdout)?;
+            for rendered in events {
+                write_rendered_lines(&rendered, &mut stdout)?;
             }
         }
         None => loop {
@@ -213,7 +261,7 @@ fn print_initial_log_human(
             if bytes == 0 {
                 break;
             }
-            write_humanized_line(&buffer, state, &mut stdout)?;
+            write_humanized_line(&buffer, state, slice, &mut stdout)?;
         },
     }
 
@@ -223,10 +271,12 @@ fn print_initial_log_human(
     Ok(())
 }
 
-fn follow_log_human(
+/// Streams new transcript lines until the task goes idle, the follow is interrupted, or forever.
+pub(crate) fn follow_log_human(
     reader: &mut BufReader<File>,
     context: FollowContext,
     state: &mut HumanRenderState,
+    slice: &mut LogSlice,
 ) -> Result<()> {
     let mut buffer = String::new();
     let mut stdout = io::stdout();
@@ -240,28 +290,42 @@ fn follow_log_human(
                     .flush()
                     .context("failed to flush log output to stdout")?;
 
+                if context
+                    .interrupt
+                    .is_some_and(|flag| flag.swap(false, Ordering::SeqCst))
+                {
+                    break;
+                }
+
                 if context.forever {
                     thread::sleep(Duration::from_millis(250));
                     continue;
                 }
 
                 match context.current_state() {
-                    Ok(Some(TaskState::Running)) => {
+                    Ok(Some(TaskState::Running | TaskState::Queued)) => {
                         idle_pending = false;
                     }
                     Ok(Some(TaskState::Stopped)) => {
                         if idle_pending {
-                            eprintln!("Task {} is STOPPED; stopping log follow.", context.task_id);
+                            if !context.quiet {
+                                eprintln!(
+                                    "Task {} is STOPPED; stopping log follow.",
+                                    context.task_id
+                                );
+                            }
                             break;
                         }
                         idle_pending = true;
                     }
                     Ok(Some(state @ (TaskState::Died | TaskState::Archived))) => {
-                        eprintln!(
-                            "Task {} is {}; stopping log follow.",
-                            context.task_id,
-                            state.as_str()
-                        );
+                        if !context.quiet {
+                            eprintln!(
+                                "Task {} is {}; stopping log follow.",
+                                context.task_id,
+                                state.as_str()
+                            );
+                        }
                         break;
                     }
                     Ok(None) => {
@@ -281,7 +345,7 @@ fn follow_log_human(
             }
             Ok(_) => {
                 idle_pending = false;
-                write_humanized_line(&buffer, state, &mut stdout)?;
+                write_humanized_line(&buffer, state, slice, &mut stdout)?;
             }
             Err(err) => {
                 return Err(err).context("failed to read from log while following");
@@ -295,22 +359,39 @@ fn follow_log_human(
 fn write_humanized_line(
     raw_line: &str,
     state: &mut HumanRenderState,
+    slice: &mut LogSlice,
     stdout: &mut io::Stdout,
 ) -> Result<()> {
+    let lines = render_humanized_line(raw_line, state, slice);
+    write_rendered_lines(&lines, stdout)
+}
+
+/// Renders one raw log line, or nothing when it is blank, malformed, or outside the slice.
+fn render_humanized_line(
+    raw_line: &str,
+    state: &mut HumanRenderState,
+    slice: &mut LogSlice,
+) -> Vec<String> {
     let trimmed = raw_line.trim_end();
     if trimmed.is_empty() {
-        return Ok(());
+        return Vec::new();
     }
 
     let value: Value = match serde_json::from_str(trimmed) {
         Ok(val) => val,
         Err(err) => {
             eprintln!("failed to parse log line as JSON: {err}");
-            return Ok(());
+            return Vec::new();
         }
     };
 
-    let lines = state.render_event(&value);
+    if !slice.admits(&value) {
+        return Vec::new();
+    }
+    state.render_event(&value)
+}
+
+fn write_rendered_lines(lines: &[String], stdout: &mut io::Stdout) -> Result<()> {
     for line in lines {
         stdout
             .write_all(line.as_bytes())
@@ -323,205 +404,568 @@ fn write_humanized_line(
     Ok(())
 }
 
-struct HumanRenderState {
-    last_agent_message: Option<String>,
+/// Sums the tokens reported by every completed turn in a task log.
+pub(crate) fn log_tokens_used(path: &Path) -> Result<u64> {
+    let file =
+        File::open(path).with_context(|| format!("failed to open log file {}", path.display()))?;
+    let mut state = HumanRenderState::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("failed to read log file {}", path.display()))?;
+        if let Ok(value) = serde_json::from_str::<Value>(&line) {
+            state.render_event(&value);
+        }
+    }
+    Ok(state.tokens_used())
 }
 
-impl HumanRenderState {
-    fn new() -> Self {
+/// Controls which events the human transcript shows and how they look.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RenderOptions {
+    /// Categories left out of the output.
+    pub(crate) hidden: Vec<LogCategory>,
+    /// Prefix each event with the time it was logged, in this format.
+    pub(crate) timestamps: Option<TimeFormat>,
+    /// Highlight headers and command outcomes with ANSI colors.
+    pub(crate) color: bool,
+    /// Maximum number of output lines shown per command.
+    pub(crate) max_output_lines: Option<usize>,
+    /// Show events without a human rendering as raw JSON instead of dropping them.
+    pub(crate) verbose: bool,
+}
+
+impl RenderOptions {
+    fn from_args(args: &LogArgs) -> Self {
+        let mut hidden: Vec<LogCategory> = if args.only.is_empty() {
+            Vec::new()
+        } else {
+            LogCategory::value_variants()
+                .iter()
+                .copied()
+                .filter(|category| !args.only.contains(category))
+                .collect()
+        };
+        hidden.extend(args.exclude.iter().copied());
+        if args.no_reasoning {
+            hidden.push(LogCategory::Reasoning);
+        }
         Self {
-            last_agent_message: None,
+            hidden,
+            timestamps: args.timestamps.then_some(args.time_format),
+            color: !args.no_color
+                && io::stdout().is_terminal()
+                && env::var_os("NO_COLOR").is_none(),
+            max_output_lines: args.max_output_lines,
+            verbose: args.verbose,
+        }
+    }
+
+    fn shows(&self, value: &Value) -> bool {
+        !self.hidden.contains(&LogCategory::of(value))
+    }
+
+    /// Whether a raw log line passes the category filters; lines that are not JSON always do.
+    fn keeps_line(&self, line: &str) -> bool {
+        match serde_json::from_str::<Value>(line.trim_end()) {
+            Ok(value) => self.shows(&value),
+            Err(_) => true,
+        }
+    }
+
+    fn paint(&self, text: &str, style: Style) -> String {
+        if self.color {
+            style.style(text).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Restricts the log to a time range and/or a single invocation.
+///
+/// Events are visited in log order so the slice can track which invocation each belongs to;
+/// events without a timestamp inherit the time of the event before them.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LogSlice {
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    invocation: Option<usize>,
+    current_invocation: usize,
+    last_time: Option<DateTime<Utc>>,
+}
+
+impl LogSlice {
+    fn from_args(args: &LogArgs, task_id: &str, log_path: &Path) -> Result<Self> {
+        let invocation = match args.invocation {
+            None => None,
+            Some(selector) => {
+                let count = count_invocations(log_path)?;
+                let number = match selector {
+                    InvocationSelector::Number(number) => number,
+                    InvocationSelector::Last => count,
+                };
+                ensure!(
+                    (1..=count).contains(&number),
+                    "task {task_id} has {count} invocation(s); --invocation must be between 1 and {count}"
+                );
+                Some(number)
+            }
+        };
+        Ok(Self {
+            since: args.since,
+            until: args.until,
+            invocation,
+            ..Self::default()
+        })
+    }
+
+    fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none() && self.invocation.is_none()
+    }
+
+    /// Whether an event falls inside the slice; must be called for every event in order.
+    fn admits(&mut self, value: &Value) -> bool {
+        if is_plain_user_message(value) {
+            self.current_invocation += 1;
+        }
+        if let Some(at) = log_event_time(value) {
+            self.last_time = Some(at);
+        }
+        if self
+            .invocation
+            .is_some_and(|number| number != self.current_invocation)
+        {
+            return false;
+        }
+        if self.since.is_none() && self.until.is_none() {
+            return true;
         }
+        self.last_time.is_some_and(|at| {
+            self.since.is_none_or(|since| at >= since) && self.until.is_none_or(|until| at <= until)
+        })
     }
 
-    fn render_event(&mut self, value: &Value) -> Vec<String> {
+    /// Like [`Self::admits`] for a raw log line; lines that are not JSON pass only when the
+    /// slice is unbounded.
+    fn admits_line(&mut self, line: &str) -> bool {
+        match serde_json::from_str::<Value>(line.trim_end()) {
+            Ok(value) => self.admits(&value),
+            Err(_) => self.is_unbounded(),
+        }
+    }
+}
+
+/// A task log event interpreted for display, shared by the transcript and exported reports.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TranscriptEvent {
+    /// An event that is understood but has nothing to show, such as `turn.started`.
+    Silent,
+    /// An event without a human rendering.
+    Unrecognised,
+    /// A prompt sent to Codex.
+    Prompt(String),
+    AgentMessage(String),
+    Reasoning(String),
+    /// A command Codex started; its outcome follows in a [`TranscriptEvent::Command`].
+    CommandStarted(String),
+    Command {
+        command: String,
+        exit_code: i64,
+        status: String,
+        output: String,
+    },
+    /// `(marker, path)` pairs of the files Codex changed.
+    FileChange {
+        status: String,
+        changes: Vec<(char, String)>,
+    },
+    WebSearch(String),
+    ToolCall {
+        server: String,
+        tool: String,
+        status: String,
+    },
+    /// `(completed, text)` entries of the agent's to-do list.
+    TodoList(Vec<(bool, String)>),
+    /// A finished turn and the tokens it used.
+    TurnCompleted(u64),
+    Error(String),
+    Stderr(String),
+    /// An automatic retry, described in a single line.
+    Retry(String),
+    /// A lifecycle hook run: a summary line followed by its captured output.
+    Hook {
+        summary: String,
+        output: Vec<String>,
+    },
+}
+
+impl TranscriptEvent {
+    pub(crate) fn parse(value: &Value) -> Self {
         let Some(event_type) = value.get("type").and_then(Value::as_str) else {
-            return Vec::new();
+            return Self::Unrecognised;
         };
+        let message = || value.get("message").and_then(Value::as_str);
 
         match event_type {
-            "thread.started" => Vec::new(),
-            "user_message" => render_user_message(value),
-            "item.completed" => self.render_item_completed(value),
-            "turn.completed" => self.render_turn_completed(value),
-            "turn.failed" => value
-                .get("error")
-                .and_then(|err| err.get("message"))
-                .and_then(Value::as_str)
-                .map(|msg| vec![format!("ERROR: {msg}")])
-                .unwrap_or_else(|| vec!["ERROR: turn failed".to_string()]),
-            "stderr" => value
-                .get("message")
-                .and_then(Value::as_str)
-                .map(|msg| vec![format!("[stderr] {msg}")])
-                .unwrap_or_default(),
-            "error" => value
-                .get("message")
-                .and_then(Value::as_str)
-                .map(|msg| vec![format!("ERROR: {msg}")])
-                .unwrap_or_default(),
-            _ => Vec::new(),
+            "thread.started" | "turn.started" | WORKSPACE_SNAPSHOT_EVENT => Self::Silent,
+            "user_message" => match message() {
+                Some(message) if !message.is_empty() => Self::Prompt(message.to_string()),
+                _ => Self::Silent,
+            },
+            "item.started" => match value.get("item") {
+                Some(item) => match item.get("type").and_then(Value::as_str) {
+                    Some("command_execution") => Self::CommandStarted(
+                        text_field(item, "command").unwrap_or("").trim().to_string(),
+                    ),
+                    Some("mcp_tool_call") => parse_tool_call(item),
+                    Some("todo_list") => parse_todo_list(item),
+                    _ => Self::Silent,
+                },
+                None => Self::Silent,
+            },
+            "item.updated" => match value.get("item") {
+                Some(item) if LogCategory::of(value) == LogCategory::Todos => parse_todo_list(item),
+                _ => Self::Silent,
+            },
+            "item.completed" => match value.get("item") {
+                Some(item) => parse_completed_item(item),
+                None => Self::Silent,
+            },
+            "turn.completed" => match value.get("usage") {
+                Some(usage) => Self::TurnCompleted(turn_tokens(usage)),
+                None => Self::Silent,
+            },
+            "turn.failed" => Self::Error(
+                value
+                    .get("error")
+                    .and_then(|err| err.get("message"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("turn failed")
+                    .to_string(),
+            ),
+            "stderr" => message().map_or(Self::Silent, |msg| Self::Stderr(msg.to_string())),
+            "error" => message().map_or(Self::Silent, |msg| Self::Error(msg.to_string())),
+            RETRY_EVENT => Self::Retry(render_retry(value)),
+            HOOK_EVENT => parse_hook_run(value),
+            _ => Self::Unrecognised,
         }
     }
+}
 
-    fn render_item_completed(&mut self, value: &Value) -> Vec<String> {
-        let item = match value.get("item") {
-            Some(item) => item,
-            None => return Vec::new(),
-        };
+fn text_field<'a>(item: &'a Value, key: &str) -> Option<&'a str> {
+    item.get(key).and_then(Value::as_str)
+}
 
-        let item_type = match item.get("type").and_then(Value::as_str) {
-            Some(kind) => kind,
-            None => return Vec::new(),
-        };
+fn parse_completed_item(item: &Value) -> TranscriptEvent {
+    let Some(item_type) = text_field(item, "type") else {
+        return TranscriptEvent::Unrecognised;
+    };
+    let text = |key: &str| text_field(item, key).map(str::to_string);
 
-        match item_type {
-            "agent_message" => item
-                .get("text")
-                .and_then(Value::as_str)
-                .map(|text| {
-                    let trimmed = text.trim_end().to_string();
-                    self.last_agent_message = Some(trimmed.clone());
-                    vec!["codex".to_string(), trimmed]
-                })
+    match item_type {
+        "agent_message" => text("text").map_or(TranscriptEvent::Silent, |text| {
+            TranscriptEvent::AgentMessage(text.trim_end().to_string())
+        }),
+        "reasoning" => text("text").map_or(TranscriptEvent::Silent, |text| {
+            TranscriptEvent::Reasoning(text.trim_end().to_string())
+        }),
+        "command_execution" => TranscriptEvent::Command {
+            command: text_field(item, "command").unwrap_or("").trim().to_string(),
+            exit_code: item
+                .get("exit_code")
+                .and_then(Value::as_i64)
                 .unwrap_or_default(),
-            "reasoning" => item
-                .get("text")
-                .and_then(Value::as_str)
-                .map(|text| {
-                    let mut lines = vec!["thinking".to_string()];
-                    lines.push(text.trim_end().to_string());
-                    lines.push(String::new());
-                    lines
-                })
+            status: text("status").unwrap_or_else(|| "completed".to_string()),
+            output: text("aggregated_output")
+                .filter(|output| !output.trim().is_empty())
                 .unwrap_or_default(),
-            "command_execution" => {
-                let command = item
-                    .get("command")
-                    .and_then(Value::as_str)
-                    .unwrap_or("")
-                    .trim();
-                let exit_code = item
-                    .get("exit_code")
-                    .and_then(Value::as_i64)
-                    .unwrap_or_default();
-                let status = item
-                    .get("status")
-                    .and_then(Value::as_str)
-                    .unwrap_or("completed");
+        },
+        "file_change" => parse_file_change(item),
+        "web_search" => text("query").map_or(TranscriptEvent::Silent, TranscriptEvent::WebSearch),
+        "mcp_tool_call" => parse_tool_call(item),
+        "todo_list" => parse_todo_list(item),
+        "error" => text("message").map_or(TranscriptEvent::Silent, TranscriptEvent::Error),
+        _ => TranscriptEvent::Unrecognised,
+    }
+}
+
+fn parse_tool_call(item: &Value) -> TranscriptEvent {
+    TranscriptEvent::ToolCall {
+        server: text_field(item, "server").unwrap_or("server").to_string(),
+        tool: text_field(item, "tool").unwrap_or("tool").to_string(),
+        status: text_field(item, "status")
+            .unwrap_or("completed")
+            .to_string(),
+    }
+}
+
+fn parse_todo_list(item: &Value) -> TranscriptEvent {
+    let entries = item
+        .get("items")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|entry| {
+            (
+                entry.get("completed").and_then(Value::as_bool) == Some(true),
+                text_field(entry, "text").unwrap_or_default().to_string(),
+            )
+        })
+        .collect();
+    TranscriptEvent::TodoList(entries)
+}
+
+fn turn_tokens(usage: &Value) -> u64 {
+    usage
+        .get("total_tokens")
+        .and_then(Value::as_u64)
+        .or_else(|| {
+            usage
+                .get("total_token_usage")
+                .and_then(|v| v.get("blended_total"))
+                .and_then(Value::as_u64)
+        })
+        .unwrap_or_else(|| {
+            let input = usage
+                .get("input_tokens")
+                .and_then(Value::as_u64)
+                .unwrap_or_default();
+            let cached = usage
+                .get("cached_input_tokens")
+                .and_then(Value::as_u64)
+                .unwrap_or_default();
+            let output = usage
+                .get("output_tokens")
+                .and_then(Value::as_u64)
+                .unwrap_or_default();
+            input.saturating_sub(cached) + output
+        })
+}
+
+/// Formats a token count with thousands separators.
+pub(crate) fn format_tokens(tokens: u64) -> String {
+    format_with_separators(i64::try_from(tokens).unwrap_or(i64::MAX))
+}
+
+/// Converts JSONL log events into the human transcript, tracking a running summary.
+pub(crate) struct HumanRenderState {
+    options: RenderOptions,
+    last_agent_message: Option<String>,
+    tokens_used: u64,
+}
+
+impl HumanRenderState {
+    pub(crate) fn new() -> Self {
+        Self::with_options(RenderOptions::default())
+    }
+
+    pub(crate) fn with_options(options: RenderOptions) -> Self {
+        Self {
+            options,
+            last_agent_message: None,
+            tokens_used: 0,
+        }
+    }
+
+    /// Most recent agent message rendered so far.
+    pub(crate) fn last_agent_message(&self) -> Option<&str> {
+        self.last_agent_message.as_deref()
+    }
 
-                let mut lines = vec!["exec".to_string()];
-                lines.push(command.to_string());
+    /// Tokens reported by every completed turn rendered so far.
+    pub(crate) fn tokens_used(&self) -> u64 {
+        self.tokens_used
+    }
+
+    pub(crate) fn render_event(&mut self, value: &Value) -> Vec<String> {
+        // Hidden events are still rendered so the running summary stays accurate.
+        let mut lines = self.render_lines(value);
+        if !self.options.shows(value) {
+            return Vec::new();
+        }
+        if let (Some(format), Some(at), Some(first)) = (
+            self.options.timestamps,
+            log_event_time(value),
+            lines.first_mut(),
+        ) {
+            let stamp = self.options.paint(
+                &format!("[{}]", format_time(at, format)),
+                Style::new().dimmed(),
+            );
+            *first = format!("{stamp} {first}");
+        }
+        lines
+    }
+
+    fn render_lines(&mut self, value: &Value) -> Vec<String> {
+        let paint = |text: &str, style: Style| self.options.paint(text, style);
+        match TranscriptEvent::parse(value) {
+            TranscriptEvent::Silent => Vec::new(),
+            TranscriptEvent::Unrecognised => {
+                if self.options.verbose {
+                    vec![paint(&value.to_string(), Style::new().dimmed())]
+                } else {
+                    Vec::new()
+                }
+            }
+            TranscriptEvent::Prompt(message) => {
+                let mut lines = vec![paint("user", Style::new().bold().cyan())];
+                lines.extend(message.lines().map(str::to_string));
+                lines.push(String::new());
+                lines
+            }
+            TranscriptEvent::AgentMessage(text) => {
+                let lines = vec![paint("codex", Style::new().bold().magenta()), text.clone()];
+                self.last_agent_message = Some(text);
+                lines
+            }
+            TranscriptEvent::Reasoning(text) => vec![
+                paint("thinking", Style::new().italic().dimmed()),
+                text,
+                String::new(),
+            ],
+            TranscriptEvent::CommandStarted(command) => vec![
+                paint("exec", Style::new().bold().yellow()),
+                command,
+                paint("running", Style::new().dimmed()),
+            ],
+            TranscriptEvent::Command {
+                command,
+                exit_code,
+                status,
+                output,
+            } => {
                 let status_line = if exit_code == 0 {
-                    format!("succeeded (exit {exit_code})")
+                    paint(
+                        &format!("succeeded (exit {exit_code})"),
+                        Style::new().green(),
+                    )
                 } else {
-                    format!("exited {exit_code} ({status})")
+                    paint(
+                        &format!("exited {exit_code} ({status})"),
+                        Style::new().red(),
+                    )
                 };
-                lines.push(status_line);
-                if let Some(output) = item
-                    .get("aggregated_output")
-                    .and_then(Value::as_str)
-                    .filter(|s| !s.trim().is_empty())
-                {
-                    for line in output.lines() {
-                        lines.push(line.to_string());
-                    }
+                let mut lines = vec![
+                    paint("exec", Style::new().bold().yellow()),
+                    command,
+                    status_line,
+                ];
+                let output: Vec<&str> = output.lines().collect();
+                let limit = self.options.max_output_lines.unwrap_or(output.len());
+                lines.extend(output.iter().take(limit).map(|line| line.to_string()));
+                if output.len() > limit {
+                    let omitted = output.len() - limit;
+                    let noun = if omitted == 1 { "line" } else { "lines" };
+                    lines.push(paint(
+                        &format!("… {omitted} more {noun}"),
+                        Style::new().dimmed(),
+                    ));
                 }
                 lines
             }
-            "file_change" => render_file_change_item(item),
-            "web_search" => item
-                .get("query")
-                .and_then(Value::as_str)
-                .map(|query| vec![format!("🌐 Searched: {query}")])
-                .unwrap_or_default(),
-            "mcp_tool_call" => {
-                let server = item
-                    .get("server")
-                    .and_then(Value::as_str)
-                    .unwrap_or("server");
-                let tool = item.get("tool").and_then(Value::as_str).unwrap_or("tool");
-                let status = item
-                    .get("status")
-                    .and_then(Value::as_str)
-                    .unwrap_or("completed");
-                vec![format!("tool {server}.{tool} {status}")]
+            TranscriptEvent::FileChange { status, changes } => {
+                let mut lines = vec![paint(
+                    &format!("file update ({status})"),
+                    Style::new().bold().blue(),
+                )];
+                lines.extend(
+                    changes
+                        .iter()
+                        .map(|(marker, path)| format!("{marker} {path}")),
+                );
+                lines
+            }
+            TranscriptEvent::WebSearch(query) => {
+                vec![paint(&format!("🌐 Searched: {query}"), Style::new().blue())]
+            }
+            TranscriptEvent::ToolCall {
+                server,
+                tool,
+                status,
+            } => vec![paint(
+                &format!("tool {server}.{tool} {status}"),
+                Style::new().blue(),
+            )],
+            TranscriptEvent::TodoList(entries) => {
+                let mut lines = vec![paint("plan", Style::new().bold().green())];
+                lines.extend(entries.iter().map(|(completed, text)| {
+                    let mark = if *completed { "[x]" } else { "[ ]" };
+                    format!("{mark} {text}")
+                }));
+                lines
+            }
+            TranscriptEvent::TurnCompleted(tokens) => {
+                let lines = vec![
+                    paint("tokens used", Style::new().dimmed()),
+                    format_tokens(tokens),
+                ];
+                self.tokens_used = self.tokens_used.saturating_add(tokens);
+                lines
+            }
+            TranscriptEvent::Error(message) => {
+                vec![paint(
+                    &format!("ERROR: {message}"),
+                    Style::new().bold().red(),
+                )]
+            }
+            TranscriptEvent::Stderr(message) => {
+                vec![paint(&format!("[stderr] {message}"), Style::new().red())]
+            }
+            TranscriptEvent::Retry(line) => vec![paint(&line, Style::new().yellow())],
+            TranscriptEvent::Hook { summary, output } => {
+                let mut lines = vec![paint(&summary, Style::new().blue())];
+                lines.extend(output.iter().map(|line| format!("  {line}")));
+                lines
             }
-            _ => Vec::new(),
         }
     }
+}
 
-    fn render_turn_completed(&mut self, value: &Value) -> Vec<String> {
-        let usage = match value.get("usage") {
-            Some(u) => u,
-            None => return Vec::new(),
-        };
-
-        let total = usage
-            .get("total_tokens")
-            .and_then(Value::as_u64)
-            .or_else(|| {
-                usage
-                    .get("total_token_usage")
-                    .and_then(|v| v.get("blended_total"))
-                    .and_then(Value::as_u64)
-            })
-            .unwrap_or_else(|| {
-                let input = usage
-                    .get("input_tokens")
-                    .and_then(Value::as_u64)
-                    .unwrap_or_default();
-                let cached = usage
-                    .get("cached_input_tokens")
-                    .and_then(Value::as_u64)
-                    .unwrap_or_default();
-                let output = usage
-                    .get("output_tokens")
-                    .and_then(Value::as_u64)
-                    .unwrap_or_default();
-                input.saturating_sub(cached) + output
-            });
-
-        let total_capped = if total > i64::MAX as u64 {
-            i64::MAX
-        } else {
-            total as i64
-        };
-
-        vec![
-            "tokens used".to_string(),
-            format_with_separators(total_capped),
-        ]
+fn render_retry(value: &Value) -> String {
+    let number = |key: &str| value.get(key).and_then(Value::as_u64).unwrap_or_default();
+    let mut line = format!(
+        "RETRY: attempt {} of {} in {}s",
+        number("attempt"),
+        number("max_retries"),
+        number("delay_secs")
+    );
+    if let Some(reason) = value.get("reason").and_then(Value::as_str) {
+        line.push_str(&format!(" ({reason})"));
     }
+    line
 }
 
-fn render_user_message(value: &Value) -> Vec<String> {
-    let message = value
-        .get("message")
-        .and_then(Value::as_str)
-        .unwrap_or_default();
-    if message.is_empty() {
-        return Vec::new();
+fn parse_hook_run(value: &Value) -> TranscriptEvent {
+    let text = |key: &str| value.get(key).and_then(Value::as_str).unwrap_or_default();
+    let outcome = if value.get("timed_out").and_then(Value::as_bool) == Some(true) {
+        "timed out".to_string()
+    } else if let Some(error) = value.get("error").and_then(Value::as_str) {
+        error.to_string()
+    } else {
+        match value.get("exit_code").and_then(Value::as_i64) {
+            Some(code) => format!("exited {code}"),
+            None => "killed".to_string(),
+        }
+    };
+    let mut output = Vec::new();
+    for stream in ["stdout", "stderr"] {
+        output.extend(text(stream).lines().map(str::to_string));
     }
-
-    let mut lines = Vec::new();
-    lines.push("user".to_string());
-    for line in message.lines() {
-        lines.push(line.to_string());
+    TranscriptEvent::Hook {
+        summary: format!("HOOK {}: {} ({outcome})", text("event"), text("command")),
+        output,
     }
-    lines.push(String::new());
-    lines
 }
 
-fn render_file_change_item(item: &Value) -> Vec<String> {
-    let status = item
-        .get("status")
-        .and_then(Value::as_str)
-        .unwrap_or("completed");
-    let mut lines = vec![format!("file update ({status})")];
+fn parse_file_change(item: &Value) -> TranscriptEvent {
+    let status = text_field(item, "status")
+        .unwrap_or("completed")
+        .to_string();
+    let mut changes = Vec::new();
 
-    if let Some(changes) = item.get("changes").and_then(Value::as_array) {
-        for change in changes {
+    if let Some(entries) = item.get("changes").and_then(Value::as_array) {
+        for change in entries {
             let path = change
                 .get("path")
                 .and_then(Value::as_str)
@@ -536,17 +980,21 @@ fn render_file_change_item(item: &Value) -> Vec<String> {
                 "update" => 'M',
                 _ => '?',
             };
-            lines.push(format!("{marker} {path}"));
+            changes.push((marker, path.to_string()));
         }
     }
 
-    lines
+    TranscriptEvent::FileChange { status, changes }
 }
 
-struct FollowContext {
-    task_id: String,
-    metadata: FollowMetadata,
-    forever: bool,
+pub(crate) struct FollowContext {
+    pub(crate) task_id: String,
+    pub(crate) metadata: FollowMetadata,
+    pub(crate) forever: bool,
+    /// Suppress the notice printed when following ends because the task went idle.
+    pub(crate) quiet: bool,
+    /// Stops following once set (the flag is cleared), e.g. by a Ctrl-C handler.
+    pub(crate) interrupt: Option<&'static AtomicBool>,
 }
 
 impl FollowContext {
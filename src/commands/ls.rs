@@ -1,48 +1,327 @@
-use std::io::Write;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 
-use anyhow::Result;
-use tabwriter::TabWriter;
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value, json};
 
-use crate::cli::LsArgs;
-use crate::tasks::{ListTasksOptions, TaskService};
-use crate::timefmt::format_time;
+use crate::cli::{LsArgs, LsColumn, LsFormat};
+use crate::commands::common;
+use crate::commands::log::log_tokens_used;
+use crate::tasks::{ListTasksOptions, TaskListEntry, TaskService, TaskState, TasksConfig};
+use crate::timefmt::{TimeFormat, format_elapsed, format_time};
 
-pub fn handle_ls(args: LsArgs) -> Result<()> {
-    let service = TaskService::with_default_store(false)?;
+/// Columns shown when `--columns` is not provided.
+pub const DEFAULT_LS_COLUMNS: &[LsColumn] = &[
+    LsColumn::Id,
+    LsColumn::Title,
+    LsColumn::State,
+    LsColumn::Created,
+    LsColumn::Updated,
+    LsColumn::WorkingDir,
+];
+
+pub fn handle_ls(args: LsArgs, config: TasksConfig) -> Result<()> {
+    if args.tree && args.format != LsFormat::Table {
+        bail!("--tree is only supported with the table format");
+    }
+
+    let service = TaskService::with_default_store(config, false)?;
     let tasks = service.list_tasks(ListTasksOptions {
         include_archived: args.include_archived,
         states: args.states.clone(),
+        tags: args.tags.clone(),
+        title: args.title.clone(),
     })?;
 
-    if tasks.is_empty() {
+    let columns = if args.columns.is_empty() {
+        DEFAULT_LS_COLUMNS.to_vec()
+    } else {
+        args.columns.clone()
+    };
+    let needs_tokens = columns.contains(&LsColumn::Tokens) || args.sort == Some(LsColumn::Tokens);
+    let now = Utc::now();
+
+    let mut rows: Vec<Row> = tasks
+        .into_iter()
+        .map(|entry| {
+            let tokens = if needs_tokens {
+                service
+                    .prepare_log_descriptor(&entry.metadata.id, false)
+                    .ok()
+                    .and_then(|descriptor| log_tokens_used(&descriptor.path).ok())
+            } else {
+                None
+            };
+            Row {
+                label: entry.metadata.id.clone(),
+                entry,
+                tokens,
+                now,
+            }
+        })
+        .collect();
+
+    if let Some(column) = args.sort {
+        rows.sort_by(|a, b| column.compare(a, b));
+        if args.desc {
+            rows.reverse();
+        }
+    }
+    if args.tree {
+        rows = tree_order(rows);
+    }
+    if let Some(limit) = args.limit {
+        rows.truncate(limit);
+    }
+
+    match args.format {
+        LsFormat::Table => print_table(&rows, &columns, args.time_format),
+        LsFormat::Json => {
+            let values: Vec<Value> = rows.iter().map(|row| row_to_json(row, &columns)).collect();
+            println!("{}", serde_json::to_string_pretty(&values)?);
+            Ok(())
+        }
+        LsFormat::Jsonl => {
+            for row in &rows {
+                println!("{}", serde_json::to_string(&row_to_json(row, &columns))?);
+            }
+            Ok(())
+        }
+        LsFormat::Csv => {
+            let header: Vec<String> = columns
+                .iter()
+                .map(|column| csv_field(column.key()))
+                .collect();
+            println!("{}", header.join(","));
+            for row in &rows {
+                let fields: Vec<String> = columns
+                    .iter()
+                    .map(|column| csv_field(&column.text_value(row)))
+                    .collect();
+                println!("{}", fields.join(","));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// A listed task together with values derived for display.
+struct Row {
+    /// Identifier as rendered in the table (prefixed with tree guides when using `--tree`).
+    label: String,
+    entry: TaskListEntry,
+    tokens: Option<u64>,
+    now: DateTime<Utc>,
+}
+
+impl Row {
+    /// Time between creation and the last update, or until now while the task is running.
+    fn duration(&self) -> chrono::Duration {
+        let metadata = &self.entry.metadata;
+        let end = if metadata.state == TaskState::Running {
+            self.now
+        } else {
+            metadata.updated_at
+        };
+        end - metadata.created_at
+    }
+}
+
+impl LsColumn {
+    fn header(self) -> &'static str {
+        match self {
+            LsColumn::Id => "ID",
+            LsColumn::Title => "Title",
+            LsColumn::State => "State",
+            LsColumn::Created => "Created At",
+            LsColumn::Updated => "Updated At",
+            LsColumn::Duration => "Duration",
+            LsColumn::WorkingDir => "Working Dir",
+            LsColumn::LastPrompt => "Last Prompt",
+            LsColumn::Tokens => "Tokens",
+        }
+    }
+
+    /// Field name used by the JSON, JSONL, and CSV formats.
+    fn key(self) -> &'static str {
+        match self {
+            LsColumn::Id => "id",
+            LsColumn::Title => "title",
+            LsColumn::State => "state",
+            LsColumn::Created => "created_at",
+            LsColumn::Updated => "updated_at",
+            LsColumn::Duration => "duration_secs",
+            LsColumn::WorkingDir => "working_dir",
+            LsColumn::LastPrompt => "last_prompt",
+            LsColumn::Tokens => "tokens",
+        }
+    }
+
+    fn table_value(self, row: &Row, time_format: TimeFormat) -> String {
+        let metadata = &row.entry.metadata;
+        let value = match self {
+            LsColumn::Id => row.label.clone(),
+            LsColumn::Created => format_time(metadata.created_at, time_format),
+            LsColumn::Updated => format_time(metadata.updated_at, time_format),
+            LsColumn::Duration => format_elapsed(row.duration()),
+            LsColumn::LastPrompt => metadata
+                .last_prompt
+                .as_deref()
+                .and_then(|prompt| prompt.lines().find(|line| !line.trim().is_empty()))
+                .unwrap_or_default()
+                .to_string(),
+            _ => self.text_value(row),
+        };
+        if value.is_empty() {
+            "-".to_string()
+        } else {
+            value.replace('\t', " ")
+        }
+    }
+
+    /// Plain-text value used by the CSV format; missing values are empty.
+    fn text_value(self, row: &Row) -> String {
+        let metadata = &row.entry.metadata;
+        match self {
+            LsColumn::Id => metadata.id.clone(),
+            LsColumn::Title => metadata.title.clone().unwrap_or_default(),
+            LsColumn::State => metadata.state.to_string(),
+            LsColumn::Created => metadata.created_at.to_rfc3339(),
+            LsColumn::Updated => metadata.updated_at.to_rfc3339(),
+            LsColumn::Duration => row.duration().num_seconds().to_string(),
+            LsColumn::WorkingDir => metadata.working_dir.clone().unwrap_or_default(),
+            LsColumn::LastPrompt => metadata.last_prompt.clone().unwrap_or_default(),
+            LsColumn::Tokens => row
+                .tokens
+                .map(|tokens| tokens.to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn json_value(self, row: &Row) -> Value {
+        let metadata = &row.entry.metadata;
+        match self {
+            LsColumn::Id => json!(metadata.id),
+            LsColumn::Title => json!(metadata.title),
+            LsColumn::State => json!(metadata.state),
+            LsColumn::Created => json!(metadata.created_at),
+            LsColumn::Updated => json!(metadata.updated_at),
+            LsColumn::Duration => json!(row.duration().num_seconds()),
+            LsColumn::WorkingDir => json!(metadata.working_dir),
+            LsColumn::LastPrompt => json!(metadata.last_prompt),
+            LsColumn::Tokens => json!(row.tokens),
+        }
+    }
+
+    fn compare(self, a: &Row, b: &Row) -> Ordering {
+        let (left, right) = (&a.entry.metadata, &b.entry.metadata);
+        match self {
+            LsColumn::Id => left.id.cmp(&right.id),
+            LsColumn::Title => left.title.cmp(&right.title),
+            LsColumn::State => left.state.as_str().cmp(right.state.as_str()),
+            LsColumn::Created => left.created_at.cmp(&right.created_at),
+            LsColumn::Updated => left.updated_at.cmp(&right.updated_at),
+            LsColumn::Duration => a.duration().cmp(&b.duration()),
+            LsColumn::WorkingDir => left.working_dir.cmp(&right.working_dir),
+            LsColumn::LastPrompt => left.last_prompt.cmp(&right.last_prompt),
+            LsColumn::Tokens => a.tokens.cmp(&b.tokens),
+        }
+    }
+}
+
+fn print_table(rows: &[Row], columns: &[LsColumn], time_format: TimeFormat) -> Result<()> {
+    if rows.is_empty() {
         println!("No tasks found.");
         return Ok(());
     }
 
-    let time_format = args.time_format;
+    let header: Vec<&str> = columns.iter().map(|column| column.header()).collect();
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            columns
+                .iter()
+                .map(|column| column.table_value(row, time_format))
+                .collect()
+        })
+        .collect();
+    common::print_table(&header, &cells)
+}
+
+fn row_to_json(row: &Row, columns: &[LsColumn]) -> Value {
+    let mut object = Map::new();
+    for column in columns {
+        object.insert(column.key().to_string(), column.json_value(row));
+    }
+    Value::Object(object)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
 
-    let mut buffer = Vec::new();
-    {
-        let mut writer = TabWriter::new(&mut buffer).padding(2);
-        writeln!(
-            &mut writer,
-            "ID\tTitle\tState\tCreated At\tUpdated At\tWorking Dir"
-        )?;
-        for entry in tasks {
-            let title = entry.metadata.title.as_deref().unwrap_or("-");
-            let created = format_time(entry.metadata.created_at, time_format);
-            let updated = format_time(entry.metadata.updated_at, time_format);
-            let working_dir = entry.metadata.working_dir.as_deref().unwrap_or("-");
-            writeln!(
-                &mut writer,
-                "{}\t{}\t{}\t{}\t{}\t{}",
-                entry.metadata.id, title, entry.metadata.state, created, updated, working_dir
-            )?;
+/// Orders entries depth-first by parent/child relationship, labelling each with a tree prefix.
+/// Tasks whose parent is not part of the listing are shown as roots.
+fn tree_order(rows: Vec<Row>) -> Vec<Row> {
+    let ids: HashSet<String> = rows
+        .iter()
+        .map(|row| row.entry.metadata.id.clone())
+        .collect();
+    let mut roots = Vec::new();
+    let mut children: HashMap<String, Vec<Row>> = HashMap::new();
+    for row in rows {
+        match row.entry.metadata.parent_id.clone() {
+            Some(parent) if ids.contains(&parent) && parent != row.entry.metadata.id => {
+                children.entry(parent).or_default().push(row);
+            }
+            _ => roots.push(row),
         }
-        writer.flush()?;
     }
 
-    print!("{}", String::from_utf8(buffer)?);
+    let mut ordered = Vec::new();
+    for row in roots {
+        push_subtree(row, "", None, &mut children, &mut ordered);
+    }
+    // Entries caught in a parent cycle are never reached from a root; list them flat.
+    ordered.extend(children.into_values().flatten());
+    ordered
+}
+
+fn push_subtree(
+    mut row: Row,
+    indent: &str,
+    is_last: Option<bool>,
+    children: &mut HashMap<String, Vec<Row>>,
+    rows: &mut Vec<Row>,
+) {
+    let id = row.entry.metadata.id.clone();
+    let child_indent = match is_last {
+        None => String::new(),
+        Some(true) => {
+            row.label = format!("{indent}└─ {id}");
+            format!("{indent}   ")
+        }
+        Some(false) => {
+            row.label = format!("{indent}├─ {id}");
+            format!("{indent}│  ")
+        }
+    };
+    let kids = children.remove(&id).unwrap_or_default();
+    rows.push(row);
 
-    Ok(())
+    let count = kids.len();
+    for (index, child) in kids.into_iter().enumerate() {
+        push_subtree(
+            child,
+            &child_indent,
+            Some(index + 1 == count),
+            children,
+            rows,
+        );
+    }
 }
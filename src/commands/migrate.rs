@@ -0,0 +1,34 @@
+use anyhow::{Result, bail};
+
+use crate::tasks::{CURRENT_SCHEMA_VERSION, TaskService, TasksConfig};
+
+pub fn handle_migrate(config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    let report = service.migrate_store()?;
+
+    for (id, directory) in &report.relocated {
+        println!("Relocated legacy task {} into {}.", id, directory.display());
+    }
+
+    for task in &report.upgraded {
+        println!(
+            "Upgraded task {} from schema v{} to v{}.",
+            task.id, task.from_version, CURRENT_SCHEMA_VERSION
+        );
+    }
+
+    if !report.failures.is_empty() {
+        for (path, err) in &report.failures {
+            eprintln!("Failed to migrate {}: {err:#}", path.display());
+        }
+        bail!("failed to migrate {} task(s)", report.failures.len());
+    }
+
+    println!(
+        "Migrated {} task(s); {} already current.",
+        report.upgraded.len(),
+        report.current
+    );
+
+    Ok(())
+}
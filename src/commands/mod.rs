@@ -1,21 +1,56 @@
 pub mod archive;
+pub mod attach;
 pub mod common;
+pub mod completions;
+pub mod config;
+pub mod diff;
+pub mod doctor;
+pub mod export;
+pub mod fork;
+pub mod hooks;
 pub mod log;
 pub mod ls;
+pub mod migrate;
+pub mod pipeline;
+pub mod queue;
+pub mod result;
+pub mod retry;
+pub mod schedule;
+pub mod search;
 pub mod send;
 pub mod start;
 pub mod status;
 pub mod stop;
 pub mod tasks;
+pub mod wait;
+pub mod watch;
+pub mod webhook;
 pub mod worker;
 
 pub use archive::handle_archive;
+pub use attach::handle_attach;
+pub use completions::{handle_complete_task_ids, handle_completions};
+pub use config::handle_config;
+pub use diff::handle_diff;
+pub use doctor::handle_doctor;
+pub use fork::handle_fork;
+pub use hooks::handle_hooks;
 pub use log::handle_log;
 pub use ls::handle_ls;
+pub use migrate::handle_migrate;
+pub use pipeline::handle_pipeline;
+pub use queue::handle_queue;
+pub use result::handle_result;
+pub use retry::handle_retry;
+pub use schedule::{handle_schedule, handle_scheduler};
+pub use search::handle_search;
 pub use send::handle_send;
 pub use start::handle_start;
 pub use status::handle_status;
 pub use stop::handle_stop;
+pub use wait::handle_wait;
+pub use watch::handle_watch;
+pub use webhook::handle_webhook;
 pub use worker::handle_worker;
 
 use anyhow::bail;
@@ -0,0 +1,86 @@
+use anyhow::{Result, bail};
+use serde_json::json;
+
+use crate::cli::{PipelineArgs, PipelineCommand, PipelineRunArgs};
+use crate::commands::common::print_table;
+use crate::tasks::pipeline::run_pipeline;
+use crate::tasks::{StepState, TaskService, TasksConfig, load_pipeline};
+
+pub fn handle_pipeline(args: PipelineArgs, config: TasksConfig) -> Result<()> {
+    let PipelineCommand::Run(args) = args.command;
+    run(args, config)
+}
+
+fn run(args: PipelineRunArgs, config: TasksConfig) -> Result<()> {
+    let steps = load_pipeline(&args.file)?;
+    if args.validate {
+        for step in &steps {
+            if step.after.is_empty() {
+                println!("{}", step.key);
+            } else {
+                println!("{} (after {})", step.key, step.after.join(", "));
+            }
+        }
+        eprintln!("Pipeline is valid: {} step(s).", steps.len());
+        return Ok(());
+    }
+
+    let service = TaskService::with_default_store(config, false)?;
+    let quiet = args.json;
+    let report = run_pipeline(&service, &steps, |status| {
+        if quiet {
+            return;
+        }
+        let task = status.task_id.as_deref().unwrap_or("-");
+        match &status.detail {
+            Some(detail) => eprintln!(
+                "[{}] {} {task}: {detail}",
+                status.key,
+                status.state.as_str()
+            ),
+            None => eprintln!("[{}] {} {task}", status.key, status.state.as_str()),
+        }
+    })?;
+    let failed = report
+        .steps
+        .iter()
+        .filter(|step| step.state != StepState::Stopped)
+        .count();
+
+    if args.json {
+        let payload = json!({
+            "status": if report.succeeded() { "succeeded" } else { "failed" },
+            "elapsed_secs": report.elapsed.as_secs_f64(),
+            "steps": report.steps,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+    } else {
+        let rows: Vec<Vec<String>> = report
+            .steps
+            .iter()
+            .map(|step| {
+                vec![
+                    step.key.clone(),
+                    step.state.as_str().to_string(),
+                    step.task_id.as_deref().unwrap_or("-").to_string(),
+                    step.detail.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+        print_table(&["STEP", "STATE", "TASK ID", "DETAIL"], &rows)?;
+    }
+
+    if failed > 0 {
+        bail!(
+            "pipeline failed: {failed} of {} step(s) did not finish",
+            report.steps.len()
+        );
+    }
+    if !args.json {
+        eprintln!(
+            "Pipeline succeeded in {:.1}s.",
+            report.elapsed.as_secs_f64()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde_json::json;
+
+use crate::cli::QueueArgs;
+use crate::commands::common::print_table;
+use crate::tasks::{TaskService, TasksConfig};
+use crate::timefmt::format_elapsed;
+
+pub fn handle_queue(args: QueueArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    if args.unlimited {
+        service.set_max_running(None)?;
+    } else if let Some(max_running) = args.max_running {
+        service.set_max_running(Some(max_running))?;
+    }
+
+    let report = service.queue_report()?;
+    if args.json {
+        let queued: Vec<_> = report
+            .queued
+            .iter()
+            .map(|metadata| {
+                let queued = metadata.queued.as_ref();
+                json!({
+                    "id": metadata.id,
+                    "title": metadata.title,
+                    "priority": queued.map(|queued| queued.priority),
+                    "queued_at": queued.map(|queued| queued.queued_at),
+                })
+            })
+            .collect();
+        let payload = json!({
+            "max_running": report.max_running,
+            "running": report.running,
+            "queued": queued,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    match report.max_running {
+        Some(max) => println!("Running: {} of {max}", report.running),
+        None => println!("Running: {} (no limit)", report.running),
+    }
+    if report.queued.is_empty() {
+        println!("No queued tasks.");
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let rows: Vec<Vec<String>> = report
+        .queued
+        .iter()
+        .enumerate()
+        .map(|(index, metadata)| {
+            let (priority, queued_at) = metadata
+                .queued
+                .as_ref()
+                .map_or((0, metadata.updated_at), |queued| {
+                    (queued.priority, queued.queued_at)
+                });
+            vec![
+                (index + 1).to_string(),
+                metadata.id.clone(),
+                priority.to_string(),
+                format_elapsed(now - queued_at),
+                metadata.title.as_deref().unwrap_or("-").to_string(),
+            ]
+        })
+        .collect();
+    print_table(&["POS", "TASK ID", "PRIORITY", "WAITING", "TITLE"], &rows)
+}
@@ -0,0 +1,58 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, bail};
+
+use crate::cli::ResultArgs;
+use crate::tasks::answer::{code_blocks, structured_payload};
+use crate::tasks::{TaskService, TaskState, TasksConfig};
+use crate::timefmt::format_duration;
+
+const WAIT_POLL_INTERVAL_MS: u64 = 300;
+
+pub fn handle_result(args: ResultArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    if args.wait {
+        let timeout = args.timeout.or(service.config().wait_timeout);
+        let started = Instant::now();
+        loop {
+            let state = service.get_status(&args.task_id)?.metadata.state;
+            if !matches!(state, TaskState::Running | TaskState::Queued) {
+                break;
+            }
+            if let Some(timeout) = timeout.filter(|timeout| started.elapsed() >= *timeout) {
+                bail!(
+                    "task {} is still {state} after waiting {}",
+                    args.task_id,
+                    format_duration(timeout)
+                );
+            }
+            sleep(Duration::from_millis(WAIT_POLL_INTERVAL_MS));
+        }
+    }
+
+    let Some(answer) = service.task_answer(&args.task_id, args.invocation)? else {
+        bail!("task {} has no result yet", args.task_id);
+    };
+
+    if args.json {
+        let payload = structured_payload(&answer)?;
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+    } else if args.code_blocks {
+        let blocks = code_blocks(&answer, args.lang.as_deref());
+        if blocks.is_empty() {
+            match &args.lang {
+                Some(lang) => bail!("the result contains no `{lang}` code blocks"),
+                None => bail!("the result contains no code blocks"),
+            }
+        }
+        for block in blocks {
+            print!("{}", block.code);
+        }
+    } else if answer.ends_with('\n') {
+        print!("{answer}");
+    } else {
+        println!("{answer}");
+    }
+    Ok(())
+}
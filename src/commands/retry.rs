@@ -0,0 +1,13 @@
+use anyhow::Result;
+
+use crate::cli::RetryArgs;
+use crate::tasks::{RetryTaskParams, TaskService, TasksConfig};
+
+pub fn handle_retry(args: RetryArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    service.retry_task(RetryTaskParams {
+        task_id: args.task_id,
+        prompt: args.prompt,
+    })?;
+    Ok(())
+}
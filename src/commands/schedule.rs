@@ -0,0 +1,178 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{Timelike, Utc};
+
+use crate::cli::{
+    ScheduleAddArgs, ScheduleArgs, ScheduleCommand, ScheduleLsArgs, SchedulerArgs, SchedulerCommand,
+};
+use crate::commands::common::print_table;
+use crate::tasks::{
+    AddScheduleParams, Schedule, ScheduleRun, ScheduleRunOutcome, ScheduleTarget, TaskService,
+    TasksConfig,
+};
+use crate::timefmt::{TimeFormat, format_time};
+
+pub fn handle_schedule(args: ScheduleArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    match args.command {
+        ScheduleCommand::Add(args) => add(&service, args),
+        ScheduleCommand::Ls(args) => list(&service, args),
+        ScheduleCommand::Rm(args) => {
+            let removed = service.remove_schedule(&args.id)?;
+            println!("Schedule {} removed.", removed.id);
+            Ok(())
+        }
+    }
+}
+
+fn add(service: &TaskService, args: ScheduleAddArgs) -> Result<()> {
+    let target = match args.task_id {
+        Some(task_id) => ScheduleTarget::Task { task_id },
+        None => ScheduleTarget::New {
+            title: args.title,
+            working_dir: args
+                .working_dir
+                .map(|dir| dir.to_string_lossy().into_owned()),
+            tags: args.tags,
+        },
+    };
+    let schedule = service.add_schedule(AddScheduleParams {
+        cron: args.cron,
+        prompt: args.prompt,
+        target,
+    })?;
+    println!("{}", schedule.id);
+    eprintln!(
+        "Next run: {}. Runs happen while `codex-tasks scheduler run` is active.",
+        format_time(schedule.next_run, TimeFormat::Human)
+    );
+    Ok(())
+}
+
+fn list(service: &TaskService, args: ScheduleLsArgs) -> Result<()> {
+    let schedules = service.list_schedules()?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&schedules)?);
+        return Ok(());
+    }
+    if schedules.is_empty() {
+        println!("No schedules.");
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = schedules
+        .iter()
+        .map(|schedule| {
+            let last_run = schedule.history.last().map_or_else(
+                || "-".to_string(),
+                |run| {
+                    format!(
+                        "{} {}",
+                        run.outcome.as_str(),
+                        format_time(run.at, args.time_format)
+                    )
+                },
+            );
+            vec![
+                schedule.id.clone(),
+                schedule.cron.clone(),
+                describe_target(schedule),
+                if schedule.disabled {
+                    "disabled".to_string()
+                } else {
+                    format_time(schedule.next_run, args.time_format)
+                },
+                last_run,
+                schedule
+                    .prompt
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+            ]
+        })
+        .collect();
+    print_table(
+        &["ID", "CRON", "TARGET", "NEXT RUN", "LAST RUN", "PROMPT"],
+        &rows,
+    )
+}
+
+fn describe_target(schedule: &Schedule) -> String {
+    match &schedule.target {
+        ScheduleTarget::Task { task_id } => task_id.clone(),
+        ScheduleTarget::New {
+            title: Some(title), ..
+        } => format!("new ({title})"),
+        ScheduleTarget::New { .. } => "new".to_string(),
+    }
+}
+
+pub fn handle_scheduler(args: SchedulerArgs, config: TasksConfig) -> Result<()> {
+    let SchedulerCommand::Run(args) = args.command;
+    let service = TaskService::with_default_store(config, false)?;
+    if !args.once {
+        eprintln!(
+            "Scheduler running with {} schedule(s); press Ctrl-C to stop.",
+            service.list_schedules()?.len()
+        );
+    }
+
+    loop {
+        let runs = match service.run_due_schedules(Utc::now()) {
+            Ok(runs) => runs,
+            Err(err) if args.once => return Err(err),
+            Err(err) => {
+                eprintln!("failed to run due schedules: {err:#}");
+                Vec::new()
+            }
+        };
+        for (schedule, run) in runs {
+            println!(
+                "{} schedule {}: {}",
+                format_time(run.at, TimeFormat::Iso),
+                schedule.id,
+                describe_run(&run)
+            );
+        }
+        match service.apply_retention() {
+            Ok(summary) => {
+                for (id, _) in &summary.archived {
+                    println!(
+                        "{} retention: archived {id}",
+                        format_time(Utc::now(), TimeFormat::Iso)
+                    );
+                }
+                for (id, err) in &summary.failures {
+                    eprintln!("failed to archive task {id} past its retention: {err:#}");
+                }
+            }
+            Err(err) => eprintln!("failed to archive tasks past their retention: {err:#}"),
+        }
+        if args.once {
+            return Ok(());
+        }
+        // Cron has minute resolution, so waking at the start of each minute is enough.
+        let now = Utc::now();
+        let elapsed = Duration::new(now.second() as u64, now.nanosecond() % 1_000_000_000);
+        sleep(Duration::from_secs(60).saturating_sub(elapsed));
+    }
+}
+
+fn describe_run(run: &ScheduleRun) -> String {
+    let task = run.task_id.as_deref().unwrap_or("-");
+    match run.outcome {
+        ScheduleRunOutcome::Started => format!("started {task}"),
+        ScheduleRunOutcome::Queued => format!("queued {task}"),
+        ScheduleRunOutcome::Skipped => format!(
+            "skipped {task} ({})",
+            run.detail.as_deref().unwrap_or("busy")
+        ),
+        ScheduleRunOutcome::Failed => format!(
+            "failed: {}",
+            run.detail.as_deref().unwrap_or("unknown error")
+        ),
+    }
+}
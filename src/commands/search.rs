@@ -0,0 +1,39 @@
+use anyhow::Result;
+
+use crate::cli::SearchArgs;
+use crate::tasks::search::compile_pattern;
+use crate::tasks::{SearchQuery, TaskService, TasksConfig};
+
+pub fn handle_search(args: SearchArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    let query = SearchQuery {
+        pattern: compile_pattern(&args.pattern, args.fixed_strings, args.ignore_case)?,
+        fields: args.fields,
+        since: args.since,
+        until: args.until,
+        include_archived: args.include_archived,
+        limit: args.limit,
+    };
+    let hits = service.search(&query)?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&hits)?);
+        return Ok(());
+    }
+
+    if hits.is_empty() {
+        println!("No matches found.");
+        return Ok(());
+    }
+
+    for hit in &hits {
+        println!(
+            "{} #{} [{}] {}",
+            hit.task_id,
+            hit.invocation,
+            hit.field.as_str(),
+            hit.text
+        );
+    }
+    Ok(())
+}
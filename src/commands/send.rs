@@ -1,12 +1,18 @@
 use anyhow::Result;
 
 use crate::cli::SendArgs;
-use crate::tasks::{SendPromptParams, TaskService};
+use crate::tasks::{LaunchOutcome, SendPromptParams, TaskService, TasksConfig};
 
-pub fn handle_send(args: SendArgs) -> Result<()> {
-    let service = TaskService::with_default_store(false)?;
-    service.send_prompt(SendPromptParams {
+pub fn handle_send(args: SendArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    let task_id = args.task_id.clone();
+    let outcome = service.send_prompt(SendPromptParams {
         task_id: args.task_id,
         prompt: args.prompt,
-    })
+        priority: args.priority,
+    })?;
+    if outcome == LaunchOutcome::Queued {
+        eprintln!("Task {task_id} is QUEUED until the concurrency limit allows it to run.");
+    }
+    Ok(())
 }
@@ -1,11 +1,21 @@
 use std::io::{self, Read};
+use std::path::Path;
 
 use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value, json};
 
 use crate::cli::StartArgs;
-use crate::tasks::{StartTaskParams, TaskService};
+use crate::commands::common::print_table;
+use crate::tasks::{
+    ExecOptions, Hooks, LaunchOutcome, ManifestTask, RetryPolicy, StartTaskParams, TaskService,
+    TasksConfig, load_manifest,
+};
+
+pub fn handle_start(args: StartArgs, config: TasksConfig) -> Result<()> {
+    if let Some(manifest) = &args.manifest {
+        return start_manifest(manifest, args.validate, args.json, config);
+    }
 
-pub fn handle_start(args: StartArgs) -> Result<()> {
     let StartArgs {
         title,
         prompt,
@@ -13,11 +23,22 @@ pub fn handle_start(args: StartArgs) -> Result<()> {
         working_dir,
         repo,
         repo_ref,
+        parent,
+        retries,
+        retry_backoff,
+        tags,
+        priority,
+        hooks,
+        hook_timeout,
+        preset,
+        env,
+        timeout,
+        ..
     } = args;
 
-    let prompt = resolve_start_prompt(prompt)?;
+    let prompt = resolve_start_prompt(prompt.unwrap_or_default())?;
 
-    let service = TaskService::with_default_store(false)?;
+    let service = TaskService::with_default_store(config, false)?;
     let result = service.start_task(StartTaskParams {
         title,
         prompt,
@@ -25,10 +46,126 @@ pub fn handle_start(args: StartArgs) -> Result<()> {
         working_dir,
         repo_url: repo,
         repo_ref,
+        parent_id: parent,
+        retry_policy: retries.map(|max_retries| RetryPolicy {
+            max_retries,
+            backoff_secs: retry_backoff.as_secs(),
+        }),
+        tags,
+        priority,
+        hooks: Hooks::from_specs(hooks, hook_timeout.map(|timeout| timeout.as_secs())),
+        preset,
+        exec: Some(ExecOptions {
+            args: Vec::new(),
+            env: env.into_iter().collect(),
+            timeout_secs: timeout.map(|timeout| timeout.as_secs()),
+        }),
     })?;
 
     println!("{}", result.thread_id);
+    if result.outcome == LaunchOutcome::Queued {
+        eprintln!(
+            "Task {} is QUEUED until the concurrency limit allows it to run.",
+            result.thread_id
+        );
+    }
+
+    Ok(())
+}
+
+/// Starts (or, with `validate`, only checks) every task described by a manifest.
+fn start_manifest(path: &Path, validate: bool, json: bool, config: TasksConfig) -> Result<()> {
+    let tasks = load_manifest(path)?;
+    if let Some(task) = tasks.iter().find(|task| !task.after.is_empty()) {
+        bail!(
+            "entry `{}` declares `after`; run {} with `codex-tasks pipeline run` instead",
+            task.key,
+            path.display()
+        );
+    }
+    if validate {
+        return print_plan(&tasks, json);
+    }
+
+    let service = TaskService::with_default_store(config, false)?;
+    let mut started = Vec::with_capacity(tasks.len());
+    let mut failed = 0;
+    for ManifestTask { key, params, .. } in tasks {
+        match service.start_task(params) {
+            Ok(result) => started.push((key, result.thread_id)),
+            Err(err) => {
+                eprintln!("failed to start `{key}`: {err:#}");
+                failed += 1;
+            }
+        }
+    }
+
+    if json {
+        let map: Map<String, Value> = started
+            .iter()
+            .map(|(key, id)| (key.clone(), json!(id)))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&map)?);
+    } else if !started.is_empty() {
+        let rows: Vec<Vec<String>> = started
+            .iter()
+            .map(|(key, id)| vec![key.clone(), id.clone()])
+            .collect();
+        print_table(&["NAME", "TASK ID"], &rows)?;
+    }
+
+    if failed > 0 {
+        bail!(
+            "{failed} of {} manifest tasks failed to start",
+            started.len() + failed
+        );
+    }
+    Ok(())
+}
+
+fn print_plan(tasks: &[ManifestTask], json: bool) -> Result<()> {
+    if json {
+        let map: Map<String, Value> = tasks
+            .iter()
+            .map(|task| {
+                let params = &task.params;
+                let value = json!({
+                    "title": params.title,
+                    "prompt": params.prompt,
+                    "working_dir": params.working_dir,
+                    "repo": params.repo_url,
+                    "ref": params.repo_ref,
+                    "config_file": params.config_file,
+                    "parent": params.parent_id,
+                    "retry_policy": params.retry_policy,
+                    "tags": params.tags,
+                });
+                (task.key.clone(), value)
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&map)?);
+        return Ok(());
+    }
 
+    let rows: Vec<Vec<String>> = tasks
+        .iter()
+        .map(|task| {
+            let params = &task.params;
+            vec![
+                task.key.clone(),
+                params.title.clone().unwrap_or_default(),
+                params
+                    .working_dir
+                    .as_ref()
+                    .map(|dir| dir.display().to_string())
+                    .unwrap_or_else(|| ".".to_string()),
+                params.repo_url.clone().unwrap_or_default(),
+                params.tags.join(","),
+            ]
+        })
+        .collect();
+    print_table(&["NAME", "TITLE", "WORKING DIR", "REPO", "TAGS"], &rows)?;
+    eprintln!("Manifest is valid: {} task(s).", tasks.len());
     Ok(())
 }
 
@@ -6,7 +6,9 @@ use anyhow::{Result, bail};
 use serde_json::json;
 
 use crate::cli::StatusArgs;
-use crate::tasks::{ListTasksOptions, TaskService, TaskState, TaskStatusSnapshot};
+use crate::tasks::{
+    HookEvent, ListTasksOptions, TaskService, TaskState, TaskStatusSnapshot, TasksConfig,
+};
 use crate::timefmt::{TimeFormat, format_time};
 
 /// Output format supported by the status command.
@@ -35,7 +37,7 @@ pub struct StatusCommandOptions {
     pub wait_mode: WaitMode,
 }
 
-pub fn handle_status(args: StatusArgs) -> Result<()> {
+pub fn handle_status(args: StatusArgs, config: TasksConfig) -> Result<()> {
     let format = if args.json {
         StatusFormat::Json
     } else {
@@ -50,18 +52,21 @@ pub fn handle_status(args: StatusArgs) -> Result<()> {
         WaitMode::None
     };
 
-    run(StatusCommandOptions {
-        task_ids: args.task_ids,
-        include_all: args.all,
-        include_all_running: args.all_running,
-        format,
-        time_format: args.time_format,
-        wait_mode,
-    })
+    run(
+        StatusCommandOptions {
+            task_ids: args.task_ids,
+            include_all: args.all,
+            include_all_running: args.all_running,
+            format,
+            time_format: args.time_format,
+            wait_mode,
+        },
+        config,
+    )
 }
 
-fn run(options: StatusCommandOptions) -> Result<()> {
-    let service = TaskService::with_default_store(false)?;
+fn run(options: StatusCommandOptions, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
     let targets = resolve_targets(&service, &options)?;
     if targets.is_empty() {
         bail!("no tasks matched the requested selectors");
@@ -151,6 +156,44 @@ fn render_human_record(record: &TaskStatusSnapshot, time_format: TimeFormat) {
         Some(dir) => println!("Working Dir: {}", dir),
         None => println!("Working Dir: <none>"),
     }
+    if let Some(parent) = &record.metadata.parent_id {
+        println!("Parent: {}", parent);
+    }
+    if let Some(source) = &record.metadata.forked_from {
+        match &record.metadata.fork_point {
+            Some(point) => println!(
+                "Forked From: {} (after prompt {}, {})",
+                source,
+                point.invocation,
+                format_time(point.at, time_format)
+            ),
+            None => println!("Forked From: {}", source),
+        }
+    }
+    if !record.metadata.tags.is_empty() {
+        println!("Tags: {}", record.metadata.tags.join(", "));
+    }
+    if let Some(preset) = &record.metadata.preset {
+        println!("Preset: {}", preset);
+    }
+    if record.metadata.retry_count > 0 {
+        println!("Retries: {}", record.metadata.retry_count);
+    }
+    if let Some(hooks) = &record.metadata.hooks {
+        let events: Vec<&str> = HookEvent::ALL
+            .iter()
+            .filter(|event| hooks.command(**event).is_some())
+            .map(HookEvent::as_str)
+            .collect();
+        println!("Hooks: {}", events.join(", "));
+    }
+    if let Some(queued) = &record.metadata.queued {
+        println!(
+            "Queued: priority {} since {}",
+            queued.priority,
+            format_time(queued.queued_at, time_format)
+        );
+    }
     if let Some(pid) = record.pid {
         println!("PID: {}", pid);
     }
@@ -193,6 +236,19 @@ fn status_to_json(record: &TaskStatusSnapshot) -> serde_json::Value {
         "last_prompt": record.metadata.last_prompt.clone(),
         "last_result": record.metadata.last_result.clone(),
         "working_dir": record.metadata.working_dir.clone(),
+        "parent_id": record.metadata.parent_id.clone(),
+        "forked_from": record.metadata.forked_from.clone(),
+        "fork_point": record.metadata.fork_point.clone(),
+        "retry_count": record.metadata.retry_count,
+        "retry_policy": record.metadata.retry_policy,
+        "tags": record.metadata.tags.clone(),
+        "hooks": record.metadata.hooks.clone(),
+        "preset": record.metadata.preset.clone(),
+        "exec": record.metadata.exec.clone(),
+        "queued": record.metadata.queued.as_ref().map(|queued| json!({
+            "priority": queued.priority,
+            "queued_at": queued.queued_at,
+        })),
         "pid": record.pid,
     })
 }
@@ -1,10 +1,10 @@
 use anyhow::Result;
 
 use crate::cli::StopArgs;
-use crate::tasks::{StopOutcome, TaskService};
+use crate::tasks::{StopOutcome, TaskService, TasksConfig};
 
-pub fn handle_stop(args: StopArgs) -> Result<()> {
-    let service = TaskService::with_default_store(false)?;
+pub fn handle_stop(args: StopArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
 
     if args.all {
         let reports = service.stop_all_running()?;
@@ -15,12 +15,14 @@ pub fn handle_stop(args: StopArgs) -> Result<()> {
 
         let mut stopped = 0usize;
         let mut already = 0usize;
+        let mut dequeued = 0usize;
 
         for report in reports {
             print_stop_outcome(&report.task_id, report.outcome);
             match report.outcome {
                 StopOutcome::Stopped => stopped += 1,
                 StopOutcome::AlreadyStopped => already += 1,
+                StopOutcome::Dequeued => dequeued += 1,
             }
         }
 
@@ -29,7 +31,25 @@ pub fn handle_stop(args: StopArgs) -> Result<()> {
             stopped = stopped,
             already = already
         );
+        if dequeued > 0 {
+            println!("Cancelled {dequeued} queued task(s).");
+        }
 
+        Ok(())
+    } else if args.recursive {
+        let task_id = args
+            .task_id
+            .expect("task id is required when --all is not specified");
+        let reports = service.stop_task_recursive(&task_id)?;
+        let total = reports.len();
+        let mut stopped = 0usize;
+        for report in reports {
+            print_stop_outcome(&report.task_id, report.outcome);
+            if report.outcome != StopOutcome::AlreadyStopped {
+                stopped += 1;
+            }
+        }
+        println!("Stopped {stopped} of {total} task(s) in the tree of {task_id}.");
         Ok(())
     } else {
         let task_id = args
@@ -49,5 +69,11 @@ fn print_stop_outcome(task_id: &str, outcome: StopOutcome) {
         StopOutcome::Stopped => {
             println!("Task {} stopped.", task_id);
         }
+        StopOutcome::Dequeued => {
+            println!(
+                "Task {} was queued; its pending prompt was cancelled.",
+                task_id
+            );
+        }
     }
 }
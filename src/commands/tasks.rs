@@ -1,10 +1,9 @@
-use std::collections::VecDeque;
 use std::fs;
 use std::path::Path;
 
-use anyhow::{Context, Result, ensure};
+use anyhow::{Context, Result};
 
-use crate::tasks::{METADATA_FILE_NAME, TaskMetadata, TaskStore, derive_active_state};
+use crate::tasks::{METADATA_FILE_NAME, TaskMetadata, TaskPaths, TaskStore, derive_active_state};
 
 #[derive(Debug)]
 pub(crate) struct ListedTask {
@@ -17,6 +16,7 @@ pub(crate) fn collect_active_tasks(store: &TaskStore) -> Result<Vec<ListedTask>>
     if !root.exists() {
         return Ok(tasks);
     }
+    store.relocate_legacy_layout()?;
 
     for entry in fs::read_dir(&root)
         .with_context(|| format!("failed to read task directory {}", root.display()))?
@@ -35,12 +35,20 @@ pub(crate) fn collect_active_tasks(store: &TaskStore) -> Result<Vec<ListedTask>>
             continue;
         }
 
-        let mut metadata = read_metadata_file(&metadata_path)?;
+        let Some(mut metadata) = read_listed_metadata(&metadata_path) else {
+            continue;
+        };
         let task_paths = store.task(metadata.id.clone());
-        let pid = task_paths.read_pid()?;
-        metadata.state = derive_active_state(&metadata.state, pid);
+        let pid = task_paths.read_pid().unwrap_or_else(|err| {
+            warn_unreadable(&task_paths.pid_path(), &err);
+            None
+        });
+        metadata.state = derive_active_state(&metadata, pid);
         if metadata.last_result.is_none() {
-            metadata.last_result = task_paths.read_last_result()?;
+            metadata.last_result = task_paths.read_last_result().unwrap_or_else(|err| {
+                warn_unreadable(&task_paths.result_path(), &err);
+                None
+            });
         }
         tasks.push(ListedTask { metadata });
     }
@@ -49,53 +57,39 @@ pub(crate) fn collect_active_tasks(store: &TaskStore) -> Result<Vec<ListedTask>>
 }
 
 pub(crate) fn collect_archived_tasks(store: &TaskStore) -> Result<Vec<ListedTask>> {
-    let mut tasks = Vec::new();
-    let archive_root = store.archive_root();
-    if !archive_root.exists() {
-        return Ok(tasks);
-    }
-
-    let mut queue = VecDeque::from([archive_root]);
-    while let Some(dir) = queue.pop_front() {
-        let metadata_path = dir.join(METADATA_FILE_NAME);
-        if metadata_path.exists() {
-            let metadata = read_metadata_file(&metadata_path)?;
-            tasks.push(ListedTask { metadata });
-            continue;
-        }
+    Ok(store
+        .archive_index()
+        .entries()?
+        .into_iter()
+        .map(|entry| ListedTask {
+            metadata: entry.metadata,
+        })
+        .collect())
+}
 
-        for entry in fs::read_dir(&dir)
-            .with_context(|| format!("failed to read archive directory {}", dir.display()))?
-        {
-            let entry = entry
-                .with_context(|| format!("failed to read archive entry in {}", dir.display()))?;
-            if entry.file_type()?.is_dir() {
-                queue.push_back(entry.path());
-            }
-        }
-    }
+/// Reads metadata for listing, skipping (with a warning) entries that cannot be loaded.
+fn read_listed_metadata(path: &Path) -> Option<TaskMetadata> {
+    read_metadata_file(path)
+        .inspect_err(|err| warn_unreadable(path, err))
+        .ok()
+}
 
-    Ok(tasks)
+fn warn_unreadable(path: &Path, err: &anyhow::Error) {
+    eprintln!(
+        "warning: ignoring unreadable {} ({err:#}); run `codex-tasks doctor` to repair the store",
+        path.display()
+    );
 }
 
 pub(crate) fn read_metadata_file(path: &Path) -> Result<TaskMetadata> {
-    let raw = fs::read_to_string(path)
-        .with_context(|| format!("failed to read metadata file {}", path.display()))?;
-    let metadata: TaskMetadata = serde_json::from_str(&raw)
-        .with_context(|| format!("failed to parse metadata file {}", path.display()))?;
-
-    if let Some(parent) = path
+    let directory = path
         .parent()
-        .and_then(|value| value.file_name())
+        .with_context(|| format!("metadata file {} has no parent directory", path.display()))?;
+    let task_id = directory
+        .file_name()
         .and_then(|value| value.to_str())
-    {
-        ensure!(
-            metadata.id == parent,
-            "metadata id {} does not match directory {}",
-            metadata.id,
-            parent
-        );
-    }
-
-    Ok(metadata)
+        .with_context(|| format!("failed to determine task id for {}", path.display()))?;
+    TaskPaths::from_directory(directory.to_path_buf(), task_id.to_string())
+        .read_metadata()
+        .with_context(|| format!("failed to load metadata file {}", path.display()))
 }
@@ -0,0 +1,94 @@
+use std::io::Write;
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::cli::WaitArgs;
+use crate::tasks::wait::wait_for_tasks;
+use crate::tasks::{
+    ListTasksOptions, TaskService, TaskState, TasksConfig, WaitOptions, WaitOutcome,
+};
+
+pub fn handle_wait(args: WaitArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    let task_ids = if args.task_ids.is_empty() {
+        let mut options = ListTasksOptions {
+            states: args.states,
+            tags: args.tags,
+            title: args.title,
+            ..Default::default()
+        };
+        if args.all_running {
+            options
+                .states
+                .extend([TaskState::Running, TaskState::Queued]);
+        }
+        service
+            .list_tasks(options)?
+            .into_iter()
+            .map(|entry| entry.metadata.id)
+            .collect()
+    } else {
+        let mut ids: Vec<String> = Vec::new();
+        for task_id in args.task_ids {
+            if !ids.contains(&task_id) {
+                ids.push(task_id);
+            }
+        }
+        ids
+    };
+
+    let options = WaitOptions {
+        until: args.until,
+        any: args.any,
+        fail_on_died: args.fail_on_died,
+        timeout: args.timeout.or(service.config().wait_timeout),
+    };
+    let report = wait_for_tasks(&service, &task_ids, &options, |_| {})?;
+
+    if args.json {
+        let tasks: Vec<_> = report
+            .tasks
+            .iter()
+            .map(|(id, status)| {
+                json!({
+                    "id": id,
+                    "state": status.as_ref().map(|status| status.metadata.state.clone()),
+                })
+            })
+            .collect();
+        let payload = json!({
+            "outcome": report.outcome,
+            "exit_code": report.outcome.exit_code(),
+            "elapsed_secs": report.elapsed.as_secs_f64(),
+            "tasks": tasks,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+    } else {
+        for (id, status) in &report.tasks {
+            match status {
+                Some(status) => println!("{id}\t{}", status.metadata.state),
+                None => println!("{id}\tNOT FOUND"),
+            }
+        }
+        match report.outcome {
+            WaitOutcome::Reached => {}
+            WaitOutcome::Died => {
+                eprintln!("A task died while waiting for {}.", options.until.as_str())
+            }
+            WaitOutcome::Unmet => eprintln!("A task stopped without dying."),
+            WaitOutcome::TimedOut => eprintln!(
+                "Timed out after {:.1}s waiting for tasks.",
+                report.elapsed.as_secs_f64()
+            ),
+            WaitOutcome::NotFound => eprintln!("One or more tasks were not found."),
+        }
+    }
+
+    let code = report.outcome.exit_code();
+    if code != 0 {
+        std::io::stdout().flush()?;
+        std::process::exit(code);
+    }
+    Ok(())
+}
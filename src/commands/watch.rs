@@ -0,0 +1,644 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, ErrorKind, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use chrono::{Local, Utc};
+use serde_json::Value;
+
+use crate::cli::WatchArgs;
+use crate::commands::log::HumanRenderState;
+use crate::tasks::{
+    ArchiveTaskOutcome, LaunchOutcome, ListTasksOptions, SendPromptParams, StopOutcome,
+    TaskListEntry, TaskService, TaskState, TasksConfig,
+};
+use crate::timefmt::format_elapsed;
+
+/// Rendered transcript lines kept per task for the log pane.
+const LOG_PANE_HISTORY: usize = 500;
+const MIN_INTERVAL_MS: u64 = 100;
+const FALLBACK_SIZE: (usize, usize) = (120, 40);
+const HELP_LINE: &str = "up/down select  l log  s send  x stop  a archive  r refresh  q quit";
+
+pub fn handle_watch(args: WatchArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    let mut dashboard = Dashboard::new(service, args.include_archived);
+    dashboard.refresh()?;
+
+    if args.once {
+        let (width, _) = terminal_size().unwrap_or(FALLBACK_SIZE);
+        for line in dashboard.frame(width, None, false) {
+            println!("{}", line.trim_end());
+        }
+        return Ok(());
+    }
+
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        bail!("watch requires an interactive terminal; use --once to print a single snapshot");
+    }
+
+    let interval = Duration::from_millis(args.interval_ms.max(MIN_INTERVAL_MS));
+    let _terminal = RawTerminal::enter()?;
+    let keys = spawn_key_reader();
+    dashboard.draw()?;
+    loop {
+        match keys.recv_timeout(interval) {
+            Ok(key) => {
+                if dashboard.handle_key(key) == Flow::Quit {
+                    break;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => dashboard.refresh_reporting_errors(),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        dashboard.draw()?;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Key {
+    Up,
+    Down,
+    Enter,
+    Esc,
+    Backspace,
+    Interrupt,
+    Char(char),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Flow {
+    Continue,
+    Quit,
+}
+
+struct Dashboard {
+    service: TaskService,
+    include_archived: bool,
+    tasks: Vec<TaskListEntry>,
+    feeds: HashMap<String, TaskFeed>,
+    selected: Option<String>,
+    show_log: bool,
+    prompt: Option<String>,
+    message: Option<String>,
+}
+
+impl Dashboard {
+    fn new(service: TaskService, include_archived: bool) -> Self {
+        Self {
+            service,
+            include_archived,
+            tasks: Vec::new(),
+            feeds: HashMap::new(),
+            selected: None,
+            show_log: false,
+            prompt: None,
+            message: None,
+        }
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        self.tasks = self.service.list_tasks(ListTasksOptions {
+            include_archived: self.include_archived,
+            ..Default::default()
+        })?;
+        for entry in &self.tasks {
+            let id = entry.metadata.id.clone();
+            let feed = self.feeds.entry(id.clone()).or_insert_with(TaskFeed::new);
+            feed.update(&self.service, &id)?;
+        }
+        self.feeds
+            .retain(|id, _| self.tasks.iter().any(|entry| &entry.metadata.id == id));
+
+        let still_listed = self
+            .selected
+            .as_ref()
+            .is_some_and(|id| self.tasks.iter().any(|entry| &entry.metadata.id == id));
+        if !still_listed {
+            self.selected = self.tasks.first().map(|entry| entry.metadata.id.clone());
+        }
+        Ok(())
+    }
+
+    fn refresh_reporting_errors(&mut self) {
+        if let Err(err) = self.refresh() {
+            self.message = Some(format!("refresh failed: {err:#}"));
+        }
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        let selected = self.selected.as_ref()?;
+        self.tasks
+            .iter()
+            .position(|entry| &entry.metadata.id == selected)
+    }
+
+    fn move_selection(&mut self, forward: bool) {
+        if self.tasks.is_empty() {
+            return;
+        }
+        let last = self.tasks.len() - 1;
+        let index = match (self.selected_index(), forward) {
+            (Some(index), true) => (index + 1).min(last),
+            (Some(index), false) => index.saturating_sub(1),
+            (None, _) => 0,
+        };
+        self.selected = Some(self.tasks[index].metadata.id.clone());
+    }
+
+    fn handle_key(&mut self, key: Key) -> Flow {
+        if let Some(buffer) = self.prompt.as_mut() {
+            match key {
+                Key::Char(ch) => buffer.push(ch),
+                Key::Backspace => {
+                    buffer.pop();
+                }
+                Key::Enter => {
+                    let prompt = self.prompt.take().unwrap_or_default();
+                    self.send_prompt(prompt);
+                }
+                Key::Esc | Key::Interrupt => {
+                    self.prompt = None;
+                    self.message = Some("Prompt cancelled.".to_string());
+                }
+                Key::Up | Key::Down => {}
+            }
+            return Flow::Continue;
+        }
+
+        match key {
+            Key::Char('q') | Key::Interrupt => return Flow::Quit,
+            Key::Up | Key::Char('k') => self.move_selection(false),
+            Key::Down | Key::Char('j') => self.move_selection(true),
+            Key::Enter | Key::Char('l') => self.show_log = !self.show_log,
+            Key::Char('s') if self.selected.is_some() => {
+                self.prompt = Some(String::new());
+                self.message = None;
+            }
+            Key::Char('x') => self.stop_selected(),
+            Key::Char('a') => self.archive_selected(),
+            Key::Char('r') => {
+                self.message = None;
+                self.refresh_reporting_errors();
+            }
+            _ => {}
+        }
+        Flow::Continue
+    }
+
+    fn send_prompt(&mut self, prompt: String) {
+        let Some(task_id) = self.selected.clone() else {
+            return;
+        };
+        if prompt.trim().is_empty() {
+            self.message = Some("Prompt was empty; nothing sent.".to_string());
+            return;
+        }
+        self.message = Some(
+            match self.service.send_prompt(SendPromptParams {
+                task_id: task_id.clone(),
+                prompt,
+                priority: 0,
+            }) {
+                Ok(LaunchOutcome::Started) => format!("Prompt sent to {task_id}."),
+                Ok(LaunchOutcome::Queued) => format!("Prompt queued for {task_id}."),
+                Err(err) => format!("Failed to send prompt: {err:#}"),
+            },
+        );
+        self.refresh_reporting_errors();
+    }
+
+    fn stop_selected(&mut self) {
+        let Some(task_id) = self.selected.clone() else {
+            return;
+        };
+        self.message = Some(format!("Stopping {task_id}..."));
+        // Stopping may wait for the worker to exit, so show progress first.
+        let _ = self.draw();
+        self.message = Some(match self.service.stop_task(&task_id) {
+            Ok(StopOutcome::Stopped) => format!("Task {task_id} stopped."),
+            Ok(StopOutcome::AlreadyStopped) => format!("Task {task_id} is not running."),
+            Ok(StopOutcome::Dequeued) => format!("Task {task_id} was removed from the queue."),
+            Err(err) => format!("Failed to stop {task_id}: {err:#}"),
+        });
+        self.refresh_reporting_errors();
+    }
+
+    fn archive_selected(&mut self) {
+        let Some(task_id) = self.selected.clone() else {
+            return;
+        };
+        self.message = Some(match self.service.archive_task(&task_id) {
+            Ok(ArchiveTaskOutcome::Archived { id, .. }) => format!("Task {id} archived."),
+            Ok(ArchiveTaskOutcome::AlreadyArchived { id }) => {
+                format!("Task {id} is already archived.")
+            }
+            Err(err) => format!("Failed to archive {task_id}: {err:#}"),
+        });
+        self.refresh_reporting_errors();
+    }
+
+    fn draw(&self) -> Result<()> {
+        let (width, height) = terminal_size().unwrap_or(FALLBACK_SIZE);
+        let mut out = String::from("\x1b[H");
+        for line in self.frame(width, Some(height), true) {
+            out.push_str(&line);
+            out.push_str("\x1b[K\r\n");
+        }
+        out.push_str("\x1b[J");
+        let mut stdout = io::stdout();
+        stdout
+            .write_all(out.as_bytes())
+            .and_then(|_| stdout.flush())
+            .context("failed to draw dashboard")
+    }
+
+    /// Builds the screen contents. `height` limits the table (and log pane) to the terminal;
+    /// `None` renders every task without a footer, for `--once`.
+    fn frame(&self, width: usize, height: Option<usize>, interactive: bool) -> Vec<String> {
+        let now = Utc::now();
+        let mut lines = vec![fit(
+            &format!(
+                "codex-tasks watch - {} task(s) - {}",
+                self.tasks.len(),
+                Local::now().format("%H:%M:%S")
+            ),
+            width,
+        )];
+
+        let columns = Columns::for_width(width);
+        lines.push(columns.row(
+            "ID", "TITLE", "STATE", "AGE", "ACTIVITY", "TOKENS", "LATEST",
+        ));
+
+        let (table_rows, log_rows) = match height {
+            Some(height) => {
+                // Header, column titles, and the footer take three lines.
+                let available = height.saturating_sub(3).max(1);
+                if self.show_log {
+                    let table = (available / 2).max(1);
+                    (table, available.saturating_sub(table + 1))
+                } else {
+                    (available, 0)
+                }
+            }
+            None => (self.tasks.len(), 0),
+        };
+
+        let selected = self.selected_index();
+        let first = match selected {
+            Some(index) if index >= table_rows => index + 1 - table_rows,
+            _ => 0,
+        };
+        for (index, entry) in self.tasks.iter().enumerate().skip(first).take(table_rows) {
+            let metadata = &entry.metadata;
+            let feed = self.feeds.get(&metadata.id);
+            let activity = match (&metadata.state, feed) {
+                (TaskState::Running, Some(feed)) => feed.activity.as_str(),
+                (TaskState::Running, None) => "starting",
+                _ => "idle",
+            };
+            let tokens = feed
+                .map(|feed| feed.render.tokens_used())
+                .filter(|tokens| *tokens > 0)
+                .map(|tokens| tokens.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            let latest = feed
+                .and_then(|feed| feed.render.last_agent_message())
+                .and_then(|message| message.lines().rev().find(|line| !line.trim().is_empty()))
+                .unwrap_or("-");
+            let row = columns.row(
+                &metadata.id,
+                metadata.title.as_deref().unwrap_or("-"),
+                metadata.state.as_str(),
+                &format_elapsed(now - metadata.created_at),
+                activity,
+                &tokens,
+                latest,
+            );
+            if interactive && Some(index) == selected {
+                lines.push(format!("\x1b[7m{row}\x1b[0m"));
+            } else {
+                lines.push(row);
+            }
+        }
+
+        if log_rows > 0 {
+            let selected_id = self.selected.as_deref().unwrap_or("-");
+            lines.push(fit(&format!("--- log: {selected_id} ---"), width));
+            let history = self
+                .selected
+                .as_ref()
+                .and_then(|id| self.feeds.get(id))
+                .map(|feed| &feed.history);
+            let tail: Vec<&String> = history
+                .map(|history| {
+                    history
+                        .iter()
+                        .skip(history.len().saturating_sub(log_rows))
+                        .collect()
+                })
+                .unwrap_or_default();
+            for line in &tail {
+                lines.push(fit(line, width));
+            }
+            for _ in tail.len()..log_rows {
+                lines.push(String::new());
+            }
+        }
+
+        if height.is_some() {
+            let footer = match (&self.prompt, &self.message) {
+                (Some(buffer), _) => format!("prompt> {buffer}"),
+                (None, Some(message)) => message.clone(),
+                (None, None) => HELP_LINE.to_string(),
+            };
+            lines.push(fit(&footer, width));
+        }
+        lines
+    }
+}
+
+/// Incrementally consumed transcript of a single task.
+struct TaskFeed {
+    path: Option<PathBuf>,
+    offset: u64,
+    pending: Vec<u8>,
+    render: HumanRenderState,
+    activity: String,
+    history: VecDeque<String>,
+}
+
+impl TaskFeed {
+    fn new() -> Self {
+        Self {
+            path: None,
+            offset: 0,
+            pending: Vec::new(),
+            render: HumanRenderState::new(),
+            activity: "starting".to_string(),
+            history: VecDeque::new(),
+        }
+    }
+
+    fn update(&mut self, service: &TaskService, task_id: &str) -> Result<()> {
+        if self.path.is_none() {
+            self.path = service
+                .prepare_log_descriptor(task_id, false)
+                .ok()
+                .map(|descriptor| descriptor.path);
+        }
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                // The task was archived since the last refresh; resolve the new location next time.
+                self.path = None;
+                return Ok(());
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to open {}", path.display()));
+            }
+        };
+        file.seek(SeekFrom::Start(self.offset))
+            .with_context(|| format!("failed to seek in {}", path.display()))?;
+        let mut chunk = Vec::new();
+        file.read_to_end(&mut chunk)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        self.offset += chunk.len() as u64;
+        self.pending.extend_from_slice(&chunk);
+
+        while let Some(newline) = self.pending.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=newline).collect();
+            self.observe(&String::from_utf8_lossy(&line));
+        }
+        Ok(())
+    }
+
+    fn observe(&mut self, line: &str) {
+        let Ok(value) = serde_json::from_str::<Value>(line.trim_end()) else {
+            return;
+        };
+        let rendered = self.render.render_event(&value);
+        if let Some(activity) = describe_activity(&rendered) {
+            self.activity = activity;
+        }
+        for line in rendered {
+            if self.history.len() == LOG_PANE_HISTORY {
+                self.history.pop_front();
+            }
+            self.history.push_back(line);
+        }
+    }
+}
+
+/// Summarises the transcript block most recently rendered for a task.
+fn describe_activity(rendered: &[String]) -> Option<String> {
+    let label = rendered.first()?;
+    Some(match label.as_str() {
+        "exec" => format!("exec {}", rendered.get(1).map(String::as_str).unwrap_or("")),
+        "thinking" => "thinking".to_string(),
+        "codex" => "replied".to_string(),
+        "tokens used" => "turn completed".to_string(),
+        "user" => "prompt received".to_string(),
+        other => other.to_string(),
+    })
+}
+
+/// Column widths for the task table; the latest-line column takes the remaining width.
+struct Columns {
+    id: usize,
+    title: usize,
+    activity: usize,
+    latest: usize,
+}
+
+impl Columns {
+    const STATE: usize = 8;
+    const AGE: usize = 4;
+    const TOKENS: usize = 8;
+
+    fn for_width(width: usize) -> Self {
+        let id = 36.min(width / 5).max(8);
+        let title = 20.min(width / 6).max(5);
+        let activity = 24.min(width / 5).max(8);
+        // Seven columns separated by single spaces.
+        let fixed = id + title + Self::STATE + Self::AGE + activity + Self::TOKENS + 6;
+        let latest = width.saturating_sub(fixed).max(10);
+        Self {
+            id,
+            title,
+            activity,
+            latest,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn row(
+        &self,
+        id: &str,
+        title: &str,
+        state: &str,
+        age: &str,
+        activity: &str,
+        tokens: &str,
+        latest: &str,
+    ) -> String {
+        [
+            fit(id, self.id),
+            fit(title, self.title),
+            fit(state, Self::STATE),
+            fit(age, Self::AGE),
+            fit(activity, self.activity),
+            fit(tokens, Self::TOKENS),
+            fit(latest, self.latest),
+        ]
+        .join(" ")
+    }
+}
+
+/// Truncates or pads `text` to exactly `width` characters on a single line.
+fn fit(text: &str, width: usize) -> String {
+    let flattened: String = text
+        .chars()
+        .map(|ch| if ch.is_control() { ' ' } else { ch })
+        .collect();
+    let count = flattened.chars().count();
+    if count > width {
+        let mut truncated: String = flattened.chars().take(width.saturating_sub(1)).collect();
+        truncated.push('~');
+        truncated
+    } else {
+        format!("{flattened}{}", " ".repeat(width - count))
+    }
+}
+
+fn terminal_size() -> Option<(usize, usize)> {
+    // SAFETY: TIOCGWINSZ only writes into the provided winsize struct.
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if result != 0 || size.ws_col == 0 || size.ws_row == 0 {
+        return None;
+    }
+    Some((size.ws_col as usize, size.ws_row as usize))
+}
+
+/// Puts the terminal into raw mode on the alternate screen, restoring it when dropped.
+struct RawTerminal {
+    original: libc::termios,
+}
+
+impl RawTerminal {
+    fn enter() -> Result<Self> {
+        // SAFETY: tcgetattr/tcsetattr only read and write the provided termios struct.
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(io::Error::last_os_error()).context("failed to read terminal settings");
+        }
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        // Return from reads after 100ms without input so a lone ESC can be recognised.
+        raw.c_cc[libc::VMIN] = 0;
+        raw.c_cc[libc::VTIME] = 1;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error()).context("failed to enable raw terminal mode");
+        }
+
+        let mut stdout = io::stdout();
+        stdout
+            .write_all(b"\x1b[?1049h\x1b[?25l")
+            .and_then(|_| stdout.flush())
+            .context("failed to switch to the alternate screen")?;
+        Ok(Self { original })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(b"\x1b[?25h\x1b[?1049l");
+        let _ = stdout.flush();
+        // SAFETY: restores the settings captured in `enter`.
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+fn spawn_key_reader() -> Receiver<Key> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0u8; 64];
+        loop {
+            // SAFETY: reads into a stack buffer of the given length.
+            let read =
+                unsafe { libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr().cast(), buffer.len()) };
+            if read < 0 {
+                if io::Error::last_os_error().kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                return;
+            }
+            for key in parse_keys(&buffer[..read as usize]) {
+                if sender.send(key).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    receiver
+}
+
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        index += 1;
+        match byte {
+            0x1b if bytes.get(index) == Some(&b'[') => {
+                // CSI sequence: parameters followed by a final byte in `@`..=`~`.
+                let rest = &bytes[index + 1..];
+                let length = rest
+                    .iter()
+                    .position(|byte| (0x40..=0x7e).contains(byte))
+                    .map_or(rest.len(), |position| position + 1);
+                match rest.get(length.saturating_sub(1)) {
+                    Some(b'A') => keys.push(Key::Up),
+                    Some(b'B') => keys.push(Key::Down),
+                    _ => {}
+                }
+                index += 1 + length;
+            }
+            0x1b => keys.push(Key::Esc),
+            0x03 => keys.push(Key::Interrupt),
+            b'\r' | b'\n' => keys.push(Key::Enter),
+            0x7f | 0x08 => keys.push(Key::Backspace),
+            byte if byte.is_ascii() && !byte.is_ascii_control() => {
+                keys.push(Key::Char(byte as char))
+            }
+            byte if byte >= 0x80 => {
+                let start = index - 1;
+                let end = (start + 4).min(bytes.len());
+                if let Some(ch) = (start + 2..=end)
+                    .filter_map(|end| std::str::from_utf8(&bytes[start..end]).ok())
+                    .find_map(|text| text.chars().next())
+                {
+                    index = start + ch.len_utf8();
+                    keys.push(Key::Char(ch));
+                }
+            }
+            _ => {}
+        }
+    }
+    keys
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+
+use crate::cli::{WebhookAddArgs, WebhookArgs, WebhookCommand, WebhookLogArgs, WebhookLsArgs};
+use crate::commands::common::print_table;
+use crate::tasks::{AddWebhookParams, TaskService, TasksConfig, WebhookEvent};
+use crate::timefmt::format_time;
+
+pub fn handle_webhook(args: WebhookArgs, config: TasksConfig) -> Result<()> {
+    let service = TaskService::with_default_store(config, false)?;
+    match args.command {
+        WebhookCommand::Add(args) => add(&service, args),
+        WebhookCommand::Ls(args) => list(&service, args),
+        WebhookCommand::Rm(args) => {
+            let removed = service.remove_webhook(&args.id)?;
+            println!("Webhook {} removed.", removed.id);
+            Ok(())
+        }
+        WebhookCommand::Log(args) => log(&service, args),
+    }
+}
+
+fn add(service: &TaskService, args: WebhookAddArgs) -> Result<()> {
+    let webhook = service.add_webhook(AddWebhookParams {
+        url: args.url,
+        events: args.events,
+        secret: args.secret,
+        retries: args.retries,
+        retry_backoff_secs: args.retry_backoff.map(|backoff| backoff.as_secs()),
+    })?;
+    println!("{}", webhook.id);
+    Ok(())
+}
+
+fn list(service: &TaskService, args: WebhookLsArgs) -> Result<()> {
+    let mut webhooks = service.list_webhooks()?;
+    for webhook in &mut webhooks {
+        if webhook.secret.is_some() {
+            webhook.secret = Some("***".to_string());
+        }
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&webhooks)?);
+        return Ok(());
+    }
+    if webhooks.is_empty() {
+        println!("No webhooks.");
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = webhooks
+        .iter()
+        .map(|webhook| {
+            let events = if webhook.events.is_empty() {
+                "all".to_string()
+            } else {
+                webhook
+                    .events
+                    .iter()
+                    .map(WebhookEvent::as_str)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            };
+            let signed = if webhook.secret.is_some() {
+                "yes"
+            } else {
+                "no"
+            };
+            vec![
+                webhook.id.clone(),
+                events,
+                signed.to_string(),
+                webhook.url.clone(),
+            ]
+        })
+        .collect();
+    print_table(&["ID", "EVENTS", "SIGNED", "URL"], &rows)
+}
+
+fn log(service: &TaskService, args: WebhookLogArgs) -> Result<()> {
+    let deliveries = service.webhook_deliveries(args.limit)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&deliveries)?);
+        return Ok(());
+    }
+    if deliveries.is_empty() {
+        println!("No webhook deliveries.");
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = deliveries
+        .iter()
+        .map(|delivery| {
+            let result = match (delivery.delivered, delivery.status, &delivery.error) {
+                (true, Some(status), _) => status.to_string(),
+                (true, None, _) => "ok".to_string(),
+                (false, _, Some(error)) => format!("failed: {error}"),
+                (false, Some(status), None) => format!("failed: {status}"),
+                (false, None, None) => "failed".to_string(),
+            };
+            vec![
+                format_time(delivery.at, args.time_format),
+                delivery.event.as_str().to_string(),
+                delivery.task_id.clone(),
+                delivery.attempts.to_string(),
+                result,
+                delivery.url.clone(),
+            ]
+        })
+        .collect();
+    print_table(&["AT", "EVENT", "TASK", "ATTEMPTS", "RESULT", "URL"], &rows)
+}
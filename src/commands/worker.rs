@@ -1,9 +1,10 @@
 use anyhow::Context;
 
 use crate::cli::WorkerArgs;
+use crate::tasks::{ExecOptions, ForkPoint, Hooks, RetryPolicy, TasksConfig};
 
-pub fn handle_worker(args: WorkerArgs) -> anyhow::Result<()> {
-    let config = crate::worker::child::WorkerConfig::new(
+pub fn handle_worker(args: WorkerArgs, tasks_config: TasksConfig) -> anyhow::Result<()> {
+    let mut config = crate::worker::child::WorkerConfig::new(
         args.store_root,
         args.task_id,
         args.title,
@@ -11,6 +12,29 @@ pub fn handle_worker(args: WorkerArgs) -> anyhow::Result<()> {
         args.config_path,
         args.working_dir,
     )?;
+    if let (Some(forked_from), Some(invocation), Some(at)) =
+        (args.forked_from, args.fork_invocation, args.fork_time)
+    {
+        config.fork = Some((forked_from, ForkPoint { invocation, at }));
+    }
+    config.parent_id = args.parent_id;
+    config.retry_policy = args.retries.map(|max_retries| RetryPolicy {
+        max_retries,
+        backoff_secs: args.retry_backoff_secs,
+    });
+    config.tags = args.tags;
+    config.hooks = Hooks::from_specs(args.hooks, args.hook_timeout_secs);
+    config.preset = args.preset;
+    let exec = ExecOptions {
+        args: args.exec_args,
+        env: args.env.into_iter().collect(),
+        timeout_secs: args.timeout_secs,
+    };
+    config.exec = Some(exec).filter(|exec| !exec.is_empty());
+    config.tasks_config = tasks_config;
+    if let Some(launch_id) = args.launch_id {
+        config.launch_id = launch_id;
+    }
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
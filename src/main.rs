@@ -9,22 +9,43 @@ use anyhow::Result;
 use clap::Parser;
 
 use crate::cli::{Cli, Command};
+use crate::tasks::TasksConfig;
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
-    dispatch(cli)
+    let config = TasksConfig::load(cli.config.as_deref())?;
+    dispatch(cli.command, config)
 }
 
-fn dispatch(cli: Cli) -> Result<()> {
-    match cli.command {
-        Command::Start(args) => commands::handle_start(args),
-        Command::Send(args) => commands::handle_send(args),
-        Command::Status(args) => commands::handle_status(args),
-        Command::Log(args) => commands::handle_log(args),
-        Command::Stop(args) => commands::handle_stop(args),
-        Command::Ls(args) => commands::handle_ls(args),
-        Command::Archive(args) => commands::handle_archive(args),
-        Command::Mcp(args) => mcp::run(args),
-        Command::Worker(args) => commands::handle_worker(args),
+fn dispatch(command: Command, config: TasksConfig) -> Result<()> {
+    match command {
+        Command::Start(args) => commands::handle_start(args, config),
+        Command::Send(args) => commands::handle_send(args, config),
+        Command::Retry(args) => commands::handle_retry(args, config),
+        Command::Fork(args) => commands::handle_fork(args, config),
+        Command::Status(args) => commands::handle_status(args, config),
+        Command::Log(args) => commands::handle_log(args, config),
+        Command::Attach(args) => commands::handle_attach(args, config),
+        Command::Diff(args) => commands::handle_diff(args, config),
+        Command::Result(args) => commands::handle_result(args, config),
+        Command::Wait(args) => commands::handle_wait(args, config),
+        Command::Stop(args) => commands::handle_stop(args, config),
+        Command::Ls(args) => commands::handle_ls(args, config),
+        Command::Queue(args) => commands::handle_queue(args, config),
+        Command::Hooks(args) => commands::handle_hooks(args, config),
+        Command::Webhook(args) => commands::handle_webhook(args, config),
+        Command::Pipeline(args) => commands::handle_pipeline(args, config),
+        Command::Schedule(args) => commands::handle_schedule(args, config),
+        Command::Scheduler(args) => commands::handle_scheduler(args, config),
+        Command::Search(args) => commands::handle_search(args, config),
+        Command::Watch(args) => commands::handle_watch(args, config),
+        Command::Archive(args) => commands::handle_archive(args, config),
+        Command::Migrate => commands::handle_migrate(config),
+        Command::Doctor(args) => commands::handle_doctor(args, config),
+        Command::Mcp(args) => mcp::run(args, config),
+        Command::Config(args) => commands::handle_config(args, config),
+        Command::Completions(args) => commands::handle_completions(args),
+        Command::CompleteTaskIds(args) => commands::handle_complete_task_ids(args, config),
+        Command::Worker(args) => commands::handle_worker(args, config),
     }
 }
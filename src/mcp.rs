@@ -12,24 +12,35 @@ use mcp_types::{
     CallToolRequestParams, CallToolResult, ContentBlock, Implementation, InitializeRequestParams,
     InitializeResult, JSONRPC_VERSION, JSONRPCError, JSONRPCErrorError, JSONRPCMessage,
     JSONRPCNotification, JSONRPCRequest, JSONRPCResponse, ListResourcesRequestParams,
-    ListResourcesResult, ListToolsResult, MCP_SCHEMA_VERSION, ReadResourceRequestParams,
-    ReadResourceResult, ReadResourceResultContents, RequestId, Resource, ServerCapabilities,
-    ServerCapabilitiesResources, ServerCapabilitiesTools, SubscribeRequestParams, TextContent,
-    TextResourceContents, Tool, ToolAnnotations, ToolInputSchema, UnsubscribeRequestParams,
+    ListResourcesResult, ListToolsResult, MCP_SCHEMA_VERSION, ProgressNotificationParams,
+    ProgressToken, ReadResourceRequestParams, ReadResourceResult, ReadResourceResultContents,
+    RequestId, Resource, ServerCapabilities, ServerCapabilitiesResources, ServerCapabilitiesTools,
+    SubscribeRequestParams, TextContent, TextResourceContents, Tool, ToolAnnotations,
+    ToolInputSchema, UnsubscribeRequestParams,
 };
 use serde::Deserialize;
 use serde::de::DeserializeOwned;
 use serde_json::{Value as JsonValue, json};
-use toml::Value as TomlValue;
 
-use crate::cli::McpArgs;
+use crate::cli::{ExportFormat, McpArgs};
+use crate::commands::export::export_transcript;
+use crate::tasks::search::compile_pattern;
+use crate::tasks::wait::{WaitProgress, wait_for_tasks};
 use crate::tasks::{
-    ArchiveAllSummary, ArchiveTaskOutcome, FollowMetadata, ListTasksOptions, LogDescriptor,
-    SendPromptParams, StartTaskParams, StopOutcome, StopTaskReport, TaskListEntry, TaskMetadata,
-    TaskService, TaskState, TaskStatusSnapshot, TaskStore,
+    ArchiveAllSummary, ArchiveTaskOutcome, FollowMetadata, LaunchOutcome, ListTasksOptions,
+    LogDescriptor, RetryPolicy, RetryTaskParams, SearchField, SearchHit, SearchQuery,
+    SendPromptParams, StartTaskParams, StopOutcome, StopTaskReport, TaskDiff, TaskListEntry,
+    TaskMetadata, TaskService, TaskState, TaskStatusSnapshot, TaskStore, TasksConfig, WaitOptions,
+    WaitReport, WaitTarget,
 };
+use crate::timefmt::parse_timestamp;
 
 const DEFAULT_LOG_TAIL: usize = 200;
+const DEFAULT_SEARCH_LIMIT: usize = 100;
+const DEFAULT_RETRY_BACKOFF_SECS: u64 = 30;
+const DEFAULT_WAIT_TIMEOUT_SECS: u64 = 60;
+/// Upper bound on `task_wait` timeouts; the server handles one request at a time.
+const MAX_WAIT_TIMEOUT_SECS: u64 = 600;
 
 const TASK_URI_PREFIX: &str = "task://";
 const TASK_STATUS_SUFFIX: &str = "/status";
@@ -165,12 +176,43 @@ impl ToolCallOutput {
     }
 }
 
+/// Sends `notifications/progress` for a tool call when the client supplied a progress token.
+struct ProgressReporter<'a> {
+    writer: &'a mut dyn Write,
+    token: Option<ProgressToken>,
+}
+
+impl ProgressReporter<'_> {
+    fn report(&mut self, progress: f64, total: Option<f64>, message: String) {
+        let Some(token) = self.token.clone() else {
+            return;
+        };
+        let params = ProgressNotificationParams {
+            message: Some(message),
+            progress,
+            progress_token: token,
+            total,
+        };
+        let notification = JSONRPCNotification {
+            jsonrpc: JSONRPC_VERSION.to_owned(),
+            method: "notifications/progress".to_string(),
+            params: serde_json::to_value(params).ok(),
+        };
+        if let Err(err) =
+            write_message(&mut self.writer, JSONRPCMessage::Notification(notification))
+        {
+            eprintln!("[mcp] failed to send progress notification: {err:#}");
+        }
+    }
+}
+
 /// Entry point for the `codex-tasks mcp` subcommand.
-pub fn run(args: McpArgs) -> Result<()> {
-    let config = McpConfig::from_args(args)?;
+pub fn run(args: McpArgs, tasks_config: TasksConfig) -> Result<()> {
+    let config = McpConfig::from_args(args, tasks_config)?;
     let store_root = format!("{}", config.store_root().display());
     let config_path = config
-        .config_path
+        .tasks_config
+        .path
         .as_ref()
         .map(|path| format!("{}", path.display()))
         .unwrap_or_else(|| "<none>".to_string());
@@ -183,25 +225,26 @@ pub fn run(args: McpArgs) -> Result<()> {
 
 struct McpConfig {
     store: TaskStore,
-    config_path: Option<PathBuf>,
-    config_document: Option<TomlValue>,
+    tasks_config: TasksConfig,
     allow_unsafe: bool,
 }
 
 impl McpConfig {
-    fn from_args(args: McpArgs) -> Result<Self> {
+    fn from_args(args: McpArgs, tasks_config: TasksConfig) -> Result<Self> {
         let store = resolve_store_root(args.store_root)?;
-        let (config_path, config_document) = resolve_config(args.config)?;
         Ok(Self {
             store,
-            config_path,
-            config_document,
+            tasks_config,
             allow_unsafe: args.allow_unsafe,
         })
     }
 
     fn task_service(&self) -> TaskService {
-        TaskService::new(self.store.clone(), self.allow_unsafe)
+        TaskService::new(
+            self.store.clone(),
+            self.tasks_config.clone(),
+            self.allow_unsafe,
+        )
     }
 
     fn store_root(&self) -> &Path {
@@ -219,14 +262,6 @@ fn run_server(config: McpConfig) -> Result<()> {
     let mut writer = BufWriter::new(io::stdout());
     let mut resources = ResourceState::default();
 
-    if let Some(doc) = config.config_document.as_ref() {
-        let top_level = doc.as_table().map(|table| table.len()).unwrap_or_default();
-        eprintln!(
-            "[mcp] loaded config document with {top_level} top-level item{}",
-            if top_level == 1 { "" } else { "s" }
-        );
-    }
-
     let (tx, rx) = mpsc::channel::<Incoming>();
 
     {
@@ -405,6 +440,10 @@ fn handle_request<W: Write>(
         }
         "tools/call" => {
             let params_json = params.unwrap_or(JsonValue::Null);
+            let progress_token = params_json
+                .get("_meta")
+                .and_then(|meta| meta.get("progressToken"))
+                .and_then(|token| serde_json::from_value::<ProgressToken>(token.clone()).ok());
             let params: CallToolRequestParams = match serde_json::from_value(params_json) {
                 Ok(value) => value,
                 Err(err) => {
@@ -412,7 +451,11 @@ fn handle_request<W: Write>(
                     return Ok(false);
                 }
             };
-            let output = handle_tool_call(config, params);
+            let mut progress = ProgressReporter {
+                writer: &mut *writer,
+                token: progress_token,
+            };
+            let output = handle_tool_call(config, params, &mut progress);
             respond_success(writer, id, serde_json::to_value(&output.result)?)?;
             dispatch_resource_events(writer, resources, output.events)?;
             Ok(false)
@@ -753,7 +796,28 @@ fn build_tools() -> Vec<Tool> {
                 "configFile": { "type": "string" },
                 "workingDir": { "type": "string" },
                 "repoUrl": { "type": "string" },
-                "repoRef": { "type": "string" }
+                "repoRef": { "type": "string" },
+                "retries": {
+                    "type": "integer",
+                    "description": "Automatically re-run failed invocations up to this many times when the failure looks transient"
+                },
+                "retryBackoffSeconds": {
+                    "type": "integer",
+                    "description": "Delay before the first automatic retry (default 30), doubling per attempt"
+                },
+                "tags": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Labels recorded on the task"
+                },
+                "priority": {
+                    "type": "integer",
+                    "description": "Queue priority used when the concurrency limit is reached; higher starts first"
+                },
+                "preset": {
+                    "type": "string",
+                    "description": "Named preset from the codex-tasks config supplying defaults for the other fields"
+                }
             }),
             &["prompt"],
             false,
@@ -766,13 +830,30 @@ fn build_tools() -> Vec<Tool> {
             "Send a follow-up prompt to an existing task",
             json!({
                 "taskId": { "type": "string" },
-                "prompt": { "type": "string" }
+                "prompt": { "type": "string" },
+                "priority": {
+                    "type": "integer",
+                    "description": "Queue priority used when the concurrency limit is reached; higher starts first"
+                }
             }),
             &["taskId", "prompt"],
             false,
             false,
             true,
         ),
+        make_tool(
+            "task_retry",
+            "Retry Task",
+            "Re-run the last prompt, or a new one, on a task that DIED",
+            json!({
+                "taskId": { "type": "string" },
+                "prompt": { "type": "string" }
+            }),
+            &["taskId"],
+            false,
+            false,
+            true,
+        ),
         make_tool(
             "task_status",
             "Get Status",
@@ -814,6 +895,107 @@ fn build_tools() -> Vec<Tool> {
             true,
             false,
         ),
+        make_tool(
+            "task_diff",
+            "Show Diff",
+            "Return the workspace patch produced by a task",
+            json!({
+                "taskId": { "type": "string" },
+                "invocation": {
+                    "type": "integer",
+                    "description": "Only include changes made during this 1-based invocation"
+                }
+            }),
+            &["taskId"],
+            true,
+            true,
+            false,
+        ),
+        make_tool(
+            "task_export",
+            "Export Transcript",
+            "Render a task transcript as a Markdown or HTML report",
+            json!({
+                "taskId": { "type": "string" },
+                "format": {
+                    "type": "string",
+                    "enum": ["md", "html"],
+                    "description": "Document format (default md)"
+                },
+                "outputPath": {
+                    "type": "string",
+                    "description": "Write the report to this new file instead of returning it; existing files are never overwritten"
+                }
+            }),
+            &["taskId"],
+            false,
+            false,
+            false,
+        ),
+        make_tool(
+            "task_search",
+            "Search Transcripts",
+            "Search task transcripts for a regular expression",
+            json!({
+                "pattern": { "type": "string" },
+                "fixedStrings": { "type": "boolean" },
+                "ignoreCase": { "type": "boolean" },
+                "in": {
+                    "type": "array",
+                    "items": {
+                        "type": "string",
+                        "enum": ["messages", "commands", "files", "prompts", "results"]
+                    }
+                },
+                "since": { "type": "string" },
+                "until": { "type": "string" },
+                "includeArchived": { "type": "boolean" },
+                "limit": { "type": "integer" }
+            }),
+            &["pattern"],
+            true,
+            true,
+            false,
+        ),
+        make_tool(
+            "task_wait",
+            "Wait for Tasks",
+            "Block until tasks finish, reporting progress and how they ended",
+            json!({
+                "taskIds": { "type": "array", "items": { "type": "string" } },
+                "states": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Instead of taskIds, wait for active tasks in these states"
+                },
+                "tags": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Instead of taskIds, wait for active tasks carrying every tag"
+                },
+                "title": {
+                    "type": "string",
+                    "description": "Instead of taskIds, wait for active tasks whose title contains this text"
+                },
+                "until": {
+                    "type": "string",
+                    "enum": ["STOPPED", "DIED", "any-terminal"]
+                },
+                "any": {
+                    "type": "boolean",
+                    "description": "Return once any task reaches the target state"
+                },
+                "failOnDied": { "type": "boolean" },
+                "timeoutSeconds": {
+                    "type": "integer",
+                    "description": "Defaults to 60, capped at 600"
+                }
+            }),
+            &[],
+            true,
+            true,
+            false,
+        ),
         make_tool(
             "task_stop",
             "Stop Task",
@@ -885,15 +1067,24 @@ fn make_tool(
     }
 }
 
-fn handle_tool_call(config: &McpConfig, params: CallToolRequestParams) -> ToolCallOutput {
+fn handle_tool_call(
+    config: &McpConfig,
+    params: CallToolRequestParams,
+    progress: &mut ProgressReporter<'_>,
+) -> ToolCallOutput {
     let name = params.name;
     let arguments = params.arguments;
     match name.as_str() {
         "task_start" => call_task_start(config, arguments),
         "task_send" => call_task_send(config, arguments),
+        "task_retry" => call_task_retry(config, arguments),
         "task_status" => call_task_status(config, arguments),
         "task_list" => call_task_list(config, arguments),
         "task_log" => call_task_log(config, arguments),
+        "task_search" => call_task_search(config, arguments),
+        "task_diff" => call_task_diff(config, arguments),
+        "task_export" => call_task_export(config, arguments),
+        "task_wait" => call_task_wait(config, arguments, progress),
         "task_stop" => call_task_stop(config, arguments),
         "task_archive" => call_task_archive(config, arguments),
         other => ToolCallOutput::new(error_text_result(format!("unknown tool '{other}'"))),
@@ -911,17 +1102,33 @@ fn call_task_start(config: &McpConfig, arguments: Option<JsonValue>) -> ToolCall
                 working_dir: optional_path(args.working_dir),
                 repo_url: args.repo_url,
                 repo_ref: args.repo_ref,
+                parent_id: None,
+                retry_policy: args.retries.map(|max_retries| RetryPolicy {
+                    max_retries,
+                    backoff_secs: args
+                        .retry_backoff_seconds
+                        .unwrap_or(DEFAULT_RETRY_BACKOFF_SECS),
+                }),
+                tags: args.tags,
+                priority: args.priority,
+                hooks: None,
+                preset: args.preset,
+                exec: None,
             };
             match service.start_task(params) {
                 Ok(result) => {
                     let task_id = result.thread_id.clone();
+                    let queued = result.outcome == LaunchOutcome::Queued;
                     let structured = json!({
                         "threadId": result.thread_id,
+                        "queued": queued,
                     });
-                    let text_result = success_text_result(
-                        format!("Task started with thread id {task_id}"),
-                        Some(structured),
-                    );
+                    let message = if queued {
+                        format!("Task queued with id {task_id}; it starts when a slot frees up")
+                    } else {
+                        format!("Task started with thread id {task_id}")
+                    };
+                    let text_result = success_text_result(message, Some(structured));
                     let new_state = current_task_state(&service, &task_id);
                     ToolCallOutput::with_events(
                         text_result,
@@ -943,17 +1150,29 @@ fn call_task_start(config: &McpConfig, arguments: Option<JsonValue>) -> ToolCall
 fn call_task_send(config: &McpConfig, arguments: Option<JsonValue>) -> ToolCallOutput {
     match parse_arguments::<SendToolArgs>(arguments) {
         Ok(args) => {
-            let SendToolArgs { task_id, prompt } = args;
+            let SendToolArgs {
+                task_id,
+                prompt,
+                priority,
+            } = args;
             let service = config.task_service();
             let params = SendPromptParams {
                 task_id: task_id.clone(),
                 prompt,
+                priority,
             };
             match service.send_prompt(params) {
-                Ok(()) => {
+                Ok(LaunchOutcome::Started) => {
                     let result = success_text_result("Prompt sent successfully", None);
                     ToolCallOutput::new(result)
                 }
+                Ok(LaunchOutcome::Queued) => {
+                    let result = success_text_result(
+                        "Prompt queued; the task starts when a slot frees up",
+                        Some(json!({ "queued": true })),
+                    );
+                    ToolCallOutput::new(result)
+                }
                 Err(err) => ToolCallOutput::new(error_text_result(format!(
                     "Failed to send prompt: {err:#}"
                 ))),
@@ -963,6 +1182,36 @@ fn call_task_send(config: &McpConfig, arguments: Option<JsonValue>) -> ToolCallO
     }
 }
 
+fn call_task_retry(config: &McpConfig, arguments: Option<JsonValue>) -> ToolCallOutput {
+    match parse_arguments::<RetryToolArgs>(arguments) {
+        Ok(args) => {
+            let service = config.task_service();
+            let task_id = args.task_id.clone();
+            let params = RetryTaskParams {
+                task_id: args.task_id,
+                prompt: args.prompt,
+            };
+            match service.retry_task(params) {
+                Ok(prompt) => {
+                    let result = success_text_result(
+                        format!("Retrying task {task_id}"),
+                        Some(json!({ "taskId": task_id, "prompt": prompt })),
+                    );
+                    let new_state = current_task_state(&service, &task_id);
+                    ToolCallOutput::with_events(
+                        result,
+                        vec![ResourceEvent::TaskStatusUpdated { task_id, new_state }],
+                    )
+                }
+                Err(err) => {
+                    ToolCallOutput::new(error_text_result(format!("Failed to retry task: {err:#}")))
+                }
+            }
+        }
+        Err(err) => ToolCallOutput::new(error_text_result(err.to_string())),
+    }
+}
+
 fn call_task_status(config: &McpConfig, arguments: Option<JsonValue>) -> ToolCallOutput {
     match parse_arguments::<StatusToolArgs>(arguments) {
         Ok(args) => {
@@ -995,6 +1244,7 @@ fn call_task_list(config: &McpConfig, arguments: Option<JsonValue>) -> ToolCallO
             match service.list_tasks(ListTasksOptions {
                 include_archived: args.include_archived,
                 states,
+                ..Default::default()
             }) {
                 Ok(entries) => {
                     let structured = list_to_json(&entries);
@@ -1034,6 +1284,190 @@ fn call_task_log(config: &McpConfig, arguments: Option<JsonValue>) -> ToolCallOu
     }
 }
 
+fn call_task_diff(config: &McpConfig, arguments: Option<JsonValue>) -> ToolCallOutput {
+    match parse_arguments::<DiffToolArgs>(arguments) {
+        Ok(args) => {
+            let service = config.task_service();
+            match service.task_diff(&args.task_id, args.invocation) {
+                Ok(diff) => ToolCallOutput::new(success_text_result(
+                    format_diff_text(&diff),
+                    Some(diff_to_json(&diff)),
+                )),
+                Err(err) => ToolCallOutput::new(error_text_result(format!(
+                    "Failed to compute diff: {err:#}"
+                ))),
+            }
+        }
+        Err(err) => ToolCallOutput::new(error_text_result(err.to_string())),
+    }
+}
+
+fn call_task_export(config: &McpConfig, arguments: Option<JsonValue>) -> ToolCallOutput {
+    let args = match parse_arguments::<ExportToolArgs>(arguments) {
+        Ok(args) => args,
+        Err(err) => return ToolCallOutput::new(error_text_result(err.to_string())),
+    };
+    let service = config.task_service();
+    let document = match export_transcript(&service, &args.task_id, args.format, &[]) {
+        Ok(document) => document,
+        Err(err) => {
+            return ToolCallOutput::new(error_text_result(format!(
+                "Failed to export transcript: {err:#}"
+            )));
+        }
+    };
+    let format = args.format.as_str();
+    match optional_path(args.output_path) {
+        // Only new files are written, so the tool cannot clobber anything the client points it at.
+        Some(path) => match write_new_file(&path, &document) {
+            Ok(()) => ToolCallOutput::new(success_text_result(
+                format!("Exported task {} to {}", args.task_id, path.display()),
+                Some(json!({ "taskId": args.task_id, "format": format, "path": path })),
+            )),
+            Err(err) => ToolCallOutput::new(error_text_result(format!(
+                "Failed to write {}: {err}",
+                path.display()
+            ))),
+        },
+        None => ToolCallOutput::new(success_text_result(
+            document,
+            Some(json!({ "taskId": args.task_id, "format": format })),
+        )),
+    }
+}
+
+fn write_new_file(path: &Path, contents: &str) -> io::Result<()> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?
+        .write_all(contents.as_bytes())
+}
+
+fn call_task_wait(
+    config: &McpConfig,
+    arguments: Option<JsonValue>,
+    progress: &mut ProgressReporter<'_>,
+) -> ToolCallOutput {
+    let args = match parse_arguments::<WaitToolArgs>(arguments) {
+        Ok(args) => args,
+        Err(err) => return ToolCallOutput::new(error_text_result(err.to_string())),
+    };
+    let selects = !args.states.is_empty() || !args.tags.is_empty() || args.title.is_some();
+    if args.task_ids.is_empty() != selects {
+        return ToolCallOutput::new(error_text_result(
+            "provide either taskIds or at least one of states, tags and title",
+        ));
+    }
+    let service = config.task_service();
+    let task_ids = if selects {
+        let states = match parse_task_states(&args.states) {
+            Ok(states) => states,
+            Err(err) => return ToolCallOutput::new(error_text_result(err.to_string())),
+        };
+        let options = ListTasksOptions {
+            states,
+            tags: args.tags,
+            title: args.title,
+            ..Default::default()
+        };
+        match service.list_tasks(options) {
+            Ok(entries) => entries.into_iter().map(|entry| entry.metadata.id).collect(),
+            Err(err) => {
+                return ToolCallOutput::new(error_text_result(format!(
+                    "Failed to list tasks: {err:#}"
+                )));
+            }
+        }
+    } else {
+        args.task_ids
+    };
+    let timeout = args
+        .timeout_seconds
+        .or_else(|| {
+            config
+                .tasks_config
+                .wait_timeout
+                .map(|timeout| timeout.as_secs())
+        })
+        .unwrap_or(DEFAULT_WAIT_TIMEOUT_SECS)
+        .min(MAX_WAIT_TIMEOUT_SECS);
+    let options = WaitOptions {
+        until: args.until.unwrap_or_default(),
+        any: args.any,
+        fail_on_died: args.fail_on_died,
+        timeout: Some(Duration::from_secs(timeout)),
+    };
+    let report = wait_for_tasks(
+        &service,
+        &task_ids,
+        &options,
+        |WaitProgress { settled, total }| {
+            progress.report(
+                settled as f64,
+                Some(total as f64),
+                format!("{settled} of {total} tasks settled"),
+            );
+        },
+    );
+    match report {
+        Ok(report) => {
+            let events = report
+                .tasks
+                .iter()
+                .filter_map(|(task_id, status)| {
+                    status
+                        .as_ref()
+                        .map(|status| ResourceEvent::TaskStatusUpdated {
+                            task_id: task_id.clone(),
+                            new_state: Some(status.metadata.state.clone()),
+                        })
+                })
+                .collect();
+            ToolCallOutput::with_events(
+                success_text_result(format_wait_text(&report), Some(wait_to_json(&report))),
+                events,
+            )
+        }
+        Err(err) => ToolCallOutput::new(error_text_result(format!(
+            "Failed to wait for tasks: {err:#}"
+        ))),
+    }
+}
+
+fn call_task_search(config: &McpConfig, arguments: Option<JsonValue>) -> ToolCallOutput {
+    let query = match parse_arguments::<SearchToolArgs>(arguments).and_then(search_query) {
+        Ok(query) => query,
+        Err(err) => return ToolCallOutput::new(error_text_result(format!("{err:#}"))),
+    };
+    let service = config.task_service();
+    match service.search(&query) {
+        Ok(hits) => ToolCallOutput::new(success_text_result(
+            format_search_text(&hits),
+            Some(search_to_json(&hits)),
+        )),
+        Err(err) => ToolCallOutput::new(error_text_result(format!(
+            "Failed to search tasks: {err:#}"
+        ))),
+    }
+}
+
+fn search_query(args: SearchToolArgs) -> Result<SearchQuery> {
+    let parse_time = |value: Option<String>| {
+        value
+            .map(|value| parse_timestamp(&value).map_err(|err| anyhow!(err)))
+            .transpose()
+    };
+    Ok(SearchQuery {
+        pattern: compile_pattern(&args.pattern, args.fixed_strings, args.ignore_case)?,
+        fields: args.fields,
+        since: parse_time(args.since)?,
+        until: parse_time(args.until)?,
+        include_archived: args.include_archived,
+        limit: Some(args.limit.unwrap_or(DEFAULT_SEARCH_LIMIT)),
+    })
+}
+
 fn call_task_stop(config: &McpConfig, arguments: Option<JsonValue>) -> ToolCallOutput {
     match parse_arguments::<StopToolArgs>(arguments) {
         Ok(args) => {
@@ -1190,6 +1624,15 @@ fn status_to_json(status: &TaskStatusSnapshot) -> JsonValue {
         "lastPrompt": status.metadata.last_prompt,
         "lastResult": status.metadata.last_result,
         "workingDir": status.metadata.working_dir,
+        "parentId": status.metadata.parent_id,
+        "forkedFrom": status.metadata.forked_from,
+        "forkPoint": status.metadata.fork_point,
+        "retryCount": status.metadata.retry_count,
+        "tags": status.metadata.tags,
+        "queued": status.metadata.queued.as_ref().map(|queued| json!({
+            "priority": queued.priority,
+            "queuedAt": queued.queued_at,
+        })),
         "pid": status.pid,
     })
 }
@@ -1213,6 +1656,12 @@ fn format_status_text(status: &TaskStatusSnapshot) -> String {
         "Working Dir: {}",
         status.metadata.working_dir.as_deref().unwrap_or("<none>")
     ));
+    if let Some(parent) = &status.metadata.parent_id {
+        lines.push(format!("Parent: {}", parent));
+    }
+    if let Some(source) = &status.metadata.forked_from {
+        lines.push(format!("Forked From: {}", source));
+    }
     if let Some(pid) = status.pid {
         lines.push(format!("PID: {}", pid));
     }
@@ -1255,6 +1704,7 @@ fn metadata_to_json(metadata: &TaskMetadata) -> JsonValue {
         "createdAt": metadata.created_at,
         "updatedAt": metadata.updated_at,
         "workingDir": metadata.working_dir,
+        "parentId": metadata.parent_id,
     })
 }
 
@@ -1274,6 +1724,112 @@ fn format_list_text(entries: &[TaskListEntry]) -> String {
     lines.join("\n")
 }
 
+fn diff_to_json(diff: &TaskDiff) -> JsonValue {
+    match diff {
+        TaskDiff::Patch { base, patch } => json!({
+            "kind": "patch",
+            "baseHead": base.head,
+            "baseDirty": base.dirty,
+            "patch": patch,
+        }),
+        TaskDiff::Files(files) => json!({
+            "kind": "files",
+            "files": files
+                .iter()
+                .map(|(marker, path)| json!({ "change": marker.to_string(), "path": path }))
+                .collect::<Vec<_>>(),
+        }),
+    }
+}
+
+fn format_diff_text(diff: &TaskDiff) -> String {
+    match diff {
+        TaskDiff::Patch { patch, .. } if patch.is_empty() => "No workspace changes.".to_string(),
+        TaskDiff::Patch { patch, .. } => patch.clone(),
+        TaskDiff::Files(files) if files.is_empty() => {
+            "No git snapshot available and no file changes recorded.".to_string()
+        }
+        TaskDiff::Files(files) => {
+            let mut lines = vec!["No git snapshot available; files changed:".to_string()];
+            lines.extend(
+                files
+                    .iter()
+                    .map(|(marker, path)| format!("{marker} {path}")),
+            );
+            lines.join("\n")
+        }
+    }
+}
+
+fn wait_to_json(report: &WaitReport) -> JsonValue {
+    let tasks: Vec<JsonValue> = report
+        .tasks
+        .iter()
+        .map(|(task_id, status)| match status {
+            Some(status) => status_to_json(status),
+            None => json!({ "id": task_id, "state": JsonValue::Null }),
+        })
+        .collect();
+    json!({
+        "outcome": report.outcome,
+        "exitCode": report.outcome.exit_code(),
+        "elapsedSeconds": report.elapsed.as_secs_f64(),
+        "tasks": tasks,
+    })
+}
+
+fn format_wait_text(report: &WaitReport) -> String {
+    let mut lines = vec![format!(
+        "Wait finished with outcome '{}' after {:.1}s",
+        report.outcome.as_str(),
+        report.elapsed.as_secs_f64()
+    )];
+    for (task_id, status) in &report.tasks {
+        match status {
+            Some(status) => lines.push(format!("{task_id}: {}", status.metadata.state)),
+            None => lines.push(format!("{task_id}: not found")),
+        }
+    }
+    lines.join("\n")
+}
+
+fn search_to_json(hits: &[SearchHit]) -> JsonValue {
+    json!({
+        "matches": hits
+            .iter()
+            .map(|hit| json!({
+                "taskId": hit.task_id,
+                "title": hit.title,
+                "archived": hit.archived,
+                "invocation": hit.invocation,
+                "field": hit.field.as_str(),
+                "logLine": hit.log_line,
+                "text": hit.text,
+            }))
+            .collect::<Vec<_>>(),
+        "count": hits.len(),
+    })
+}
+
+fn format_search_text(hits: &[SearchHit]) -> String {
+    if hits.is_empty() {
+        return "No matches found.".to_string();
+    }
+
+    let mut lines = Vec::new();
+    lines.push(format!("Found {} match(es):", hits.len()));
+    for hit in hits {
+        lines.push(format!(
+            "- {} #{} [{}] {}",
+            hit.task_id,
+            hit.invocation,
+            hit.field.as_str(),
+            hit.text
+        ));
+    }
+    lines.join("\n")
+}
+
 fn parse_task_states(values: &[String]) -> Result<Vec<TaskState>> {
     let mut states = Vec::new();
     for value in values {
@@ -1282,6 +1838,7 @@ fn parse_task_states(values: &[String]) -> Result<Vec<TaskState>> {
             "STOPPED" => TaskState::Stopped,
             "ARCHIVED" => TaskState::Archived,
             "DIED" => TaskState::Died,
+            "QUEUED" => TaskState::Queued,
             other => bail!("unknown task state '{other}'"),
         };
         states.push(parsed);
@@ -1293,6 +1850,7 @@ fn format_stop_outcome(outcome: &StopOutcome) -> &'static str {
     match outcome {
         StopOutcome::AlreadyStopped => "already_stopped",
         StopOutcome::Stopped => "stopped",
+        StopOutcome::Dequeued => "dequeued",
     }
 }
 
@@ -1302,18 +1860,26 @@ fn format_stop_outcome_text(task_id: &str, outcome: StopOutcome) -> String {
             format!("Task {} is not running; nothing to stop.", task_id)
         }
         StopOutcome::Stopped => format!("Task {} stopped.", task_id),
+        StopOutcome::Dequeued => {
+            format!(
+                "Task {} was queued; its pending prompt was cancelled.",
+                task_id
+            )
+        }
     }
 }
 
 fn stop_reports_to_json(reports: &[StopTaskReport]) -> JsonValue {
     let mut stopped = 0usize;
     let mut already = 0usize;
+    let mut dequeued = 0usize;
     let items: Vec<JsonValue> = reports
         .iter()
         .map(|report| {
             match report.outcome {
                 StopOutcome::Stopped => stopped += 1,
                 StopOutcome::AlreadyStopped => already += 1,
+                StopOutcome::Dequeued => dequeued += 1,
             }
             json!({
                 "taskId": report.task_id,
@@ -1327,6 +1893,7 @@ fn stop_reports_to_json(reports: &[StopTaskReport]) -> JsonValue {
         "summary": {
             "stopped": stopped,
             "alreadyStopped": already,
+            "dequeued": dequeued,
         }
     })
 }
@@ -1338,12 +1905,14 @@ fn format_stop_reports(reports: &[StopTaskReport]) -> String {
 
     let mut stopped = 0usize;
     let mut already = 0usize;
+    let mut dequeued = 0usize;
     let mut lines = Vec::new();
     for report in reports {
         lines.push(format_stop_outcome_text(&report.task_id, report.outcome));
         match report.outcome {
             StopOutcome::Stopped => stopped += 1,
             StopOutcome::AlreadyStopped => already += 1,
+            StopOutcome::Dequeued => dequeued += 1,
         }
     }
     lines.push(format!(
@@ -1351,6 +1920,9 @@ fn format_stop_reports(reports: &[StopTaskReport]) -> String {
         stopped = stopped,
         already = already
     ));
+    if dequeued > 0 {
+        lines.push(format!("Cancelled {dequeued} queued task(s)."));
+    }
     lines.join("\n")
 }
 
@@ -1488,6 +2060,24 @@ struct StartToolArgs {
     repo_url: Option<String>,
     #[serde(default)]
     repo_ref: Option<String>,
+    #[serde(default)]
+    retries: Option<u32>,
+    #[serde(default)]
+    retry_backoff_seconds: Option<u64>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    preset: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RetryToolArgs {
+    task_id: String,
+    #[serde(default)]
+    prompt: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -1495,6 +2085,8 @@ struct StartToolArgs {
 struct SendToolArgs {
     task_id: String,
     prompt: String,
+    #[serde(default)]
+    priority: i32,
 }
 
 #[derive(Deserialize)]
@@ -1520,6 +2112,65 @@ struct LogToolArgs {
     tail: Option<usize>,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiffToolArgs {
+    task_id: String,
+    #[serde(default)]
+    invocation: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportToolArgs {
+    task_id: String,
+    #[serde(default)]
+    format: ExportFormat,
+    #[serde(default)]
+    output_path: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WaitToolArgs {
+    #[serde(default)]
+    task_ids: Vec<String>,
+    #[serde(default)]
+    states: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    until: Option<WaitTarget>,
+    #[serde(default)]
+    any: bool,
+    #[serde(default)]
+    fail_on_died: bool,
+    #[serde(default)]
+    timeout_seconds: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchToolArgs {
+    pattern: String,
+    #[serde(default)]
+    fixed_strings: bool,
+    #[serde(default)]
+    ignore_case: bool,
+    #[serde(default, rename = "in")]
+    fields: Vec<SearchField>,
+    #[serde(default)]
+    since: Option<String>,
+    #[serde(default)]
+    until: Option<String>,
+    #[serde(default)]
+    include_archived: bool,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct StopToolArgs {
@@ -1568,37 +2219,6 @@ fn resolve_store_root(candidate: Option<PathBuf>) -> Result<TaskStore> {
     }
 }
 
-fn resolve_config(candidate: Option<PathBuf>) -> Result<(Option<PathBuf>, Option<TomlValue>)> {
-    let Some(path) = candidate else {
-        return Ok((None, None));
-    };
-
-    let absolute = make_absolute(&path)?;
-    let canonical = absolute
-        .canonicalize()
-        .with_context(|| format!("failed to resolve config file at {}", absolute.display()))?;
-    ensure!(
-        canonical.is_file(),
-        "config path {} is not a file",
-        canonical.display()
-    );
-    let file_name = canonical
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("");
-    ensure!(
-        file_name == "config.toml",
-        "config file must be named `config.toml` (got {file_name})"
-    );
-
-    let contents = fs::read_to_string(&canonical)
-        .with_context(|| format!("failed to read config file {}", canonical.display()))?;
-    let document: TomlValue = toml::from_str(&contents)
-        .with_context(|| format!("failed to parse config.toml at {}", canonical.display()))?;
-
-    Ok((Some(canonical), Some(document)))
-}
-
 fn make_absolute(path: &Path) -> Result<PathBuf> {
     if path.is_absolute() {
         return Ok(path.to_path_buf());
@@ -1638,34 +2258,6 @@ mod tests {
         Ok(())
     }
 
-    #[test]
-    fn resolve_config_parses_toml() -> Result<()> {
-        let temp = tempfile::tempdir()?;
-        let config_path = temp.path().join("config.toml");
-        fs::write(&config_path, "foo = \"bar\"")?;
-        let (resolved, document) = resolve_config(Some(config_path.clone()))?;
-        assert_eq!(
-            resolved.expect("path"),
-            config_path.canonicalize().context("canonicalize config")?
-        );
-        let doc = document.expect("document");
-        assert_eq!(doc["foo"].as_str(), Some("bar"));
-        Ok(())
-    }
-
-    #[test]
-    fn resolve_config_rejects_wrong_filename() -> Result<()> {
-        let temp = tempfile::tempdir()?;
-        let config_path = temp.path().join("custom.toml");
-        fs::write(&config_path, "foo = 1")?;
-        let err = resolve_config(Some(config_path)).expect_err("expected error");
-        assert!(
-            err.to_string().contains("must be named `config.toml`"),
-            "unexpected error: {err:#}"
-        );
-        Ok(())
-    }
-
     #[test]
     fn task_status_uri_round_trip() {
         let task_id = "task-123";
@@ -1751,12 +2343,139 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn task_export_returns_or_writes_report() -> Result<()> {
+        let (config, tempdir) = config_with_temp_store()?;
+        let task_dir = tempdir.path().join("task-export");
+        fs::create_dir_all(&task_dir)?;
+        fs::write(
+            task_dir.join("task.log"),
+            format!(
+                "{}\n{}\n",
+                json!({"type": "user_message", "message": "Explain <main>"}),
+                json!({"type": "item.completed", "item": {"type": "agent_message", "text": "It starts here."}}),
+            ),
+        )?;
+        let mut resources = ResourceState::default();
+
+        let message = run_request(
+            &config,
+            &mut resources,
+            "tools/call",
+            json!({ "name": "task_export", "arguments": { "taskId": "task-export", "format": "html" } }),
+        )?;
+        let result = expect_response(message).result;
+        assert_eq!(result["structuredContent"]["format"], json!("html"));
+        let document = result["content"][0]["text"].as_str().unwrap_or_default();
+        assert!(document.starts_with("<!DOCTYPE html>"));
+        assert!(document.contains("Explain &lt;main&gt;"));
+        assert!(document.contains("It starts here."));
+
+        let output = tempdir.path().join("report.md");
+        let message = run_request(
+            &config,
+            &mut resources,
+            "tools/call",
+            json!({
+                "name": "task_export",
+                "arguments": { "taskId": "task-export", "outputPath": output.to_string_lossy() }
+            }),
+        )?;
+        let result = expect_response(message).result;
+        assert_eq!(
+            result["structuredContent"]["path"],
+            json!(output.to_string_lossy())
+        );
+        let report = fs::read_to_string(&output)?;
+        assert!(report.starts_with("# Task task-export\n"));
+        assert!(report.contains("> Explain <main>"));
+
+        let existing = tempdir.path().join("notes.txt");
+        fs::write(&existing, "keep me")?;
+        let message = run_request(
+            &config,
+            &mut resources,
+            "tools/call",
+            json!({
+                "name": "task_export",
+                "arguments": { "taskId": "task-export", "outputPath": existing.to_string_lossy() }
+            }),
+        )?;
+        let result = expect_response(message).result;
+        assert_eq!(result["isError"], json!(true));
+        assert!(
+            result["content"][0]["text"]
+                .as_str()
+                .unwrap_or_default()
+                .contains("Failed to write")
+        );
+        assert_eq!(fs::read_to_string(&existing)?, "keep me");
+        Ok(())
+    }
+
+    #[test]
+    fn task_wait_selects_tasks_by_tag_and_title() -> Result<()> {
+        let (config, _tempdir) = config_with_temp_store()?;
+        for (id, title, tags) in [
+            ("task-nightly", "Nightly build", vec!["ci".to_string()]),
+            (
+                "task-docs",
+                "Docs build",
+                vec!["ci".to_string(), "docs".to_string()],
+            ),
+            ("task-other", "Nightly notes", Vec::new()),
+        ] {
+            let mut metadata =
+                TaskMetadata::new(id.to_string(), Some(title.to_string()), TaskState::Stopped);
+            metadata.tags = tags;
+            config
+                .store
+                .task(id.to_string())
+                .write_metadata(&metadata)?;
+        }
+        let mut resources = ResourceState::default();
+        let mut wait = |arguments: JsonValue| -> Result<JsonValue> {
+            let message = run_request(
+                &config,
+                &mut resources,
+                "tools/call",
+                json!({ "name": "task_wait", "arguments": arguments }),
+            )?;
+            Ok(expect_response(message).result)
+        };
+        let ids = |result: &JsonValue| -> Vec<String> {
+            let mut ids: Vec<String> = result["structuredContent"]["tasks"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|task| task["id"].as_str().map(str::to_string))
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        let result = wait(json!({ "tags": ["ci"], "title": "NIGHTLY" }))?;
+        assert_eq!(result["structuredContent"]["outcome"], json!("reached"));
+        assert_eq!(ids(&result), vec!["task-nightly".to_string()]);
+
+        let result = wait(json!({ "tags": ["ci"], "states": ["STOPPED"] }))?;
+        assert_eq!(
+            ids(&result),
+            vec!["task-docs".to_string(), "task-nightly".to_string()]
+        );
+
+        let result = wait(json!({}))?;
+        assert_eq!(result["isError"], json!(true));
+        let result = wait(json!({ "taskIds": ["task-docs"], "tags": ["ci"] }))?;
+        assert_eq!(result["isError"], json!(true));
+        Ok(())
+    }
+
     fn config_with_temp_store() -> Result<(McpConfig, tempfile::TempDir)> {
         let tempdir = tempfile::tempdir()?;
         let config = McpConfig {
             store: TaskStore::new(tempdir.path().to_path_buf()),
-            config_path: None,
-            config_document: None,
+            tasks_config: TasksConfig::default(),
             allow_unsafe: false,
         };
         Ok((config, tempdir))
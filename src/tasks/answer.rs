@@ -0,0 +1,159 @@
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+use crate::tasks::transcript::{for_each_event, is_plain_user_message};
+
+/// A fenced code block found in an answer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CodeBlock {
+    /// Language tag following the opening fence, if any.
+    pub lang: Option<String>,
+    pub code: String,
+}
+
+/// Returns the final agent message of every invocation in a task log, in order.
+pub fn invocation_answers(log_path: &Path) -> Result<Vec<Option<String>>> {
+    let mut answers: Vec<Option<String>> = Vec::new();
+    for_each_event(log_path, |value| {
+        match value.get("type").and_then(Value::as_str) {
+            Some("user_message") if is_plain_user_message(value) => answers.push(None),
+            Some("item.completed") => {
+                let item = value.get("item");
+                let is_agent = item
+                    .and_then(|item| item.get("type"))
+                    .and_then(Value::as_str)
+                    == Some("agent_message");
+                let text = item
+                    .filter(|_| is_agent)
+                    .and_then(|item| item.get("text"))
+                    .and_then(Value::as_str);
+                if let (Some(text), Some(current)) = (text, answers.last_mut()) {
+                    *current = Some(text.to_string());
+                }
+            }
+            _ => {}
+        }
+    })?;
+    Ok(answers)
+}
+
+/// Extracts fenced (```` ``` ```` or `~~~`) code blocks, optionally keeping only those tagged
+/// with `lang` (compared case-insensitively).
+pub fn code_blocks(text: &str, lang: Option<&str>) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(String, Option<String>, Vec<&str>)> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match open.take() {
+            None => {
+                if let Some(fence) = fence_marker(trimmed) {
+                    let tag = trimmed[fence.len()..]
+                        .split_whitespace()
+                        .next()
+                        .map(str::to_string);
+                    open = Some((fence, tag, Vec::new()));
+                }
+            }
+            Some((fence, tag, body)) if trimmed.trim_end() == fence => {
+                blocks.push(CodeBlock {
+                    lang: tag,
+                    code: body.iter().map(|line| format!("{line}\n")).collect(),
+                });
+            }
+            Some((fence, tag, mut body)) => {
+                body.push(line);
+                open = Some((fence, tag, body));
+            }
+        }
+    }
+
+    blocks.retain(|block| match lang {
+        Some(lang) => block
+            .lang
+            .as_deref()
+            .is_some_and(|tag| tag.eq_ignore_ascii_case(lang)),
+        None => true,
+    });
+    blocks
+}
+
+/// Parses an answer produced with an output schema: either bare JSON or a single fenced JSON
+/// block.
+pub fn structured_payload(text: &str) -> Result<Value> {
+    if let Ok(value) = serde_json::from_str(text.trim()) {
+        return Ok(value);
+    }
+    match code_blocks(text, None).as_slice() {
+        [block] => serde_json::from_str(&block.code)
+            .map_err(|err| anyhow!("result code block is not valid JSON: {err}")),
+        _ => Err(anyhow!("result is not a JSON payload")),
+    }
+}
+
+fn fence_marker(line: &str) -> Option<String> {
+    ['`', '~'].into_iter().find_map(|symbol| {
+        let count = line.chars().take_while(|c| *c == symbol).count();
+        (count >= 3).then(|| symbol.to_string().repeat(count))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn extracts_code_blocks_by_language() {
+        let text =
+            "Here:\n```rust\nfn main() {}\n```\nand\n~~~sh\ncargo run\n~~~\n```\nplain\n```\n";
+        let all = code_blocks(text, None);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[1].lang.as_deref(), Some("sh"));
+        assert_eq!(all[2].code, "plain\n");
+
+        let rust = code_blocks(text, Some("Rust"));
+        assert_eq!(
+            rust,
+            vec![CodeBlock {
+                lang: Some("rust".to_string()),
+                code: "fn main() {}\n".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_structured_payloads() -> Result<()> {
+        assert_eq!(structured_payload("{\"ok\": true}")?, json!({"ok": true}));
+        assert_eq!(
+            structured_payload("Result:\n```json\n[1, 2]\n```")?,
+            json!([1, 2])
+        );
+        assert!(structured_payload("not json").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn collects_final_answer_per_invocation() -> Result<()> {
+        let tmp = tempdir()?;
+        let log = tmp.path().join("task.log");
+        let events = [
+            json!({"type": "user_message", "message": "first"}),
+            json!({"type": "item.completed", "item": {"type": "agent_message", "text": "draft"}}),
+            json!({"type": "item.completed", "item": {"type": "agent_message", "text": "answer one"}}),
+            json!({"type": "user_message", "kind": "environment_context", "message": "ctx"}),
+            json!({"type": "user_message", "message": "second"}),
+        ];
+        let lines: Vec<String> = events.iter().map(Value::to_string).collect();
+        fs::write(&log, lines.join("\n"))?;
+
+        assert_eq!(
+            invocation_answers(&log)?,
+            vec![Some("answer one".to_string()), None]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+
+use crate::tasks::queue::lock_exclusive;
+use crate::tasks::{METADATA_FILE_NAME, TaskId, TaskMetadata, TaskPaths};
+
+/// File inside the archive root holding one JSON entry per archived task.
+pub const ARCHIVE_INDEX_FILE_NAME: &str = "index.jsonl";
+/// Lock file serialising writes to [`ARCHIVE_INDEX_FILE_NAME`].
+pub const ARCHIVE_INDEX_LOCK_FILE_NAME: &str = "index.lock";
+
+/// Line stored in the archive index for a single archived task.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ArchiveIndexEntry {
+    pub id: TaskId,
+    /// Task directory relative to the archive root (e.g. `2024/05/06/<id>`).
+    pub path: PathBuf,
+    /// Snapshot of the task metadata taken when the entry was written.
+    pub metadata: TaskMetadata,
+}
+
+/// Append-only index mapping archived task ids to their location under `archive/YYYY/MM/DD`.
+///
+/// The index is a cache of the archive tree, which stays authoritative: it is rebuilt from
+/// disk when it is missing or unreadable, when it lists a task whose directory is gone, and
+/// by `doctor --fix`. Lookups that miss the index fall back to scanning the archive and add
+/// what they find.
+#[derive(Clone, Debug)]
+pub struct ArchiveIndex {
+    archive_root: PathBuf,
+}
+
+impl ArchiveIndex {
+    pub fn new(archive_root: PathBuf) -> Self {
+        Self { archive_root }
+    }
+
+    /// Location of the index file.
+    pub fn path(&self) -> PathBuf {
+        self.archive_root.join(ARCHIVE_INDEX_FILE_NAME)
+    }
+
+    /// Records a freshly archived task. When no index exists yet the full index is rebuilt
+    /// instead, so a partial file is never mistaken for a complete one.
+    pub fn record(&self, directory: &Path, metadata: &TaskMetadata) -> Result<()> {
+        let _lock = self.lock()?;
+        let path = self.path();
+        if !path.exists() {
+            self.rebuild_locked()?;
+            return Ok(());
+        }
+
+        let entry = ArchiveIndexEntry {
+            id: metadata.id.clone(),
+            path: self.relative(directory),
+            metadata: metadata.clone(),
+        };
+        let mut line = serde_json::to_string(&entry).context("failed to serialize index entry")?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to open archive index {}", path.display()))?;
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("failed to append to archive index {}", path.display()))
+    }
+
+    /// Returns every indexed archived task, rebuilding the index when it is missing,
+    /// unreadable or lists a task whose directory has been removed.
+    pub fn entries(&self) -> Result<Vec<ArchiveIndexEntry>> {
+        match self.load()? {
+            Some(entries)
+                if entries.iter().all(|entry| {
+                    self.archive_root
+                        .join(&entry.path)
+                        .join(METADATA_FILE_NAME)
+                        .exists()
+                }) =>
+            {
+                Ok(entries)
+            }
+            _ => self.rebuild(),
+        }
+    }
+
+    /// Resolves the archive directory for `task_id`. Tasks missing from the index are looked
+    /// up in the archive tree and recorded when found, which also finds task directories from
+    /// before the index existed that have a log but no metadata.
+    pub fn locate(&self, task_id: &str) -> Result<Option<PathBuf>> {
+        if let Some(entry) = self
+            .entries()?
+            .into_iter()
+            .find(|entry| entry.id == task_id)
+        {
+            return Ok(Some(self.archive_root.join(entry.path)));
+        }
+
+        let Some(directory) = scan_for_task_directory(&self.archive_root, task_id)? else {
+            return Ok(None);
+        };
+        if let Ok(metadata) =
+            TaskPaths::from_directory(directory.clone(), task_id.to_string()).read_metadata()
+        {
+            self.record(&directory, &metadata)?;
+        }
+        Ok(Some(directory))
+    }
+
+    /// Rewrites the index from the archive directories currently on disk.
+    pub fn rebuild(&self) -> Result<Vec<ArchiveIndexEntry>> {
+        if !self.archive_root.exists() {
+            return Ok(Vec::new());
+        }
+        let _lock = self.lock()?;
+        self.rebuild_locked()
+    }
+
+    fn rebuild_locked(&self) -> Result<Vec<ArchiveIndexEntry>> {
+        let mut entries = Vec::new();
+
+        let mut queue = VecDeque::from([self.archive_root.clone()]);
+        while let Some(dir) = queue.pop_front() {
+            if dir.join(METADATA_FILE_NAME).exists() {
+                let Some(task_id) = dir.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                // Unreadable tasks are left out; `doctor` reports them.
+                if let Ok(metadata) =
+                    TaskPaths::from_directory(dir.clone(), task_id.to_string()).read_metadata()
+                {
+                    entries.push(ArchiveIndexEntry {
+                        id: metadata.id.clone(),
+                        path: self.relative(&dir),
+                        metadata,
+                    });
+                }
+                continue;
+            }
+
+            for directory in child_directories(&dir)? {
+                queue.push_back(directory);
+            }
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut temp = NamedTempFile::new_in(&self.archive_root).with_context(|| {
+            format!(
+                "failed to create temporary index in {}",
+                self.archive_root.display()
+            )
+        })?;
+        for entry in &entries {
+            serde_json::to_writer(&mut temp, entry).context("failed to serialize index entry")?;
+            temp.write_all(b"\n")
+                .context("failed to write archive index")?;
+        }
+        let path = self.path();
+        temp.persist(&path)
+            .with_context(|| format!("failed to persist archive index {}", path.display()))?;
+        Ok(entries)
+    }
+
+    /// Reads the index without repairing it, returning `None` when it is missing or contains
+    /// malformed lines.
+    pub fn load(&self) -> Result<Option<Vec<ArchiveIndexEntry>>> {
+        let path = self.path();
+        let file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to open archive index {}", path.display()));
+            }
+        };
+
+        let mut positions: HashMap<TaskId, usize> = HashMap::new();
+        let mut entries: Vec<ArchiveIndexEntry> = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line =
+                line.with_context(|| format!("failed to read archive index {}", path.display()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Ok(entry) = serde_json::from_str::<ArchiveIndexEntry>(&line) else {
+                return Ok(None);
+            };
+            match positions.get(&entry.id) {
+                Some(&index) => entries[index] = entry,
+                None => {
+                    positions.insert(entry.id.clone(), entries.len());
+                    entries.push(entry);
+                }
+            }
+        }
+        Ok(Some(entries))
+    }
+
+    /// Takes the exclusive lock guarding index writes; released when the file is dropped.
+    fn lock(&self) -> Result<File> {
+        lock_exclusive(&self.archive_root.join(ARCHIVE_INDEX_LOCK_FILE_NAME))
+    }
+
+    fn relative(&self, directory: &Path) -> PathBuf {
+        directory
+            .strip_prefix(&self.archive_root)
+            .unwrap_or(directory)
+            .to_path_buf()
+    }
+}
+
+fn scan_for_task_directory(archive_root: &Path, task_id: &str) -> Result<Option<PathBuf>> {
+    let mut queue = VecDeque::from([archive_root.to_path_buf()]);
+    while let Some(dir) = queue.pop_front() {
+        if dir
+            .file_name()
+            .and_then(|value| value.to_str())
+            .is_some_and(|name| name == task_id)
+        {
+            return Ok(Some(dir));
+        }
+        for directory in child_directories(&dir)? {
+            queue.push_back(directory);
+        }
+    }
+    Ok(None)
+}
+
+fn child_directories(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to read archive directory {}", dir.display()));
+        }
+    };
+
+    let mut directories = Vec::new();
+    for entry in entries {
+        let entry = entry
+            .with_context(|| format!("failed to inspect archive entry in {}", dir.display()))?;
+        if entry.file_type()?.is_dir() {
+            directories.push(entry.path());
+        }
+    }
+    Ok(directories)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{TaskState, TaskStore};
+    use chrono::{TimeZone, Utc};
+    use tempfile::tempdir;
+
+    fn archive(store: &TaskStore, day: u32, task_id: &str) -> PathBuf {
+        let timestamp = Utc
+            .with_ymd_and_hms(2024, 5, day, 7, 8, 9)
+            .single()
+            .expect("timestamp");
+        let directory = store
+            .ensure_archive_task_dir(timestamp, &task_id.to_string())
+            .expect("archive dir");
+        TaskPaths::from_directory(directory.clone(), task_id.to_string())
+            .write_metadata(&TaskMetadata::new(
+                task_id.to_string(),
+                None,
+                TaskState::Archived,
+            ))
+            .expect("write metadata");
+        directory
+    }
+
+    #[test]
+    fn rebuilds_missing_index_and_appends_new_entries() -> Result<()> {
+        let tmp = tempdir()?;
+        let store = TaskStore::new(tmp.path().join("root"));
+        store.ensure_layout()?;
+        archive(&store, 1, "task-a");
+        let index = store.archive_index();
+        assert!(!index.path().exists());
+
+        let entries = index.entries()?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, PathBuf::from("2024/05/01/task-a"));
+        assert!(index.path().exists());
+
+        let directory = archive(&store, 2, "task-b");
+        index.record(
+            &directory,
+            &TaskMetadata::new("task-b".to_string(), None, TaskState::Archived),
+        )?;
+        let ids: Vec<_> = index.entries()?.into_iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec!["task-a".to_string(), "task-b".to_string()]);
+        assert_eq!(index.locate("task-b")?, Some(directory));
+        Ok(())
+    }
+
+    #[test]
+    fn lookups_fall_back_to_the_archive_tree_and_heal_the_index() -> Result<()> {
+        let tmp = tempdir()?;
+        let store = TaskStore::new(tmp.path().join("root"));
+        store.ensure_layout()?;
+        let removed = archive(&store, 1, "task-gone");
+        let index = store.archive_index();
+        index.rebuild()?;
+
+        fs::remove_dir_all(&removed)?;
+        let unindexed = archive(&store, 3, "task-unindexed");
+
+        assert_eq!(index.locate("task-unindexed")?, Some(unindexed.clone()));
+        let ids: Vec<_> = index.entries()?.into_iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec!["task-unindexed".to_string()]);
+        let indexed: Vec<_> = index
+            .load()?
+            .expect("index")
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect();
+        assert_eq!(indexed, vec!["task-unindexed".to_string()]);
+
+        assert_eq!(index.locate("task-gone")?, None);
+        assert_eq!(index.locate("task-unindexed")?, Some(unindexed));
+        Ok(())
+    }
+}
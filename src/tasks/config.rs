@@ -0,0 +1,537 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use chrono::Local;
+use dirs::home_dir;
+use serde::{Deserialize, Serialize};
+
+use crate::tasks::hooks::DEFAULT_HOOK_TIMEOUT_SECS;
+use crate::tasks::manifest::render;
+use crate::tasks::{ExecOptions, Hooks, TaskStore};
+use crate::timefmt::{format_duration, parse_duration};
+
+/// Name of the configuration file read from the default store root.
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+/// Overrides the configuration file location; exported to workers so they read the same file.
+pub const CONFIG_ENV_VAR: &str = "CODEX_TASKS_CONFIG";
+
+/// How long `stop` waits for a worker to exit when no `timeouts.stop` is configured.
+pub const DEFAULT_STOP_TIMEOUT_SECS: u64 = 10;
+/// How long a webhook request may take when no `timeouts.webhook` is configured.
+pub const DEFAULT_WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+/// On-disk layout of `config.toml`; also used to print the effective configuration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub defaults: DefaultsSection,
+    #[serde(default)]
+    pub exec: ExecSection,
+    #[serde(default)]
+    pub timeouts: TimeoutsSection,
+    #[serde(default)]
+    pub limits: LimitsSection,
+    /// Lifecycle hooks run for every task, below those saved with `hooks --set`.
+    #[serde(default)]
+    pub hooks: Hooks,
+    #[serde(default)]
+    pub retention: RetentionSection,
+    /// Named bundles of `start` settings, selected with `start --preset NAME`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<String, PresetSpec>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DefaultsSection {
+    /// Codex `config.toml` used by tasks started without `--config-file`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_file: Option<String>,
+    /// Directory that relative task working directories are resolved against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir_root: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExecSection {
+    /// Extra arguments passed to every `codex exec` invocation.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeoutsSection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hook: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsSection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_running: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionSection {
+    /// STOPPED and DIED tasks untouched for this long are archived by `archive --expired` and
+    /// `scheduler run`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_after: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PresetSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_file: Option<String>,
+    /// Working directory; may use `{{title}}`, `{{preset}}`, and `{{date}}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub repo_ref: Option<String>,
+    /// Extra arguments passed to `codex exec`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// How long each invocation may run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+}
+
+/// Validated preset; values given explicitly to `start` take precedence over it.
+#[derive(Clone, Debug, Default)]
+pub struct Preset {
+    pub config_file: Option<PathBuf>,
+    pub working_dir: Option<String>,
+    pub repo: Option<String>,
+    pub repo_ref: Option<String>,
+    pub tags: Vec<String>,
+    pub exec: ExecOptions,
+}
+
+impl Preset {
+    fn from_spec(name: &str, spec: PresetSpec, base_dir: &Path) -> Result<Self> {
+        let key = |field: &str| format!("presets.{name}.{field}");
+        if let Some(variable) = spec
+            .env
+            .keys()
+            .find(|variable| variable.is_empty() || variable.contains('='))
+        {
+            bail!("`{}`: invalid variable name {variable:?}", key("env"));
+        }
+        Ok(Self {
+            config_file: config_file_path(&key("config_file"), spec.config_file, base_dir)?,
+            working_dir: spec
+                .working_dir
+                .map(|pattern| match pattern.strip_prefix("~/") {
+                    Some(rest) => home_dir().map_or(pattern.clone(), |home| {
+                        home.join(rest).to_string_lossy().into_owned()
+                    }),
+                    None => pattern,
+                }),
+            repo: spec.repo,
+            repo_ref: spec.repo_ref,
+            tags: spec.tags,
+            exec: ExecOptions {
+                args: spec.args,
+                env: spec.env,
+                timeout_secs: duration(&key("timeout"), &spec.timeout)?
+                    .map(|timeout| timeout.as_secs()),
+            },
+        })
+    }
+
+    fn to_spec(&self) -> PresetSpec {
+        PresetSpec {
+            config_file: self
+                .config_file
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned()),
+            working_dir: self.working_dir.clone(),
+            repo: self.repo.clone(),
+            repo_ref: self.repo_ref.clone(),
+            args: self.exec.args.clone(),
+            env: self.exec.env.clone(),
+            tags: self.tags.clone(),
+            timeout: self
+                .exec
+                .timeout_secs
+                .map(|secs| format_duration(Duration::from_secs(secs))),
+        }
+    }
+
+    /// Expands the `working_dir` pattern for a task started with this preset.
+    pub fn render_working_dir(&self, name: &str, title: Option<&str>) -> Result<Option<PathBuf>> {
+        let Some(pattern) = &self.working_dir else {
+            return Ok(None);
+        };
+        let mut vars = BTreeMap::from([
+            ("preset".to_string(), name.to_string()),
+            (
+                "date".to_string(),
+                Local::now().format("%Y-%m-%d").to_string(),
+            ),
+        ]);
+        if let Some(title) = title {
+            vars.insert("title".to_string(), slug(title));
+        }
+        render(pattern, &vars, &[])
+            .map(|dir| Some(PathBuf::from(dir)))
+            .with_context(|| format!("failed to expand `presets.{name}.working_dir`"))
+    }
+}
+
+/// Validated `codex-tasks` configuration, with built-in defaults for everything left unset.
+#[derive(Clone, Debug)]
+pub struct TasksConfig {
+    /// File the configuration was read from; `None` when only built-in defaults apply.
+    pub path: Option<PathBuf>,
+    pub config_file: Option<PathBuf>,
+    pub working_dir_root: Option<PathBuf>,
+    pub exec_args: Vec<String>,
+    pub stop_timeout: Duration,
+    /// Default for `wait` and `task_wait`; `None` keeps their own defaults.
+    pub wait_timeout: Option<Duration>,
+    pub hook_timeout: Duration,
+    pub webhook_timeout: Duration,
+    pub max_running: Option<usize>,
+    pub hooks: Hooks,
+    pub archive_after: Option<Duration>,
+    pub presets: BTreeMap<String, Preset>,
+}
+
+impl Default for TasksConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            config_file: None,
+            working_dir_root: None,
+            exec_args: Vec::new(),
+            stop_timeout: Duration::from_secs(DEFAULT_STOP_TIMEOUT_SECS),
+            wait_timeout: None,
+            hook_timeout: Duration::from_secs(DEFAULT_HOOK_TIMEOUT_SECS),
+            webhook_timeout: Duration::from_secs(DEFAULT_WEBHOOK_TIMEOUT_SECS),
+            max_running: None,
+            hooks: Hooks::default(),
+            archive_after: None,
+            presets: BTreeMap::new(),
+        }
+    }
+}
+
+impl TasksConfig {
+    /// Reads the configuration from `explicit`, else from [`CONFIG_ENV_VAR`], else from
+    /// `~/.codex/tasks/config.toml`. Only the default location may be missing.
+    pub fn load(explicit: Option<&Path>) -> Result<Self> {
+        let from_env = env::var_os(CONFIG_ENV_VAR)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from);
+        let (path, required) = match explicit.map(Path::to_path_buf).or(from_env) {
+            Some(path) => (path, true),
+            None => match TaskStore::default() {
+                Ok(store) => (store.root().join(CONFIG_FILE_NAME), false),
+                Err(_) => return Ok(Self::default()),
+            },
+        };
+        let path = if path.is_absolute() {
+            path
+        } else {
+            env::current_dir()
+                .context("failed to determine current working directory")?
+                .join(path)
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(&contents, &path)
+                .with_context(|| format!("invalid config file {}", path.display())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !required => Ok(Self::default()),
+            Err(err) => {
+                Err(err).with_context(|| format!("failed to read config file {}", path.display()))
+            }
+        }
+    }
+
+    /// Parses and validates `contents`, resolving relative paths against `path`'s directory.
+    pub fn parse(contents: &str, path: &Path) -> Result<Self> {
+        let file: ConfigFile = toml::from_str(contents)?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        let defaults = Self::default();
+
+        ensure!(
+            file.hooks.timeout_secs.is_none(),
+            "`hooks.timeout_secs` is not supported here; set `timeouts.hook` instead"
+        );
+        ensure!(
+            file.limits.max_running != Some(0),
+            "`limits.max_running` must be at least 1"
+        );
+        let config_file =
+            config_file_path("defaults.config_file", file.defaults.config_file, base_dir)?;
+        let presets = file
+            .presets
+            .into_iter()
+            .map(|(name, spec)| {
+                let preset = Preset::from_spec(&name, spec, base_dir)?;
+                Ok((name, preset))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            config_file,
+            working_dir_root: file
+                .defaults
+                .working_dir_root
+                .as_deref()
+                .map(|value| resolve_path(value, base_dir))
+                .transpose()?,
+            exec_args: file.exec.args,
+            stop_timeout: duration("timeouts.stop", &file.timeouts.stop)?
+                .unwrap_or(defaults.stop_timeout),
+            wait_timeout: duration("timeouts.wait", &file.timeouts.wait)?,
+            hook_timeout: duration("timeouts.hook", &file.timeouts.hook)?
+                .unwrap_or(defaults.hook_timeout),
+            webhook_timeout: duration("timeouts.webhook", &file.timeouts.webhook)?
+                .unwrap_or(defaults.webhook_timeout),
+            max_running: file.limits.max_running,
+            hooks: file.hooks,
+            archive_after: duration("retention.archive_after", &file.retention.archive_after)?,
+            presets,
+        })
+    }
+
+    /// Renders the configuration in the layout of `config.toml`, defaults included.
+    pub fn to_file(&self) -> ConfigFile {
+        let display = |path: &PathBuf| path.to_string_lossy().into_owned();
+        ConfigFile {
+            defaults: DefaultsSection {
+                config_file: self.config_file.as_ref().map(display),
+                working_dir_root: self.working_dir_root.as_ref().map(display),
+            },
+            exec: ExecSection {
+                args: self.exec_args.clone(),
+            },
+            timeouts: TimeoutsSection {
+                stop: Some(format_duration(self.stop_timeout)),
+                wait: self.wait_timeout.map(format_duration),
+                hook: Some(format_duration(self.hook_timeout)),
+                webhook: Some(format_duration(self.webhook_timeout)),
+            },
+            limits: LimitsSection {
+                max_running: self.max_running,
+            },
+            hooks: self.hooks.clone(),
+            retention: RetentionSection {
+                archive_after: self.archive_after.map(format_duration),
+            },
+            presets: self
+                .presets
+                .iter()
+                .map(|(name, preset)| (name.clone(), preset.to_spec()))
+                .collect(),
+        }
+    }
+
+    /// Looks up a preset by name.
+    pub fn preset(&self, name: &str) -> Result<&Preset> {
+        self.presets.get(name).ok_or_else(|| {
+            if self.presets.is_empty() {
+                anyhow!("unknown preset `{name}`: no presets are configured")
+            } else {
+                let names: Vec<&str> = self.presets.keys().map(String::as_str).collect();
+                anyhow!("unknown preset `{name}` (configured: {})", names.join(", "))
+            }
+        })
+    }
+}
+
+fn duration(key: &str, value: &Option<String>) -> Result<Option<Duration>> {
+    value
+        .as_deref()
+        .map(|value| parse_duration(value).map_err(|err| anyhow!("`{key}`: {err}")))
+        .transpose()
+}
+
+/// Resolves a Codex `config.toml` path, which must keep that file name.
+fn config_file_path(key: &str, value: Option<String>, base_dir: &Path) -> Result<Option<PathBuf>> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let path = resolve_path(&value, base_dir)?;
+    ensure!(
+        path.file_name() == Some("config.toml".as_ref()),
+        "`{key}` must point at a file named `config.toml` (got {})",
+        path.display()
+    );
+    Ok(Some(path))
+}
+
+/// Lower-cases `text` and replaces runs of other characters than letters and digits with `-`.
+fn slug(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if ch.is_alphanumeric() {
+            slug.extend(ch.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Expands a leading `~/` and resolves relative paths against `base_dir`.
+fn resolve_path(value: &str, base_dir: &Path) -> Result<PathBuf> {
+    let path = match value.strip_prefix("~/") {
+        Some(rest) => home_dir()
+            .context("failed to locate home directory")?
+            .join(rest),
+        None => PathBuf::from(value),
+    };
+    Ok(if path.is_absolute() {
+        path
+    } else {
+        base_dir.join(path)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sections_and_fills_defaults() -> Result<()> {
+        let config = TasksConfig::parse(
+            r#"
+[defaults]
+config_file = "codex/config.toml"
+working_dir_root = "/srv/work"
+
+[exec]
+args = ["--model", "o3"]
+
+[timeouts]
+hook = "2m"
+wait = "90"
+
+[limits]
+max_running = 3
+
+[hooks]
+on_died = "notify-send died"
+
+[retention]
+archive_after = "30d"
+"#,
+            Path::new("/etc/codex-tasks/config.toml"),
+        )?;
+        assert_eq!(
+            config.config_file,
+            Some(PathBuf::from("/etc/codex-tasks/codex/config.toml"))
+        );
+        assert_eq!(config.working_dir_root, Some(PathBuf::from("/srv/work")));
+        assert_eq!(config.exec_args, ["--model", "o3"]);
+        assert_eq!(config.hook_timeout, Duration::from_secs(120));
+        assert_eq!(config.wait_timeout, Some(Duration::from_secs(90)));
+        assert_eq!(
+            config.stop_timeout,
+            Duration::from_secs(DEFAULT_STOP_TIMEOUT_SECS)
+        );
+        assert_eq!(config.max_running, Some(3));
+        assert_eq!(config.hooks.on_died.as_deref(), Some("notify-send died"));
+        assert_eq!(config.archive_after, Some(Duration::from_secs(30 * 86_400)));
+
+        let rendered = toml::to_string(&config.to_file())?;
+        assert!(rendered.contains("stop = \"10s\""), "{rendered}");
+        assert!(rendered.contains("archive_after = \"30d\""), "{rendered}");
+
+        // The rendered file parses back to the same limits and hooks.
+        let reparsed = TasksConfig::parse(&rendered, Path::new("/etc/codex-tasks/config.toml"))?;
+        assert_eq!(reparsed.max_running, Some(3));
+        assert_eq!(reparsed.hooks, config.hooks);
+        assert_eq!(reparsed.archive_after, config.archive_after);
+        Ok(())
+    }
+
+    #[test]
+    fn presets_expand_working_dir_and_merge_exec_options() -> Result<()> {
+        let config = TasksConfig::parse(
+            r#"
+[presets.review]
+config_file = "review/config.toml"
+working_dir = "/work/{{preset}}/{{title}}"
+tags = ["review"]
+args = ["--model", "o3"]
+env = { RUST_LOG = "debug", CI = "1" }
+timeout = "30m"
+"#,
+            Path::new("/etc/codex-tasks/config.toml"),
+        )?;
+        let preset = config.preset("review")?;
+        assert_eq!(
+            preset.config_file,
+            Some(PathBuf::from("/etc/codex-tasks/review/config.toml"))
+        );
+        assert_eq!(
+            preset.render_working_dir("review", Some("Fix Login / SSO"))?,
+            Some(PathBuf::from("/work/review/fix-login-sso"))
+        );
+        assert!(preset.render_working_dir("review", None).is_err());
+        assert_eq!(preset.exec.timeout_secs, Some(1_800));
+
+        let explicit = ExecOptions {
+            env: BTreeMap::from([("CI".to_string(), "0".to_string())]),
+            timeout_secs: Some(60),
+            ..ExecOptions::default()
+        };
+        let merged = explicit.or(&preset.exec);
+        assert_eq!(merged.args, ["--model", "o3"]);
+        assert_eq!(merged.env["CI"], "0");
+        assert_eq!(merged.env["RUST_LOG"], "debug");
+        assert_eq!(merged.timeout_secs, Some(60));
+
+        let err = config.preset("deploy").expect_err("unknown preset");
+        assert!(err.to_string().contains("configured: review"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn errors_name_the_offending_key() {
+        let path = Path::new("config.toml");
+        let message = |contents: &str| {
+            format!(
+                "{:#}",
+                TasksConfig::parse(contents, path).expect_err("expected error")
+            )
+        };
+        assert!(message("[timeouts]\nstop = \"soon\"\n").contains("`timeouts.stop`"));
+        assert!(message("[limits]\nmax_running = 0\n").contains("`limits.max_running`"));
+        assert!(message("[exec]\nflags = []\n").contains("unknown field `flags`"));
+        assert!(message("[limits]\nmax_running = \"two\"\n").contains("max_running"));
+        assert!(message("[exec]\nargs = \"--model\"\n").contains("args"));
+        assert!(
+            message("[retention]\narchive_after = \"2w\"\n").contains("`retention.archive_after`")
+        );
+        assert!(message("[presets.ci]\ntimeout = \"later\"\n").contains("`presets.ci.timeout`"));
+    }
+}
@@ -0,0 +1,578 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde_json::Value as JsonValue;
+
+use crate::commands::common::is_process_running;
+use crate::tasks::{
+    METADATA_FILE_NAME, RESULT_TEMP_PREFIX, TaskPaths, TaskState, TaskStore, derive_active_state,
+};
+
+/// Default prefix used by `tempfile` for files created before workers named their temp files.
+const LEGACY_TEMP_PREFIX: &str = ".tmp";
+
+/// Temp files inside task directories only live for the duration of an atomic metadata write.
+const TASK_DIR_TEMP_GRACE: Duration = Duration::from_secs(60);
+
+/// Classes of store inconsistencies detected by [`diagnose_store`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DoctorIssueKind {
+    CorruptMetadata,
+    IdMismatch,
+    StalePid,
+    OrphanedRunning,
+    LeakedTempFile,
+    StaleArchiveIndex,
+}
+
+impl DoctorIssueKind {
+    /// Returns the kebab-case label used in CLI output.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DoctorIssueKind::CorruptMetadata => "corrupt-metadata",
+            DoctorIssueKind::IdMismatch => "id-mismatch",
+            DoctorIssueKind::StalePid => "stale-pid",
+            DoctorIssueKind::OrphanedRunning => "orphaned-running",
+            DoctorIssueKind::LeakedTempFile => "leaked-temp-file",
+            DoctorIssueKind::StaleArchiveIndex => "stale-archive-index",
+        }
+    }
+}
+
+/// A single problem found while scanning the store.
+#[derive(Debug)]
+pub struct DoctorIssue {
+    pub kind: DoctorIssueKind,
+    pub path: PathBuf,
+    pub detail: String,
+    /// Description of the repair applied when running with `fix`.
+    pub fix: Option<String>,
+}
+
+/// Summary produced by [`diagnose_store`].
+#[derive(Debug, Default)]
+pub struct DoctorReport {
+    pub scanned: usize,
+    pub issues: Vec<DoctorIssue>,
+}
+
+impl DoctorReport {
+    /// Number of issues that remain unrepaired.
+    pub fn unresolved(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.fix.is_none())
+            .count()
+    }
+}
+
+/// Scans active and archived tasks for inconsistencies, optionally repairing them.
+pub fn diagnose_store(store: &TaskStore, fix: bool) -> Result<DoctorReport> {
+    let mut report = DoctorReport::default();
+    let mut any_running = false;
+
+    for directory in child_directories(store.root())? {
+        if !directory.join(METADATA_FILE_NAME).exists() {
+            continue;
+        }
+        report.scanned += 1;
+        any_running |= inspect_task(store, &directory, false, fix, &mut report)?;
+        inspect_task_temp_files(&directory, fix, &mut report)?;
+    }
+
+    let mut archived = Vec::new();
+    let mut queue = vec![store.archive_root()];
+    while let Some(dir) = queue.pop() {
+        for directory in child_directories(&dir)? {
+            if directory.join(METADATA_FILE_NAME).exists() {
+                report.scanned += 1;
+                inspect_task(store, &directory, true, fix, &mut report)?;
+                archived.push(directory);
+            } else {
+                queue.push(directory);
+            }
+        }
+    }
+
+    inspect_archive_index(store, &archived, fix, &mut report)?;
+    inspect_root_temp_files(store.root(), any_running, fix, &mut report)?;
+    Ok(report)
+}
+
+/// Compares the archive index with the readable archived tasks found on disk.
+fn inspect_archive_index(
+    store: &TaskStore,
+    archived: &[PathBuf],
+    fix: bool,
+    report: &mut DoctorReport,
+) -> Result<()> {
+    let archive_root = store.archive_root();
+    let mut on_disk: Vec<PathBuf> = archived
+        .iter()
+        .filter(|directory| {
+            let task_id = directory
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            TaskPaths::from_directory(directory.to_path_buf(), task_id.to_string())
+                .peek_metadata()
+                .is_ok()
+        })
+        .filter_map(|directory| directory.strip_prefix(&archive_root).ok())
+        .map(Path::to_path_buf)
+        .collect();
+    on_disk.sort();
+
+    let index = store.archive_index();
+    let detail = match index.load()? {
+        None if on_disk.is_empty() => return Ok(()),
+        None => "archive index is missing or unreadable".to_string(),
+        Some(entries) => {
+            let mut indexed: Vec<PathBuf> = entries.into_iter().map(|entry| entry.path).collect();
+            indexed.sort();
+            if indexed == on_disk {
+                return Ok(());
+            }
+            let stale = indexed
+                .iter()
+                .filter(|path| !on_disk.contains(path))
+                .count();
+            let missing = on_disk
+                .iter()
+                .filter(|path| !indexed.contains(path))
+                .count();
+            format!(
+                "archive index lists {stale} task(s) that are not on disk and misses {missing} \
+                 archived task(s)"
+            )
+        }
+    };
+    let fixed = fix && index.rebuild().is_ok();
+    report.issues.push(DoctorIssue {
+        kind: DoctorIssueKind::StaleArchiveIndex,
+        path: index.path(),
+        detail,
+        fix: fixed.then(|| "rebuilt index".to_string()),
+    });
+    Ok(())
+}
+
+/// Inspects one task directory, returning whether its worker is currently running.
+fn inspect_task(
+    store: &TaskStore,
+    directory: &Path,
+    archived: bool,
+    fix: bool,
+    report: &mut DoctorReport,
+) -> Result<bool> {
+    let Some(dir_name) = directory.file_name().and_then(|name| name.to_str()) else {
+        return Ok(false);
+    };
+    let paths = TaskPaths::from_directory(directory.to_path_buf(), dir_name.to_string());
+
+    let recorded_id = fs::read_to_string(paths.metadata_path())
+        .ok()
+        .and_then(|raw| serde_json::from_str::<JsonValue>(&raw).ok())
+        .and_then(|value| {
+            value
+                .get("id")
+                .and_then(JsonValue::as_str)
+                .map(str::to_owned)
+        });
+    if let Some(id) = recorded_id.as_deref().filter(|id| *id != dir_name) {
+        let detail = format!("metadata id {id} does not match directory {dir_name}");
+        push_quarantined(
+            store,
+            directory,
+            DoctorIssueKind::IdMismatch,
+            detail,
+            fix,
+            report,
+        )?;
+        return Ok(false);
+    }
+
+    // Report-only runs must not migrate or relocate anything.
+    let loaded = if fix {
+        paths.read_metadata()
+    } else {
+        paths.peek_metadata()
+    };
+    let mut metadata = match loaded {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            let detail = format!("{err:#}");
+            push_quarantined(
+                store,
+                directory,
+                DoctorIssueKind::CorruptMetadata,
+                detail,
+                fix,
+                report,
+            )?;
+            return Ok(false);
+        }
+    };
+
+    let pid = match paths.read_pid() {
+        Ok(pid) => pid,
+        Err(err) => {
+            let fixed = fix && paths.remove_pid().is_ok();
+            report.issues.push(DoctorIssue {
+                kind: DoctorIssueKind::StalePid,
+                path: paths.pid_path(),
+                detail: format!("{err:#}"),
+                fix: fixed.then(|| "removed pid file".to_string()),
+            });
+            None
+        }
+    };
+
+    let alive = match pid {
+        Some(pid) => is_process_running(pid)?,
+        None => false,
+    };
+    if let Some(pid) = pid.filter(|_| archived || !alive) {
+        let fixed = fix && paths.remove_pid().is_ok();
+        let detail = if archived {
+            format!("archived task still has pid file for process {pid}")
+        } else {
+            format!("process {pid} is no longer running")
+        };
+        report.issues.push(DoctorIssue {
+            kind: DoctorIssueKind::StalePid,
+            path: paths.pid_path(),
+            detail,
+            fix: fixed.then(|| "removed pid file".to_string()),
+        });
+    }
+
+    if !archived
+        && metadata.state == TaskState::Running
+        && derive_active_state(&metadata, pid.filter(|_| alive)) == TaskState::Died
+    {
+        let fixed = if fix {
+            metadata.set_state(TaskState::Died);
+            paths.write_metadata(&metadata)?;
+            Some("marked task DIED".to_string())
+        } else {
+            None
+        };
+        report.issues.push(DoctorIssue {
+            kind: DoctorIssueKind::OrphanedRunning,
+            path: paths.metadata_path(),
+            detail: if metadata.reserved.is_some() {
+                format!(
+                    "task {} is RUNNING but the process launching its worker is gone",
+                    metadata.id
+                )
+            } else {
+                format!("task {} is RUNNING but has no live worker", metadata.id)
+            },
+            fix: fixed,
+        });
+    }
+
+    Ok(alive)
+}
+
+fn push_quarantined(
+    store: &TaskStore,
+    directory: &Path,
+    kind: DoctorIssueKind,
+    detail: String,
+    fix: bool,
+    report: &mut DoctorReport,
+) -> Result<()> {
+    let fix = if fix {
+        let destination = quarantine(store, directory)?;
+        Some(format!("quarantined to {}", destination.display()))
+    } else {
+        None
+    };
+    report.issues.push(DoctorIssue {
+        kind,
+        path: directory.to_path_buf(),
+        detail,
+        fix,
+    });
+    Ok(())
+}
+
+fn quarantine(store: &TaskStore, directory: &Path) -> Result<PathBuf> {
+    let quarantine_root = store.quarantine_root();
+    fs::create_dir_all(&quarantine_root).with_context(|| {
+        format!(
+            "failed to create quarantine directory {}",
+            quarantine_root.display()
+        )
+    })?;
+    let name = directory
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("task");
+    let destination = quarantine_root.join(format!(
+        "{name}-{}",
+        Utc::now().format("%Y%m%dT%H%M%S%.3fZ")
+    ));
+    fs::rename(directory, &destination).with_context(|| {
+        format!(
+            "failed to move {} into quarantine at {}",
+            directory.display(),
+            destination.display()
+        )
+    })?;
+    Ok(destination)
+}
+
+fn inspect_root_temp_files(
+    root: &Path,
+    any_running: bool,
+    fix: bool,
+    report: &mut DoctorReport,
+) -> Result<()> {
+    for path in child_files(root)? {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let leaked = if let Some(rest) = name.strip_prefix(RESULT_TEMP_PREFIX) {
+            match rest
+                .split('-')
+                .next()
+                .and_then(|pid| pid.parse::<i32>().ok())
+            {
+                Some(pid) => !is_process_running(pid)?,
+                None => true,
+            }
+        } else if name.starts_with(LEGACY_TEMP_PREFIX) {
+            !any_running
+        } else {
+            false
+        };
+        if leaked {
+            push_temp_file(path, fix, report);
+        }
+    }
+    Ok(())
+}
+
+fn inspect_task_temp_files(directory: &Path, fix: bool, report: &mut DoctorReport) -> Result<()> {
+    for path in child_files(directory)? {
+        let is_temp = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(LEGACY_TEMP_PREFIX));
+        let stale = fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > TASK_DIR_TEMP_GRACE);
+        if is_temp && stale {
+            push_temp_file(path, fix, report);
+        }
+    }
+    Ok(())
+}
+
+fn push_temp_file(path: PathBuf, fix: bool, report: &mut DoctorReport) {
+    let fixed = fix && fs::remove_file(&path).is_ok();
+    report.issues.push(DoctorIssue {
+        kind: DoctorIssueKind::LeakedTempFile,
+        path,
+        detail: "temporary file left behind by an interrupted worker".to_string(),
+        fix: fixed.then(|| "deleted file".to_string()),
+    });
+}
+
+fn child_directories(dir: &Path) -> Result<Vec<PathBuf>> {
+    child_entries(dir, true)
+}
+
+fn child_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    child_entries(dir, false)
+}
+
+fn child_entries(dir: &Path, directories: bool) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read directory {}", dir.display()));
+        }
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("failed to read entry in {}", dir.display()))?;
+        let file_type = entry
+            .file_type()
+            .with_context(|| format!("failed to inspect {}", entry.path().display()))?;
+        if (directories && file_type.is_dir()) || (!directories && file_type.is_file()) {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{Reservation, TaskMetadata};
+    use tempfile::tempdir;
+
+    fn unused_pid() -> i32 {
+        (400_000..4_000_000)
+            .find(|candidate| !is_process_running(*candidate).unwrap_or(true))
+            .expect("unused pid")
+    }
+
+    #[test]
+    fn detects_and_repairs_store_problems() -> Result<()> {
+        let tmp = tempdir()?;
+        let store = TaskStore::new(tmp.path().join("store"));
+        store.ensure_layout()?;
+
+        let orphan = store.task("task-orphan".to_string());
+        orphan.write_metadata(&TaskMetadata::new(
+            "task-orphan".to_string(),
+            None,
+            TaskState::Running,
+        ))?;
+        orphan.write_pid(unused_pid())?;
+
+        let corrupt = store.task("task-corrupt".to_string());
+        corrupt.ensure_directory()?;
+        fs::write(corrupt.metadata_path(), "{ not json")?;
+
+        let mismatch = store.task("task-mismatch".to_string());
+        mismatch.ensure_directory()?;
+        fs::write(
+            mismatch.metadata_path(),
+            serde_json::to_string(&TaskMetadata::new(
+                "other-id".to_string(),
+                None,
+                TaskState::Stopped,
+            ))?,
+        )?;
+
+        let leaked = store
+            .root()
+            .join(format!("{RESULT_TEMP_PREFIX}{}-abc", unused_pid()));
+        fs::write(&leaked, "partial")?;
+
+        let report = diagnose_store(&store, false)?;
+        let mut kinds: Vec<_> = report
+            .issues
+            .iter()
+            .map(|issue| issue.kind.as_str())
+            .collect();
+        kinds.sort();
+        assert_eq!(
+            kinds,
+            vec![
+                "corrupt-metadata",
+                "id-mismatch",
+                "leaked-temp-file",
+                "orphaned-running",
+                "stale-pid",
+            ]
+        );
+        assert_eq!(report.unresolved(), 5);
+
+        let report = diagnose_store(&store, true)?;
+        assert_eq!(report.unresolved(), 0);
+        assert!(!leaked.exists());
+        assert!(!corrupt.directory().exists());
+        assert!(!mismatch.directory().exists());
+        assert_eq!(fs::read_dir(store.quarantine_root())?.count(), 2);
+        assert_eq!(orphan.read_pid()?, None);
+        assert_eq!(orphan.read_metadata()?.state, TaskState::Died);
+
+        let report = diagnose_store(&store, false)?;
+        assert!(report.issues.is_empty(), "unexpected issues: {report:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn reserved_slots_count_as_running_while_their_launcher_lives() -> Result<()> {
+        let tmp = tempdir()?;
+        let store = TaskStore::new(tmp.path().join("store"));
+        store.ensure_layout()?;
+        let reserve = |id: &str, launcher_pid: i32| -> Result<()> {
+            let mut metadata = TaskMetadata::new(id.to_string(), None, TaskState::Running);
+            metadata.reserved = Some(Reservation {
+                launcher_pid,
+                reserved_at: Utc::now(),
+            });
+            store.task(id.to_string()).write_metadata(&metadata)
+        };
+        reserve("task-starting", std::process::id() as i32)?;
+        reserve("task-abandoned", unused_pid())?;
+
+        let report = diagnose_store(&store, false)?;
+        assert_eq!(report.issues.len(), 1, "unexpected issues: {report:?}");
+        assert_eq!(report.issues[0].kind, DoctorIssueKind::OrphanedRunning);
+        assert!(report.issues[0].detail.contains("task-abandoned"));
+        assert!(report.issues[0].detail.contains("launching its worker"));
+
+        diagnose_store(&store, true)?;
+        let abandoned = store.task("task-abandoned".to_string()).read_metadata()?;
+        assert_eq!(abandoned.state, TaskState::Died);
+        assert_eq!(abandoned.reserved, None);
+        Ok(())
+    }
+
+    #[test]
+    fn detects_and_rebuilds_stale_archive_index() -> Result<()> {
+        let tmp = tempdir()?;
+        let store = TaskStore::new(tmp.path().join("store"));
+        store.ensure_layout()?;
+        let index = store.archive_index();
+        index.rebuild()?;
+
+        let directory = store.ensure_archive_task_dir(Utc::now(), &"task-archived".to_string())?;
+        TaskPaths::from_directory(directory, "task-archived".to_string()).write_metadata(
+            &TaskMetadata::new("task-archived".to_string(), None, TaskState::Archived),
+        )?;
+
+        let report = diagnose_store(&store, false)?;
+        let kinds: Vec<_> = report.issues.iter().map(|issue| issue.kind).collect();
+        assert_eq!(kinds, vec![DoctorIssueKind::StaleArchiveIndex]);
+        assert_eq!(index.load()?.map(|entries| entries.len()), Some(0));
+
+        let report = diagnose_store(&store, true)?;
+        assert_eq!(report.unresolved(), 0);
+        assert_eq!(index.load()?.map(|entries| entries.len()), Some(1));
+        assert!(diagnose_store(&store, false)?.issues.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn report_only_run_leaves_legacy_tasks_untouched() -> Result<()> {
+        let tmp = tempdir()?;
+        let store = TaskStore::new(tmp.path().join("store"));
+        let legacy = store.task("task-legacy".to_string());
+        legacy.ensure_directory()?;
+        let raw = serde_json::json!({
+            "id": "task-legacy",
+            "state": "IDLE",
+            "created_at": "2025-09-01T10:00:00Z",
+            "updated_at": "2025-09-01T10:05:00Z",
+        })
+        .to_string();
+        fs::write(legacy.metadata_path(), &raw)?;
+        fs::write(legacy.pipe_path(), "")?;
+
+        let report = diagnose_store(&store, false)?;
+        assert!(report.issues.is_empty(), "unexpected issues: {report:?}");
+        assert_eq!(report.scanned, 1);
+        assert_eq!(fs::read_to_string(legacy.metadata_path())?, raw);
+        assert!(legacy.pipe_path().exists());
+        assert!(!store.archive_root().exists());
+        Ok(())
+    }
+}
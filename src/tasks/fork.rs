@@ -0,0 +1,219 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use dirs::home_dir;
+use serde_json::Value;
+use tempfile::NamedTempFile;
+
+use crate::tasks::transcript::{for_each_event, is_plain_user_message};
+
+/// Upper bound on the transcript embedded in a fallback fork preamble.
+const PREAMBLE_MAX_CHARS: usize = 32_000;
+
+/// Resolves the Codex home used by a task: the directory of its custom config file, then
+/// `$CODEX_HOME`, then `~/.codex`.
+pub fn codex_home(config_path: Option<&str>) -> Result<PathBuf> {
+    if let Some(parent) = config_path.and_then(|path| Path::new(path).parent()) {
+        return Ok(parent.to_path_buf());
+    }
+    if let Some(home) = env::var_os("CODEX_HOME").filter(|value| !value.is_empty()) {
+        return Ok(PathBuf::from(home));
+    }
+    let home = home_dir().context("failed to locate home directory")?;
+    Ok(home.join(".codex"))
+}
+
+/// Finds the Codex rollout file (`sessions/**/rollout-*-<thread_id>.jsonl`) for a thread.
+pub fn find_rollout(codex_home: &Path, thread_id: &str) -> Result<Option<PathBuf>> {
+    let suffix = format!("-{thread_id}.jsonl");
+    let mut stack = vec![codex_home.join("sessions")];
+    while let Some(dir) = stack.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!("failed to read sessions directory {}", dir.display())
+                });
+            }
+        };
+        for entry in entries {
+            let entry =
+                entry.with_context(|| format!("failed to read entry in {}", dir.display()))?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                stack.push(path);
+                continue;
+            }
+            let matches = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("rollout-") && name.ends_with(&suffix));
+            if matches {
+                return Ok(Some(path));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Copies a rollout file under a new thread id, rewriting the `session_meta` record so Codex
+/// treats the copy as an independent session. Returns the path of the copy.
+pub fn copy_rollout(rollout: &Path, source_id: &str, new_id: &str) -> Result<PathBuf> {
+    let file_name = rollout
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("rollout path {} has no file name", rollout.display()))?;
+    let stem = file_name
+        .strip_suffix(&format!("{source_id}.jsonl"))
+        .ok_or_else(|| {
+            anyhow!(
+                "rollout {} does not belong to {source_id}",
+                rollout.display()
+            )
+        })?;
+    let parent = rollout
+        .parent()
+        .ok_or_else(|| anyhow!("rollout path {} has no parent", rollout.display()))?;
+    let destination = parent.join(format!("{stem}{new_id}.jsonl"));
+
+    let reader = BufReader::new(
+        File::open(rollout)
+            .with_context(|| format!("failed to open rollout {}", rollout.display()))?,
+    );
+    let mut temp = NamedTempFile::new_in(parent)
+        .with_context(|| format!("failed to create rollout copy in {}", parent.display()))?;
+    for line in reader.lines() {
+        let line = line.with_context(|| format!("failed to read rollout {}", rollout.display()))?;
+        let rewritten = match serde_json::from_str::<Value>(&line) {
+            Ok(mut value) if value.get("type").and_then(Value::as_str) == Some("session_meta") => {
+                let meta = match value.get_mut("payload") {
+                    Some(payload) => payload,
+                    None => &mut value,
+                };
+                meta["id"] = Value::String(new_id.to_string());
+                value.to_string()
+            }
+            _ => line,
+        };
+        writeln!(temp, "{rewritten}").context("failed to write rollout copy")?;
+    }
+    temp.persist(&destination)
+        .with_context(|| format!("failed to persist rollout copy {}", destination.display()))?;
+    Ok(destination)
+}
+
+/// Builds the context sent ahead of the fork prompt when no Codex session file is available:
+/// the source transcript, ending with a line that introduces the new request.
+pub fn build_preamble(source_id: &str, log_path: &Path) -> Result<String> {
+    let mut transcript = String::new();
+    for_each_event(log_path, |value| {
+        if is_plain_user_message(value) {
+            if let Some(message) = value.get("message").and_then(Value::as_str) {
+                transcript.push_str(&format!("User:\n{}\n\n", message.trim_end()));
+            }
+        } else if value.get("type").and_then(Value::as_str) == Some("item.completed") {
+            let item = value.get("item");
+            let is_agent = item
+                .and_then(|item| item.get("type"))
+                .and_then(Value::as_str)
+                == Some("agent_message");
+            if let Some(text) = item
+                .filter(|_| is_agent)
+                .and_then(|item| item.get("text"))
+                .and_then(Value::as_str)
+            {
+                transcript.push_str(&format!("Codex:\n{}\n\n", text.trim_end()));
+            }
+        }
+    })?;
+
+    if transcript.len() > PREAMBLE_MAX_CHARS {
+        let mut cut = transcript.len() - PREAMBLE_MAX_CHARS;
+        while !transcript.is_char_boundary(cut) {
+            cut += 1;
+        }
+        transcript = format!("[earlier conversation omitted]\n\n{}", &transcript[cut..]);
+    }
+
+    Ok(format!(
+        "This conversation continues from Codex task {source_id}. The transcript so far:\n\n\
+         {transcript}Continue from this point with the following request.\n\n"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::transcript::count_invocations;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn copy_rollout_rewrites_session_meta() -> Result<()> {
+        let tmp = tempdir()?;
+        let day = tmp
+            .path()
+            .join("sessions")
+            .join("2025")
+            .join("01")
+            .join("02");
+        fs::create_dir_all(&day)?;
+        let rollout = day.join("rollout-2025-01-02T03-04-05-old-id.jsonl");
+        fs::write(
+            &rollout,
+            format!(
+                "{}\n{}\n",
+                json!({"type": "session_meta", "payload": {"id": "old-id", "cwd": "/w"}}),
+                json!({"type": "response_item", "payload": {"text": "old-id stays"}}),
+            ),
+        )?;
+
+        assert_eq!(find_rollout(tmp.path(), "old-id")?, Some(rollout.clone()));
+        let copy = copy_rollout(&rollout, "old-id", "new-id")?;
+        assert_eq!(
+            copy.file_name().and_then(|name| name.to_str()),
+            Some("rollout-2025-01-02T03-04-05-new-id.jsonl")
+        );
+        let lines: Vec<Value> = fs::read_to_string(&copy)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lines[0]["payload"]["id"], json!("new-id"));
+        assert_eq!(lines[0]["payload"]["cwd"], json!("/w"));
+        assert_eq!(lines[1]["payload"]["text"], json!("old-id stays"));
+        assert!(rollout.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn preamble_includes_prompts_and_replies() -> Result<()> {
+        let tmp = tempdir()?;
+        let log = tmp.path().join("task.log");
+        fs::write(
+            &log,
+            [
+                json!({"type": "user_message", "message": "<environment_context>", "kind": "environment_context"}),
+                json!({"type": "user_message", "message": "first question"}),
+                json!({"type": "item.completed", "item": {"type": "reasoning", "text": "hmm"}}),
+                json!({"type": "item.completed", "item": {"type": "agent_message", "text": "first answer"}}),
+            ]
+            .iter()
+            .map(|value| format!("{value}\n"))
+            .collect::<String>(),
+        )?;
+
+        assert_eq!(count_invocations(&log)?, 1);
+        let preamble = build_preamble("task-src", &log)?;
+        assert!(preamble.contains("Codex task task-src"));
+        assert!(preamble.contains("User:\nfirst question"));
+        assert!(preamble.contains("Codex:\nfirst answer"));
+        assert!(!preamble.contains("hmm"));
+        assert!(!preamble.contains("environment_context"));
+        assert!(preamble.ends_with("following request.\n\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,493 @@
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::tasks::settings::StoreSettings;
+use crate::tasks::{TaskMetadata, TaskPaths, TaskStore, TasksConfig, stamp_log_line};
+
+/// Log event type recording the outcome of a lifecycle hook.
+pub const HOOK_EVENT: &str = "task.hook";
+/// How long a hook may run when neither its configuration nor `timeouts.hook` sets a limit.
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 30;
+
+pub const HOOK_TASK_ID_ENV_VAR: &str = "CODEX_TASK_ID";
+pub const HOOK_EVENT_ENV_VAR: &str = "CODEX_TASK_EVENT";
+pub const HOOK_STATE_ENV_VAR: &str = "CODEX_TASK_STATE";
+pub const HOOK_TITLE_ENV_VAR: &str = "CODEX_TASK_TITLE";
+pub const HOOK_WORKING_DIR_ENV_VAR: &str = "CODEX_TASK_WORKING_DIR";
+pub const HOOK_RESULT_PATH_ENV_VAR: &str = "CODEX_TASK_RESULT_PATH";
+
+/// Captured output beyond this many bytes per stream is dropped from the log.
+const OUTPUT_LIMIT: usize = 16 * 1024;
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How long to keep reading a hook's output after it finished, for processes that left its
+/// process group and still hold the pipes open.
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+/// Moments in a task's lifecycle that can run a hook.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    /// A worker started an invocation.
+    #[value(name = "on_start")]
+    OnStart,
+    /// The last invocation finished and the task is STOPPED.
+    #[value(name = "on_stopped")]
+    OnStopped,
+    /// The last invocation failed (after any automatic retries) and the task DIED.
+    #[value(name = "on_died")]
+    OnDied,
+    /// The task was moved to the archive.
+    #[value(name = "on_archived")]
+    OnArchived,
+    /// Codex completed a turn; the task is still RUNNING.
+    #[value(name = "on_turn_completed")]
+    OnTurnCompleted,
+}
+
+impl HookEvent {
+    pub const ALL: [HookEvent; 5] = [
+        HookEvent::OnStart,
+        HookEvent::OnStopped,
+        HookEvent::OnDied,
+        HookEvent::OnArchived,
+        HookEvent::OnTurnCompleted,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::OnStart => "on_start",
+            HookEvent::OnStopped => "on_stopped",
+            HookEvent::OnDied => "on_died",
+            HookEvent::OnArchived => "on_archived",
+            HookEvent::OnTurnCompleted => "on_turn_completed",
+        }
+    }
+}
+
+impl FromStr for HookEvent {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        HookEvent::ALL
+            .into_iter()
+            .find(|event| event.as_str() == value)
+            .ok_or_else(|| {
+                let names: Vec<&str> = HookEvent::ALL.iter().map(HookEvent::as_str).collect();
+                format!(
+                    "unknown hook event `{value}` (expected one of {})",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Shell commands run on task lifecycle events, configured for the whole store or per task.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_stopped: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_died: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_archived: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_turn_completed: Option<String>,
+    /// Seconds each hook may run before it is killed; defaults to the configured `timeouts.hook`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+impl Hooks {
+    /// Builds hooks from `EVENT=COMMAND` pairs, returning `None` when nothing is configured.
+    pub fn from_specs(specs: Vec<(HookEvent, String)>, timeout_secs: Option<u64>) -> Option<Self> {
+        let mut hooks = Hooks {
+            timeout_secs,
+            ..Hooks::default()
+        };
+        for (event, command) in specs {
+            hooks.set(event, Some(command));
+        }
+        (!hooks.is_empty()).then_some(hooks)
+    }
+
+    fn slot(&mut self, event: HookEvent) -> &mut Option<String> {
+        match event {
+            HookEvent::OnStart => &mut self.on_start,
+            HookEvent::OnStopped => &mut self.on_stopped,
+            HookEvent::OnDied => &mut self.on_died,
+            HookEvent::OnArchived => &mut self.on_archived,
+            HookEvent::OnTurnCompleted => &mut self.on_turn_completed,
+        }
+    }
+
+    /// Command configured for `event`, if any.
+    pub fn command(&self, event: HookEvent) -> Option<&str> {
+        match event {
+            HookEvent::OnStart => self.on_start.as_deref(),
+            HookEvent::OnStopped => self.on_stopped.as_deref(),
+            HookEvent::OnDied => self.on_died.as_deref(),
+            HookEvent::OnArchived => self.on_archived.as_deref(),
+            HookEvent::OnTurnCompleted => self.on_turn_completed.as_deref(),
+        }
+    }
+
+    /// Sets (or, with `None`, removes) the command run on `event`.
+    pub fn set(&mut self, event: HookEvent, command: Option<String>) {
+        *self.slot(event) = command;
+    }
+
+    /// Fills the events left unset here with the commands (and timeout) of `defaults`.
+    pub fn or(mut self, defaults: &Hooks) -> Hooks {
+        for event in HookEvent::ALL {
+            let slot = self.slot(event);
+            if slot.is_none() {
+                *slot = defaults.command(event).map(str::to_string);
+            }
+        }
+        self.timeout_secs = self.timeout_secs.or(defaults.timeout_secs);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        HookEvent::ALL
+            .iter()
+            .all(|event| self.command(*event).is_none())
+            && self.timeout_secs.is_none()
+    }
+
+    /// How long these hooks may run, falling back to `default` when no timeout is set.
+    pub fn timeout_or(&self, default: Duration) -> Duration {
+        self.timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or(default)
+    }
+}
+
+/// Parses an `EVENT=COMMAND` hook specification.
+pub fn parse_hook(spec: &str) -> Result<(HookEvent, String), String> {
+    let (event, command) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected EVENT=COMMAND, got `{spec}`"))?;
+    let event = event.trim().parse::<HookEvent>()?;
+    if command.trim().is_empty() {
+        return Err(format!("hook `{}` has an empty command", event.as_str()));
+    }
+    Ok((event, command.to_string()))
+}
+
+/// Outcome of one hook command.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HookRun {
+    pub event: HookEvent,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+    /// Why the command could not be run at all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub stdout: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub stderr: String,
+}
+
+impl HookRun {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// Serialises the run as a [`HOOK_EVENT`] log line.
+    pub fn to_event(&self) -> String {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        value["type"] = Value::String(HOOK_EVENT.to_string());
+        value.to_string()
+    }
+}
+
+/// Runs the store-wide hook for `event` and then the task's own one, returning their outcomes.
+/// Failures are reported in the returned runs and never as errors, so a broken hook cannot
+/// affect the task.
+pub fn run_hooks(
+    store: &TaskStore,
+    paths: &TaskPaths,
+    event: HookEvent,
+    config: &TasksConfig,
+) -> Vec<HookRun> {
+    let metadata = match paths.read_metadata() {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            eprintln!(
+                "failed to read task {} for {} hooks: {err:#}",
+                paths.id(),
+                event.as_str()
+            );
+            return Vec::new();
+        }
+    };
+    let global = match StoreSettings::load(store) {
+        Ok(settings) => settings.hooks.or(&config.hooks),
+        Err(err) => {
+            eprintln!("failed to load global hooks: {err:#}");
+            Hooks::default()
+        }
+    };
+
+    let mut runs = Vec::new();
+    for hooks in [Some(&global), metadata.hooks.as_ref()]
+        .into_iter()
+        .flatten()
+    {
+        if let Some(command) = hooks.command(event) {
+            let timeout = hooks.timeout_or(config.hook_timeout);
+            runs.push(run_hook(event, command, &metadata, paths, timeout));
+        }
+    }
+    runs
+}
+
+/// Appends hook outcomes to a task log as [`HOOK_EVENT`] lines.
+pub fn record_hook_runs(log_path: &Path, runs: &[HookRun]) -> Result<()> {
+    if runs.is_empty() {
+        return Ok(());
+    }
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .with_context(|| format!("failed to open log {}", log_path.display()))?;
+    for run in runs {
+        writeln!(log, "{}", stamp_log_line(&run.to_event(), Utc::now()))
+            .with_context(|| format!("failed to write log {}", log_path.display()))?;
+    }
+    Ok(())
+}
+
+fn run_hook(
+    event: HookEvent,
+    command: &str,
+    metadata: &TaskMetadata,
+    paths: &TaskPaths,
+    timeout: Duration,
+) -> HookRun {
+    let mut run = HookRun {
+        event,
+        command: command.to_string(),
+        exit_code: None,
+        timed_out: false,
+        error: None,
+        stdout: String::new(),
+        stderr: String::new(),
+    };
+
+    let mut process = Command::new("sh");
+    process
+        .arg("-c")
+        .arg(command)
+        .env(HOOK_TASK_ID_ENV_VAR, &metadata.id)
+        .env(HOOK_EVENT_ENV_VAR, event.as_str())
+        .env(HOOK_STATE_ENV_VAR, metadata.state.as_str())
+        .env(HOOK_RESULT_PATH_ENV_VAR, paths.result_path())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Own process group, so whatever the shell started can be killed along with it.
+        .process_group(0);
+    match &metadata.title {
+        Some(title) => process.env(HOOK_TITLE_ENV_VAR, title),
+        None => process.env_remove(HOOK_TITLE_ENV_VAR),
+    };
+    match metadata
+        .working_dir
+        .as_deref()
+        .filter(|dir| Path::new(dir).is_dir())
+    {
+        Some(dir) => process.env(HOOK_WORKING_DIR_ENV_VAR, dir).current_dir(dir),
+        None => process.env_remove(HOOK_WORKING_DIR_ENV_VAR),
+    };
+
+    let mut child = match process.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            run.error = Some(format!("failed to run hook: {err}"));
+            return run;
+        }
+    };
+    let stdout = child.stdout.take().map(capture);
+    let stderr = child.stderr.take().map(capture);
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if Instant::now() >= deadline => {
+                run.timed_out = true;
+                kill_process_group(child.id());
+                break child.wait().ok();
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(err) => {
+                run.error = Some(format!("failed to wait for hook: {err}"));
+                break None;
+            }
+        }
+    };
+    // Background processes the hook left behind would otherwise keep the pipes open and the
+    // caller waiting for them.
+    kill_process_group(child.id());
+    run.exit_code = status.and_then(|status| status.code());
+    let deadline = Instant::now() + OUTPUT_GRACE;
+    let collect = |output: Option<mpsc::Receiver<String>>| {
+        output
+            .and_then(|output| {
+                let wait = deadline.saturating_duration_since(Instant::now());
+                output.recv_timeout(wait).ok()
+            })
+            .unwrap_or_default()
+    };
+    run.stdout = collect(stdout);
+    run.stderr = collect(stderr);
+    run
+}
+
+fn kill_process_group(leader: u32) {
+    // SAFETY: signals the process group created for the hook by `run_hook`.
+    unsafe { libc::kill(-(leader as i32), libc::SIGKILL) };
+}
+
+/// Reads a hook's output stream on its own thread so a chatty hook cannot block on a full pipe.
+fn capture(mut stream: impl Read + Send + 'static) -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut buffer = [0u8; 4096];
+        while let Ok(read) = stream.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+            let room = OUTPUT_LIMIT.saturating_sub(output.len());
+            output.extend_from_slice(&buffer[..read.min(room)]);
+        }
+        let _ = sender.send(String::from_utf8_lossy(&output).trim_end().to_string());
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::TaskState;
+    use tempfile::tempdir;
+
+    #[test]
+    fn parses_specs_and_merges_with_defaults() {
+        assert_eq!(
+            parse_hook("on_died=notify --loud"),
+            Ok((HookEvent::OnDied, "notify --loud".to_string()))
+        );
+        assert!(parse_hook("on_finish=x").is_err());
+        assert!(parse_hook("on_start=").is_err());
+
+        let mut task = Hooks::default();
+        task.set(HookEvent::OnStopped, Some("task".to_string()));
+        let defaults = Hooks {
+            on_stopped: Some("default".to_string()),
+            on_died: Some("default".to_string()),
+            timeout_secs: Some(5),
+            ..Hooks::default()
+        };
+        let merged = task.or(&defaults);
+        assert_eq!(merged.command(HookEvent::OnStopped), Some("task"));
+        assert_eq!(merged.command(HookEvent::OnDied), Some("default"));
+        assert_eq!(merged.timeout_or(Duration::ZERO), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn runs_global_then_task_hooks_with_task_environment() -> Result<()> {
+        let tmp = tempdir()?;
+        let store = TaskStore::new(tmp.path().join("tasks"));
+        StoreSettings {
+            hooks: Hooks {
+                on_stopped: Some("echo global $CODEX_TASK_EVENT".to_string()),
+                ..Hooks::default()
+            },
+            ..StoreSettings::default()
+        }
+        .save(&store)?;
+
+        let mut metadata = TaskMetadata::new("task-1".to_string(), None, TaskState::Stopped);
+        metadata.title = Some("Nightly".to_string());
+        metadata.hooks = Some(Hooks {
+            on_stopped: Some(
+                "echo \"$CODEX_TASK_ID $CODEX_TASK_STATE $CODEX_TASK_TITLE\"; exit 3".to_string(),
+            ),
+            on_died: Some("sleep 5".to_string()),
+            timeout_secs: Some(1),
+            ..Hooks::default()
+        });
+        store.save_metadata(&metadata)?;
+        let paths = store.task("task-1");
+        let config = TasksConfig::default();
+
+        let runs = run_hooks(&store, &paths, HookEvent::OnStopped, &config);
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].stdout, "global on_stopped");
+        assert!(runs[0].succeeded());
+        assert_eq!(runs[1].stdout, "task-1 STOPPED Nightly");
+        assert_eq!(runs[1].exit_code, Some(3));
+
+        let runs = run_hooks(&store, &paths, HookEvent::OnDied, &config);
+        assert!(runs[0].timed_out);
+        assert!(!runs[0].succeeded());
+        assert!(run_hooks(&store, &paths, HookEvent::OnStart, &config).is_empty());
+
+        record_hook_runs(&paths.log_path(), &runs)?;
+        let log = std::fs::read_to_string(paths.log_path())?;
+        let event: Value = serde_json::from_str(log.trim())?;
+        assert_eq!(event["type"], HOOK_EVENT);
+        assert_eq!(event["event"], "on_died");
+        assert_eq!(event["timed_out"], true);
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_wait_for_processes_a_hook_leaves_running() -> Result<()> {
+        let tmp = tempdir()?;
+        let store = TaskStore::new(tmp.path().join("tasks"));
+        let mut metadata = TaskMetadata::new("task-1".to_string(), None, TaskState::Stopped);
+        metadata.hooks = Some(Hooks {
+            on_stopped: Some("sleep 10 & echo started".to_string()),
+            ..Hooks::default()
+        });
+        store.save_metadata(&metadata)?;
+
+        let started = Instant::now();
+        let runs = run_hooks(
+            &store,
+            &store.task("task-1"),
+            HookEvent::OnStopped,
+            &TasksConfig::default(),
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(runs[0].stdout, "started");
+        assert!(runs[0].succeeded());
+        Ok(())
+    }
+}
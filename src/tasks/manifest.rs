@@ -0,0 +1,412 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use serde::Deserialize;
+
+use crate::tasks::{HookEvent, Hooks, RetryPolicy, StartTaskParams};
+use crate::timefmt::parse_duration;
+
+/// Backoff applied to automatic retries when a manifest sets `retries` without `retry_backoff`.
+const DEFAULT_RETRY_BACKOFF: &str = "30s";
+
+/// A task described by a manifest, ready to be passed to [`TaskService::start_task`].
+///
+/// [`TaskService::start_task`]: crate::tasks::TaskService::start_task
+#[derive(Clone, Debug)]
+pub struct ManifestTask {
+    /// Entry name, suffixed with the matrix values (e.g. `lint[main,api]`) for expanded entries.
+    pub key: String,
+    /// Name of the entry the task was expanded from.
+    pub name: String,
+    /// Entry names that must finish before this task starts (pipelines only).
+    pub after: Vec<String>,
+    pub params: StartTaskParams,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestFile {
+    /// Settings applied to every entry that does not override them.
+    #[serde(default)]
+    defaults: EntrySpec,
+    #[serde(default, rename = "task")]
+    tasks: Vec<EntrySpec>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EntrySpec {
+    name: Option<String>,
+    title: Option<String>,
+    prompt: Option<String>,
+    /// Prompt template file, relative to the manifest.
+    template: Option<PathBuf>,
+    working_dir: Option<String>,
+    repo: Option<String>,
+    #[serde(rename = "ref")]
+    repo_ref: Option<String>,
+    config_file: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    parent: Option<String>,
+    retries: Option<u32>,
+    retry_backoff: Option<String>,
+    /// Admission priority when the store's concurrency limit is reached.
+    priority: Option<i32>,
+    /// Lifecycle hooks; per event, an entry's hooks replace those from [defaults].
+    hooks: Option<Hooks>,
+    /// Configuration preset filling the settings the entry leaves unset.
+    preset: Option<String>,
+    /// Entries whose tasks must reach STOPPED first; the prompt may reference their
+    /// `{{name.result}}` and `{{name.diff}}`.
+    #[serde(default)]
+    after: Vec<String>,
+    /// Values to expand the entry over; one task is started per combination.
+    #[serde(default)]
+    matrix: BTreeMap<String, Vec<MatrixValue>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum MatrixValue {
+    /// Available as `{{key}}`.
+    Text(String),
+    /// Each field is available as `{{key.field}}`; `name` labels the combination.
+    Fields(BTreeMap<String, String>),
+}
+
+/// Reads a TOML manifest and expands it into the tasks it describes, resolving relative paths
+/// against the manifest's directory. Fails without starting anything if any entry is invalid.
+pub fn load_manifest(path: &Path) -> Result<Vec<ManifestTask>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read manifest {}", path.display()))?;
+    let base_dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    parse_manifest(&contents, base_dir)
+        .with_context(|| format!("invalid manifest {}", path.display()))
+}
+
+fn parse_manifest(contents: &str, base_dir: &Path) -> Result<Vec<ManifestTask>> {
+    let manifest: ManifestFile = toml::from_str(contents)?;
+    ensure!(
+        manifest.defaults.name.is_none()
+            && manifest.defaults.matrix.is_empty()
+            && manifest.defaults.after.is_empty(),
+        "`name`, `matrix`, and `after` cannot be set in [defaults]"
+    );
+    ensure!(
+        !manifest.tasks.is_empty(),
+        "manifest defines no [[task]] entries"
+    );
+
+    let mut tasks = Vec::new();
+    let mut keys = HashSet::new();
+    for (index, entry) in manifest.tasks.iter().enumerate() {
+        let name = entry
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("task-{}", index + 1));
+        for (labels, vars) in combinations(&entry.matrix)
+            .with_context(|| format!("entry `{name}` has an invalid matrix"))?
+        {
+            let key = if labels.is_empty() {
+                name.clone()
+            } else {
+                format!("{name}[{}]", labels.join(","))
+            };
+            let params = resolve_entry(entry, &manifest.defaults, &vars, base_dir)
+                .with_context(|| format!("entry `{key}` is invalid"))?;
+            ensure!(keys.insert(key.clone()), "duplicate manifest entry `{key}`");
+            tasks.push(ManifestTask {
+                key,
+                name: name.clone(),
+                after: entry.after.clone(),
+                params,
+            });
+        }
+    }
+    Ok(tasks)
+}
+
+/// Labels (one per matrix key, in key order) and placeholder values of a matrix combination.
+type Combination = (Vec<String>, BTreeMap<String, String>);
+
+/// Expands a matrix into every combination of its values.
+fn combinations(matrix: &BTreeMap<String, Vec<MatrixValue>>) -> Result<Vec<Combination>> {
+    let mut combinations = vec![(Vec::new(), BTreeMap::new())];
+    for (key, values) in matrix {
+        ensure!(!values.is_empty(), "matrix `{key}` has no values");
+        let mut expanded = Vec::with_capacity(combinations.len() * values.len());
+        for (labels, vars) in &combinations {
+            for (position, value) in values.iter().enumerate() {
+                let mut labels = labels.clone();
+                let mut vars = vars.clone();
+                match value {
+                    MatrixValue::Text(text) => {
+                        labels.push(text.clone());
+                        vars.insert(key.clone(), text.clone());
+                    }
+                    MatrixValue::Fields(fields) => {
+                        labels.push(
+                            fields
+                                .get("name")
+                                .cloned()
+                                .unwrap_or_else(|| (position + 1).to_string()),
+                        );
+                        for (field, text) in fields {
+                            vars.insert(format!("{key}.{field}"), text.clone());
+                        }
+                    }
+                }
+                expanded.push((labels, vars));
+            }
+        }
+        combinations = expanded;
+    }
+    Ok(combinations)
+}
+
+fn resolve_entry(
+    entry: &EntrySpec,
+    defaults: &EntrySpec,
+    vars: &BTreeMap<String, String>,
+    base_dir: &Path,
+) -> Result<StartTaskParams> {
+    let render_opt = |value: Option<&String>| value.map(|text| render(text, vars, &[])).transpose();
+
+    let (prompt, template) = if entry.prompt.is_some() || entry.template.is_some() {
+        (&entry.prompt, &entry.template)
+    } else {
+        (&defaults.prompt, &defaults.template)
+    };
+    let prompt = match (prompt, template) {
+        (Some(prompt), None) => render(prompt, vars, &entry.after)?,
+        (None, Some(template)) => {
+            let path = base_dir.join(template);
+            let text = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read template {}", path.display()))?;
+            render(&text, vars, &entry.after)?
+        }
+        (Some(_), Some(_)) => bail!("`prompt` and `template` are mutually exclusive"),
+        (None, None) => bail!("either `prompt` or `template` is required"),
+    };
+    ensure!(!prompt.trim().is_empty(), "prompt must not be empty");
+
+    let working_dir = render_opt(entry.working_dir.as_ref().or(defaults.working_dir.as_ref()))?
+        .map(|dir| base_dir.join(dir));
+    let repo_url = render_opt(entry.repo.as_ref().or(defaults.repo.as_ref()))?.map(|repo| {
+        // Local repositories are relative to the manifest, like every other path.
+        let local = base_dir.join(&repo);
+        if !Path::new(&repo).is_absolute() && local.exists() {
+            local.to_string_lossy().into_owned()
+        } else {
+            repo
+        }
+    });
+    ensure!(
+        repo_url.is_none() || working_dir.is_some(),
+        "`working_dir` is required when `repo` is set"
+    );
+    let config_file = render_opt(entry.config_file.as_ref().or(defaults.config_file.as_ref()))?
+        .map(|file| base_dir.join(file));
+    if let Some(file) = &config_file {
+        ensure!(
+            file.is_file(),
+            "config file {} does not exist",
+            file.display()
+        );
+    }
+
+    let retry_policy = match entry.retries.or(defaults.retries) {
+        Some(max_retries) => {
+            let backoff = entry
+                .retry_backoff
+                .as_deref()
+                .or(defaults.retry_backoff.as_deref())
+                .unwrap_or(DEFAULT_RETRY_BACKOFF);
+            Some(RetryPolicy {
+                max_retries,
+                backoff_secs: parse_duration(backoff)
+                    .map_err(|err| anyhow!(err))?
+                    .as_secs(),
+            })
+        }
+        None => None,
+    };
+
+    let mut tags = Vec::new();
+    for tag in defaults.tags.iter().chain(&entry.tags) {
+        let tag = render(tag, vars, &[])?;
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    let hooks = match (&entry.hooks, &defaults.hooks) {
+        (Some(hooks), Some(defaults)) => Some(hooks.clone().or(defaults)),
+        (hooks, defaults) => hooks.clone().or_else(|| defaults.clone()),
+    };
+    let hooks = match hooks {
+        Some(mut hooks) => {
+            for event in HookEvent::ALL {
+                let command = hooks
+                    .command(event)
+                    .map(|command| render(command, vars, &[]))
+                    .transpose()?;
+                hooks.set(event, command);
+            }
+            Some(hooks)
+        }
+        None => None,
+    };
+
+    Ok(StartTaskParams {
+        title: render_opt(entry.title.as_ref().or(defaults.title.as_ref()))?,
+        prompt,
+        config_file,
+        working_dir,
+        repo_url,
+        repo_ref: render_opt(entry.repo_ref.as_ref().or(defaults.repo_ref.as_ref()))?,
+        parent_id: entry.parent.clone().or_else(|| defaults.parent.clone()),
+        retry_policy,
+        tags,
+        priority: entry.priority.or(defaults.priority).unwrap_or_default(),
+        hooks,
+        preset: render_opt(entry.preset.as_ref().or(defaults.preset.as_ref()))?,
+        exec: None,
+    })
+}
+
+/// Substitutes `{{placeholder}}` references with matrix values. References to the outputs of
+/// `deps` (`{{name.result}}`, `{{name.diff}}`) are kept for the pipeline runner.
+pub(crate) fn render(
+    text: &str,
+    vars: &BTreeMap<String, String>,
+    deps: &[String],
+) -> Result<String> {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| anyhow!("unterminated placeholder in {text:?}"))?;
+        let name = after[..end].trim();
+        match vars.get(name) {
+            Some(value) => rendered.push_str(value),
+            None if output_reference(name)
+                .is_some_and(|(entry, _)| deps.iter().any(|dep| dep == entry)) =>
+            {
+                rendered.push_str(&rest[start..start + 2 + end + 2]);
+            }
+            None => bail!("unknown placeholder `{{{{{name}}}}}`"),
+        }
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Splits a `name.result` or `name.diff` placeholder into the entry name and the output.
+pub(crate) fn output_reference(placeholder: &str) -> Option<(&str, &str)> {
+    placeholder
+        .rsplit_once('.')
+        .filter(|(_, output)| matches!(*output, "result" | "diff"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn expands_matrix_entries_with_defaults() -> Result<()> {
+        let tmp = tempdir()?;
+        fs::write(tmp.path().join("fix.md"), "Fix lint in {{repo.name}}.\n")?;
+        let manifest = r#"
+            [defaults]
+            tags = ["batch"]
+            retries = 2
+
+            [[task]]
+            name = "lint"
+            title = "Lint {{repo.name}} ({{branch}})"
+            template = "fix.md"
+            working_dir = "work/{{repo.name}}-{{branch}}"
+            repo = "{{repo.url}}"
+            ref = "{{branch}}"
+            tags = ["{{repo.name}}"]
+            matrix.repo = [
+                { name = "api", url = "https://example.com/api.git" },
+                { name = "web", url = "https://example.com/web.git" },
+            ]
+            matrix.branch = ["main", "next"]
+
+            [[task]]
+            prompt = "Summarize the changelog"
+            retries = 0
+        "#;
+
+        let tasks = parse_manifest(manifest, tmp.path())?;
+        let keys: Vec<&str> = tasks.iter().map(|task| task.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "lint[main,api]",
+                "lint[main,web]",
+                "lint[next,api]",
+                "lint[next,web]",
+                "task-2"
+            ]
+        );
+
+        let web = &tasks[1].params;
+        assert_eq!(web.title.as_deref(), Some("Lint web (main)"));
+        assert_eq!(web.prompt, "Fix lint in web.\n");
+        assert_eq!(web.working_dir, Some(tmp.path().join("work/web-main")));
+        assert_eq!(web.repo_url.as_deref(), Some("https://example.com/web.git"));
+        assert_eq!(web.repo_ref.as_deref(), Some("main"));
+        assert_eq!(web.tags, ["batch", "web"]);
+        assert_eq!(web.retry_policy.map(|policy| policy.backoff_secs), Some(30));
+
+        let summary = &tasks[4].params;
+        assert_eq!(summary.title, None);
+        assert_eq!(
+            summary.retry_policy.map(|policy| policy.max_retries),
+            Some(0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        let error = |manifest: &str| {
+            format!(
+                "{:#}",
+                parse_manifest(manifest, Path::new(".")).expect_err("manifest should be invalid")
+            )
+        };
+
+        assert!(error("").contains("no [[task]] entries"));
+        assert!(error("[[task]]\ntitle = \"x\"").contains("`prompt` or `template` is required"));
+        assert!(
+            error("[[task]]\nprompt = \"Fix {{target}}\"")
+                .contains("unknown placeholder `{{target}}`")
+        );
+        assert!(
+            error("[[task]]\nprompt = \"x\"\nrepo = \"https://example.com/x.git\"")
+                .contains("`working_dir` is required")
+        );
+        assert!(
+            error("[[task]]\nname = \"a\"\nprompt = \"x\"\n[[task]]\nname = \"a\"\nprompt = \"y\"")
+                .contains("duplicate manifest entry `a`")
+        );
+        assert!(error("[[task]]\nprompt = \"x\"\nmatrix.repo = []").contains("has no values"));
+        assert!(error("[[task]]\nprompt = \"x\"\ncolour = \"red\"").contains("unknown field"));
+    }
+}
@@ -1,9 +1,40 @@
+pub mod answer;
+pub mod archive_index;
+pub mod config;
+pub mod doctor;
+pub mod fork;
+pub mod hooks;
+pub mod manifest;
 pub mod model;
+pub mod pipeline;
+pub mod queue;
+pub mod retry;
+pub mod schedule;
+pub mod search;
 pub mod service;
+pub mod settings;
 pub mod status;
 pub mod store;
+pub mod transcript;
+pub mod wait;
+pub mod webhooks;
+pub mod workspace;
 
+pub use archive_index::{ARCHIVE_INDEX_FILE_NAME, ArchiveIndex, ArchiveIndexEntry};
+pub use config::TasksConfig;
+pub use doctor::{DoctorIssue, DoctorIssueKind, DoctorReport};
+pub use hooks::{HookEvent, HookRun, Hooks};
+pub use manifest::{ManifestTask, load_manifest};
 pub use model::*;
+pub use pipeline::{PipelineReport, PipelineStep, StepState, StepStatus, load_pipeline};
+pub use queue::QueueLock;
+pub use retry::RetryPolicy;
+pub use schedule::{CronExpr, Schedule, ScheduleRun, ScheduleRunOutcome, ScheduleTarget};
+pub use search::{SearchField, SearchHit, SearchQuery};
 pub use service::*;
-pub use status::derive_active_state;
+pub use settings::StoreSettings;
+pub use status::{derive_active_state, is_reservation_live};
 pub use store::*;
+pub use wait::{WaitOptions, WaitOutcome, WaitReport, WaitTarget};
+pub use webhooks::{Webhook, WebhookDelivery, WebhookEvent};
+pub use workspace::WorkspaceSnapshot;
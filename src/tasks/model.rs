@@ -1,12 +1,19 @@
+use std::collections::BTreeMap;
 use std::fmt;
 
 use chrono::{DateTime, Utc};
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 
+use crate::tasks::hooks::Hooks;
+use crate::tasks::retry::RetryPolicy;
+
 /// Identifier used for a Codex task.
 pub type TaskId = String;
 
+/// Schema version written to `task.json` by this build. Older records are upgraded on read.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
 /// Possible lifecycle states for a Codex task.
 #[derive(Clone, Debug, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
@@ -19,6 +26,9 @@ pub enum TaskState {
     Archived,
     #[value(name = "DIED")]
     Died,
+    /// Waiting for a slot under the store's `max_running` limit.
+    #[value(name = "QUEUED")]
+    Queued,
 }
 
 impl TaskState {
@@ -29,6 +39,7 @@ impl TaskState {
             TaskState::Stopped => "STOPPED",
             TaskState::Archived => "ARCHIVED",
             TaskState::Died => "DIED",
+            TaskState::Queued => "QUEUED",
         }
     }
 }
@@ -42,6 +53,9 @@ impl fmt::Display for TaskState {
 /// Core metadata tracked for each task on disk.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct TaskMetadata {
+    /// Version of the on-disk schema; records written before versioning default to `0`.
+    #[serde(default)]
+    pub schema_version: u32,
     pub id: TaskId,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
@@ -60,6 +74,119 @@ pub struct TaskMetadata {
     pub config_path: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub working_dir: Option<String>,
+    /// Task this one was forked from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forked_from: Option<TaskId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_point: Option<ForkPoint>,
+    /// Task whose Codex session started this one, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<TaskId>,
+    /// Identifier exported to the initial `codex exec` before its thread id is known, so
+    /// subtasks started during that invocation can be linked back to this task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launch_id: Option<String>,
+    /// Number of times a failed invocation was re-run, manually or by the retry policy.
+    #[serde(default)]
+    pub retry_count: u32,
+    /// Automatic retry settings applied by the worker to transient failures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_policy: Option<RetryPolicy>,
+    /// Free-form labels attached when the task was started.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Prompt waiting for admission while the task is QUEUED.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queued: Option<QueuedPrompt>,
+    /// Lifecycle hooks run for this task in addition to the store-wide ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<Hooks>,
+    /// Name of the configuration preset the task was started with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// `codex exec` settings reused by every invocation of the task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec: Option<ExecOptions>,
+    /// Slot held while a worker is being launched for the task; cleared by the next state change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reserved: Option<Reservation>,
+}
+
+/// Extra `codex exec` settings applied to every invocation of a task.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ExecOptions {
+    /// Arguments appended after those from the configuration file's `[exec]` section.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Seconds an invocation may run before `codex exec` is killed and the task DIES.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+impl ExecOptions {
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty() && self.env.is_empty() && self.timeout_secs.is_none()
+    }
+
+    /// Fills what is left unset here from `defaults`; variables set here replace theirs.
+    pub fn or(self, defaults: &ExecOptions) -> ExecOptions {
+        let mut env = defaults.env.clone();
+        env.extend(self.env);
+        ExecOptions {
+            args: if self.args.is_empty() {
+                defaults.args.clone()
+            } else {
+                self.args
+            },
+            env,
+            timeout_secs: self.timeout_secs.or(defaults.timeout_secs),
+        }
+    }
+}
+
+/// Parses a `NAME=VALUE` environment variable assignment.
+pub fn parse_env_var(spec: &str) -> Result<(String, String), String> {
+    match spec.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("expected NAME=VALUE, got `{spec}`")),
+    }
+}
+
+/// Position in the source conversation at which a fork was taken.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ForkPoint {
+    /// Number of prompts the source task had processed when it was forked.
+    pub invocation: usize,
+    /// Last update of the source task at the time of the fork.
+    #[serde(with = "serde_datetime")]
+    pub at: DateTime<Utc>,
+}
+
+/// Invocation held back by the concurrency limit until a running task finishes.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct QueuedPrompt {
+    pub prompt: String,
+    /// Higher priorities are admitted first; equal priorities in FIFO order.
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(with = "serde_datetime")]
+    pub queued_at: DateTime<Utc>,
+    /// Whether the task was queued by `start`, so no Codex thread exists yet and the task id
+    /// is a placeholder that later resolves through `launch_id`.
+    #[serde(default)]
+    pub new_task: bool,
+}
+
+/// Marks a task RUNNING before its worker has a pid, so admission decisions count it against
+/// the concurrency limit.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Reservation {
+    /// Process launching the worker; only checked for liveness, never signalled.
+    pub launcher_pid: i32,
+    #[serde(with = "serde_datetime")]
+    pub reserved_at: DateTime<Utc>,
 }
 
 impl TaskMetadata {
@@ -67,6 +194,7 @@ impl TaskMetadata {
     pub fn new(id: TaskId, title: Option<String>, state: TaskState) -> Self {
         let now = Utc::now();
         Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
             id,
             title,
             state,
@@ -77,6 +205,18 @@ impl TaskMetadata {
             last_prompt: None,
             config_path: None,
             working_dir: None,
+            forked_from: None,
+            fork_point: None,
+            parent_id: None,
+            launch_id: None,
+            retry_count: 0,
+            retry_policy: None,
+            tags: Vec::new(),
+            queued: None,
+            hooks: None,
+            preset: None,
+            exec: None,
+            reserved: None,
         }
     }
 
@@ -85,11 +225,13 @@ impl TaskMetadata {
         self.updated_at = Utc::now();
     }
 
-    /// Sets the task state and refreshes the `updated_at` timestamp.
+    /// Sets the task state and refreshes the `updated_at` timestamp. Any slot reservation ends
+    /// with the state it was made for.
     pub fn set_state(&mut self, state: TaskState) {
         if self.state != state {
             self.state = state;
         }
+        self.reserved = None;
         self.touch();
     }
 }
@@ -0,0 +1,407 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use serde::Serialize;
+
+use crate::tasks::manifest::output_reference;
+use crate::tasks::{
+    ManifestTask, StartTaskParams, TaskDiff, TaskService, TaskState, load_manifest,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// A pipeline task together with the steps it waits for.
+#[derive(Clone, Debug)]
+pub struct PipelineStep {
+    /// Manifest entry key, e.g. `review` or `lint[api]`.
+    pub key: String,
+    /// Name of the manifest entry, used by `{{name.result}}` references.
+    pub name: String,
+    /// Keys of the steps that must reach STOPPED first.
+    pub after: Vec<String>,
+    pub params: StartTaskParams,
+}
+
+/// Lifecycle of a pipeline step.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepState {
+    /// Waiting for its dependencies.
+    Pending,
+    Running,
+    Stopped,
+    /// Stopped without producing a result, e.g. by `stop`.
+    Cancelled,
+    Died,
+    /// Not started because a dependency did not finish.
+    Aborted,
+    /// The task could not be started.
+    Failed,
+}
+
+impl StepState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepState::Pending => "PENDING",
+            StepState::Running => "RUNNING",
+            StepState::Stopped => "STOPPED",
+            StepState::Cancelled => "CANCELLED",
+            StepState::Died => "DIED",
+            StepState::Aborted => "ABORTED",
+            StepState::Failed => "FAILED",
+        }
+    }
+
+    fn is_settled(self) -> bool {
+        !matches!(self, StepState::Pending | StepState::Running)
+    }
+}
+
+/// Current state of one pipeline step.
+#[derive(Clone, Debug, Serialize)]
+pub struct StepStatus {
+    pub key: String,
+    pub state: StepState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Final state of every step in a pipeline run.
+#[derive(Clone, Debug)]
+pub struct PipelineReport {
+    pub steps: Vec<StepStatus>,
+    pub elapsed: Duration,
+}
+
+impl PipelineReport {
+    /// Whether every step reached STOPPED.
+    pub fn succeeded(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| step.state == StepState::Stopped)
+    }
+}
+
+/// Reads a pipeline definition (a task manifest whose entries may declare `after`) and checks
+/// that its dependencies exist and form no cycle.
+pub fn load_pipeline(path: &Path) -> Result<Vec<PipelineStep>> {
+    let tasks = load_manifest(path)?;
+    resolve_steps(tasks).with_context(|| format!("invalid pipeline {}", path.display()))
+}
+
+fn resolve_steps(tasks: Vec<ManifestTask>) -> Result<Vec<PipelineStep>> {
+    let mut keys_by_name: HashMap<String, Vec<String>> = HashMap::new();
+    for task in &tasks {
+        keys_by_name
+            .entry(task.name.clone())
+            .or_default()
+            .push(task.key.clone());
+    }
+
+    let mut steps = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        let mut after = Vec::new();
+        for dep in &task.after {
+            ensure!(
+                *dep != task.name,
+                "entry `{}` cannot run after itself",
+                task.key
+            );
+            let keys = keys_by_name
+                .get(dep)
+                .ok_or_else(|| anyhow!("entry `{}` runs after unknown entry `{dep}`", task.key))?;
+            after.extend(keys.iter().cloned());
+        }
+        for (dep, _) in output_references(&task.params.prompt) {
+            let count = keys_by_name.get(dep).map_or(0, Vec::len);
+            ensure!(
+                count == 1,
+                "entry `{}` references the output of `{dep}`, which expands to {count} tasks",
+                task.key
+            );
+        }
+        steps.push(PipelineStep {
+            key: task.key,
+            name: task.name,
+            after,
+            params: task.params,
+        });
+    }
+    ensure_acyclic(&steps)?;
+    Ok(steps)
+}
+
+fn ensure_acyclic(steps: &[PipelineStep]) -> Result<()> {
+    let mut done: HashSet<&str> = HashSet::new();
+    while done.len() < steps.len() {
+        let ready: Vec<&str> = steps
+            .iter()
+            .filter(|step| !done.contains(step.key.as_str()))
+            .filter(|step| step.after.iter().all(|dep| done.contains(dep.as_str())))
+            .map(|step| step.key.as_str())
+            .collect();
+        if ready.is_empty() {
+            let blocked: Vec<&str> = steps
+                .iter()
+                .map(|step| step.key.as_str())
+                .filter(|key| !done.contains(key))
+                .collect();
+            bail!("dependency cycle between {}", blocked.join(", "));
+        }
+        done.extend(ready);
+    }
+    Ok(())
+}
+
+/// Returns the `(entry, output)` pairs of the `{{entry.result}}`/`{{entry.diff}}` placeholders
+/// left in a prompt.
+fn output_references(prompt: &str) -> Vec<(&str, &str)> {
+    let mut references = Vec::new();
+    let mut rest = prompt;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        if let Some(reference) = output_reference(after[..end].trim()) {
+            references.push(reference);
+        }
+        rest = &after[end + 2..];
+    }
+    references
+}
+
+/// Starts each step once its dependencies reach STOPPED with a result, aborts the steps
+/// downstream of a task that was stopped early, DIED, or could not start, and returns when every step has settled. `on_change` is called
+/// whenever a step changes state.
+pub fn run_pipeline(
+    service: &TaskService,
+    steps: &[PipelineStep],
+    mut on_change: impl FnMut(&StepStatus),
+) -> Result<PipelineReport> {
+    let started = Instant::now();
+    let mut statuses: Vec<StepStatus> = steps
+        .iter()
+        .map(|step| StepStatus {
+            key: step.key.clone(),
+            state: StepState::Pending,
+            task_id: None,
+            detail: None,
+        })
+        .collect();
+    let index: HashMap<&str, usize> = steps
+        .iter()
+        .enumerate()
+        .map(|(position, step)| (step.key.as_str(), position))
+        .collect();
+
+    while !statuses.iter().all(|status| status.state.is_settled()) {
+        let mut changed = false;
+
+        for status in statuses.iter_mut() {
+            let Some(task_id) = status
+                .task_id
+                .as_deref()
+                .filter(|_| status.state == StepState::Running)
+            else {
+                continue;
+            };
+            let metadata = service
+                .find_status(task_id)?
+                .map(|snapshot| snapshot.metadata);
+            status.state = match metadata {
+                Some(metadata) => match metadata.state {
+                    TaskState::Running | TaskState::Queued => continue,
+                    // A task stopped mid-turn has no answer for `{{name.result}}`.
+                    TaskState::Stopped | TaskState::Archived if metadata.last_result.is_none() => {
+                        StepState::Cancelled
+                    }
+                    TaskState::Stopped | TaskState::Archived => StepState::Stopped,
+                    TaskState::Died => StepState::Died,
+                },
+                None => {
+                    status.detail = Some("task disappeared".to_string());
+                    StepState::Died
+                }
+            };
+            on_change(status);
+            changed = true;
+        }
+
+        for (position, step) in steps.iter().enumerate() {
+            if statuses[position].state != StepState::Pending {
+                continue;
+            }
+            let deps: Vec<&StepStatus> = step
+                .after
+                .iter()
+                .map(|dep| &statuses[index[dep.as_str()]])
+                .collect();
+            if let Some(blocker) = deps
+                .iter()
+                .find(|dep| dep.state.is_settled() && dep.state != StepState::Stopped)
+            {
+                let detail = format!("`{}` is {}", blocker.key, blocker.state.as_str());
+                let status = &mut statuses[position];
+                status.state = StepState::Aborted;
+                status.detail = Some(detail);
+                on_change(status);
+                changed = true;
+                continue;
+            }
+            if !deps.iter().all(|dep| dep.state == StepState::Stopped) {
+                continue;
+            }
+
+            let launched =
+                render_outputs(service, steps, &statuses, &step.params.prompt).and_then(|prompt| {
+                    service.start_task(StartTaskParams {
+                        prompt,
+                        ..step.params.clone()
+                    })
+                });
+            let status = &mut statuses[position];
+            match launched {
+                Ok(result) => {
+                    status.state = StepState::Running;
+                    status.task_id = Some(result.thread_id);
+                }
+                Err(err) => {
+                    status.state = StepState::Failed;
+                    status.detail = Some(format!("{err:#}"));
+                }
+            }
+            on_change(status);
+            changed = true;
+        }
+
+        if !changed {
+            sleep(POLL_INTERVAL);
+        }
+    }
+
+    Ok(PipelineReport {
+        steps: statuses,
+        elapsed: started.elapsed(),
+    })
+}
+
+/// Replaces `{{entry.result}}` and `{{entry.diff}}` with the final answer and workspace patch
+/// of the (finished) task started for that entry.
+fn render_outputs(
+    service: &TaskService,
+    steps: &[PipelineStep],
+    statuses: &[StepStatus],
+    prompt: &str,
+) -> Result<String> {
+    let mut rendered = String::with_capacity(prompt.len());
+    let mut rest = prompt;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        let Some((name, output)) = output_reference(after[..end].trim()) else {
+            rendered.push_str(&rest[start..start + 2 + end + 2]);
+            rest = &after[end + 2..];
+            continue;
+        };
+        let task_id = steps
+            .iter()
+            .zip(statuses)
+            .find(|(step, _)| step.name == name)
+            .and_then(|(_, status)| status.task_id.as_deref())
+            .ok_or_else(|| anyhow!("entry `{name}` has no task"))?;
+        if output == "result" {
+            let answer = service.task_answer(task_id, None)?.unwrap_or_default();
+            rendered.push_str(answer.trim_end());
+        } else {
+            match service.task_diff(task_id, None)? {
+                TaskDiff::Patch { patch, .. } => rendered.push_str(patch.trim_end()),
+                TaskDiff::Files(files) => {
+                    let lines: Vec<String> = files
+                        .iter()
+                        .map(|(marker, path)| format!("{marker} {path}"))
+                        .collect();
+                    rendered.push_str(&lines.join("\n"));
+                }
+            }
+        }
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn load(contents: &str) -> Result<Vec<PipelineStep>> {
+        let tmp = tempdir()?;
+        let path = tmp.path().join("pipeline.toml");
+        fs::write(&path, contents)?;
+        load_pipeline(&path)
+    }
+
+    #[test]
+    fn resolves_dependencies_and_keeps_output_placeholders() -> Result<()> {
+        let steps = load(
+            r#"
+            [[task]]
+            name = "investigate"
+            prompt = "Find the bug in {{area}}"
+            matrix.area = ["api"]
+
+            [[task]]
+            name = "implement"
+            after = ["investigate"]
+            prompt = "Fix it: {{investigate.result}}"
+
+            [[task]]
+            name = "review"
+            after = ["implement"]
+            prompt = "Review:\n{{implement.diff}}"
+            "#,
+        )?;
+        assert_eq!(steps[1].after, ["investigate[api]"]);
+        assert_eq!(steps[1].params.prompt, "Fix it: {{investigate.result}}");
+        assert_eq!(steps[2].after, ["implement"]);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_cyclic_and_undeclared_dependencies() {
+        let cases = [
+            (
+                "[[task]]\nname = \"a\"\nafter = [\"missing\"]\nprompt = \"x\"\n",
+                "unknown entry `missing`",
+            ),
+            (
+                "[[task]]\nname = \"a\"\nafter = [\"b\"]\nprompt = \"x\"\n\
+                 [[task]]\nname = \"b\"\nafter = [\"a\"]\nprompt = \"y\"\n",
+                "dependency cycle between a, b",
+            ),
+            (
+                "[[task]]\nname = \"a\"\nprompt = \"x\"\n\
+                 [[task]]\nname = \"b\"\nprompt = \"{{a.result}}\"\n",
+                "unknown placeholder `{{a.result}}`",
+            ),
+        ];
+        for (contents, expected) in cases {
+            let err = load(contents).expect_err("pipeline should be rejected");
+            assert!(
+                format!("{err:#}").contains(expected),
+                "expected `{expected}` in `{err:#}`"
+            );
+        }
+    }
+}
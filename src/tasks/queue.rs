@@ -0,0 +1,83 @@
+use std::cmp::Reverse;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::tasks::{TaskMetadata, TaskStore};
+
+/// Lock file serialising admission decisions between concurrent `codex-tasks` processes.
+pub const QUEUE_LOCK_FILE_NAME: &str = "queue.lock";
+
+/// Exclusive advisory lock on the store's queue, released when dropped.
+pub struct QueueLock {
+    _file: File,
+}
+
+impl QueueLock {
+    /// Blocks until no other process is making admission decisions for the store.
+    pub fn acquire(store: &TaskStore) -> Result<Self> {
+        store.ensure_layout()?;
+        let file = lock_exclusive(&store.root().join(QUEUE_LOCK_FILE_NAME))?;
+        Ok(Self { _file: file })
+    }
+}
+
+/// Opens (creating if needed) a lock file and blocks until holding an exclusive `flock` on it.
+pub(crate) fn lock_exclusive(path: &Path) -> Result<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("failed to open lock file {}", path.display()))?;
+    // SAFETY: flock only operates on the descriptor, which stays open for the call.
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("failed to lock {}", path.display()));
+    }
+    Ok(file)
+}
+
+/// Sorts QUEUED tasks into admission order: highest priority first, then oldest first.
+pub fn sort_by_admission(tasks: &mut [TaskMetadata]) {
+    tasks.sort_by_key(|metadata| {
+        let queued = metadata.queued.as_ref();
+        (
+            Reverse(queued.map_or(0, |queued| queued.priority)),
+            queued.map_or(metadata.updated_at, |queued| queued.queued_at),
+        )
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{QueuedPrompt, TaskState};
+    use chrono::{Duration, Utc};
+
+    fn queued(id: &str, priority: i32, age_secs: i64) -> TaskMetadata {
+        let mut metadata = TaskMetadata::new(id.to_string(), None, TaskState::Queued);
+        metadata.queued = Some(QueuedPrompt {
+            prompt: "prompt".to_string(),
+            priority,
+            queued_at: Utc::now() - Duration::seconds(age_secs),
+            new_task: false,
+        });
+        metadata
+    }
+
+    #[test]
+    fn admits_by_priority_then_fifo() {
+        let mut tasks = vec![
+            queued("newer", 0, 10),
+            queued("urgent", 5, 1),
+            queued("older", 0, 20),
+        ];
+        sort_by_admission(&mut tasks);
+        let ids: Vec<&str> = tasks.iter().map(|task| task.id.as_str()).collect();
+        assert_eq!(ids, ["urgent", "older", "newer"]);
+    }
+}
@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Log event type written by the worker before it automatically retries an invocation.
+pub const RETRY_EVENT: &str = "task.retry";
+
+/// Lower-cased fragments of `codex exec` errors that usually clear up on their own.
+const TRANSIENT_PATTERNS: &[&str] = &[
+    "rate limit",
+    "too many requests",
+    "429",
+    "500 internal server error",
+    "502",
+    "503",
+    "504",
+    "overloaded",
+    "temporarily unavailable",
+    "timed out",
+    "timeout",
+    "connection reset",
+    "connection refused",
+    "connection closed",
+    "network",
+    "stream disconnected",
+    "stream error",
+];
+
+/// Automatic retry settings stored with a task and applied by its worker.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Retries allowed per invocation after the first attempt fails.
+    pub max_retries: u32,
+    /// Delay before the first retry; it doubles with each further attempt.
+    pub backoff_secs: u64,
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (1-based).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        Duration::from_secs(self.backoff_secs.saturating_mul(factor))
+    }
+}
+
+/// Extracts the error message carried by an `error` or `turn.failed` event.
+pub fn failure_message(value: &Value) -> Option<String> {
+    let message = match value.get("type").and_then(Value::as_str)? {
+        "error" => value.get("message"),
+        "turn.failed" => value.get("error").and_then(|error| error.get("message")),
+        _ => None,
+    };
+    message.and_then(Value::as_str).map(str::to_string)
+}
+
+/// Decides whether a failed invocation is worth retrying: `codex exec` crashed (was killed by a
+/// signal other than a stop request) or reported a network, rate-limit or server error.
+pub fn is_transient_failure(signal: Option<i32>, messages: &[String]) -> bool {
+    if let Some(signal) = signal {
+        return !matches!(
+            signal,
+            libc::SIGTERM | libc::SIGKILL | libc::SIGINT | libc::SIGHUP
+        );
+    }
+    messages.iter().any(|message| {
+        let message = message.to_lowercase();
+        TRANSIENT_PATTERNS
+            .iter()
+            .any(|pattern| message.contains(pattern))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn classifies_transient_failures() {
+        let messages = |text: &str| vec![text.to_string()];
+        assert!(is_transient_failure(
+            None,
+            &messages("stream error: 429 Too Many Requests")
+        ));
+        assert!(is_transient_failure(
+            None,
+            &messages("stream disconnected before completion")
+        ));
+        assert!(!is_transient_failure(
+            None,
+            &messages("error: unknown model 'gpt-9'")
+        ));
+        assert!(!is_transient_failure(None, &[]));
+        assert!(is_transient_failure(Some(libc::SIGSEGV), &[]));
+        assert!(!is_transient_failure(
+            Some(libc::SIGTERM),
+            &messages("connection reset")
+        ));
+    }
+
+    #[test]
+    fn extracts_failure_messages_from_events() {
+        assert_eq!(
+            failure_message(&json!({"type": "error", "message": "boom"})),
+            Some("boom".to_string())
+        );
+        assert_eq!(
+            failure_message(&json!({"type": "turn.failed", "error": {"message": "rate limit"}})),
+            Some("rate limit".to_string())
+        );
+        assert_eq!(failure_message(&json!({"type": "turn.completed"})), None);
+    }
+
+    #[test]
+    fn backoff_doubles_per_attempt() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            backoff_secs: 30,
+        };
+        assert_eq!(policy.delay(1), Duration::from_secs(30));
+        assert_eq!(policy.delay(3), Duration::from_secs(120));
+    }
+}
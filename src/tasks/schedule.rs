@@ -0,0 +1,387 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+
+use crate::tasks::TaskStore;
+use crate::tasks::queue::lock_exclusive;
+
+/// File in the store root holding every schedule and its recent run history.
+pub const SCHEDULES_FILE_NAME: &str = "schedules.json";
+/// Lock file serialising updates to [`SCHEDULES_FILE_NAME`].
+pub const SCHEDULES_LOCK_FILE_NAME: &str = "schedules.lock";
+/// Number of runs kept in each schedule's history.
+pub const SCHEDULE_HISTORY_LIMIT: usize = 50;
+
+/// A five-field cron expression (`minute hour day-of-month month day-of-week`), evaluated in
+/// local time.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CronExpr {
+    source: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    /// Cron matches either day field when both are restricted, and both otherwise.
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+impl FromStr for CronExpr {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        let fields: Vec<&str> = source.split_whitespace().collect();
+        ensure!(
+            fields.len() == 5,
+            "cron expression `{source}` must have 5 fields (minute hour day month weekday)"
+        );
+        let mut days_of_week = parse_field(fields[4], 0, 7, "day-of-week")?;
+        // Both 0 and 7 mean Sunday.
+        days_of_week[0] |= days_of_week[7];
+        days_of_week.truncate(7);
+        Ok(Self {
+            source: fields.join(" "),
+            minutes: parse_field(fields[0], 0, 59, "minute")?,
+            hours: parse_field(fields[1], 0, 23, "hour")?,
+            days_of_month: parse_field(fields[2], 1, 31, "day-of-month")?,
+            months: parse_field(fields[3], 1, 12, "month")?,
+            days_of_week,
+            any_day_of_month: fields[2] == "*",
+            any_day_of_week: fields[4] == "*",
+        })
+    }
+}
+
+impl CronExpr {
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the first matching minute strictly after `after`, searching up to five years
+    /// ahead (enough for `29 2 *` style leap-day schedules).
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut candidate = start.with_timezone(&Local);
+        let limit = candidate + Duration::days(5 * 366);
+        while candidate < limit {
+            if !self.months[candidate.month() as usize] || !self.matches_day(&candidate) {
+                candidate = start_of_next_day(&candidate)?;
+            } else if !self.hours[candidate.hour() as usize] {
+                candidate += Duration::minutes(60 - candidate.minute() as i64);
+            } else if !self.minutes[candidate.minute() as usize] {
+                candidate += Duration::minutes(1);
+            } else {
+                return Some(candidate.with_timezone(&Utc));
+            }
+        }
+        None
+    }
+
+    fn matches_day<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> bool {
+        let day_of_month = self.days_of_month[time.day() as usize];
+        let day_of_week = self.days_of_week[time.weekday().num_days_from_sunday() as usize];
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+}
+
+fn start_of_next_day(time: &DateTime<Local>) -> Option<DateTime<Local>> {
+    let midnight = time.date_naive().succ_opt()?.and_hms_opt(0, 0, 0)?;
+    Local.from_local_datetime(&midnight).earliest()
+}
+
+/// Parses one cron field (`*`, `N`, `A-B`, `*/S`, `A-B/S`, and comma-separated lists) into a
+/// table indexed by value.
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<Vec<bool>> {
+    let mut allowed = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 =
+                    step.parse().ok().filter(|step| *step > 0).ok_or_else(|| {
+                        anyhow!("invalid step `{step}` in {name} field `{field}`")
+                    })?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                parse_value(start, min, max, name)?,
+                parse_value(end, min, max, name)?,
+            )
+        } else {
+            let value = parse_value(range, min, max, name)?;
+            // `N/S` means "from N to the end of the range in steps of S".
+            (value, if step > 1 { max } else { value })
+        };
+        ensure!(
+            start <= end,
+            "range `{range}` in {name} field `{field}` is reversed"
+        );
+        for value in (start..=end).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+    Ok(allowed)
+}
+
+fn parse_value(value: &str, min: u32, max: u32, name: &str) -> Result<u32> {
+    value
+        .parse()
+        .ok()
+        .filter(|value| (min..=max).contains(value))
+        .ok_or_else(|| anyhow!("{name} value `{value}` must be between {min} and {max}"))
+}
+
+/// What a schedule does when it fires.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScheduleTarget {
+    /// Send the prompt to an existing task.
+    Task { task_id: String },
+    /// Start a fresh task with the prompt.
+    New {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        working_dir: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+    },
+}
+
+/// How a scheduled run ended.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleRunOutcome {
+    Started,
+    Queued,
+    /// The target task was still busy with an earlier prompt.
+    Skipped,
+    Failed,
+}
+
+impl ScheduleRunOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduleRunOutcome::Started => "started",
+            ScheduleRunOutcome::Queued => "queued",
+            ScheduleRunOutcome::Skipped => "skipped",
+            ScheduleRunOutcome::Failed => "failed",
+        }
+    }
+}
+
+/// One entry of a schedule's run history.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleRun {
+    pub at: DateTime<Utc>,
+    pub outcome: ScheduleRunOutcome,
+    /// Task that received the prompt, including the task started for `New` targets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// A recurring prompt run by `codex-tasks scheduler run`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    pub cron: String,
+    pub prompt: String,
+    pub target: ScheduleTarget,
+    pub created_at: DateTime<Utc>,
+    /// Next time the scheduler fires this schedule; runs missed while it was not running are
+    /// collapsed into one.
+    pub next_run: DateTime<Utc>,
+    /// Most recent runs, oldest first, capped at [`SCHEDULE_HISTORY_LIMIT`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<ScheduleRun>,
+    /// Set when the next run could not be computed; the failure is the last run in `history`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disabled: bool,
+}
+
+impl Schedule {
+    pub fn cron_expr(&self) -> Result<CronExpr> {
+        self.cron
+            .parse()
+            .with_context(|| format!("schedule {} has an invalid cron expression", self.id))
+    }
+
+    pub fn record_run(&mut self, run: ScheduleRun) {
+        self.history.push(run);
+        let excess = self.history.len().saturating_sub(SCHEDULE_HISTORY_LIMIT);
+        self.history.drain(..excess);
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ScheduleFile {
+    #[serde(default)]
+    schedules: Vec<Schedule>,
+}
+
+/// Exclusive access to the store's schedules, released when dropped.
+pub struct ScheduleBook {
+    store: TaskStore,
+    _lock: File,
+}
+
+impl ScheduleBook {
+    /// Blocks until no other process is updating the schedules.
+    pub fn lock(store: &TaskStore) -> Result<Self> {
+        store.ensure_layout()?;
+        Ok(Self {
+            store: store.clone(),
+            _lock: lock_exclusive(&store.root().join(SCHEDULES_LOCK_FILE_NAME))?,
+        })
+    }
+
+    fn path(&self) -> PathBuf {
+        self.store.root().join(SCHEDULES_FILE_NAME)
+    }
+
+    pub fn load(&self) -> Result<Vec<Schedule>> {
+        let path = self.path();
+        match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice::<ScheduleFile>(&contents)
+                .map(|file| file.schedules)
+                .with_context(|| format!("failed to parse schedules at {}", path.display())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => {
+                Err(err).with_context(|| format!("failed to read schedules at {}", path.display()))
+            }
+        }
+    }
+
+    pub fn save(&self, schedules: Vec<Schedule>) -> Result<()> {
+        let path = self.path();
+        let payload = serde_json::to_vec_pretty(&ScheduleFile { schedules })?;
+        let mut tmp = NamedTempFile::new_in(self.store.root())
+            .context("failed to create temporary schedules file")?;
+        tmp.write_all(&payload)
+            .context("failed to write temporary schedules file")?;
+        tmp.persist(&path)
+            .map_err(|err| err.error)
+            .with_context(|| format!("failed to write schedules at {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Resolves a schedule by id or unique id prefix.
+pub fn find_schedule<'a>(schedules: &'a mut [Schedule], id: &str) -> Result<&'a mut Schedule> {
+    let matches: Vec<usize> = schedules
+        .iter()
+        .enumerate()
+        .filter(|(_, schedule)| schedule.id.starts_with(id))
+        .map(|(index, _)| index)
+        .collect();
+    match matches.as_slice() {
+        [index] => Ok(&mut schedules[*index]),
+        [] => bail!("schedule {id} was not found"),
+        _ => bail!("schedule id {id} is ambiguous"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(text: &str) -> DateTime<Utc> {
+        let naive = chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn computes_next_run_for_common_expressions() -> Result<()> {
+        let nightly: CronExpr = "0 2 * * *".parse()?;
+        assert_eq!(
+            nightly.next_after(local("2025-03-10 01:30")),
+            Some(local("2025-03-10 02:00"))
+        );
+        assert_eq!(
+            nightly.next_after(local("2025-03-10 02:00")),
+            Some(local("2025-03-11 02:00"))
+        );
+
+        let weekdays: CronExpr = "*/15 9-17 * * 1-5".parse()?;
+        // 2025-03-08 is a Saturday.
+        assert_eq!(
+            weekdays.next_after(local("2025-03-08 12:00")),
+            Some(local("2025-03-10 09:00"))
+        );
+        assert_eq!(
+            weekdays.next_after(local("2025-03-10 09:07")),
+            Some(local("2025-03-10 09:15"))
+        );
+
+        // Restricting both day fields matches either of them.
+        let either: CronExpr = "0 0 1 * 0".parse()?;
+        assert_eq!(
+            either.next_after(local("2025-03-02 12:00")),
+            Some(local("2025-03-09 00:00"))
+        );
+        let leap: CronExpr = "0 0 29 2 *".parse()?;
+        assert_eq!(
+            leap.next_after(local("2025-03-01 00:00")),
+            Some(local("2028-02-29 00:00"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for expr in [
+            "0 2 * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "5-1 * * * *",
+            "*/0 * * * *",
+        ] {
+            assert!(expr.parse::<CronExpr>().is_err(), "accepted `{expr}`");
+        }
+    }
+
+    #[test]
+    fn caps_run_history() {
+        let mut schedule = Schedule {
+            id: "abc".to_string(),
+            cron: "* * * * *".to_string(),
+            prompt: "audit".to_string(),
+            target: ScheduleTarget::Task {
+                task_id: "task".to_string(),
+            },
+            created_at: Utc::now(),
+            next_run: Utc::now(),
+            history: Vec::new(),
+            disabled: false,
+        };
+        for _ in 0..SCHEDULE_HISTORY_LIMIT + 3 {
+            schedule.record_run(ScheduleRun {
+                at: Utc::now(),
+                outcome: ScheduleRunOutcome::Skipped,
+                task_id: None,
+                detail: None,
+            });
+        }
+        assert_eq!(schedule.history.len(), SCHEDULE_HISTORY_LIMIT);
+    }
+}
@@ -0,0 +1,316 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::tasks::{LOG_FILE_NAME, RESULT_FILE_NAME, TaskId, TaskMetadata};
+
+/// Upper bound on the characters kept from a matching line.
+const MAX_SNIPPET_CHARS: usize = 200;
+
+/// Part of a task transcript that a search can match.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum SearchField {
+    /// Agent messages.
+    Messages,
+    /// Shell commands and their output.
+    Commands,
+    /// Paths touched by file changes.
+    Files,
+    /// Prompts sent to the task.
+    Prompts,
+    /// The task's last result.
+    Results,
+}
+
+impl SearchField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchField::Messages => "messages",
+            SearchField::Commands => "commands",
+            SearchField::Files => "files",
+            SearchField::Prompts => "prompts",
+            SearchField::Results => "results",
+        }
+    }
+}
+
+/// Criteria for searching task transcripts.
+#[derive(Clone, Debug)]
+pub struct SearchQuery {
+    pub pattern: Regex,
+    /// Fields to match; empty means every field.
+    pub fields: Vec<SearchField>,
+    /// Skip tasks whose last update is before this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Skip tasks created after this time.
+    pub until: Option<DateTime<Utc>>,
+    pub include_archived: bool,
+    /// Stop after this many matches.
+    pub limit: Option<usize>,
+}
+
+impl SearchQuery {
+    fn includes(&self, field: SearchField) -> bool {
+        self.fields.is_empty() || self.fields.contains(&field)
+    }
+
+    fn overlaps(&self, metadata: &TaskMetadata) -> bool {
+        self.since.is_none_or(|since| metadata.updated_at >= since)
+            && self.until.is_none_or(|until| metadata.created_at <= until)
+    }
+}
+
+/// A single line of a transcript that matched a search.
+#[derive(Clone, Debug, Serialize)]
+pub struct SearchHit {
+    pub task_id: TaskId,
+    pub title: Option<String>,
+    pub archived: bool,
+    /// 1-based index of the prompt the match belongs to (0 before the first prompt).
+    pub invocation: usize,
+    pub field: SearchField,
+    /// 1-based line in `task.log`, absent for matches in `task.result`.
+    pub log_line: Option<usize>,
+    pub text: String,
+}
+
+/// Compiles a search pattern, escaping it first when `fixed` is set.
+pub fn compile_pattern(pattern: &str, fixed: bool, ignore_case: bool) -> Result<Regex> {
+    let source = if fixed {
+        regex::escape(pattern)
+    } else {
+        pattern.to_string()
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(ignore_case)
+        .build()
+        .with_context(|| format!("invalid search pattern '{pattern}'"))
+}
+
+/// Searches the transcript and result stored in a task directory.
+pub fn search_task(
+    directory: &Path,
+    metadata: &TaskMetadata,
+    archived: bool,
+    query: &SearchQuery,
+) -> Result<Vec<SearchHit>> {
+    let mut hits = Vec::new();
+    if !query.overlaps(metadata) {
+        return Ok(hits);
+    }
+
+    let mut push = |invocation: usize, field: SearchField, log_line: Option<usize>, text: &str| {
+        for line in text.lines() {
+            if query.pattern.is_match(line) {
+                hits.push(SearchHit {
+                    task_id: metadata.id.clone(),
+                    title: metadata.title.clone(),
+                    archived,
+                    invocation,
+                    field,
+                    log_line,
+                    text: snippet(line),
+                });
+            }
+        }
+    };
+
+    let log_path = directory.join(LOG_FILE_NAME);
+    let mut invocation = 0;
+    match File::open(&log_path) {
+        Ok(file) => {
+            for (index, line) in BufReader::new(file).lines().enumerate() {
+                let line =
+                    line.with_context(|| format!("failed to read {}", log_path.display()))?;
+                let Ok(value) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                let line_number = Some(index + 1);
+                match value.get("type").and_then(Value::as_str) {
+                    Some("user_message") if value.get("kind").is_none() => {
+                        invocation += 1;
+                        if let Some(message) = value
+                            .get("message")
+                            .and_then(Value::as_str)
+                            .filter(|_| query.includes(SearchField::Prompts))
+                        {
+                            push(invocation, SearchField::Prompts, line_number, message);
+                        }
+                    }
+                    Some("item.completed") => {
+                        let Some(item) = value.get("item") else {
+                            continue;
+                        };
+                        let text_of = |key: &str| item.get(key).and_then(Value::as_str);
+                        match item.get("type").and_then(Value::as_str) {
+                            Some("agent_message") if query.includes(SearchField::Messages) => {
+                                if let Some(text) = text_of("text") {
+                                    push(invocation, SearchField::Messages, line_number, text);
+                                }
+                            }
+                            Some("command_execution") if query.includes(SearchField::Commands) => {
+                                for text in [text_of("command"), text_of("aggregated_output")]
+                                    .into_iter()
+                                    .flatten()
+                                {
+                                    push(invocation, SearchField::Commands, line_number, text);
+                                }
+                            }
+                            Some("file_change") if query.includes(SearchField::Files) => {
+                                let changes = item.get("changes").and_then(Value::as_array);
+                                for path in changes
+                                    .into_iter()
+                                    .flatten()
+                                    .filter_map(|change| change.get("path").and_then(Value::as_str))
+                                {
+                                    push(invocation, SearchField::Files, line_number, path);
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to open log file {}", log_path.display()));
+        }
+    }
+
+    if query.includes(SearchField::Results) {
+        let result_path = directory.join(RESULT_FILE_NAME);
+        match std::fs::read_to_string(&result_path) {
+            Ok(contents) => push(invocation, SearchField::Results, None, &contents),
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to read {}", result_path.display()));
+            }
+        }
+    }
+
+    Ok(hits)
+}
+
+fn snippet(line: &str) -> String {
+    let trimmed = line.trim();
+    match trimmed.char_indices().nth(MAX_SNIPPET_CHARS) {
+        Some((cut, _)) => format!("{}…", &trimmed[..cut]),
+        None => trimmed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::TaskState;
+    use serde_json::json;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn write_transcript(directory: &Path) -> Result<()> {
+        let events = [
+            json!({"type": "user_message", "message": "Fix the flaky parser test"}),
+            json!({"type": "user_message", "kind": "user_instructions", "message": "parser rules"}),
+            json!({"type": "item.completed", "item": {"type": "command_execution", "command": "cargo test parser", "aggregated_output": "error: parser panicked\nok", "exit_code": 101}}),
+            json!({"type": "item.completed", "item": {"type": "file_change", "changes": [{"path": "src/parser.rs", "kind": "update"}]}}),
+            json!({"type": "user_message", "message": "Now run clippy"}),
+            json!({"type": "item.completed", "item": {"type": "agent_message", "text": "The parser is fixed."}}),
+        ];
+        let log: Vec<String> = events.iter().map(Value::to_string).collect();
+        fs::write(directory.join(LOG_FILE_NAME), log.join("\n"))?;
+        fs::write(directory.join(RESULT_FILE_NAME), "The parser is fixed.")?;
+        Ok(())
+    }
+
+    fn query(pattern: &str, fields: Vec<SearchField>) -> Result<SearchQuery> {
+        Ok(SearchQuery {
+            pattern: compile_pattern(pattern, false, true)?,
+            fields,
+            since: None,
+            until: None,
+            include_archived: false,
+            limit: None,
+        })
+    }
+
+    #[test]
+    fn matches_fields_with_invocation_context() -> Result<()> {
+        let tmp = tempdir()?;
+        write_transcript(tmp.path())?;
+        let metadata = TaskMetadata::new("task-1".to_string(), None, TaskState::Stopped);
+
+        let hits = search_task(tmp.path(), &metadata, false, &query("parser", Vec::new())?)?;
+        let summary: Vec<(SearchField, usize, Option<usize>, &str)> = hits
+            .iter()
+            .map(|hit| (hit.field, hit.invocation, hit.log_line, hit.text.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    SearchField::Prompts,
+                    1,
+                    Some(1),
+                    "Fix the flaky parser test"
+                ),
+                (SearchField::Commands, 1, Some(3), "cargo test parser"),
+                (SearchField::Commands, 1, Some(3), "error: parser panicked"),
+                (SearchField::Files, 1, Some(4), "src/parser.rs"),
+                (SearchField::Messages, 2, Some(6), "The parser is fixed."),
+                (SearchField::Results, 2, None, "The parser is fixed."),
+            ]
+        );
+
+        let commands_only = search_task(
+            tmp.path(),
+            &metadata,
+            false,
+            &query("PARSER", vec![SearchField::Commands])?,
+        )?;
+        assert!(
+            commands_only
+                .iter()
+                .all(|hit| hit.field == SearchField::Commands)
+        );
+        assert_eq!(commands_only.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn skips_tasks_outside_date_window() -> Result<()> {
+        let tmp = tempdir()?;
+        write_transcript(tmp.path())?;
+        let metadata = TaskMetadata::new("task-1".to_string(), None, TaskState::Stopped);
+
+        let mut window = query("parser", Vec::new())?;
+        window.since = Some(metadata.updated_at + chrono::Duration::hours(1));
+        assert!(search_task(tmp.path(), &metadata, false, &window)?.is_empty());
+
+        window.since = None;
+        window.until = Some(metadata.created_at - chrono::Duration::hours(1));
+        assert!(search_task(tmp.path(), &metadata, false, &window)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_strings_escape_regex_syntax() -> Result<()> {
+        let pattern = compile_pattern("a.b", true, false)?;
+        assert!(pattern.is_match("a.b"));
+        assert!(!pattern.is_match("axb"));
+        assert!(compile_pattern("(", false, false).is_err());
+        Ok(())
+    }
+}
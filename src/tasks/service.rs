@@ -1,3 +1,5 @@
+use std::cmp::Reverse;
+use std::collections::{HashSet, VecDeque};
 use std::env;
 use std::ffi::OsStr;
 use std::fs;
@@ -9,16 +11,27 @@ use std::thread;
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result, anyhow, bail, ensure};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
 
 use crate::commands::common::is_process_running;
-use crate::commands::tasks::{collect_active_tasks, collect_archived_tasks};
+use crate::commands::tasks::{ListedTask, collect_active_tasks, collect_archived_tasks};
+use crate::tasks::hooks::{record_hook_runs, run_hooks};
+use crate::tasks::queue::{QueueLock, sort_by_admission};
+use crate::tasks::schedule::{ScheduleBook, find_schedule};
+use crate::tasks::settings::{self, StoreSettings};
+use crate::tasks::webhooks::{self, notify_webhooks};
 use crate::tasks::{
-    LOG_FILE_NAME, TaskMetadata, TaskPaths, TaskState, TaskStore, derive_active_state,
+    CronExpr, DoctorReport, ExecOptions, ForkPoint, HookEvent, Hooks, LOG_FILE_NAME, QueuedPrompt,
+    Reservation, RetryPolicy, Schedule, ScheduleRun, ScheduleRunOutcome, ScheduleTarget, SearchHit,
+    SearchQuery, StoreMigrationReport, TaskMetadata, TaskPaths, TaskState, TaskStore, TasksConfig,
+    Webhook, WebhookDelivery, WebhookEvent, WorkspaceSnapshot, derive_active_state,
+    is_reservation_live,
 };
+use crate::tasks::{answer, doctor, fork, search, transcript, workspace};
+use crate::worker::child::PARENT_ENV_VAR;
 use crate::worker::launcher::{WorkerLaunchRequest, spawn_worker};
 
-const SHUTDOWN_TIMEOUT_SECS: u64 = 10;
 const SHUTDOWN_POLL_INTERVAL_MS: u64 = 100;
 
 pub const LOG_WAIT_TIMEOUT_SECS: u64 = 10;
@@ -29,24 +42,28 @@ pub const LOG_WAIT_POLL_INTERVAL_MS: u64 = 100;
 #[derive(Clone, Debug)]
 pub struct TaskService {
     store: TaskStore,
+    config: TasksConfig,
     _allow_unsafe: bool,
 }
 
 impl TaskService {
-    /// Creates a service backed by an explicit task store.
-    pub fn new(store: TaskStore, allow_unsafe: bool) -> Self {
+    /// Creates a service backed by an explicit task store and configuration.
+    pub fn new(store: TaskStore, config: TasksConfig, allow_unsafe: bool) -> Self {
         Self {
             store,
+            config,
             _allow_unsafe: allow_unsafe,
         }
     }
 
     /// Creates a service using the default on-disk task store layout.
-    pub fn with_default_store(allow_unsafe: bool) -> Result<Self> {
-        Ok(Self {
-            store: TaskStore::default()?,
-            _allow_unsafe: allow_unsafe,
-        })
+    pub fn with_default_store(config: TasksConfig, allow_unsafe: bool) -> Result<Self> {
+        Ok(Self::new(TaskStore::default()?, config, allow_unsafe))
+    }
+
+    /// The configuration applied to the tasks this service starts and manages.
+    pub fn config(&self) -> &TasksConfig {
+        &self.config
     }
 
     /// Starts a new task worker using the provided parameters and returns the spawned thread id.
@@ -54,10 +71,17 @@ impl TaskService {
         let StartTaskParams {
             title,
             prompt,
-            config_file,
-            working_dir,
-            repo_url,
-            repo_ref,
+            mut config_file,
+            mut working_dir,
+            mut repo_url,
+            mut repo_ref,
+            parent_id,
+            retry_policy,
+            mut tags,
+            priority,
+            hooks,
+            preset,
+            exec,
         } = params;
 
         if prompt.trim().is_empty() {
@@ -66,7 +90,29 @@ impl TaskService {
 
         self.store.ensure_layout()?;
 
-        let config_file = resolve_config_file(config_file)?;
+        let defaults = &self.config;
+        let mut exec = exec.unwrap_or_default();
+        if let Some(name) = &preset {
+            let preset = defaults.preset(name)?;
+            config_file = config_file.or_else(|| preset.config_file.clone());
+            if working_dir.is_none() {
+                working_dir = preset.render_working_dir(name, title.as_deref())?;
+            }
+            if repo_url.is_none() {
+                repo_url = preset.repo.clone();
+                repo_ref = repo_ref.or_else(|| preset.repo_ref.clone());
+            }
+            if tags.is_empty() {
+                tags = preset.tags.clone();
+            }
+            exec = exec.or(&preset.exec);
+        }
+        let config_file =
+            resolve_config_file(config_file.or_else(|| defaults.config_file.clone()))?;
+        let working_dir = working_dir.map(|dir| match &defaults.working_dir_root {
+            Some(root) if dir.is_relative() => root.join(dir),
+            _ => dir,
+        });
         let working_dir =
             prepare_working_directory(working_dir, repo_url.as_deref(), repo_ref.as_deref())?;
         let working_dir = match working_dir {
@@ -78,21 +124,260 @@ impl TaskService {
             }
         };
 
+        let parent_id = match parent_id {
+            Some(reference) => Some(
+                self.resolve_parent(&reference)?
+                    .ok_or_else(|| anyhow!("parent task {reference} was not found"))?,
+            ),
+            None => match env::var(PARENT_ENV_VAR) {
+                Ok(reference) if !reference.trim().is_empty() => {
+                    self.resolve_parent(reference.trim())?
+                }
+                _ => None,
+            },
+        };
+
+        // Until the worker reports a Codex thread id, the launch id identifies the task; a queued
+        // task is stored under it.
+        let launch_id = Uuid::new_v4().to_string();
+        let mut metadata = TaskMetadata::new(launch_id.clone(), title, TaskState::Queued);
+        metadata.initial_prompt = Some(prompt.clone());
+        metadata.last_prompt = Some(prompt.clone());
+        metadata.config_path = config_file.map(|path| path.to_string_lossy().into_owned());
+        metadata.working_dir = working_dir.map(|path| path.to_string_lossy().into_owned());
+        metadata.parent_id = parent_id;
+        metadata.retry_policy = retry_policy;
+        metadata.tags = tags;
+        metadata.launch_id = Some(launch_id.clone());
+        metadata.hooks = hooks;
+        metadata.preset = preset;
+        metadata.exec = Some(exec).filter(|exec| !exec.is_empty());
+
+        // Without a concurrency limit nothing queues, so the worker is launched directly and
+        // creates the task itself once Codex reports its thread.
+        if self.effective_settings()?.max_running.is_none() {
+            let thread_id = self.launch_new_task(&metadata, prompt)?;
+            return Ok(StartTaskResult {
+                thread_id,
+                outcome: LaunchOutcome::Started,
+            });
+        }
+
+        // With a limit, the task is stored under its launch id either way: queued, or as a RUNNING placeholder
+        // holding its slot until the worker has created the real task.
+        let placeholder = self.store.task(launch_id.clone());
+        let (admissions, free) = {
+            let _lock = QueueLock::acquire(&self.store)?;
+            let (admissions, free) = self.claim_queued_locked()?;
+            if free == 0 {
+                metadata.queued = Some(QueuedPrompt {
+                    prompt: prompt.clone(),
+                    priority,
+                    queued_at: Utc::now(),
+                    new_task: true,
+                });
+                self.store.save_metadata(&metadata)?;
+            } else {
+                self.store.save_metadata(&metadata)?;
+                reserve_slot(&placeholder)?;
+            }
+            (admissions, free)
+        };
+        self.launch_admitted(admissions);
+        if free == 0 {
+            return Ok(StartTaskResult {
+                thread_id: launch_id,
+                outcome: LaunchOutcome::Queued,
+            });
+        }
+
+        let launched = self.launch_new_task(&metadata, prompt);
+        fs::remove_dir_all(placeholder.directory())
+            .with_context(|| format!("failed to remove placeholder of started task {launch_id}"))?;
+        let thread_id = launched?;
+        Ok(StartTaskResult {
+            thread_id,
+            outcome: LaunchOutcome::Started,
+        })
+    }
+
+    /// A worker launch request for this store that reads the same configuration file.
+    fn worker_request(&self, prompt: String) -> WorkerLaunchRequest {
         let mut request = WorkerLaunchRequest::new(self.store.root().to_path_buf(), prompt);
-        request.title = title;
-        request.config_path = config_file;
-        request.working_directory = working_dir.clone();
+        request.tasks_config = self.config.path.clone();
+        request
+    }
+
+    /// Spawns a worker for a task that has no Codex thread yet and waits for its thread id.
+    fn launch_new_task(&self, metadata: &TaskMetadata, prompt: String) -> Result<String> {
+        let mut request = self.worker_request(prompt);
+        request.title = metadata.title.clone();
+        request.config_path = metadata.config_path.as_ref().map(PathBuf::from);
+        request.working_directory = metadata.working_dir.as_ref().map(PathBuf::from);
+        request.parent_id = metadata.parent_id.clone();
+        request.retry_policy = metadata.retry_policy;
+        request.tags = metadata.tags.clone();
+        request.launch_id = metadata.launch_id.clone();
+        request.preset = metadata.preset.clone();
+        request.exec = metadata.exec.clone();
+        request.hooks = metadata.hooks.clone();
 
         let mut child = spawn_worker(request).context("failed to launch worker process")?;
         let thread_id = receive_thread_id(&mut child)?;
         drop(child);
+        Ok(thread_id)
+    }
+
+    /// Starts queued invocations, in priority then FIFO order, while the store's `max_running`
+    /// limit leaves room. Workers call this when they finish so queued work proceeds without a
+    /// daemon.
+    pub fn admit_queued(&self) -> Result<()> {
+        let (admissions, _) = {
+            let _lock = QueueLock::acquire(&self.store)?;
+            self.claim_queued_locked()?
+        };
+        self.launch_admitted(admissions);
+        Ok(())
+    }
 
-        Ok(StartTaskResult { thread_id })
+    /// Claims queued tasks for the free slots while holding the queue lock, returning them with
+    /// the number of slots left afterwards. The claimed tasks are launched by
+    /// [`Self::launch_admitted`] once the lock is released, so other processes never wait on a
+    /// Codex startup.
+    fn claim_queued_locked(&self) -> Result<(Vec<Admission>, usize)> {
+        let settings = self.effective_settings()?;
+        let tasks: Vec<TaskMetadata> = collect_active_tasks(&self.store)?
+            .into_iter()
+            .map(|task| task.metadata)
+            .collect();
+        let running = tasks
+            .iter()
+            .filter(|metadata| metadata.state == TaskState::Running)
+            .count();
+        let mut free = settings.free_slots(running);
+        let mut queued: Vec<TaskMetadata> = tasks
+            .into_iter()
+            .filter(|metadata| metadata.state == TaskState::Queued)
+            .collect();
+        sort_by_admission(&mut queued);
+
+        let mut admissions = Vec::new();
+        for metadata in queued {
+            if free == 0 {
+                break;
+            }
+            let task_id = metadata.id.clone();
+            match self.claim(metadata) {
+                Ok(admission) => {
+                    admissions.push(admission);
+                    free -= 1;
+                }
+                Err(err) => eprintln!("failed to start queued task {task_id}: {err:#}"),
+            }
+        }
+        Ok((admissions, free))
+    }
+
+    fn claim(&self, metadata: TaskMetadata) -> Result<Admission> {
+        let paths = self.store.task(metadata.id.clone());
+        let Some(queued) = metadata.queued.clone() else {
+            paths.update_metadata(|metadata| metadata.set_state(TaskState::Stopped))?;
+            bail!("task {} was QUEUED without a prompt", metadata.id);
+        };
+        reserve_slot(&paths)?;
+        Ok(Admission { metadata, queued })
+    }
+
+    /// Launches the workers of tasks claimed by [`Self::claim_queued_locked`].
+    fn launch_admitted(&self, admissions: Vec<Admission>) {
+        for admission in admissions {
+            let task_id = admission.metadata.id.clone();
+            if let Err(err) = self.launch_admission(admission) {
+                eprintln!("failed to start queued task {task_id}: {err:#}");
+            }
+        }
+    }
+
+    fn launch_admission(&self, admission: Admission) -> Result<()> {
+        let Admission { metadata, queued } = admission;
+        let paths = self.store.task(metadata.id.clone());
+        if !queued.new_task {
+            return self.resume_worker(&metadata, queued.prompt);
+        }
+
+        match self.launch_new_task(&metadata, queued.prompt) {
+            // The task created by the worker keeps the placeholder id as its launch id.
+            Ok(_) => fs::remove_dir_all(paths.directory()).with_context(|| {
+                format!(
+                    "failed to remove placeholder of queued task {}",
+                    metadata.id
+                )
+            }),
+            Err(err) => {
+                let _ = paths.remove_pid();
+                paths.update_metadata(|metadata| {
+                    metadata.queued = None;
+                    metadata.set_state(TaskState::Died);
+                })?;
+                let runs = run_hooks(&self.store, &paths, HookEvent::OnDied, &self.config);
+                if let Err(err) = record_hook_runs(&paths.log_path(), &runs) {
+                    eprintln!("failed to log hooks of task {}: {err:#}", metadata.id);
+                }
+                let error = format!("{err:#}");
+                notify_webhooks(
+                    &self.store,
+                    &paths,
+                    HookEvent::OnDied,
+                    Some(&error),
+                    &self.config,
+                    false,
+                );
+                Err(err)
+            }
+        }
+    }
+
+    /// Maps a placeholder id handed out by a queued `start` to the task that replaced it once
+    /// admitted; other ids are returned unchanged.
+    fn resolve_task_id(&self, task_id: &str) -> Result<String> {
+        if self
+            .store
+            .task(task_id.to_string())
+            .metadata_path()
+            .exists()
+        {
+            return Ok(task_id.to_string());
+        }
+        Ok(self
+            .resolve_parent(task_id)?
+            .unwrap_or_else(|| task_id.to_string()))
+    }
+
+    /// Maps a parent reference (a thread id, or the launch id exported during a task's initial
+    /// invocation) to the id of an active task.
+    fn resolve_parent(&self, reference: &str) -> Result<Option<String>> {
+        if self
+            .store
+            .task(reference.to_string())
+            .metadata_path()
+            .exists()
+        {
+            return Ok(Some(reference.to_string()));
+        }
+        Ok(collect_active_tasks(&self.store)?
+            .into_iter()
+            .find(|task| task.metadata.launch_id.as_deref() == Some(reference))
+            .map(|task| task.metadata.id))
     }
 
     /// Restarts a task worker to process an additional prompt for an existing task.
-    pub fn send_prompt(&self, params: SendPromptParams) -> Result<()> {
-        let SendPromptParams { task_id, prompt } = params;
+    pub fn send_prompt(&self, params: SendPromptParams) -> Result<LaunchOutcome> {
+        let SendPromptParams {
+            task_id,
+            prompt,
+            priority,
+        } = params;
+        let task_id = self.resolve_task_id(&task_id)?;
 
         if prompt.trim().is_empty() {
             bail!("prompt must not be empty");
@@ -123,6 +408,10 @@ impl TaskService {
                 metadata.id
             ),
             TaskState::Died => bail!("task {} has DIED and cannot receive prompts", metadata.id),
+            TaskState::Queued => bail!(
+                "task {} is QUEUED; stop it to cancel the queued prompt first",
+                metadata.id
+            ),
             TaskState::Stopped | TaskState::Running => {}
         }
 
@@ -137,7 +426,89 @@ impl TaskService {
             let _ = paths.remove_pid();
         }
 
-        let mut request = WorkerLaunchRequest::new(self.store.root().to_path_buf(), prompt);
+        self.resume_or_queue(&metadata, prompt, priority)
+    }
+
+    /// Resumes a task with a prompt, or queues the prompt when the concurrency limit is reached.
+    fn resume_or_queue(
+        &self,
+        metadata: &TaskMetadata,
+        prompt: String,
+        priority: i32,
+    ) -> Result<LaunchOutcome> {
+        let paths = self.store.task(metadata.id.clone());
+        let (admissions, free) = {
+            let _lock = QueueLock::acquire(&self.store)?;
+            let (admissions, free) = self.claim_queued_locked()?;
+            if free == 0 {
+                paths.update_metadata(|metadata| {
+                    metadata.queued = Some(QueuedPrompt {
+                        prompt: prompt.clone(),
+                        priority,
+                        queued_at: Utc::now(),
+                        new_task: false,
+                    });
+                    metadata.set_state(TaskState::Queued);
+                })?;
+            } else {
+                reserve_slot(&paths)?;
+            }
+            (admissions, free)
+        };
+        self.launch_admitted(admissions);
+        if free == 0 {
+            return Ok(LaunchOutcome::Queued);
+        }
+        self.resume_worker(metadata, prompt)?;
+        Ok(LaunchOutcome::Started)
+    }
+
+    /// Re-runs the last prompt, or a replacement prompt, on a DIED task via `exec resume`,
+    /// returning the prompt that was submitted.
+    pub fn retry_task(&self, params: RetryTaskParams) -> Result<String> {
+        let RetryTaskParams { task_id, prompt } = params;
+        let status = self.get_status(&task_id)?;
+        let metadata = status.metadata;
+        if metadata.state != TaskState::Died {
+            bail!(
+                "task {} is {}; only DIED tasks can be retried",
+                metadata.id,
+                metadata.state
+            );
+        }
+        // A task still stored under its launch id died before Codex reported a thread, so
+        // there is no session to resume.
+        if metadata.launch_id.as_deref() == Some(metadata.id.as_str()) {
+            bail!(
+                "task {} never started a thread; use `start` again",
+                metadata.id
+            );
+        }
+
+        let prompt = match prompt {
+            Some(prompt) if prompt.trim().is_empty() => bail!("prompt must not be empty"),
+            Some(prompt) => prompt,
+            None => metadata.last_prompt.clone().ok_or_else(|| {
+                anyhow!(
+                    "task {} has no previous prompt; pass one to retry with",
+                    metadata.id
+                )
+            })?,
+        };
+
+        let paths = self.store.task(metadata.id.clone());
+        let _ = paths.remove_pid();
+        paths.update_metadata(|metadata| metadata.retry_count += 1)?;
+        self.resume_or_queue(&metadata, prompt.clone(), 0)?;
+        Ok(prompt)
+    }
+
+    /// Launches a worker that resumes an existing task with another prompt, once
+    /// [`reserve_slot`] has marked the task RUNNING. `metadata` is the task as it was before, which
+    /// is restored if the worker cannot be started.
+    fn resume_worker(&self, metadata: &TaskMetadata, prompt: String) -> Result<()> {
+        let paths = self.store.task(metadata.id.clone());
+        let mut request = self.worker_request(prompt);
         request.task_id = Some(metadata.id.clone());
         request.title = metadata.title.clone();
         if let Some(path) = metadata.config_path.as_ref() {
@@ -147,7 +518,21 @@ impl TaskService {
             request.working_directory = Some(PathBuf::from(dir));
         }
 
-        let mut child = spawn_worker(request).context("failed to launch worker process")?;
+        let mut child = match spawn_worker(request) {
+            Ok(child) => child,
+            Err(err) => {
+                let _ = paths.remove_pid();
+                paths.update_metadata(|current| {
+                    current.queued = metadata.queued.clone();
+                    current.set_state(metadata.state.clone());
+                })?;
+                return Err(err).context("failed to launch worker process");
+            }
+        };
+        // Record the worker as the task's process unless it already recorded `codex exec`.
+        if paths.read_pid()?.is_none() {
+            paths.write_pid(child.id() as i32)?;
+        }
         if let Some(stdout) = child.stdout.take() {
             drop(stdout);
         }
@@ -156,18 +541,128 @@ impl TaskService {
         Ok(())
     }
 
+    /// Creates a new task seeded from an existing task's conversation.
+    ///
+    /// The source's Codex session file is copied under a fresh thread id when it can be found,
+    /// so the fork resumes with full history. Otherwise a new task is started with a preamble
+    /// rebuilt from the source transcript, which requires a prompt.
+    pub fn fork_task(&self, params: ForkTaskParams) -> Result<ForkTaskResult> {
+        let ForkTaskParams {
+            task_id,
+            prompt,
+            title,
+        } = params;
+        if prompt
+            .as_deref()
+            .is_some_and(|prompt| prompt.trim().is_empty())
+        {
+            bail!("prompt must not be empty");
+        }
+
+        self.store.ensure_layout()?;
+        let snapshot = self.get_status(&task_id)?;
+        if snapshot.metadata.state == TaskState::Running {
+            bail!(
+                "task {} is RUNNING; wait for it to finish before forking",
+                snapshot.metadata.id
+            );
+        }
+        let source = snapshot.metadata;
+        let source_paths = match self.store.find_archived_task(&source.id)? {
+            Some((paths, _)) if source.state == TaskState::Archived => paths,
+            _ => self.store.task(source.id.clone()),
+        };
+        let fork_point = ForkPoint {
+            invocation: transcript::count_invocations(&source_paths.log_path())?,
+            at: source.updated_at,
+        };
+        let title = title.or_else(|| source.title.clone());
+
+        let codex_home = fork::codex_home(source.config_path.as_deref())?;
+        if let Some(rollout) = fork::find_rollout(&codex_home, &source.id)? {
+            let thread_id = Uuid::new_v4().to_string();
+            fork::copy_rollout(&rollout, &source.id, &thread_id)?;
+
+            let paths = self.store.task(thread_id.clone());
+            paths.ensure_directory()?;
+            if source_paths.log_path().exists() {
+                fs::copy(source_paths.log_path(), paths.log_path())
+                    .with_context(|| format!("failed to copy transcript of task {}", source.id))?;
+            }
+            if let Some(result) = source_paths.read_last_result()? {
+                paths.write_last_result(&result)?;
+            }
+
+            let mut metadata = TaskMetadata::new(thread_id.clone(), title, TaskState::Stopped);
+            metadata.initial_prompt = source.initial_prompt.clone();
+            metadata.last_prompt = source.last_prompt.clone();
+            metadata.last_result = source.last_result.clone();
+            metadata.config_path = source.config_path.clone();
+            metadata.working_dir = source.working_dir.clone();
+            metadata.preset = source.preset.clone();
+            metadata.exec = source.exec.clone();
+            metadata.forked_from = Some(source.id.clone());
+            metadata.fork_point = Some(fork_point);
+            paths.write_metadata(&metadata)?;
+
+            if let Some(prompt) = prompt {
+                self.send_prompt(SendPromptParams {
+                    task_id: thread_id.clone(),
+                    prompt,
+                    priority: 0,
+                })?;
+            }
+            return Ok(ForkTaskResult {
+                thread_id,
+                mode: ForkMode::Session,
+            });
+        }
+
+        let Some(prompt) = prompt else {
+            bail!(
+                "no Codex session file was found for task {}; provide a prompt to fork it from its transcript",
+                source.id
+            );
+        };
+        let mut request = self.worker_request(prompt);
+        request.preamble = Some(fork::build_preamble(&source.id, &source_paths.log_path())?);
+        request.title = title;
+        request.config_path = source.config_path.as_ref().map(PathBuf::from);
+        request.working_directory = source.working_dir.as_ref().map(PathBuf::from);
+        request.fork = Some((source.id.clone(), fork_point));
+        request.preset = source.preset.clone();
+        request.exec = source.exec.clone();
+
+        let mut child = spawn_worker(request).context("failed to launch worker process")?;
+        let thread_id = receive_thread_id(&mut child)?;
+        drop(child);
+
+        Ok(ForkTaskResult {
+            thread_id,
+            mode: ForkMode::Transcript,
+        })
+    }
+
     /// Loads metadata and runtime information for the requested task.
     pub fn get_status(&self, task_id: &str) -> Result<TaskStatusSnapshot> {
+        self.find_status(task_id)?
+            .ok_or_else(|| anyhow!("task {task_id} was not found in the task store"))
+    }
+
+    /// Like [`TaskService::get_status`], but returns `None` for unknown task ids.
+    pub fn find_status(&self, task_id: &str) -> Result<Option<TaskStatusSnapshot>> {
+        let task_id = self.resolve_task_id(task_id)?;
+        let task_id = task_id.as_str();
         let paths = self.store.task(task_id.to_string());
         match paths.read_metadata() {
             Ok(mut metadata) => {
                 let pid = paths.read_pid()?;
-                let derived_state = derive_active_state(&metadata.state, pid);
+                let derived_state = derive_active_state(&metadata, pid);
                 metadata.state = derived_state;
                 if metadata.last_result.is_none() {
                     metadata.last_result = paths.read_last_result()?;
                 }
-                Ok(TaskStatusSnapshot { metadata, pid })
+                Ok(Some(TaskStatusSnapshot { metadata, pid }))
             }
             Err(err) => {
                 let not_found = err
@@ -178,16 +673,16 @@ impl TaskService {
                 }
 
                 let Some((paths, mut metadata)) = self.store.find_archived_task(task_id)? else {
-                    bail!("task {task_id} was not found in the task store");
+                    return Ok(None);
                 };
                 metadata.state = TaskState::Archived;
                 if metadata.last_result.is_none() {
                     metadata.last_result = paths.read_last_result()?;
                 }
-                Ok(TaskStatusSnapshot {
+                Ok(Some(TaskStatusSnapshot {
                     metadata,
                     pid: None,
-                })
+                }))
             }
         }
     }
@@ -196,8 +691,7 @@ impl TaskService {
     pub fn list_tasks(&self, options: ListTasksOptions) -> Result<Vec<TaskListEntry>> {
         self.store.ensure_layout()?;
 
-        let mut tasks = Vec::new();
-        tasks.extend(collect_active_tasks(&self.store)?);
+        let mut tasks = self.collect_active_admitting()?;
         if options.include_archived {
             tasks.extend(collect_archived_tasks(&self.store)?);
         }
@@ -205,6 +699,23 @@ impl TaskService {
         if !options.states.is_empty() {
             tasks.retain(|task| options.states.contains(&task.metadata.state));
         }
+        if !options.tags.is_empty() {
+            tasks.retain(|task| {
+                options
+                    .tags
+                    .iter()
+                    .all(|tag| task.metadata.tags.contains(tag))
+            });
+        }
+        if let Some(title) = options.title.as_deref() {
+            let needle = title.to_lowercase();
+            tasks.retain(|task| {
+                task.metadata
+                    .title
+                    .as_deref()
+                    .is_some_and(|title| title.to_lowercase().contains(&needle))
+            });
+        }
 
         tasks.sort_by(|a, b| b.metadata.updated_at.cmp(&a.metadata.updated_at));
 
@@ -216,31 +727,517 @@ impl TaskService {
             .collect())
     }
 
+    /// Searches task transcripts, most recently updated tasks first.
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        self.store.ensure_layout()?;
+
+        let mut tasks: Vec<(PathBuf, TaskMetadata, bool)> = collect_active_tasks(&self.store)?
+            .into_iter()
+            .map(|task| {
+                let directory = self
+                    .store
+                    .task(task.metadata.id.clone())
+                    .directory()
+                    .to_path_buf();
+                (directory, task.metadata, false)
+            })
+            .collect();
+        if query.include_archived {
+            let archive_root = self.store.archive_root();
+            tasks.extend(
+                self.store
+                    .archive_index()
+                    .entries()?
+                    .into_iter()
+                    .map(|entry| (archive_root.join(&entry.path), entry.metadata, true)),
+            );
+        }
+        tasks.sort_by_key(|(_, metadata, _)| Reverse(metadata.updated_at));
+
+        let mut hits = Vec::new();
+        for (directory, metadata, archived) in tasks {
+            hits.extend(search::search_task(&directory, &metadata, archived, query)?);
+            if let Some(limit) = query.limit.filter(|limit| hits.len() >= *limit) {
+                hits.truncate(limit);
+                break;
+            }
+        }
+        Ok(hits)
+    }
+
+    /// Returns a task's last result, or the final agent message of a single 1-based invocation.
+    pub fn task_answer(&self, task_id: &str, invocation: Option<usize>) -> Result<Option<String>> {
+        let status = self.get_status(task_id)?;
+        let task_id = status.metadata.id.as_str();
+        let Some(number) = invocation else {
+            return Ok(status.metadata.last_result);
+        };
+
+        let log_path = resolve_log_path(&self.store, task_id, false)?;
+        let mut answers = answer::invocation_answers(&log_path)?;
+        ensure!(
+            (1..=answers.len()).contains(&number),
+            "task {task_id} has {} invocation(s); --invocation must be between 1 and {}",
+            answers.len(),
+            answers.len()
+        );
+        Ok(answers.swap_remove(number - 1))
+    }
+
+    /// Computes the workspace changes made by a task since its first invocation, or during a
+    /// single 1-based invocation.
+    pub fn task_diff(&self, task_id: &str, invocation: Option<usize>) -> Result<TaskDiff> {
+        let status = self.get_status(task_id)?;
+        let task_id = status.metadata.id.as_str();
+        let log_path = resolve_log_path(&self.store, task_id, false)?;
+        let invocations = workspace::read_invocations(&log_path)?;
+        ensure!(
+            !invocations.is_empty(),
+            "task {task_id} has no recorded invocations"
+        );
+        let (start, end) = match invocation {
+            Some(number) => {
+                ensure!(
+                    (1..=invocations.len()).contains(&number),
+                    "task {task_id} has {} invocation(s); --invocation must be between 1 and {}",
+                    invocations.len(),
+                    invocations.len()
+                );
+                (number - 1, number)
+            }
+            None => (0, invocations.len()),
+        };
+
+        let working_dir = status
+            .metadata
+            .working_dir
+            .as_deref()
+            .map(Path::new)
+            .filter(|dir| dir.is_dir());
+        // Diff up to the next invocation's snapshot, or the current working tree for the last one.
+        let to_tree = match invocations.get(end) {
+            Some(next) => next
+                .snapshot
+                .as_ref()
+                .map(|snapshot| Some(snapshot.tree.as_str())),
+            None => Some(None),
+        };
+        if let (Some(dir), Some(base), Some(to_tree)) =
+            (working_dir, invocations[start].snapshot.as_ref(), to_tree)
+        {
+            let patch = workspace::diff(dir, &base.tree, to_tree)?;
+            return Ok(TaskDiff::Patch {
+                base: base.clone(),
+                patch,
+            });
+        }
+
+        Ok(TaskDiff::Files(
+            invocations[start..end]
+                .iter()
+                .flat_map(|changes| changes.files.iter().cloned())
+                .collect(),
+        ))
+    }
+
     /// Resolves the log path and metadata for the specified task, optionally waiting for the log
     /// file to appear.
     pub fn prepare_log_descriptor(&self, task_id: &str, wait: bool) -> Result<LogDescriptor> {
         self.store.ensure_layout()?;
-        let path = resolve_log_path(&self.store, task_id, wait)?;
-        let metadata = resolve_follow_metadata(&self.store, task_id)?;
+        let task_id = self.resolve_task_id(task_id)?;
+        let path = resolve_log_path(&self.store, &task_id, wait)?;
+        let metadata = resolve_follow_metadata(&self.store, &task_id)?;
         Ok(LogDescriptor {
-            task_id: task_id.to_string(),
+            task_id,
             path,
             metadata,
         })
     }
 
+    /// Returns the concurrency limit, the number of running tasks, and the queue in admission
+    /// order.
+    pub fn queue_report(&self) -> Result<QueueReport> {
+        let settings = self.effective_settings()?;
+        let mut running = 0;
+        let mut queued = Vec::new();
+        for task in self.collect_active_admitting()? {
+            match task.metadata.state {
+                TaskState::Running => running += 1,
+                TaskState::Queued => queued.push(task.metadata),
+                _ => {}
+            }
+        }
+        sort_by_admission(&mut queued);
+        Ok(QueueReport {
+            max_running: settings.max_running,
+            running,
+            queued,
+        })
+    }
+
+    /// Collects the active tasks, first admitting queued work if there is room for it. Workers
+    /// admit queued work when they finish, so room alongside queued tasks means a worker died
+    /// without doing so.
+    fn collect_active_admitting(&self) -> Result<Vec<ListedTask>> {
+        let tasks = collect_active_tasks(&self.store)?;
+        let mut running = 0;
+        let mut queued = false;
+        for task in &tasks {
+            match task.metadata.state {
+                TaskState::Running => running += 1,
+                TaskState::Queued => queued = true,
+                _ => {}
+            }
+        }
+        if !queued || self.effective_settings()?.free_slots(running) == 0 {
+            return Ok(tasks);
+        }
+        self.admit_queued()?;
+        collect_active_tasks(&self.store)
+    }
+
+    /// Changes the store-wide concurrency limit (`None` removes it) and starts any queued tasks
+    /// the new limit leaves room for.
+    pub fn set_max_running(&self, max_running: Option<usize>) -> Result<()> {
+        if max_running == Some(0) {
+            bail!("max running tasks must be at least 1");
+        }
+        let (admissions, _) = {
+            let _lock = QueueLock::acquire(&self.store)?;
+            StoreSettings::update(&self.store, |settings| {
+                settings.max_running = max_running;
+                Ok(())
+            })?;
+            self.claim_queued_locked()?
+        };
+        self.launch_admitted(admissions);
+        Ok(())
+    }
+
+    /// Store settings with the configuration file's limits and hooks filling what they leave
+    /// unset. Only for reading; saving it would copy configuration into `settings.json`.
+    fn effective_settings(&self) -> Result<StoreSettings> {
+        let mut settings = StoreSettings::load(&self.store)?;
+        let config = &self.config;
+        settings.max_running = settings.max_running.or(config.max_running);
+        settings.hooks = settings.hooks.or(&config.hooks);
+        Ok(settings)
+    }
+
+    /// Returns the service's configuration merged with the store's settings, as applied to tasks.
+    pub fn effective_config(&self) -> Result<TasksConfig> {
+        let settings = self.effective_settings()?;
+        let mut config = self.config.clone();
+        config.max_running = settings.max_running;
+        if let Some(timeout_secs) = settings.hooks.timeout_secs {
+            config.hook_timeout = Duration::from_secs(timeout_secs);
+        }
+        config.hooks = Hooks {
+            timeout_secs: None,
+            ..settings.hooks
+        };
+        Ok(config)
+    }
+
+    /// Archives STOPPED and DIED tasks left untouched for longer than the configured
+    /// `retention.archive_after`; does nothing when it is unset.
+    pub fn apply_retention(&self) -> Result<ArchiveAllSummary> {
+        let Some(archive_after) = self.config.archive_after else {
+            return Ok(ArchiveAllSummary::default());
+        };
+        let cutoff = Utc::now() - chrono::Duration::from_std(archive_after)?;
+        let states = collect_active_tasks(&self.store)?
+            .into_iter()
+            .map(|task| task.metadata)
+            .filter(|metadata| {
+                matches!(metadata.state, TaskState::Stopped | TaskState::Died)
+                    && metadata.updated_at < cutoff
+            })
+            .map(|metadata| (metadata.id, metadata.state))
+            .collect();
+        Ok(archive_each(&self.store, &self.config, states))
+    }
+
+    /// Returns the lifecycle hooks run for every task in the store.
+    pub fn global_hooks(&self) -> Result<Hooks> {
+        Ok(StoreSettings::load(&self.store)?.hooks)
+    }
+
+    /// Changes the store-wide lifecycle hooks and returns the updated configuration.
+    pub fn update_global_hooks(&self, update: impl FnOnce(&mut Hooks)) -> Result<Hooks> {
+        settings::update_hooks(&self.store, update)
+    }
+
+    /// Registers a URL to be notified of task events and returns it with its new id.
+    pub fn add_webhook(&self, params: AddWebhookParams) -> Result<Webhook> {
+        let AddWebhookParams {
+            url,
+            events,
+            secret,
+            retries,
+            retry_backoff_secs,
+        } = params;
+        ensure!(
+            url.starts_with("http://") || url.starts_with("https://"),
+            "webhook URL must start with http:// or https://"
+        );
+        let webhook = Webhook {
+            id: Uuid::new_v4().simple().to_string()[..8].to_string(),
+            url,
+            events,
+            secret: secret.filter(|secret| !secret.is_empty()),
+            retries,
+            retry_backoff_secs,
+        };
+        settings::add_webhook(&self.store, webhook.clone())?;
+        Ok(webhook)
+    }
+
+    pub fn list_webhooks(&self) -> Result<Vec<Webhook>> {
+        Ok(StoreSettings::load(&self.store)?.webhooks)
+    }
+
+    /// Removes a webhook by id, unique id prefix, or URL.
+    pub fn remove_webhook(&self, reference: &str) -> Result<Webhook> {
+        settings::remove_webhook(&self.store, reference)
+    }
+
+    /// Returns the most recent webhook deliveries, oldest first.
+    pub fn webhook_deliveries(&self, limit: usize) -> Result<Vec<WebhookDelivery>> {
+        let mut deliveries = webhooks::read_deliveries(&self.store)?;
+        let skip = deliveries.len().saturating_sub(limit);
+        deliveries.drain(..skip);
+        Ok(deliveries)
+    }
+
+    /// Registers a recurring prompt; `scheduler run` fires it at every cron match.
+    pub fn add_schedule(&self, params: AddScheduleParams) -> Result<Schedule> {
+        let AddScheduleParams {
+            cron,
+            prompt,
+            mut target,
+        } = params;
+        if prompt.trim().is_empty() {
+            bail!("prompt must not be empty");
+        }
+        let expr: CronExpr = cron.parse()?;
+        let now = Utc::now();
+        let next_run = expr
+            .next_after(now)
+            .ok_or_else(|| anyhow!("cron expression `{cron}` never matches"))?;
+
+        match &mut target {
+            ScheduleTarget::Task { task_id } => {
+                let status = self.get_status(task_id)?;
+                if status.metadata.state == TaskState::Archived {
+                    bail!("task {task_id} is ARCHIVED and cannot receive prompts");
+                }
+                *task_id = status.metadata.id;
+            }
+            ScheduleTarget::New { working_dir, .. } => {
+                // Like `start`, fresh tasks run where the schedule was created unless told
+                // otherwise.
+                let dir = match working_dir.take() {
+                    Some(dir) => make_absolute(PathBuf::from(dir))?,
+                    None => env::current_dir()
+                        .context("failed to determine current working directory")?,
+                };
+                *working_dir = Some(dir.to_string_lossy().into_owned());
+            }
+        }
+
+        let schedule = Schedule {
+            id: Uuid::new_v4().simple().to_string()[..8].to_string(),
+            cron: expr.as_str().to_string(),
+            prompt,
+            target,
+            created_at: now,
+            next_run,
+            history: Vec::new(),
+            disabled: false,
+        };
+        let book = ScheduleBook::lock(&self.store)?;
+        let mut schedules = book.load()?;
+        schedules.push(schedule.clone());
+        book.save(schedules)?;
+        Ok(schedule)
+    }
+
+    /// Returns every schedule, in creation order.
+    pub fn list_schedules(&self) -> Result<Vec<Schedule>> {
+        ScheduleBook::lock(&self.store)?.load()
+    }
+
+    /// Deletes a schedule by id or unique id prefix and returns it.
+    pub fn remove_schedule(&self, id: &str) -> Result<Schedule> {
+        let book = ScheduleBook::lock(&self.store)?;
+        let mut schedules = book.load()?;
+        let id = find_schedule(&mut schedules, id)?.id.clone();
+        let index = schedules
+            .iter()
+            .position(|schedule| schedule.id == id)
+            .expect("schedule was just found");
+        let removed = schedules.remove(index);
+        book.save(schedules)?;
+        Ok(removed)
+    }
+
+    /// Fires every schedule due at `now` and records each run in its history.
+    ///
+    /// Due schedules are advanced to their next match before firing, so concurrent schedulers
+    /// never run the same occurrence twice and runs missed while no scheduler was active are
+    /// collapsed into one.
+    pub fn run_due_schedules(&self, now: DateTime<Utc>) -> Result<Vec<(Schedule, ScheduleRun)>> {
+        let mut disabled = Vec::new();
+        let due = {
+            let book = ScheduleBook::lock(&self.store)?;
+            let mut schedules = book.load()?;
+            let mut due = Vec::new();
+            for schedule in schedules
+                .iter_mut()
+                .filter(|schedule| !schedule.disabled && schedule.next_run <= now)
+            {
+                let next_run = schedule.cron_expr().and_then(|cron| {
+                    cron.next_after(now)
+                        .ok_or_else(|| anyhow!("schedule {} never matches again", schedule.id))
+                });
+                match next_run {
+                    Ok(next_run) => {
+                        due.push(schedule.clone());
+                        schedule.next_run = next_run;
+                    }
+                    // Left enabled, the schedule would stay due and fail on every pass.
+                    Err(err) => {
+                        let run = ScheduleRun {
+                            at: now,
+                            outcome: ScheduleRunOutcome::Failed,
+                            task_id: None,
+                            detail: Some(format!("{err:#}; schedule disabled")),
+                        };
+                        schedule.record_run(run.clone());
+                        schedule.disabled = true;
+                        disabled.push((schedule.clone(), run));
+                    }
+                }
+            }
+            if !due.is_empty() || !disabled.is_empty() {
+                book.save(schedules)?;
+            }
+            due
+        };
+        if due.is_empty() {
+            return Ok(disabled);
+        }
+
+        let mut runs: Vec<(Schedule, ScheduleRun)> = due
+            .into_iter()
+            .map(|schedule| {
+                let run = self.run_schedule(&schedule, now);
+                (schedule, run)
+            })
+            .collect();
+
+        let book = ScheduleBook::lock(&self.store)?;
+        let mut schedules = book.load()?;
+        for (schedule, run) in &runs {
+            // The schedule may have been removed while it was running.
+            if let Some(stored) = schedules.iter_mut().find(|stored| stored.id == schedule.id) {
+                stored.record_run(run.clone());
+            }
+        }
+        book.save(schedules)?;
+        runs.extend(disabled);
+        Ok(runs)
+    }
+
+    fn run_schedule(&self, schedule: &Schedule, at: DateTime<Utc>) -> ScheduleRun {
+        let run = |outcome, task_id: Option<String>, detail: Option<String>| ScheduleRun {
+            at,
+            outcome,
+            task_id,
+            detail,
+        };
+        let launched = |outcome: LaunchOutcome| match outcome {
+            LaunchOutcome::Started => ScheduleRunOutcome::Started,
+            LaunchOutcome::Queued => ScheduleRunOutcome::Queued,
+        };
+
+        match &schedule.target {
+            ScheduleTarget::Task { task_id } => {
+                let state = match self.get_status(task_id) {
+                    Ok(status) => status.metadata.state,
+                    Err(err) => {
+                        return run(
+                            ScheduleRunOutcome::Failed,
+                            Some(task_id.clone()),
+                            Some(format!("{err:#}")),
+                        );
+                    }
+                };
+                if matches!(state, TaskState::Running | TaskState::Queued) {
+                    return run(
+                        ScheduleRunOutcome::Skipped,
+                        Some(task_id.clone()),
+                        Some(format!("task is {state}")),
+                    );
+                }
+                match self.send_prompt(SendPromptParams {
+                    task_id: task_id.clone(),
+                    prompt: schedule.prompt.clone(),
+                    priority: 0,
+                }) {
+                    Ok(outcome) => run(launched(outcome), Some(task_id.clone()), None),
+                    Err(err) => run(
+                        ScheduleRunOutcome::Failed,
+                        Some(task_id.clone()),
+                        Some(format!("{err:#}")),
+                    ),
+                }
+            }
+            ScheduleTarget::New {
+                title,
+                working_dir,
+                tags,
+            } => match self.start_task(StartTaskParams {
+                title: title.clone(),
+                prompt: schedule.prompt.clone(),
+                config_file: None,
+                working_dir: working_dir.as_ref().map(PathBuf::from),
+                repo_url: None,
+                repo_ref: None,
+                parent_id: None,
+                retry_policy: None,
+                tags: tags.clone(),
+                priority: 0,
+                hooks: None,
+                preset: None,
+                exec: None,
+            }) {
+                Ok(result) => run(launched(result.outcome), Some(result.thread_id), None),
+                Err(err) => run(ScheduleRunOutcome::Failed, None, Some(format!("{err:#}"))),
+            },
+        }
+    }
+
     /// Stops a specific task if it is running.
     pub fn stop_task(&self, task_id: &str) -> Result<StopOutcome> {
         self.store.ensure_layout()?;
-        let paths = self.store.task(task_id.to_string());
-        stop_task_paths(&paths)
+        let paths = self.store.task(self.resolve_task_id(task_id)?);
+        stop_task_paths(&paths, &self.config)
     }
 
-    /// Stops every running task and returns their outcomes.
+    /// Stops every running task, cancels queued ones, and returns their outcomes. Tasks whose
+    /// worker is still starting are left alone.
     pub fn stop_all_running(&self) -> Result<Vec<StopTaskReport>> {
         self.store.ensure_layout()?;
+        // Queued tasks are cancelled first so stopping the running ones does not admit them.
         let mut running = Vec::new();
+        let mut queued = Vec::new();
         for task in collect_active_tasks(&self.store)? {
+            if task.metadata.state == TaskState::Queued {
+                queued.push(task.metadata.id.clone());
+                continue;
+            }
             let paths = self.store.task(task.metadata.id.clone());
             let pid = paths.read_pid()?;
             if let Some(pid) = pid {
@@ -250,63 +1247,121 @@ impl TaskService {
             }
         }
 
-        let mut reports = Vec::with_capacity(running.len());
-        for task_id in running {
+        let mut reports = Vec::with_capacity(queued.len() + running.len());
+        for task_id in queued.into_iter().chain(running) {
             let paths = self.store.task(task_id.clone());
-            let outcome = stop_task_paths(&paths)?;
+            let outcome = stop_task_paths(&paths, &self.config)?;
             reports.push(StopTaskReport { task_id, outcome });
         }
 
         Ok(reports)
     }
 
+    /// Stops a task and every active descendant, parents first.
+    pub fn stop_task_recursive(&self, task_id: &str) -> Result<Vec<StopTaskReport>> {
+        self.store.ensure_layout()?;
+        let task_id = self.get_status(task_id)?.metadata.id;
+        let mut task_ids = self.descendants(&task_id)?;
+        task_ids.insert(0, task_id);
+
+        let mut reports = Vec::with_capacity(task_ids.len());
+        for task_id in task_ids {
+            let outcome = stop_task_paths(&self.store.task(task_id.clone()), &self.config)?;
+            reports.push(StopTaskReport { task_id, outcome });
+        }
+        Ok(reports)
+    }
+
+    /// Returns every active task descending from `task_id`, breadth-first.
+    pub fn descendants(&self, task_id: &str) -> Result<Vec<String>> {
+        let tasks = collect_active_tasks(&self.store)?;
+        let mut visited = HashSet::from([task_id.to_string()]);
+        let mut queue = VecDeque::from([task_id.to_string()]);
+        let mut descendants = Vec::new();
+        while let Some(parent) = queue.pop_front() {
+            for task in &tasks {
+                let id = &task.metadata.id;
+                if task.metadata.parent_id.as_deref() == Some(parent.as_str())
+                    && visited.insert(id.clone())
+                {
+                    descendants.push(id.clone());
+                    queue.push_back(id.clone());
+                }
+            }
+        }
+        Ok(descendants)
+    }
+
     /// Archives a specific task if it is stopped or died.
     pub fn archive_task(&self, task_id: &str) -> Result<ArchiveTaskOutcome> {
         self.store.ensure_layout()?;
-        archive_task_inner(&self.store, task_id)
+        archive_task_inner(&self.store, &self.config, &self.resolve_task_id(task_id)?)
     }
 
     /// Archives all eligible tasks, returning a summary of actions taken.
     pub fn archive_all(&self) -> Result<ArchiveAllSummary> {
         self.store.ensure_layout()?;
         let tasks = collect_active_tasks(&self.store)?;
+        let states = tasks
+            .into_iter()
+            .map(|task| (task.metadata.id, task.metadata.state))
+            .collect();
+        Ok(archive_each(&self.store, &self.config, states))
+    }
 
-        let mut candidates = Vec::new();
-        let mut skipped = Vec::new();
-
-        for task in tasks {
-            match task.metadata.state {
-                TaskState::Stopped | TaskState::Died => candidates.push(task.metadata.id.clone()),
-                TaskState::Running => skipped.push((task.metadata.id.clone(), task.metadata.state)),
-                TaskState::Archived => {}
-            }
+    /// Archives a task together with its descendants (children before parents), skipping any
+    /// that are still running.
+    pub fn archive_task_recursive(&self, task_id: &str) -> Result<ArchiveAllSummary> {
+        self.store.ensure_layout()?;
+        let task_id = self.get_status(task_id)?.metadata.id;
+        let mut task_ids = self.descendants(&task_id)?;
+        task_ids.reverse();
+        task_ids.push(task_id);
+
+        let mut states = Vec::with_capacity(task_ids.len());
+        for id in task_ids {
+            let state = self.get_status(&id)?.metadata.state;
+            states.push((id, state));
         }
+        Ok(archive_each(&self.store, &self.config, states))
+    }
 
-        let mut summary = ArchiveAllSummary {
-            skipped,
-            archived: Vec::new(),
-            already: Vec::new(),
-            failures: Vec::new(),
-        };
+    /// Upgrades every active and archived task to the current on-disk schema.
+    pub fn migrate_store(&self) -> Result<StoreMigrationReport> {
+        self.store.ensure_layout()?;
+        self.store.migrate_all()
+    }
 
-        for task_id in candidates {
-            match archive_task_inner(&self.store, &task_id) {
-                Ok(ArchiveTaskOutcome::Archived { id, destination }) => {
-                    summary.archived.push((id, destination));
-                }
-                Ok(ArchiveTaskOutcome::AlreadyArchived { id }) => {
-                    summary.already.push(id);
-                }
-                Err(err) => {
-                    summary.failures.push((task_id, err));
-                }
-            }
+    /// Scans the store for inconsistencies, repairing them when `fix` is set.
+    pub fn diagnose_store(&self, fix: bool) -> Result<DoctorReport> {
+        if fix {
+            self.store.ensure_layout()?;
         }
-
-        Ok(summary)
+        doctor::diagnose_store(&self.store, fix)
     }
 }
 
+/// Parameters for [`TaskService::add_webhook`].
+#[derive(Clone, Debug)]
+pub struct AddWebhookParams {
+    pub url: String,
+    /// Events to deliver; every event when empty.
+    pub events: Vec<WebhookEvent>,
+    /// Key used to sign payloads with HMAC-SHA256.
+    pub secret: Option<String>,
+    pub retries: Option<u32>,
+    pub retry_backoff_secs: Option<u64>,
+}
+
+/// Parameters for [`TaskService::add_schedule`].
+#[derive(Clone, Debug)]
+pub struct AddScheduleParams {
+    /// Five-field cron expression evaluated in local time.
+    pub cron: String,
+    pub prompt: String,
+    pub target: ScheduleTarget,
+}
+
 /// Parameters required to start a task worker.
 #[derive(Clone, Debug)]
 pub struct StartTaskParams {
@@ -316,12 +1371,59 @@ pub struct StartTaskParams {
     pub working_dir: Option<PathBuf>,
     pub repo_url: Option<String>,
     pub repo_ref: Option<String>,
+    /// Parent task reference; when absent, the task running this process (if any) is used.
+    pub parent_id: Option<String>,
+    /// Automatic retries applied by the worker to transient failures.
+    pub retry_policy: Option<RetryPolicy>,
+    /// Labels recorded on the new task.
+    pub tags: Vec<String>,
+    /// Admission priority used when the task has to wait for the concurrency limit.
+    pub priority: i32,
+    /// Lifecycle hooks run for this task in addition to the store-wide ones.
+    pub hooks: Option<Hooks>,
+    /// Configuration preset supplying defaults for the fields left unset here.
+    pub preset: Option<String>,
+    /// `codex exec` settings; values set here take precedence over the preset's.
+    pub exec: Option<ExecOptions>,
+}
+
+/// Whether an invocation started right away or is waiting for the concurrency limit.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LaunchOutcome {
+    Started,
+    Queued,
 }
 
 /// Result of starting a task worker.
 #[derive(Clone, Debug)]
 pub struct StartTaskResult {
+    /// Codex thread id of the new task, or its placeholder id while it is queued.
+    pub thread_id: String,
+    pub outcome: LaunchOutcome,
+}
+
+/// Parameters required to fork an existing task.
+#[derive(Clone, Debug)]
+pub struct ForkTaskParams {
+    pub task_id: String,
+    pub prompt: Option<String>,
+    pub title: Option<String>,
+}
+
+/// How a fork was seeded with the source task's history.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ForkMode {
+    /// The Codex session file was copied under the new thread id.
+    Session,
+    /// The new task was started with a preamble rebuilt from `task.log`.
+    Transcript,
+}
+
+/// Result returned after forking a task.
+#[derive(Clone, Debug)]
+pub struct ForkTaskResult {
     pub thread_id: String,
+    pub mode: ForkMode,
 }
 
 /// Parameters required to send a prompt to an existing task.
@@ -329,6 +1431,16 @@ pub struct StartTaskResult {
 pub struct SendPromptParams {
     pub task_id: String,
     pub prompt: String,
+    /// Admission priority used when the prompt has to wait for the concurrency limit.
+    pub priority: i32,
+}
+
+/// Parameters required to retry a DIED task.
+#[derive(Clone, Debug)]
+pub struct RetryTaskParams {
+    pub task_id: String,
+    /// Prompt to submit instead of the task's last prompt.
+    pub prompt: Option<String>,
 }
 
 /// Snapshot of task metadata and derived runtime state.
@@ -338,6 +1450,18 @@ pub struct TaskStatusSnapshot {
     pub pid: Option<i32>,
 }
 
+/// Workspace changes made by a task.
+#[derive(Clone, Debug)]
+pub enum TaskDiff {
+    /// Unified diff against the git snapshot taken when the invocation started.
+    Patch {
+        base: WorkspaceSnapshot,
+        patch: String,
+    },
+    /// `(marker, path)` pairs reported by Codex, used when no git snapshot is available.
+    Files(Vec<(char, String)>),
+}
+
 /// A task entry returned by list operations.
 #[derive(Clone, Debug)]
 pub struct TaskListEntry {
@@ -349,6 +1473,10 @@ pub struct TaskListEntry {
 pub struct ListTasksOptions {
     pub include_archived: bool,
     pub states: Vec<TaskState>,
+    /// Only tasks carrying every one of these tags.
+    pub tags: Vec<String>,
+    /// Only tasks whose title contains this text, ignoring case.
+    pub title: Option<String>,
 }
 
 /// Outcome of attempting to stop a worker.
@@ -356,6 +1484,17 @@ pub struct ListTasksOptions {
 pub enum StopOutcome {
     AlreadyStopped,
     Stopped,
+    /// The task was QUEUED; its pending invocation was cancelled.
+    Dequeued,
+}
+
+/// Snapshot of the admission queue returned by [`TaskService::queue_report`].
+#[derive(Clone, Debug)]
+pub struct QueueReport {
+    pub max_running: Option<usize>,
+    pub running: usize,
+    /// QUEUED tasks, next to start first.
+    pub queued: Vec<TaskMetadata>,
 }
 
 /// Report produced when stopping multiple tasks.
@@ -397,11 +1536,52 @@ pub struct LogDescriptor {
     pub metadata: FollowMetadata,
 }
 
-fn archive_task_inner(store: &TaskStore, task_id: &str) -> Result<ArchiveTaskOutcome> {
-    if let Some((_, metadata)) = store.find_archived_task(task_id)? {
-        return Ok(ArchiveTaskOutcome::AlreadyArchived { id: metadata.id });
+/// Archives every STOPPED or DIED task in `states`, recording RUNNING ones as skipped.
+fn archive_each(
+    store: &TaskStore,
+    config: &TasksConfig,
+    states: Vec<(String, TaskState)>,
+) -> ArchiveAllSummary {
+    let mut candidates = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (task_id, state) in states {
+        match state {
+            TaskState::Stopped | TaskState::Died => candidates.push(task_id),
+            TaskState::Running | TaskState::Queued => skipped.push((task_id, state)),
+            TaskState::Archived => {}
+        }
+    }
+
+    let mut summary = ArchiveAllSummary {
+        skipped,
+        archived: Vec::new(),
+        already: Vec::new(),
+        failures: Vec::new(),
+    };
+
+    for task_id in candidates {
+        match archive_task_inner(store, config, &task_id) {
+            Ok(ArchiveTaskOutcome::Archived { id, destination }) => {
+                summary.archived.push((id, destination));
+            }
+            Ok(ArchiveTaskOutcome::AlreadyArchived { id }) => {
+                summary.already.push(id);
+            }
+            Err(err) => {
+                summary.failures.push((task_id, err));
+            }
+        }
     }
 
+    summary
+}
+
+fn archive_task_inner(
+    store: &TaskStore,
+    config: &TasksConfig,
+    task_id: &str,
+) -> Result<ArchiveTaskOutcome> {
     let paths = store.task(task_id.to_string());
     let mut metadata = match paths.read_metadata() {
         Ok(metadata) => metadata,
@@ -410,6 +1590,9 @@ fn archive_task_inner(store: &TaskStore, task_id: &str) -> Result<ArchiveTaskOut
                 .downcast_ref::<std::io::Error>()
                 .is_some_and(|io_err| io_err.kind() == std::io::ErrorKind::NotFound);
             if not_found {
+                if let Some((_, metadata)) = store.find_archived_task(task_id)? {
+                    return Ok(ArchiveTaskOutcome::AlreadyArchived { id: metadata.id });
+                }
                 bail!("task {task_id} was not found");
             }
             return Err(err);
@@ -417,14 +1600,17 @@ fn archive_task_inner(store: &TaskStore, task_id: &str) -> Result<ArchiveTaskOut
     };
 
     let pid = paths.read_pid()?;
-    let derived_state = derive_active_state(&metadata.state, pid);
+    let derived_state = derive_active_state(&metadata, pid);
     if metadata.state != derived_state {
         metadata.set_state(derived_state.clone());
         paths.write_metadata(&metadata)?;
     }
 
-    if derived_state == TaskState::Running {
-        bail!("task {} is RUNNING; stop it before archiving", metadata.id);
+    if matches!(derived_state, TaskState::Running | TaskState::Queued) {
+        bail!(
+            "task {} is {derived_state}; stop it before archiving",
+            metadata.id
+        );
     }
 
     if let Some(pid) = pid {
@@ -458,6 +1644,15 @@ fn archive_task_inner(store: &TaskStore, task_id: &str) -> Result<ArchiveTaskOut
             destination.display()
         )
     })?;
+    store.archive_index().record(&destination, &metadata)?;
+
+    let archived = TaskPaths::from_directory(destination.clone(), metadata.id.clone());
+    let runs = run_hooks(store, &archived, HookEvent::OnArchived, config);
+    if let Err(err) = record_hook_runs(&archived.log_path(), &runs) {
+        eprintln!("failed to log hooks of task {}: {err:#}", metadata.id);
+    }
+    // The user is waiting on `archive`, so a failing endpoint gets no retries.
+    notify_webhooks(store, &archived, HookEvent::OnArchived, None, config, false);
 
     Ok(ArchiveTaskOutcome::Archived {
         id: metadata.id,
@@ -515,53 +1710,11 @@ fn resolve_follow_metadata(store: &TaskStore, task_id: &str) -> Result<FollowMet
 }
 
 fn find_archived_log_path(store: &TaskStore, task_id: &str) -> Result<Option<PathBuf>> {
-    let archive_root = store.archive_root();
-    if !archive_root.exists() {
-        return Ok(None);
-    }
-
-    let mut stack = vec![archive_root];
-    while let Some(dir) = stack.pop() {
-        let entries = match fs::read_dir(&dir) {
-            Ok(entries) => entries,
-            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
-            Err(err) => {
-                return Err(err).with_context(|| {
-                    format!("failed to read archive directory {}", dir.display())
-                });
-            }
-        };
-
-        for entry in entries {
-            let entry = entry.with_context(|| {
-                format!(
-                    "failed to read entry in archive directory {}",
-                    dir.display()
-                )
-            })?;
-            let path = entry.path();
-            let file_type = entry
-                .file_type()
-                .with_context(|| format!("failed to inspect archive entry {}", path.display()))?;
-
-            if file_type.is_dir() {
-                if path
-                    .file_name()
-                    .and_then(|name| name.to_str())
-                    .map(|name| name == task_id)
-                    .unwrap_or(false)
-                {
-                    let candidate = path.join(LOG_FILE_NAME);
-                    if candidate.exists() {
-                        return Ok(Some(candidate));
-                    }
-                }
-                stack.push(path);
-            }
-        }
-    }
-
-    Ok(None)
+    Ok(store
+        .archive_index()
+        .locate(task_id)?
+        .map(|directory| directory.join(LOG_FILE_NAME))
+        .filter(|candidate| candidate.exists()))
 }
 
 fn resolve_config_file(path: Option<PathBuf>) -> Result<Option<PathBuf>> {
@@ -709,6 +1862,28 @@ fn make_absolute(path: PathBuf) -> Result<PathBuf> {
     }
 }
 
+/// A queued task claimed under the queue lock, waiting to be launched.
+struct Admission {
+    metadata: TaskMetadata,
+    queued: QueuedPrompt,
+}
+
+/// Marks a task RUNNING with a reservation naming this process until its worker starts, so
+/// admission decisions made meanwhile count it against the concurrency limit. No pid is
+/// recorded: `stop` only ever signals the task's own worker.
+fn reserve_slot(paths: &TaskPaths) -> Result<()> {
+    paths.remove_pid()?;
+    paths.update_metadata(|metadata| {
+        metadata.queued = None;
+        metadata.set_state(TaskState::Running);
+        metadata.reserved = Some(Reservation {
+            launcher_pid: std::process::id() as i32,
+            reserved_at: Utc::now(),
+        });
+    })?;
+    Ok(())
+}
+
 fn receive_thread_id(child: &mut Child) -> Result<String> {
     let stdout = child
         .stdout
@@ -755,9 +1930,32 @@ fn receive_thread_id(child: &mut Child) -> Result<String> {
     }
 }
 
-fn stop_task_paths(paths: &TaskPaths) -> Result<StopOutcome> {
+fn stop_task_paths(paths: &TaskPaths, config: &TasksConfig) -> Result<StopOutcome> {
+    let metadata = paths.read_metadata().ok();
+    let queued = metadata
+        .as_ref()
+        .filter(|metadata| metadata.state == TaskState::Queued)
+        .and_then(|metadata| metadata.queued.clone());
+    if let Some(queued) = queued {
+        if queued.new_task {
+            fs::remove_dir_all(paths.directory())
+                .with_context(|| format!("failed to remove queued task {}", paths.id()))?;
+        } else {
+            paths.update_metadata(|metadata| {
+                metadata.queued = None;
+                metadata.set_state(TaskState::Stopped);
+            })?;
+        }
+        return Ok(StopOutcome::Dequeued);
+    }
+
     let pid = match paths.read_pid()? {
         Some(pid) => pid,
+        // A reserved slot has no worker to signal yet.
+        None if metadata.as_ref().is_some_and(is_reservation_live) => bail!(
+            "task {} is still starting; stop it again once it is running",
+            paths.id()
+        ),
         None => return Ok(StopOutcome::AlreadyStopped),
     };
 
@@ -766,16 +1964,17 @@ fn stop_task_paths(paths: &TaskPaths) -> Result<StopOutcome> {
         return Ok(StopOutcome::AlreadyStopped);
     }
 
+    paths.request_stop()?;
     send_signal(pid, libc::SIGTERM)?;
-    wait_for_worker_shutdown(pid)?;
+    wait_for_worker_shutdown(pid, config.stop_timeout)?;
     let _ = paths.remove_pid();
     mark_task_state(paths, TaskState::Stopped)?;
 
     Ok(StopOutcome::Stopped)
 }
 
-fn wait_for_worker_shutdown(pid: i32) -> Result<()> {
-    let deadline = Instant::now() + Duration::from_secs(SHUTDOWN_TIMEOUT_SECS);
+fn wait_for_worker_shutdown(pid: i32, timeout: Duration) -> Result<()> {
+    let deadline = Instant::now() + timeout;
     loop {
         let mut status: libc::c_int = 0;
         let wait_result =
@@ -845,7 +2044,7 @@ fn mark_task_state(paths: &TaskPaths, state: TaskState) -> Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use super::{StopOutcome, TaskService, TaskStore};
+    use super::{StopOutcome, TaskService, TaskStore, TasksConfig};
     use anyhow::Result;
     use tempfile::tempdir;
 
@@ -854,7 +2053,7 @@ mod tests {
         let tmp = tempdir()?;
         let store = TaskStore::new(tmp.path().join("store"));
         store.ensure_layout()?;
-        let service = TaskService::new(store.clone(), false);
+        let service = TaskService::new(store.clone(), TasksConfig::default(), false);
         let paths = store.task("task-1".to_string());
         paths.ensure_directory()?;
 
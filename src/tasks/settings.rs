@@ -0,0 +1,154 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+
+use crate::tasks::queue::lock_exclusive;
+use crate::tasks::webhooks::find_webhook;
+use crate::tasks::{Hooks, TaskStore, Webhook};
+
+/// File in the store root holding store-wide settings such as the concurrency limit.
+pub const SETTINGS_FILE_NAME: &str = "settings.json";
+/// Lock file serialising updates to [`SETTINGS_FILE_NAME`].
+pub const SETTINGS_LOCK_FILE_NAME: &str = "settings.lock";
+
+/// Store-wide settings shared by every process using the store.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StoreSettings {
+    /// Maximum number of tasks running at once; further `start`/`send` requests are queued.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_running: Option<usize>,
+    /// Lifecycle hooks run for every task in the store.
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    /// URLs notified of task events.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<Webhook>,
+}
+
+impl StoreSettings {
+    fn path(store: &TaskStore) -> PathBuf {
+        store.root().join(SETTINGS_FILE_NAME)
+    }
+
+    /// Reads the store settings, returning defaults when none were saved. Workers and hooks read
+    /// without the lock, which is safe because [`Self::save`] replaces the file atomically.
+    pub fn load(store: &TaskStore) -> Result<Self> {
+        let path = Self::path(store);
+        match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .with_context(|| format!("failed to parse store settings at {}", path.display())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err)
+                .with_context(|| format!("failed to read store settings at {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, store: &TaskStore) -> Result<()> {
+        store.ensure_layout()?;
+        let path = Self::path(store);
+        let payload = serde_json::to_vec_pretty(self)?;
+        let mut tmp = NamedTempFile::new_in(store.root())
+            .context("failed to create temporary store settings file")?;
+        tmp.write_all(&payload)
+            .context("failed to write temporary store settings file")?;
+        tmp.persist(&path)
+            .map_err(|err| err.error)
+            .with_context(|| format!("failed to write store settings at {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Applies `change` to the saved settings while holding the settings lock, so concurrent
+    /// updates never overwrite each other.
+    pub fn update<T>(store: &TaskStore, change: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        store.ensure_layout()?;
+        let _lock = lock_exclusive(&store.root().join(SETTINGS_LOCK_FILE_NAME))?;
+        let mut settings = Self::load(store)?;
+        let value = change(&mut settings)?;
+        settings.save(store)?;
+        Ok(value)
+    }
+
+    /// Number of additional tasks that may start while `running` tasks are active.
+    pub fn free_slots(&self, running: usize) -> usize {
+        self.max_running
+            .map_or(usize::MAX, |max| max.saturating_sub(running))
+    }
+}
+
+/// Changes the store-wide lifecycle hooks and returns the updated configuration.
+pub fn update_hooks(store: &TaskStore, update: impl FnOnce(&mut Hooks)) -> Result<Hooks> {
+    StoreSettings::update(store, |settings| {
+        update(&mut settings.hooks);
+        Ok(settings.hooks.clone())
+    })
+}
+
+/// Registers a webhook in the store settings.
+pub fn add_webhook(store: &TaskStore, webhook: Webhook) -> Result<()> {
+    StoreSettings::update(store, |settings| {
+        settings.webhooks.push(webhook);
+        Ok(())
+    })
+}
+
+/// Removes a webhook by id, unique id prefix, or URL, returning it.
+pub fn remove_webhook(store: &TaskStore, reference: &str) -> Result<Webhook> {
+    StoreSettings::update(store, |settings| {
+        let index = find_webhook(&settings.webhooks, reference)?;
+        Ok(settings.webhooks.remove(index))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::HookEvent;
+    use tempfile::tempdir;
+
+    #[test]
+    fn settings_round_trip_and_limit_slots() -> Result<()> {
+        let tmp = tempdir()?;
+        let store = TaskStore::new(tmp.path().join("tasks"));
+        assert_eq!(StoreSettings::load(&store)?, StoreSettings::default());
+        assert_eq!(StoreSettings::default().free_slots(7), usize::MAX);
+
+        let settings = StoreSettings {
+            max_running: Some(2),
+            ..StoreSettings::default()
+        };
+        settings.save(&store)?;
+        let loaded = StoreSettings::load(&store)?;
+        assert_eq!(loaded, settings);
+        assert_eq!(loaded.free_slots(1), 1);
+        assert_eq!(loaded.free_slots(3), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn updates_keep_the_other_settings() -> Result<()> {
+        let tmp = tempdir()?;
+        let store = TaskStore::new(tmp.path().join("tasks"));
+        StoreSettings::update(&store, |settings| {
+            settings.max_running = Some(3);
+            Ok(())
+        })?;
+        let hooks = update_hooks(&store, |hooks| {
+            hooks.set(HookEvent::OnStopped, Some("true".to_string()))
+        })?;
+
+        let loaded = StoreSettings::load(&store)?;
+        assert_eq!(loaded.max_running, Some(3));
+        assert_eq!(loaded.hooks, hooks);
+        let leftovers: Vec<_> = fs::read_dir(store.root())?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty(), "temporary files left: {leftovers:?}");
+        Ok(())
+    }
+}
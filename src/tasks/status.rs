@@ -1,7 +1,9 @@
-use crate::tasks::TaskState;
+use crate::tasks::{TaskMetadata, TaskState};
 
 /// Derives the effective task state by combining stored metadata with the worker PID (if any).
-pub fn derive_active_state(metadata_state: &TaskState, pid: Option<i32>) -> TaskState {
+/// A task whose slot is reserved stays RUNNING without a pid while its launcher is alive.
+pub fn derive_active_state(metadata: &TaskMetadata, pid: Option<i32>) -> TaskState {
+    let metadata_state = &metadata.state;
     if let Some(pid) = pid {
         if is_process_running(pid) {
             return match metadata_state {
@@ -9,12 +11,24 @@ pub fn derive_active_state(metadata_state: &TaskState, pid: Option<i32>) -> Task
                 TaskState::Stopped => TaskState::Stopped,
                 TaskState::Archived => TaskState::Archived,
                 TaskState::Died => TaskState::Running,
+                TaskState::Queued => TaskState::Queued,
             };
         }
     }
+    if *metadata_state == TaskState::Running && is_reservation_live(metadata) {
+        return TaskState::Running;
+    }
     derive_state_without_pid(metadata_state.clone())
 }
 
+/// Whether the task holds a slot reserved by a process that is still launching its worker.
+pub fn is_reservation_live(metadata: &TaskMetadata) -> bool {
+    metadata
+        .reserved
+        .as_ref()
+        .is_some_and(|reservation| is_process_running(reservation.launcher_pid))
+}
+
 fn derive_state_without_pid(metadata_state: TaskState) -> TaskState {
     match metadata_state {
         TaskState::Running => TaskState::Died,
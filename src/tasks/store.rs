@@ -1,16 +1,56 @@
-use std::collections::VecDeque;
 use std::fs;
 use std::io::{ErrorKind, Write};
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result, ensure};
-use chrono::{DateTime, Datelike, Utc};
+use anyhow::{Context, Result, bail, ensure};
+use chrono::{DateTime, Datelike, SecondsFormat, Utc};
 use dirs::home_dir;
+use serde_json::{Map as JsonMap, Value as JsonValue};
 use tempfile::NamedTempFile;
 
-use crate::tasks::{TaskId, TaskMetadata};
+use crate::tasks::{ArchiveIndex, CURRENT_SCHEMA_VERSION, TaskId, TaskMetadata};
 
 const ARCHIVE_DIR_NAME: &str = "archive";
+const QUARANTINE_DIR_NAME: &str = "quarantine";
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// State used by `codex proto` workers before the `codex exec` migration removed it.
+const LEGACY_IDLE_STATE: &str = "IDLE";
+
+/// Flat `<task_id>.<ext>` artifacts written by pre-exec workers and their current filenames.
+const LEGACY_FLAT_ARTIFACTS: &[(&str, &str)] = &[
+    ("json", METADATA_FILE_NAME),
+    ("log", LOG_FILE_NAME),
+    ("result", RESULT_FILE_NAME),
+    ("pid", PID_FILE_NAME),
+];
+const LEGACY_FLAT_PIPE_EXTENSION: &str = "pipe";
+
+/// A single step that upgrades task metadata (and any related files) by one schema version.
+struct Migration {
+    from: u32,
+    description: &'static str,
+    /// Rewrites the metadata document.
+    upgrade: fn(&mut JsonMap<String, JsonValue>),
+    /// Brings the files next to the metadata up to date; only run when the upgrade is persisted.
+    cleanup: fn(&TaskPaths) -> Result<()>,
+}
+
+/// Ordered list of migrations; `MIGRATIONS[n].from == n` for every entry.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "map legacy IDLE state to STOPPED and drop the task.pipe FIFO",
+        upgrade: migrate_v0_to_v1,
+        cleanup: TaskPaths::remove_pipe,
+    },
+    Migration {
+        from: 1,
+        description: "allow the QUEUED state and the queued and reserved fields",
+        upgrade: migrate_v1_to_v2,
+        cleanup: keep_task_files,
+    },
+];
 
 /// Canonical filenames for task artifacts stored on disk.
 pub const METADATA_FILE_NAME: &str = "task.json";
@@ -18,6 +58,13 @@ pub const PID_FILE_NAME: &str = "task.pid";
 pub const PIPE_FILE_NAME: &str = "task.pipe";
 pub const LOG_FILE_NAME: &str = "task.log";
 pub const RESULT_FILE_NAME: &str = "task.result";
+/// Marker written by `stop` before it signals `codex exec`, so the worker can tell a requested
+/// stop from the process being killed by something else.
+pub const STOP_REQUEST_FILE_NAME: &str = "task.stop";
+/// Prefix for the worker's `--output-last-message` temp files; followed by the worker pid.
+pub const RESULT_TEMP_PREFIX: &str = ".task-result-";
+/// Field holding the time a task log event was written, added when the event has none.
+pub const LOG_TIMESTAMP_FIELD: &str = "timestamp";
 
 /// Rooted view into the filesystem layout backing Codex tasks.
 #[derive(Clone, Debug)]
@@ -47,6 +94,11 @@ impl TaskStore {
         self.root.join(ARCHIVE_DIR_NAME)
     }
 
+    /// Directory where `doctor --fix` moves unreadable task directories.
+    pub fn quarantine_root(&self) -> PathBuf {
+        self.root.join(QUARANTINE_DIR_NAME)
+    }
+
     /// Ensures the primary directories required by the store exist.
     pub fn ensure_layout(&self) -> Result<()> {
         fs::create_dir_all(self.root())
@@ -114,49 +166,233 @@ impl TaskStore {
         self.task(id).read_metadata()
     }
 
+    /// Index of archived task locations.
+    pub fn archive_index(&self) -> ArchiveIndex {
+        ArchiveIndex::new(self.archive_root())
+    }
+
     /// Attempts to locate an archived task by identifier, returning its paths and metadata.
     pub fn find_archived_task(&self, task_id: &str) -> Result<Option<(TaskPaths, TaskMetadata)>> {
-        let archive_root = self.archive_root();
-        if !archive_root.exists() {
+        let Some(directory) = self.archive_index().locate(task_id)? else {
             return Ok(None);
-        }
+        };
+        let paths = TaskPaths::from_directory(directory, task_id.to_string());
+        let metadata = paths.read_metadata()?;
+        Ok(Some((paths, metadata)))
+    }
+
+    /// Moves flat-layout tasks left in the store root by pre-exec workers into task directories.
+    pub fn relocate_legacy_layout(&self) -> Result<Vec<TaskId>> {
+        relocate_flat_layouts(&self.root)
+    }
 
-        let mut queue = VecDeque::from([archive_root]);
-        while let Some(dir) = queue.pop_front() {
-            if dir
-                .file_name()
-                .and_then(|value| value.to_str())
-                .is_some_and(|name| name == task_id)
-            {
-                let paths = TaskPaths::from_directory(dir.clone(), task_id.to_string());
-                let metadata = paths.read_metadata()?;
-                return Ok(Some((paths, metadata)));
+    /// Rewrites every task in the store (including archive buckets) to the current schema.
+    pub fn migrate_all(&self) -> Result<StoreMigrationReport> {
+        let mut report = StoreMigrationReport::default();
+        let mut containers = vec![self.root.clone()];
+        containers.extend(self.archive_day_buckets()?);
+
+        for container in containers {
+            for task_id in relocate_flat_layouts(&container)? {
+                report
+                    .relocated
+                    .push((task_id.clone(), container.join(&task_id)));
             }
 
-            let entries = match fs::read_dir(&dir) {
-                Ok(entries) => entries,
-                Err(err) if err.kind() == ErrorKind::NotFound => continue,
-                Err(err) => {
-                    return Err(err).with_context(|| {
-                        format!("failed to read archive directory {}", dir.display())
-                    });
+            let entries = fs::read_dir(&container).with_context(|| {
+                format!("failed to read task directory {}", container.display())
+            })?;
+            for entry in entries {
+                let entry = entry
+                    .with_context(|| format!("failed to read entry in {}", container.display()))?;
+                let path = entry.path();
+                if !entry.file_type()?.is_dir() || !path.join(METADATA_FILE_NAME).exists() {
+                    continue;
+                }
+                let Some(task_id) = path.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                let paths = TaskPaths::from_directory(path.clone(), task_id.to_string());
+                match paths.migrate() {
+                    Ok(Some(from)) => report.upgraded.push(MigratedTask {
+                        id: task_id.to_string(),
+                        from_version: from,
+                        directory: path,
+                    }),
+                    Ok(None) => report.current += 1,
+                    Err(err) => report.failures.push((path, err)),
                 }
-            };
+            }
+        }
 
-            for entry in entries {
-                let entry = entry.with_context(|| {
-                    format!("failed to inspect archive entry in {}", dir.display())
-                })?;
-                if entry.file_type()?.is_dir() {
-                    queue.push_back(entry.path());
+        self.archive_index().rebuild()?;
+        Ok(report)
+    }
+
+    /// Returns every `archive/YYYY/MM/DD` bucket currently present on disk.
+    fn archive_day_buckets(&self) -> Result<Vec<PathBuf>> {
+        let mut level = vec![self.archive_root()];
+        for _ in 0..3 {
+            let mut next = Vec::new();
+            for dir in level {
+                let entries = match fs::read_dir(&dir) {
+                    Ok(entries) => entries,
+                    Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                    Err(err) => {
+                        return Err(err).with_context(|| {
+                            format!("failed to read archive directory {}", dir.display())
+                        });
+                    }
+                };
+                for entry in entries {
+                    let entry = entry.with_context(|| {
+                        format!("failed to inspect archive entry in {}", dir.display())
+                    })?;
+                    if entry.file_type()?.is_dir() {
+                        next.push(entry.path());
+                    }
                 }
             }
+            level = next;
+        }
+        Ok(level)
+    }
+}
+
+/// Task upgraded by [`TaskStore::migrate_all`].
+#[derive(Debug)]
+pub struct MigratedTask {
+    pub id: TaskId,
+    pub from_version: u32,
+    pub directory: PathBuf,
+}
+
+/// Summary of a full-store migration pass.
+#[derive(Debug, Default)]
+pub struct StoreMigrationReport {
+    /// Legacy flat-layout tasks moved into per-task directories.
+    pub relocated: Vec<(TaskId, PathBuf)>,
+    pub upgraded: Vec<MigratedTask>,
+    /// Number of tasks that were already at the current schema version.
+    pub current: usize,
+    pub failures: Vec<(PathBuf, anyhow::Error)>,
+}
+
+/// Moves every flat `<task_id>.json` layout found directly inside `container` into its own
+/// task directory, returning the identifiers that were relocated.
+fn relocate_flat_layouts(container: &Path) -> Result<Vec<TaskId>> {
+    let entries = match fs::read_dir(container) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to read directory {}", container.display()));
+        }
+    };
+
+    let mut candidates = Vec::new();
+    for entry in entries {
+        let entry =
+            entry.with_context(|| format!("failed to read entry in {}", container.display()))?;
+        let path = entry.path();
+        if !entry.file_type()?.is_file()
+            || path.extension().and_then(|ext| ext.to_str()) != Some("json")
+        {
+            continue;
+        }
+        if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+            candidates.push(stem.to_string());
+        }
+    }
+
+    let mut relocated = Vec::new();
+    for task_id in candidates {
+        if relocate_flat_task(container, &task_id)? {
+            relocated.push(task_id);
+        }
+    }
+    Ok(relocated)
+}
+
+/// Moves a single flat-layout task into `<container>/<task_id>/`. Returns `false` when the
+/// `<task_id>.json` file is not legacy task metadata.
+fn relocate_flat_task(container: &Path, task_id: &str) -> Result<bool> {
+    let legacy_metadata = container.join(format!("{task_id}.json"));
+    let raw = match fs::read_to_string(&legacy_metadata) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(err) => {
+            return Err(err).with_context(|| {
+                format!(
+                    "failed to read legacy metadata {}",
+                    legacy_metadata.display()
+                )
+            });
+        }
+    };
+    let is_task = serde_json::from_str::<JsonValue>(&raw)
+        .ok()
+        .and_then(|value| {
+            value
+                .get("id")
+                .and_then(JsonValue::as_str)
+                .map(str::to_owned)
+        })
+        .is_some_and(|id| id == task_id);
+    if !is_task {
+        return Ok(false);
+    }
+
+    let directory = container.join(task_id);
+    ensure!(
+        !directory.join(METADATA_FILE_NAME).exists(),
+        "task {task_id} has both a legacy {} and a task directory at {}",
+        legacy_metadata.display(),
+        directory.display()
+    );
+    fs::create_dir_all(&directory)
+        .with_context(|| format!("failed to create task directory {}", directory.display()))?;
+
+    for (extension, file_name) in LEGACY_FLAT_ARTIFACTS {
+        let source = container.join(format!("{task_id}.{extension}"));
+        match fs::rename(&source, directory.join(file_name)) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!("failed to relocate legacy artifact {}", source.display())
+                });
+            }
         }
+    }
+
+    let pipe = container.join(format!("{task_id}.{LEGACY_FLAT_PIPE_EXTENSION}"));
+    match fs::remove_file(&pipe) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to remove legacy pipe {}", pipe.display()));
+        }
+    }
+
+    Ok(true)
+}
 
-        Ok(None)
+fn migrate_v0_to_v1(metadata: &mut JsonMap<String, JsonValue>) {
+    if metadata.get("state").and_then(JsonValue::as_str) == Some(LEGACY_IDLE_STATE) {
+        metadata.insert("state".to_string(), JsonValue::from("STOPPED"));
     }
 }
 
+/// Version 2 only adds the QUEUED state and optional fields, so v1 records are already valid; the
+/// bump makes older builds refuse v2 records instead of failing to parse them.
+fn migrate_v1_to_v2(_metadata: &mut JsonMap<String, JsonValue>) {}
+
+fn keep_task_files(_paths: &TaskPaths) -> Result<()> {
+    Ok(())
+}
+
 /// Helper for working with the files associated with a particular task.
 #[derive(Clone, Debug)]
 pub struct TaskPaths {
@@ -213,6 +449,11 @@ impl TaskPaths {
         self.file_path(METADATA_FILE_NAME)
     }
 
+    /// Location of the marker recording that `stop` ended the current invocation.
+    pub fn stop_request_path(&self) -> PathBuf {
+        self.file_path(STOP_REQUEST_FILE_NAME)
+    }
+
     fn ensure_parent(&self, path: &Path) -> Result<()> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)
@@ -260,12 +501,34 @@ impl TaskPaths {
         Ok(metadata)
     }
 
-    /// Loads structured metadata for the task from disk.
+    /// Loads structured metadata for the task from disk, upgrading older schema versions.
     pub fn read_metadata(&self) -> Result<TaskMetadata> {
+        self.load_and_upgrade(true).map(|(metadata, _)| metadata)
+    }
+
+    /// Loads metadata like [`Self::read_metadata`] without changing anything on disk: older
+    /// schemas are upgraded in memory only and flat legacy layouts are not relocated.
+    pub fn peek_metadata(&self) -> Result<TaskMetadata> {
+        self.load_and_upgrade(false).map(|(metadata, _)| metadata)
+    }
+
+    /// Upgrades the task to the current schema, returning the version it was upgraded from or
+    /// `None` when it was already current.
+    pub fn migrate(&self) -> Result<Option<u32>> {
+        self.load_and_upgrade(true).map(|(_, from)| from)
+    }
+
+    fn load_and_upgrade(&self, persist: bool) -> Result<(TaskMetadata, Option<u32>)> {
         let path = self.metadata_path();
+        if let Some(container) = self.base.parent().filter(|_| persist && !path.exists()) {
+            relocate_flat_task(container, &self.task_id)?;
+        }
         let data = fs::read_to_string(&path)
             .with_context(|| format!("failed to read metadata for task {}", self.task_id))?;
-        let metadata: TaskMetadata = serde_json::from_str(&data)
+        let mut value: JsonValue = serde_json::from_str(&data)
+            .with_context(|| format!("failed to parse metadata for task {}", self.task_id))?;
+        let from = self.upgrade_metadata_value(&mut value)?;
+        let metadata: TaskMetadata = serde_json::from_value(value)
             .with_context(|| format!("failed to parse metadata for task {}", self.task_id))?;
         ensure!(
             metadata.id == self.task_id,
@@ -273,14 +536,67 @@ impl TaskPaths {
             metadata.id,
             self.task_id
         );
-        Ok(metadata)
+        if let Some(from) = from.filter(|_| persist) {
+            for migration in MIGRATIONS.iter().skip(from as usize) {
+                (migration.cleanup)(self).with_context(|| {
+                    format!(
+                        "failed to migrate task {} from schema v{} ({})",
+                        self.task_id, migration.from, migration.description
+                    )
+                })?;
+            }
+            self.write_metadata(&metadata)?;
+        }
+        Ok((metadata, from))
     }
 
-    /// Writes the PID of the associated worker to disk.
+    /// Applies every pending migration to a raw metadata document.
+    fn upgrade_metadata_value(&self, value: &mut JsonValue) -> Result<Option<u32>> {
+        let object = value
+            .as_object_mut()
+            .with_context(|| format!("metadata for task {} is not a JSON object", self.task_id))?;
+        let original = match object.get(SCHEMA_VERSION_KEY) {
+            None => 0,
+            Some(raw) => raw
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .with_context(|| {
+                    format!("invalid schema_version {raw} for task {}", self.task_id)
+                })?,
+        };
+        if original > CURRENT_SCHEMA_VERSION {
+            bail!(
+                "task {} uses schema version {original}, but this build only understands up to \
+                 version {CURRENT_SCHEMA_VERSION}; upgrade codex-tasks",
+                self.task_id
+            );
+        }
+        if original == CURRENT_SCHEMA_VERSION {
+            return Ok(None);
+        }
+
+        let mut version = original;
+        for migration in MIGRATIONS.iter().skip(version as usize) {
+            debug_assert_eq!(migration.from, version);
+            (migration.upgrade)(object);
+            version = migration.from + 1;
+        }
+        object.insert(SCHEMA_VERSION_KEY.to_string(), JsonValue::from(version));
+        Ok(Some(original))
+    }
+
+    /// Writes the PID of the associated worker to disk, replacing the file atomically so readers
+    /// never see it half-written while a stand-in pid is handed over to the worker.
     pub fn write_pid(&self, pid: i32) -> Result<()> {
         let path = self.pid_path();
         self.ensure_parent(&path)?;
-        fs::write(&path, pid.to_string())
+        let parent = path.parent().context("pid path missing parent directory")?;
+        let mut temp = NamedTempFile::new_in(parent)
+            .with_context(|| format!("failed to create temp file for task {}", self.task_id))?;
+        temp.write_all(pid.to_string().as_bytes())
+            .with_context(|| format!("failed to write pid for task {}", self.task_id))?;
+        temp.persist(&path)
+            .map_err(|err| err.error)
             .with_context(|| format!("failed to write pid for task {}", self.task_id))?;
         Ok(())
     }
@@ -314,6 +630,24 @@ impl TaskPaths {
         }
     }
 
+    /// Records that the running invocation is being stopped on request.
+    pub fn request_stop(&self) -> Result<()> {
+        let path = self.stop_request_path();
+        fs::write(&path, Utc::now().to_rfc3339())
+            .with_context(|| format!("failed to record stop request for task {}", self.task_id))
+    }
+
+    /// Removes the stop marker, returning whether a stop had been requested.
+    pub fn take_stop_request(&self) -> Result<bool> {
+        let path = self.stop_request_path();
+        match fs::remove_file(&path) {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err)
+                .with_context(|| format!("failed to clear stop request for task {}", self.task_id)),
+        }
+    }
+
     /// Removes the pipe file, ignoring missing files.
     pub fn remove_pipe(&self) -> Result<()> {
         let path = self.pipe_path();
@@ -358,6 +692,38 @@ impl TaskPaths {
     }
 }
 
+/// Adds the current time as [`LOG_TIMESTAMP_FIELD`] to a JSON object log line. Lines that are
+/// not JSON objects or already carry the field are returned unchanged; the rest of the line is
+/// kept byte for byte.
+pub fn stamp_log_line(line: &str, at: DateTime<Utc>) -> String {
+    let trimmed = line.trim();
+    let stampable = matches!(
+        serde_json::from_str::<JsonValue>(trimmed),
+        Ok(JsonValue::Object(object)) if !object.contains_key(LOG_TIMESTAMP_FIELD)
+    );
+    if !stampable {
+        return line.to_string();
+    }
+    let stamp = format!(
+        "\"{LOG_TIMESTAMP_FIELD}\":\"{}\"",
+        at.to_rfc3339_opts(SecondsFormat::Millis, true)
+    );
+    let rest = trimmed[1..].trim_start();
+    if rest.starts_with('}') {
+        format!("{{{stamp}}}")
+    } else {
+        format!("{{{stamp},{rest}")
+    }
+}
+
+/// Time recorded in a task log event's [`LOG_TIMESTAMP_FIELD`], if any.
+pub fn log_event_time(event: &JsonValue) -> Option<DateTime<Utc>> {
+    let stamp = event.get(LOG_TIMESTAMP_FIELD)?.as_str()?;
+    DateTime::parse_from_rfc3339(stamp)
+        .ok()
+        .map(|at| at.with_timezone(&Utc))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -486,4 +852,214 @@ mod tests {
         assert_eq!(found.0.directory(), paths.directory());
         assert_eq!(found.1, metadata);
     }
+
+    fn write_raw_metadata(dir: &Path, payload: serde_json::Value) {
+        fs::create_dir_all(dir).expect("task dir");
+        fs::write(
+            dir.join(METADATA_FILE_NAME),
+            serde_json::to_string_pretty(&payload).expect("serialize"),
+        )
+        .expect("write metadata");
+    }
+
+    fn legacy_payload(task_id: &str, state: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": task_id,
+            "state": state,
+            "created_at": "2025-09-01T10:00:00Z",
+            "updated_at": "2025-09-01T10:05:00Z",
+        })
+    }
+
+    fn stored_schema_version(paths: &TaskPaths) -> Option<u64> {
+        let raw = fs::read_to_string(paths.metadata_path()).expect("read metadata");
+        let value: serde_json::Value = serde_json::from_str(&raw).expect("parse metadata");
+        value.get(SCHEMA_VERSION_KEY).and_then(|v| v.as_u64())
+    }
+
+    #[test]
+    fn unversioned_metadata_is_upgraded_on_read() {
+        let tmp = tempdir().expect("tempdir");
+        let store = TaskStore::new(tmp.path().join("root"));
+        store.ensure_layout().expect("layout");
+        let paths = store.task("task-v0".to_string());
+        write_raw_metadata(paths.directory(), legacy_payload("task-v0", "STOPPED"));
+
+        let metadata = paths.read_metadata().expect("read metadata");
+        assert_eq!(metadata.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(metadata.state, crate::tasks::TaskState::Stopped);
+        assert_eq!(
+            stored_schema_version(&paths),
+            Some(u64::from(CURRENT_SCHEMA_VERSION))
+        );
+        assert_eq!(paths.migrate().expect("migrate again"), None);
+    }
+
+    #[test]
+    fn version_one_metadata_is_upgraded_unchanged() {
+        let tmp = tempdir().expect("tempdir");
+        let store = TaskStore::new(tmp.path().join("root"));
+        store.ensure_layout().expect("layout");
+        let paths = store.task("task-v1".to_string());
+        let mut payload = legacy_payload("task-v1", "RUNNING");
+        payload[SCHEMA_VERSION_KEY] = serde_json::json!(1);
+        payload["title"] = serde_json::json!("Versioned");
+        write_raw_metadata(paths.directory(), payload);
+
+        assert_eq!(paths.migrate().expect("migrate"), Some(1));
+        let metadata = paths.read_metadata().expect("read metadata");
+        assert_eq!(metadata.schema_version, 2);
+        assert_eq!(metadata.state, crate::tasks::TaskState::Running);
+        assert_eq!(metadata.title.as_deref(), Some("Versioned"));
+        assert_eq!(stored_schema_version(&paths), Some(2));
+    }
+
+    #[test]
+    fn legacy_idle_state_and_pipe_are_migrated() {
+        let tmp = tempdir().expect("tempdir");
+        let store = TaskStore::new(tmp.path().join("root"));
+        store.ensure_layout().expect("layout");
+        let paths = store.task("task-idle".to_string());
+        write_raw_metadata(paths.directory(), legacy_payload("task-idle", "IDLE"));
+        fs::write(paths.pipe_path(), "").expect("write pipe placeholder");
+
+        assert_eq!(paths.migrate().expect("migrate"), Some(0));
+        let metadata = paths.read_metadata().expect("read metadata");
+        assert_eq!(metadata.state, crate::tasks::TaskState::Stopped);
+        assert!(!paths.pipe_path().exists());
+    }
+
+    #[test]
+    fn flat_legacy_layout_is_relocated_on_read() {
+        let tmp = tempdir().expect("tempdir");
+        let store = TaskStore::new(tmp.path().join("root"));
+        store.ensure_layout().expect("layout");
+        let root = store.root();
+        fs::write(
+            root.join("task-flat.json"),
+            legacy_payload("task-flat", "IDLE").to_string(),
+        )
+        .expect("write flat metadata");
+        fs::write(root.join("task-flat.log"), "{}\n").expect("write flat log");
+        fs::write(root.join("task-flat.result"), "done").expect("write flat result");
+        fs::write(root.join("task-flat.pipe"), "").expect("write flat pipe");
+
+        let metadata = store.load_metadata("task-flat").expect("load metadata");
+        assert_eq!(metadata.state, crate::tasks::TaskState::Stopped);
+        let paths = store.task("task-flat".to_string());
+        assert!(paths.log_path().exists());
+        assert_eq!(
+            paths.read_last_result().expect("result"),
+            Some("done".to_string())
+        );
+        assert!(!root.join("task-flat.json").exists());
+        assert!(!root.join("task-flat.pipe").exists());
+    }
+
+    #[test]
+    fn newer_schema_versions_are_rejected() {
+        let tmp = tempdir().expect("tempdir");
+        let store = TaskStore::new(tmp.path().join("root"));
+        store.ensure_layout().expect("layout");
+        let paths = store.task("task-future".to_string());
+        let mut payload = legacy_payload("task-future", "STOPPED");
+        payload[SCHEMA_VERSION_KEY] = serde_json::json!(CURRENT_SCHEMA_VERSION + 1);
+        write_raw_metadata(paths.directory(), payload);
+
+        let err = paths
+            .read_metadata()
+            .expect_err("future schema should fail");
+        assert!(
+            err.to_string().contains("upgrade codex-tasks"),
+            "unexpected error: {err:#}"
+        );
+    }
+
+    #[test]
+    fn migrate_all_covers_active_and_archived_tasks() {
+        let tmp = tempdir().expect("tempdir");
+        let store = TaskStore::new(tmp.path().join("root"));
+        store.ensure_layout().expect("layout");
+
+        write_raw_metadata(
+            &store.root().join("task-active"),
+            legacy_payload("task-active", "STOPPED"),
+        );
+        store
+            .save_metadata(&TaskMetadata::new(
+                "task-current".to_string(),
+                None,
+                crate::tasks::TaskState::Stopped,
+            ))
+            .expect("current metadata");
+        fs::write(
+            store.root().join("task-flat.json"),
+            legacy_payload("task-flat", "IDLE").to_string(),
+        )
+        .expect("flat metadata");
+        let timestamp = Utc
+            .with_ymd_and_hms(2024, 2, 3, 4, 5, 6)
+            .single()
+            .expect("timestamp");
+        let bucket = store.ensure_archive_bucket(timestamp).expect("bucket");
+        write_raw_metadata(
+            &bucket.join("task-archived"),
+            legacy_payload("task-archived", "ARCHIVED"),
+        );
+        fs::write(
+            bucket.join("task-archived-flat.json"),
+            legacy_payload("task-archived-flat", "ARCHIVED").to_string(),
+        )
+        .expect("archived flat metadata");
+
+        let report = store.migrate_all().expect("migrate store");
+        assert!(report.failures.is_empty());
+        let mut relocated: Vec<_> = report.relocated.iter().map(|(id, _)| id.clone()).collect();
+        relocated.sort();
+        assert_eq!(relocated, vec!["task-archived-flat", "task-flat"]);
+        let mut upgraded: Vec<_> = report.upgraded.iter().map(|task| task.id.clone()).collect();
+        upgraded.sort();
+        assert_eq!(
+            upgraded,
+            vec![
+                "task-active",
+                "task-archived",
+                "task-archived-flat",
+                "task-flat"
+            ]
+        );
+        assert_eq!(report.current, 1);
+
+        let archived = TaskPaths::from_directory(
+            bucket.join("task-archived-flat"),
+            "task-archived-flat".to_string(),
+        );
+        assert_eq!(
+            stored_schema_version(&archived),
+            Some(u64::from(CURRENT_SCHEMA_VERSION))
+        );
+    }
+
+    #[test]
+    fn stamp_log_line_adds_timestamp_to_json_objects() {
+        let at = Utc
+            .with_ymd_and_hms(2026, 3, 4, 5, 6, 7)
+            .single()
+            .expect("timestamp");
+        let stamped = stamp_log_line(r#"{"type":"turn.started","z":1}"#, at);
+        assert_eq!(
+            stamped,
+            r#"{"timestamp":"2026-03-04T05:06:07.000Z","type":"turn.started","z":1}"#
+        );
+        let event: JsonValue = serde_json::from_str(&stamped).expect("valid json");
+        assert_eq!(log_event_time(&event), Some(at));
+
+        assert_eq!(
+            stamp_log_line("{}", at),
+            r#"{"timestamp":"2026-03-04T05:06:07.000Z"}"#
+        );
+        assert_eq!(stamp_log_line(&stamped, Utc::now()), stamped);
+        assert_eq!(stamp_log_line("not json", at), "not json");
+        assert_eq!(stamp_log_line("[1]", at), "[1]");
+    }
 }
@@ -0,0 +1,99 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde_json::Value;
+
+use crate::tasks::hooks::HOOK_EVENT;
+use crate::tasks::retry::RETRY_EVENT;
+
+/// Kind of transcript event, used to choose which events `log` shows.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+#[value(rename_all = "lowercase")]
+pub enum LogCategory {
+    /// Prompts and agent replies.
+    Messages,
+    /// Reasoning summaries.
+    Reasoning,
+    /// Shell commands and their output.
+    Commands,
+    /// File changes.
+    Files,
+    /// MCP tool calls and web searches.
+    Tools,
+    /// The agent's to-do list.
+    Todos,
+    /// Tokens used per turn.
+    Usage,
+    /// Failures, stderr output, and retries.
+    Errors,
+    /// Lifecycle hook runs.
+    Hooks,
+    /// Every other event.
+    Other,
+}
+
+impl LogCategory {
+    /// Category of a task log event.
+    pub(crate) fn of(value: &Value) -> Self {
+        match value.get("type").and_then(Value::as_str) {
+            Some("user_message") => Self::Messages,
+            Some("item.started" | "item.updated" | "item.completed") => {
+                match value.pointer("/item/type").and_then(Value::as_str) {
+                    Some("agent_message") => Self::Messages,
+                    Some("reasoning") => Self::Reasoning,
+                    Some("command_execution") => Self::Commands,
+                    Some("file_change") => Self::Files,
+                    Some("mcp_tool_call" | "web_search") => Self::Tools,
+                    Some("todo_list") => Self::Todos,
+                    Some("error") => Self::Errors,
+                    _ => Self::Other,
+                }
+            }
+            Some("turn.completed") => Self::Usage,
+            Some("turn.failed" | "stderr" | "error" | RETRY_EVENT) => Self::Errors,
+            Some(HOOK_EVENT) => Self::Hooks,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Whether an event is a prompt sent by the user rather than injected instructions or context.
+pub(crate) fn is_plain_user_message(value: &Value) -> bool {
+    value.get("type").and_then(Value::as_str) == Some("user_message") && value.get("kind").is_none()
+}
+
+/// Calls `visit` for every JSON event in a task log; a missing log has no events.
+pub(crate) fn for_each_event<F>(log_path: &Path, mut visit: F) -> Result<()>
+where
+    F: FnMut(&Value),
+{
+    let file = match File::open(log_path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to open log file {}", log_path.display()));
+        }
+    };
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("failed to read {}", log_path.display()))?;
+        if let Ok(value) = serde_json::from_str::<Value>(&line) {
+            visit(&value);
+        }
+    }
+    Ok(())
+}
+
+/// Counts the prompts recorded in a task transcript.
+pub fn count_invocations(log_path: &Path) -> Result<usize> {
+    let mut count = 0;
+    for_each_event(log_path, |value| {
+        if is_plain_user_message(value) {
+            count += 1;
+        }
+    })?;
+    Ok(count)
+}
@@ -0,0 +1,292 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::tasks::{TaskService, TaskState, TaskStatusSnapshot};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// State a wait considers successful.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum WaitTarget {
+    /// The task finished normally (STOPPED or ARCHIVED).
+    #[value(name = "STOPPED")]
+    #[serde(rename = "STOPPED")]
+    Stopped,
+    /// The task's worker died.
+    #[value(name = "DIED")]
+    #[serde(rename = "DIED")]
+    Died,
+    /// Any state other than RUNNING.
+    #[default]
+    #[value(name = "any-terminal")]
+    #[serde(rename = "any-terminal")]
+    AnyTerminal,
+}
+
+impl WaitTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WaitTarget::Stopped => "STOPPED",
+            WaitTarget::Died => "DIED",
+            WaitTarget::AnyTerminal => "any-terminal",
+        }
+    }
+}
+
+/// Parameters controlling how long and for what a wait blocks.
+#[derive(Clone, Debug, Default)]
+pub struct WaitOptions {
+    pub until: WaitTarget,
+    /// Succeed as soon as one task reaches the target instead of all of them.
+    pub any: bool,
+    /// Treat a DIED task as a failure even when waiting for any terminal state.
+    pub fail_on_died: bool,
+    pub timeout: Option<Duration>,
+}
+
+/// How a wait ended.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitOutcome {
+    /// Every task (or one, with `any`) reached the target state.
+    Reached,
+    /// A task died while that counted as a failure.
+    Died,
+    /// A task finished in a state that can no longer satisfy the target.
+    Unmet,
+    TimedOut,
+    NotFound,
+}
+
+impl WaitOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WaitOutcome::Reached => "reached",
+            WaitOutcome::Died => "died",
+            WaitOutcome::Unmet => "unmet",
+            WaitOutcome::TimedOut => "timed_out",
+            WaitOutcome::NotFound => "not_found",
+        }
+    }
+
+    /// Process exit code reported by `codex-tasks wait` for this outcome.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            WaitOutcome::Reached => 0,
+            WaitOutcome::Died => 3,
+            WaitOutcome::TimedOut => 4,
+            WaitOutcome::NotFound => 5,
+            WaitOutcome::Unmet => 6,
+        }
+    }
+}
+
+/// Result of waiting on a set of tasks.
+#[derive(Clone, Debug)]
+pub struct WaitReport {
+    pub outcome: WaitOutcome,
+    /// Last observed status of each task, `None` for ids that do not exist.
+    pub tasks: Vec<(String, Option<TaskStatusSnapshot>)>,
+    pub elapsed: Duration,
+}
+
+/// Progress observed between polls: how many tasks have settled out of the total.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WaitProgress {
+    pub settled: usize,
+    pub total: usize,
+}
+
+/// Polls the given tasks until the options are satisfied, a failure is certain or the timeout
+/// expires. `on_progress` is called whenever the number of settled tasks changes.
+pub fn wait_for_tasks(
+    service: &TaskService,
+    task_ids: &[String],
+    options: &WaitOptions,
+    mut on_progress: impl FnMut(WaitProgress),
+) -> Result<WaitReport> {
+    let started = Instant::now();
+    let mut last_settled = None;
+    loop {
+        let mut tasks = Vec::with_capacity(task_ids.len());
+        for task_id in task_ids {
+            tasks.push((task_id.clone(), service.find_status(task_id)?));
+        }
+
+        let timed_out = options
+            .timeout
+            .is_some_and(|timeout| started.elapsed() >= timeout);
+        let outcome = if tasks.iter().any(|(_, status)| status.is_none()) {
+            Some(WaitOutcome::NotFound)
+        } else {
+            let states: Vec<TaskState> = tasks
+                .iter()
+                .filter_map(|(_, status)| status.as_ref())
+                .map(|status| status.metadata.state.clone())
+                .collect();
+            let settled = states
+                .iter()
+                .filter(|state| !matches!(state, TaskState::Running | TaskState::Queued))
+                .count();
+            if last_settled != Some(settled) {
+                last_settled = Some(settled);
+                on_progress(WaitProgress {
+                    settled,
+                    total: states.len(),
+                });
+            }
+            evaluate(&states, options).or(timed_out.then_some(WaitOutcome::TimedOut))
+        };
+
+        if let Some(outcome) = outcome {
+            return Ok(WaitReport {
+                outcome,
+                tasks,
+                elapsed: started.elapsed(),
+            });
+        }
+        sleep(POLL_INTERVAL);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Progress {
+    Pending,
+    Reached,
+    Failed(WaitOutcome),
+}
+
+fn classify(state: &TaskState, options: &WaitOptions) -> Progress {
+    match (state, options.until) {
+        (TaskState::Running | TaskState::Queued, _) => Progress::Pending,
+        (TaskState::Died, WaitTarget::Died) => Progress::Reached,
+        (TaskState::Died, WaitTarget::Stopped) => Progress::Failed(WaitOutcome::Died),
+        (TaskState::Died, WaitTarget::AnyTerminal) if options.fail_on_died => {
+            Progress::Failed(WaitOutcome::Died)
+        }
+        (_, WaitTarget::Died) => Progress::Failed(WaitOutcome::Unmet),
+        _ => Progress::Reached,
+    }
+}
+
+/// Decides whether the wait is over for the given states, returning its outcome if so.
+fn evaluate(states: &[TaskState], options: &WaitOptions) -> Option<WaitOutcome> {
+    // Nothing to wait for, e.g. `--all-running` when no task is running.
+    if states.is_empty() {
+        return Some(WaitOutcome::Reached);
+    }
+    let progress: Vec<Progress> = states
+        .iter()
+        .map(|state| classify(state, options))
+        .collect();
+    let first_failure = progress.iter().find_map(|progress| match progress {
+        Progress::Failed(outcome) => Some(*outcome),
+        _ => None,
+    });
+
+    if options.any {
+        if progress.contains(&Progress::Reached) {
+            return Some(WaitOutcome::Reached);
+        }
+        if progress.contains(&Progress::Pending) {
+            return None;
+        }
+        return first_failure;
+    }
+
+    if first_failure.is_some() {
+        return first_failure;
+    }
+    if progress.contains(&Progress::Pending) {
+        return None;
+    }
+    Some(WaitOutcome::Reached)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(until: WaitTarget, any: bool, fail_on_died: bool) -> WaitOptions {
+        WaitOptions {
+            until,
+            any,
+            fail_on_died,
+            timeout: None,
+        }
+    }
+
+    #[test]
+    fn waits_for_every_task_by_default() {
+        let all = options(WaitTarget::AnyTerminal, false, false);
+        assert_eq!(
+            evaluate(&[TaskState::Stopped, TaskState::Running], &all),
+            None
+        );
+        assert_eq!(evaluate(&[TaskState::Queued], &all), None);
+        assert_eq!(
+            evaluate(&[TaskState::Stopped, TaskState::Died], &all),
+            Some(WaitOutcome::Reached)
+        );
+        assert_eq!(
+            evaluate(
+                &[TaskState::Archived],
+                &options(WaitTarget::Stopped, false, false)
+            ),
+            Some(WaitOutcome::Reached)
+        );
+    }
+
+    #[test]
+    fn reports_deaths_and_unmet_targets_early() {
+        let stopped = options(WaitTarget::Stopped, false, false);
+        assert_eq!(
+            evaluate(&[TaskState::Running, TaskState::Died], &stopped),
+            Some(WaitOutcome::Died)
+        );
+
+        let strict = options(WaitTarget::AnyTerminal, false, true);
+        assert_eq!(
+            evaluate(&[TaskState::Died, TaskState::Running], &strict),
+            Some(WaitOutcome::Died)
+        );
+
+        let died = options(WaitTarget::Died, false, false);
+        assert_eq!(
+            evaluate(&[TaskState::Stopped], &died),
+            Some(WaitOutcome::Unmet)
+        );
+        assert_eq!(
+            evaluate(&[TaskState::Died], &died),
+            Some(WaitOutcome::Reached)
+        );
+    }
+
+    #[test]
+    fn any_succeeds_on_first_match_and_fails_once_all_settle() {
+        let any = options(WaitTarget::Stopped, true, false);
+        assert_eq!(
+            evaluate(&[TaskState::Running, TaskState::Stopped], &any),
+            Some(WaitOutcome::Reached)
+        );
+        assert_eq!(evaluate(&[TaskState::Running, TaskState::Died], &any), None);
+        assert_eq!(
+            evaluate(&[TaskState::Died, TaskState::Died], &any),
+            Some(WaitOutcome::Died)
+        );
+    }
+
+    #[test]
+    fn empty_task_set_is_reached_immediately() {
+        for any in [false, true] {
+            assert_eq!(
+                evaluate(&[], &options(WaitTarget::AnyTerminal, any, false)),
+                Some(WaitOutcome::Reached)
+            );
+        }
+    }
+}
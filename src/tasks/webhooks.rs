@@ -0,0 +1,444 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::tasks::settings::StoreSettings;
+use crate::tasks::{HookEvent, TaskMetadata, TaskPaths, TaskStore, TasksConfig};
+
+/// Append-only log of webhook deliveries in the store root.
+pub const WEBHOOK_LOG_FILE_NAME: &str = "webhooks.jsonl";
+/// Header carrying `sha256=<hex HMAC of the body>` when the webhook has a secret.
+pub const SIGNATURE_HEADER: &str = "X-Codex-Tasks-Signature";
+/// Header naming the [`WebhookEvent`] of a delivery.
+pub const EVENT_HEADER: &str = "X-Codex-Tasks-Event";
+/// Header carrying the delivery id, identical across retries of the same delivery.
+pub const DELIVERY_HEADER: &str = "X-Codex-Tasks-Delivery";
+
+pub const DEFAULT_WEBHOOK_RETRIES: u32 = 3;
+pub const DEFAULT_WEBHOOK_BACKOFF_SECS: u64 = 1;
+
+/// Kinds of notification a webhook can subscribe to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// The task became RUNNING, STOPPED, DIED, or ARCHIVED.
+    #[value(name = "state_changed")]
+    StateChanged,
+    /// An invocation finished; carries the final answer.
+    #[value(name = "result")]
+    Result,
+    /// An invocation failed for good; carries the error.
+    #[value(name = "died")]
+    Died,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::StateChanged => "state_changed",
+            WebhookEvent::Result => "result",
+            WebhookEvent::Died => "died",
+        }
+    }
+
+    /// Notifications sent when a task reaches a lifecycle event.
+    fn for_hook(event: HookEvent) -> &'static [WebhookEvent] {
+        match event {
+            HookEvent::OnStart | HookEvent::OnArchived => &[WebhookEvent::StateChanged],
+            HookEvent::OnStopped => &[WebhookEvent::StateChanged, WebhookEvent::Result],
+            HookEvent::OnDied => &[WebhookEvent::StateChanged, WebhookEvent::Died],
+            HookEvent::OnTurnCompleted => &[],
+        }
+    }
+}
+
+/// A URL notified of task events, stored in the store settings.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// Events delivered to this URL; every event when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<WebhookEvent>,
+    /// Key used to sign payloads with HMAC-SHA256.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Further attempts after a failed delivery; defaults to [`DEFAULT_WEBHOOK_RETRIES`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    /// Delay before the first retry, doubling per attempt; defaults to
+    /// [`DEFAULT_WEBHOOK_BACKOFF_SECS`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_backoff_secs: Option<u64>,
+}
+
+impl Webhook {
+    pub fn subscribes_to(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+/// Outcome of delivering one notification to one webhook, as kept in the delivery log.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub at: DateTime<Utc>,
+    pub webhook_id: String,
+    pub url: String,
+    pub event: WebhookEvent,
+    pub task_id: String,
+    pub attempts: u32,
+    pub delivered: bool,
+    /// HTTP status of the last attempt, if the server answered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Sends the webhook notifications for a task reaching `event` and records every delivery in
+/// the store's delivery log. `error` is reported by `died` notifications. Without `retry`, each
+/// webhook gets a single attempt, for callers a user is waiting on. Failures are recorded rather
+/// than returned, so a broken endpoint cannot affect the task.
+pub fn notify_webhooks(
+    store: &TaskStore,
+    paths: &TaskPaths,
+    event: HookEvent,
+    error: Option<&str>,
+    config: &TasksConfig,
+    retry: bool,
+) -> Vec<WebhookDelivery> {
+    let notices = WebhookEvent::for_hook(event);
+    let webhooks = match StoreSettings::load(store) {
+        Ok(settings) => settings.webhooks,
+        Err(err) => {
+            eprintln!("failed to load webhooks: {err:#}");
+            return Vec::new();
+        }
+    };
+    if notices.is_empty() || webhooks.is_empty() {
+        return Vec::new();
+    }
+    let metadata = match paths.read_metadata() {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            eprintln!("failed to read task {} for webhooks: {err:#}", paths.id());
+            return Vec::new();
+        }
+    };
+
+    let mut deliveries = Vec::new();
+    for notice in notices {
+        let body = payload(*notice, &metadata, error).to_string();
+        for webhook in webhooks
+            .iter()
+            .filter(|webhook| webhook.subscribes_to(*notice))
+        {
+            let delivery = deliver(
+                webhook,
+                *notice,
+                &metadata.id,
+                &body,
+                config.webhook_timeout,
+                retry,
+            );
+            if let Err(err) = append_delivery(store, &delivery) {
+                eprintln!("failed to record webhook delivery: {err:#}");
+            }
+            deliveries.push(delivery);
+        }
+    }
+    deliveries
+}
+
+fn payload(event: WebhookEvent, metadata: &TaskMetadata, error: Option<&str>) -> Value {
+    let mut payload = json!({
+        "event": event.as_str(),
+        "task_id": metadata.id,
+        "title": metadata.title,
+        "state": metadata.state.as_str(),
+        "working_dir": metadata.working_dir,
+        "tags": metadata.tags,
+        "timestamp": Utc::now().to_rfc3339(),
+    });
+    match event {
+        WebhookEvent::StateChanged => {}
+        WebhookEvent::Result => payload["result"] = json!(metadata.last_result),
+        WebhookEvent::Died => payload["error"] = json!(error),
+    }
+    payload
+}
+
+/// POSTs `body` to the webhook, retrying network errors, 429s, and server errors with
+/// exponential backoff.
+pub fn deliver(
+    webhook: &Webhook,
+    event: WebhookEvent,
+    task_id: &str,
+    body: &str,
+    timeout: Duration,
+    retry: bool,
+) -> WebhookDelivery {
+    let mut delivery = WebhookDelivery {
+        id: Uuid::new_v4().to_string(),
+        at: Utc::now(),
+        webhook_id: webhook.id.clone(),
+        url: webhook.url.clone(),
+        event,
+        task_id: task_id.to_string(),
+        attempts: 0,
+        delivered: false,
+        status: None,
+        error: None,
+    };
+    let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+    let retries = if retry {
+        webhook.retries.unwrap_or(DEFAULT_WEBHOOK_RETRIES)
+    } else {
+        0
+    };
+    let backoff = webhook
+        .retry_backoff_secs
+        .unwrap_or(DEFAULT_WEBHOOK_BACKOFF_SECS);
+
+    loop {
+        delivery.attempts += 1;
+        let mut request = agent
+            .post(&webhook.url)
+            .set("Content-Type", "application/json")
+            .set(EVENT_HEADER, event.as_str())
+            .set(DELIVERY_HEADER, &delivery.id);
+        if let Some(secret) = &webhook.secret {
+            request = request.set(SIGNATURE_HEADER, &sign(secret, body));
+        }
+        let retryable = match request.send_string(body) {
+            Ok(response) => {
+                delivery.status = Some(response.status());
+                delivery.error = None;
+                delivery.delivered = true;
+                false
+            }
+            Err(ureq::Error::Status(status, _)) => {
+                delivery.status = Some(status);
+                delivery.error = Some(format!("server answered {status}"));
+                status == 429 || status >= 500
+            }
+            Err(err) => {
+                delivery.status = None;
+                delivery.error = Some(err.to_string());
+                true
+            }
+        };
+        if !retryable || delivery.attempts > retries {
+            return delivery;
+        }
+        let factor = 2u64.saturating_pow(delivery.attempts - 1);
+        thread::sleep(Duration::from_secs(backoff.saturating_mul(factor)));
+    }
+}
+
+/// Computes the `sha256=<hex>` signature of `body` under `secret`.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body.as_bytes());
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("sha256={digest}")
+}
+
+fn append_delivery(store: &TaskStore, delivery: &WebhookDelivery) -> Result<()> {
+    store.ensure_layout()?;
+    let path = store.root().join(WEBHOOK_LOG_FILE_NAME);
+    let mut line = serde_json::to_string(delivery)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut log| log.write_all(line.as_bytes()))
+        .with_context(|| format!("failed to append to {}", path.display()))
+}
+
+/// Reads the delivery log, oldest first, skipping lines that cannot be parsed.
+pub fn read_deliveries(store: &TaskStore) -> Result<Vec<WebhookDelivery>> {
+    let path = store.root().join(WEBHOOK_LOG_FILE_NAME);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read {}", path.display()));
+        }
+    };
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Finds a webhook by id, unique id prefix, or URL.
+pub fn find_webhook(webhooks: &[Webhook], reference: &str) -> Result<usize> {
+    let matches: Vec<usize> = webhooks
+        .iter()
+        .enumerate()
+        .filter(|(_, webhook)| webhook.id.starts_with(reference) || webhook.url == reference)
+        .map(|(index, _)| index)
+        .collect();
+    match matches.as_slice() {
+        [index] => Ok(*index),
+        [] => bail!("webhook {reference} was not found"),
+        _ => bail!("webhook {reference} is ambiguous"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::TaskState;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use tempfile::tempdir;
+
+    /// Answers each connection with the next status and forwards the request head and body.
+    fn stand_in(statuses: Vec<u16>) -> (String, mpsc::Receiver<(String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut head = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                    head.push_str(&line);
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                sender
+                    .send((head, String::from_utf8(body).unwrap()))
+                    .unwrap();
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 {status} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+            }
+        });
+        (url, receiver)
+    }
+
+    #[test]
+    fn signs_with_hmac_sha256() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn retries_server_errors_and_logs_signed_deliveries() -> Result<()> {
+        let (url, requests) = stand_in(vec![503, 200, 200]);
+        let tmp = tempdir()?;
+        let store = TaskStore::new(tmp.path().join("tasks"));
+        StoreSettings {
+            webhooks: vec![Webhook {
+                id: "abc".to_string(),
+                url: url.clone(),
+                events: vec![WebhookEvent::StateChanged, WebhookEvent::Died],
+                secret: Some("s3cret".to_string()),
+                retries: Some(2),
+                retry_backoff_secs: Some(0),
+            }],
+            ..StoreSettings::default()
+        }
+        .save(&store)?;
+        let metadata = TaskMetadata::new("task-1".to_string(), None, TaskState::Died);
+        store.save_metadata(&metadata)?;
+
+        let deliveries = notify_webhooks(
+            &store,
+            &store.task("task-1"),
+            HookEvent::OnDied,
+            Some("boom"),
+            &TasksConfig::default(),
+            true,
+        );
+        assert_eq!(deliveries.len(), 2);
+        assert!(deliveries.iter().all(|delivery| delivery.delivered));
+        assert_eq!(deliveries[0].attempts, 2);
+        assert_eq!(deliveries[1].event, WebhookEvent::Died);
+
+        let (head, _) = requests.recv()?;
+        assert!(head.contains("X-Codex-Tasks-Event: state_changed"));
+        let (head, body) = requests.recv()?;
+        assert!(head.contains(&sign("s3cret", &body)));
+        let (_, body) = requests.recv()?;
+        let payload: Value = serde_json::from_str(&body)?;
+        assert_eq!(payload["event"], "died");
+        assert_eq!(payload["error"], "boom");
+
+        let logged = read_deliveries(&store)?;
+        assert_eq!(logged, deliveries);
+        Ok(())
+    }
+
+    #[test]
+    fn delivers_once_without_retry() -> Result<()> {
+        let (url, _requests) = stand_in(vec![503]);
+        let tmp = tempdir()?;
+        let store = TaskStore::new(tmp.path().join("tasks"));
+        StoreSettings {
+            webhooks: vec![Webhook {
+                id: "abc".to_string(),
+                url,
+                events: vec![WebhookEvent::Died],
+                secret: None,
+                retries: Some(2),
+                retry_backoff_secs: Some(0),
+            }],
+            ..StoreSettings::default()
+        }
+        .save(&store)?;
+        let metadata = TaskMetadata::new("task-1".to_string(), None, TaskState::Died);
+        store.save_metadata(&metadata)?;
+
+        let deliveries = notify_webhooks(
+            &store,
+            &store.task("task-1"),
+            HookEvent::OnDied,
+            None,
+            &TasksConfig::default(),
+            false,
+        );
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].attempts, 1);
+        assert!(!deliveries[0].delivered);
+        assert_eq!(deliveries[0].status, Some(503));
+        Ok(())
+    }
+}
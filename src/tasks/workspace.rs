@@ -0,0 +1,280 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tempfile::tempdir;
+
+use crate::tasks::transcript::{for_each_event, is_plain_user_message};
+
+/// Log event type recording the state of the working directory when an invocation starts.
+pub const WORKSPACE_SNAPSHOT_EVENT: &str = "workspace_snapshot";
+
+/// Git state of a task's working directory captured at the start of an invocation.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WorkspaceSnapshot {
+    /// Commit checked out at the time, absent on an unborn branch.
+    pub head: Option<String>,
+    /// Tree object holding every tracked and untracked (non-ignored) file.
+    pub tree: String,
+    /// Whether the working tree differed from `head`.
+    pub dirty: bool,
+}
+
+/// Changes recorded for a single invocation while reading a task log.
+#[derive(Clone, Debug, Default)]
+pub struct InvocationChanges {
+    pub snapshot: Option<WorkspaceSnapshot>,
+    /// `(marker, path)` pairs from `file_change` events, e.g. `('M', "src/main.rs")`.
+    pub files: Vec<(char, String)>,
+}
+
+/// Captures the git state of `dir`, returning `None` when it is not inside a git work tree.
+pub fn capture(dir: &Path) -> Result<Option<WorkspaceSnapshot>> {
+    let inside = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .output();
+    match inside {
+        Ok(output) if output.status.success() => {}
+        Ok(_) => return Ok(None),
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).context("failed to run git"),
+    }
+
+    let head = git(dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).ok();
+    let tree = snapshot_tree(dir)?;
+    let dirty = match &head {
+        Some(head) => git(dir, &["rev-parse", &format!("{head}^{{tree}}")])? != tree,
+        None => true,
+    };
+    Ok(Some(WorkspaceSnapshot { head, tree, dirty }))
+}
+
+/// Builds the unified diff between a snapshot tree and `to_tree`, or the current working tree
+/// when `to_tree` is `None`.
+pub fn diff(dir: &Path, from_tree: &str, to_tree: Option<&str>) -> Result<String> {
+    let to_tree = match to_tree {
+        Some(tree) => tree.to_string(),
+        None => snapshot_tree(dir)?,
+    };
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["diff", "--no-color", "--no-ext-diff", from_tree, &to_tree])
+        .output()
+        .context("failed to run git diff")?;
+    if !output.status.success() {
+        bail!(
+            "git diff failed in {}: {}",
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Groups the snapshots and file changes in a task log by invocation.
+pub fn read_invocations(log_path: &Path) -> Result<Vec<InvocationChanges>> {
+    let mut invocations: Vec<InvocationChanges> = Vec::new();
+    for_each_event(log_path, |value| {
+        if is_plain_user_message(value) {
+            invocations.push(InvocationChanges::default());
+            return;
+        }
+        let Some(current) = invocations.last_mut() else {
+            return;
+        };
+        match value.get("type").and_then(Value::as_str) {
+            Some(WORKSPACE_SNAPSHOT_EVENT) => {
+                current.snapshot = serde_json::from_value(value.clone()).ok();
+            }
+            Some("item.completed") => {
+                let item = value.get("item");
+                let is_file_change = item
+                    .and_then(|item| item.get("type"))
+                    .and_then(Value::as_str)
+                    == Some("file_change");
+                let changes = item
+                    .filter(|_| is_file_change)
+                    .and_then(|item| item.get("changes"))
+                    .and_then(Value::as_array);
+                for change in changes.into_iter().flatten() {
+                    let Some(path) = change.get("path").and_then(Value::as_str) else {
+                        continue;
+                    };
+                    let marker = match change.get("kind").and_then(Value::as_str) {
+                        Some("add") => 'A',
+                        Some("delete") => 'D',
+                        Some("update") | None => 'M',
+                        Some(_) => '?',
+                    };
+                    current.files.push((marker, path.to_string()));
+                }
+            }
+            _ => {}
+        }
+    })?;
+    Ok(invocations)
+}
+
+/// Writes a tree object for the working directory without touching the real index, by staging
+/// everything into a copy of it.
+fn snapshot_tree(dir: &Path) -> Result<String> {
+    let scratch = tempdir().context("failed to create scratch directory for git index")?;
+    let index = scratch.path().join("index");
+    let real_index = git(
+        dir,
+        &["rev-parse", "--path-format=absolute", "--git-path", "index"],
+    )?;
+    match fs::copy(&real_index, &index) {
+        Ok(_) => {
+            // Keep the original timestamp so git still re-reads files modified in the same
+            // second the index was written ("racy git") instead of trusting their stat data.
+            let modified = fs::metadata(&real_index).and_then(|meta| meta.modified());
+            if let Ok(modified) = modified {
+                fs::File::options()
+                    .write(true)
+                    .open(&index)
+                    .and_then(|file| file.set_modified(modified))
+                    .with_context(|| format!("failed to preserve timestamp of {real_index}"))?;
+            }
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to copy git index {real_index}"));
+        }
+    }
+
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["add", "--all", "--", "."])
+        .env("GIT_INDEX_FILE", &index)
+        .output()
+        .context("failed to run git add")?;
+    if !status.status.success() {
+        bail!(
+            "failed to stage working tree of {}: {}",
+            dir.display(),
+            String::from_utf8_lossy(&status.stderr).trim()
+        );
+    }
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("write-tree")
+        .env("GIT_INDEX_FILE", &index)
+        .output()
+        .context("failed to run git write-tree")?;
+    if !output.status.success() {
+        bail!(
+            "failed to write tree for {}: {}",
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed in {}: {}",
+            args.join(" "),
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn init_repo(dir: &Path) -> Result<()> {
+        git(dir, &["init", "--quiet"])?;
+        fs::write(dir.join("tracked.txt"), "one\n")?;
+        git(dir, &["add", "tracked.txt"])?;
+        git(
+            dir,
+            &[
+                "-c",
+                "user.name=Test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "--quiet",
+                "-m",
+                "initial",
+            ],
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_diff_covers_untracked_files_without_touching_index() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        init_repo(tmp.path())?;
+
+        let snapshot = capture(tmp.path())?.expect("git work tree");
+        assert!(!snapshot.dirty);
+        assert!(snapshot.head.is_some());
+
+        fs::write(tmp.path().join("tracked.txt"), "two\n")?;
+        fs::write(tmp.path().join("new.txt"), "fresh\n")?;
+        let patch = diff(tmp.path(), &snapshot.tree, None)?;
+        assert!(patch.contains("-one\n+two"), "patch:\n{patch}");
+        assert!(patch.contains("+++ b/new.txt"), "patch:\n{patch}");
+        assert_eq!(git(tmp.path(), &["diff", "--cached", "--name-only"])?, "");
+
+        assert!(capture(tmp.path())?.expect("git work tree").dirty);
+        Ok(())
+    }
+
+    #[test]
+    fn capture_skips_non_git_directories() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        assert_eq!(capture(tmp.path())?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn groups_log_events_by_invocation() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let log = tmp.path().join("task.log");
+        let events = [
+            json!({"type": "user_message", "message": "first"}),
+            json!({"type": WORKSPACE_SNAPSHOT_EVENT, "head": "abc", "tree": "def", "dirty": false}),
+            json!({"type": "item.completed", "item": {"type": "file_change", "changes": [{"path": "a.rs", "kind": "add"}]}}),
+            json!({"type": "user_message", "kind": "user_instructions", "message": "rules"}),
+            json!({"type": "user_message", "message": "second"}),
+            json!({"type": "item.completed", "item": {"type": "file_change", "changes": [{"path": "b.rs", "kind": "delete"}]}}),
+        ];
+        let lines: Vec<String> = events.iter().map(Value::to_string).collect();
+        fs::write(&log, lines.join("\n"))?;
+
+        let invocations = read_invocations(&log)?;
+        assert_eq!(invocations.len(), 2);
+        assert_eq!(
+            invocations[0].snapshot.as_ref().map(|s| s.tree.as_str()),
+            Some("def")
+        );
+        assert_eq!(invocations[0].files, vec![('A', "a.rs".to_string())]);
+        assert!(invocations[1].snapshot.is_none());
+        assert_eq!(invocations[1].files, vec![('D', "b.rs".to_string())]);
+        Ok(())
+    }
+}
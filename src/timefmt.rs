@@ -1,4 +1,4 @@
-use chrono::{DateTime, Local, SecondsFormat, Utc};
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat, TimeZone, Utc};
 use clap::ValueEnum;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
@@ -6,12 +6,14 @@ use clap::ValueEnum;
 pub enum TimeFormat {
     Human,
     Iso,
+    Relative,
 }
 
 pub fn format_time(datetime: DateTime<Utc>, format: TimeFormat) -> String {
     match format {
         TimeFormat::Human => format_human(datetime),
         TimeFormat::Iso => format_iso(datetime),
+        TimeFormat::Relative => format_relative(datetime, Utc::now()),
     }
 }
 
@@ -25,3 +27,79 @@ fn format_iso(datetime: DateTime<Utc>) -> String {
         .with_timezone(&Local)
         .to_rfc3339_opts(SecondsFormat::Secs, true)
 }
+
+/// Formats a timestamp relative to `now`, e.g. `5m ago` or `in 2h`.
+fn format_relative(datetime: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    if datetime > now {
+        format!("in {}", format_elapsed(datetime - now))
+    } else {
+        format!("{} ago", format_elapsed(now - datetime))
+    }
+}
+
+/// Formats an elapsed duration compactly using its largest unit (e.g. `45s`, `12m`, `3h`, `2d`).
+pub fn format_elapsed(elapsed: chrono::Duration) -> String {
+    let seconds = elapsed.num_seconds().max(0);
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3_600 => format!("{}m", seconds / 60),
+        3_600..86_400 => format!("{}h", seconds / 3_600),
+        _ => format!("{}d", seconds / 86_400),
+    }
+}
+
+/// Parses an RFC 3339 timestamp or a `YYYY-MM-DD` date (local midnight).
+pub fn parse_timestamp(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+        .map(|datetime| datetime.with_timezone(&Utc))
+        .ok_or_else(|| format!("invalid time '{value}' (expected RFC 3339 or YYYY-MM-DD)"))
+}
+
+/// Parses a duration given in seconds, optionally suffixed with `s`, `m`, `h` or `d` (e.g. `90`,
+/// `15m`, `2h`, `30d`).
+pub fn parse_duration(value: &str) -> Result<std::time::Duration, String> {
+    let trimmed = value.trim();
+    let (digits, multiplier) = match trimmed.char_indices().last() {
+        Some((index, 's')) => (&trimmed[..index], 1),
+        Some((index, 'm')) => (&trimmed[..index], 60),
+        Some((index, 'h')) => (&trimmed[..index], 3_600),
+        Some((index, 'd')) => (&trimmed[..index], 86_400),
+        _ => (trimmed, 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|amount| amount.checked_mul(multiplier))
+        .map(std::time::Duration::from_secs)
+        .ok_or_else(|| format!("invalid duration '{value}' (expected e.g. 90, 30s, 15m, 2h or 7d)"))
+}
+
+/// Parses a point in time given as a timestamp (see [`parse_timestamp`]) or as a duration
+/// before now (see [`parse_duration`]), e.g. `10m` for ten minutes ago.
+pub fn parse_time_bound(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(elapsed) = parse_duration(value) {
+        let elapsed = chrono::Duration::from_std(elapsed).map_err(|err| err.to_string())?;
+        return Ok(Utc::now() - elapsed);
+    }
+    parse_timestamp(value).map_err(|_| {
+        format!("invalid time '{value}' (expected e.g. 10m, 2h, RFC 3339, or YYYY-MM-DD)")
+    })
+}
+
+/// Renders a duration in the largest unit [`parse_duration`] accepts that represents it exactly.
+pub fn format_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0 => "0s".to_string(),
+        _ if secs.is_multiple_of(86_400) => format!("{}d", secs / 86_400),
+        _ if secs.is_multiple_of(3_600) => format!("{}h", secs / 3_600),
+        _ if secs.is_multiple_of(60) => format!("{}m", secs / 60),
+        _ => format!("{secs}s"),
+    }
+}
@@ -1,21 +1,37 @@
 use std::env;
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Result, anyhow, bail};
+use chrono::Utc;
 use serde::Serialize;
 use serde_json::{Value, json};
-use tempfile::NamedTempFile;
 use tokio::fs::OpenOptions as TokioOpenOptions;
 use tokio::io::{self as tokio_io, AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
 use tokio::process::Command;
-
-use crate::tasks::{TaskId, TaskMetadata, TaskPaths, TaskState, TaskStore};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::tasks::hooks::run_hooks;
+use crate::tasks::retry::{RETRY_EVENT, failure_message, is_transient_failure};
+use crate::tasks::webhooks::notify_webhooks;
+use crate::tasks::workspace::{self, WORKSPACE_SNAPSHOT_EVENT};
+use crate::tasks::{
+    ExecOptions, ForkPoint, HookEvent, HookRun, Hooks, RESULT_TEMP_PREFIX, RetryPolicy, TaskId,
+    TaskMetadata, TaskPaths, TaskService, TaskState, TaskStore, TasksConfig, stamp_log_line,
+};
+use crate::timefmt::format_duration;
 
 pub const TITLE_ENV_VAR: &str = "CODEX_TASK_TITLE";
 pub const PROMPT_ENV_VAR: &str = "CODEX_TASK_PROMPT";
+pub const PREAMBLE_ENV_VAR: &str = "CODEX_TASK_PREAMBLE";
 pub const EXIT_AFTER_START_ENV_VAR: &str = "CODEX_TASKS_EXIT_AFTER_START";
+/// Exported to `codex exec` so tasks started from inside it (e.g. via the MCP server) can
+/// record their parent. Holds the thread id, or the launch id during the initial invocation.
+pub const PARENT_ENV_VAR: &str = "CODEX_TASKS_PARENT_ID";
 
 #[derive(Clone, Debug)]
 pub struct WorkerConfig {
@@ -23,8 +39,28 @@ pub struct WorkerConfig {
     pub task_id: Option<TaskId>,
     pub title: Option<String>,
     pub prompt: String,
+    /// Sent ahead of the prompt on the initial invocation, e.g. a forked task's transcript.
+    pub preamble: Option<String>,
     pub config_path: Option<PathBuf>,
     pub working_dir: Option<PathBuf>,
+    /// Lineage recorded on the task created by this worker, when it is a fork.
+    pub fork: Option<(TaskId, ForkPoint)>,
+    /// Parent recorded on the task created by this worker.
+    pub parent_id: Option<TaskId>,
+    /// Exported as [`PARENT_ENV_VAR`] until the thread id is known.
+    pub launch_id: String,
+    /// Automatic retries for transient failures; loaded from metadata for existing tasks.
+    pub retry_policy: Option<RetryPolicy>,
+    /// Labels recorded on the task created by this worker.
+    pub tags: Vec<String>,
+    /// Lifecycle hooks recorded on the task created by this worker.
+    pub hooks: Option<Hooks>,
+    /// Configuration preset recorded on the task created by this worker.
+    pub preset: Option<String>,
+    /// Extra `codex exec` settings; loaded from metadata for existing tasks.
+    pub exec: Option<ExecOptions>,
+    /// The `codex-tasks` configuration applied to `codex exec`, hooks, and webhooks.
+    pub tasks_config: TasksConfig,
 }
 
 impl WorkerConfig {
@@ -54,8 +90,18 @@ impl WorkerConfig {
             task_id,
             title,
             prompt,
+            preamble: env::var(PREAMBLE_ENV_VAR).ok(),
             config_path,
             working_dir,
+            fork: None,
+            parent_id: None,
+            launch_id: Uuid::new_v4().to_string(),
+            retry_policy: None,
+            tags: Vec::new(),
+            hooks: None,
+            preset: None,
+            exec: None,
+            tasks_config: TasksConfig::default(),
         })
     }
 
@@ -103,6 +149,12 @@ struct Worker {
     config: WorkerConfig,
     store: TaskStore,
     session: Option<ActiveSession>,
+    /// Hooks still running in the background; their outcomes are logged once they finish.
+    pending_hooks: Vec<JoinHandle<Vec<HookRun>>>,
+    /// Whether `on_start` already ran, so automatic retries do not repeat it.
+    start_hooks_run: bool,
+    /// Error reported by the last failed invocation, sent with `died` webhooks.
+    last_failure: Option<String>,
 }
 
 impl Worker {
@@ -122,6 +174,14 @@ impl Worker {
             if config.working_dir.is_none() {
                 config.working_dir = metadata.working_dir.as_ref().map(PathBuf::from);
             }
+            if config.retry_policy.is_none() {
+                config.retry_policy = metadata.retry_policy;
+            }
+            if config.exec.is_none() {
+                config.exec = metadata.exec.clone();
+            }
+            // Stand in for `codex exec` until it is spawned so the task never looks DIED.
+            paths.write_pid(std::process::id() as i32)?;
 
             let log_file = TokioOpenOptions::new()
                 .create(true)
@@ -142,32 +202,101 @@ impl Worker {
             config,
             store,
             session,
+            pending_hooks: Vec::new(),
+            start_hooks_run: false,
+            last_failure: None,
         })
     }
 
     async fn run(mut self) -> Result<()> {
-        let initial = self.session.is_none();
         let prompt = self.config.prompt.clone();
-        let request = if initial {
-            InvocationKind::Initial
-        } else {
-            InvocationKind::Resume
-        };
+        let mut attempt = 0;
+        loop {
+            let request = if self.session.is_none() {
+                InvocationKind::Initial
+            } else {
+                InvocationKind::Resume
+            };
+            let outcome = self
+                .run_invocation(prompt.clone(), request, attempt > 0)
+                .await?;
 
-        self.run_invocation(prompt, request).await?;
+            let InvocationOutcome::Failed { transient, reason } = outcome else {
+                break;
+            };
+            self.last_failure = reason.clone();
+            let Some(policy) = self
+                .config
+                .retry_policy
+                .filter(|policy| transient && attempt < policy.max_retries)
+            else {
+                break;
+            };
+            attempt += 1;
+            self.prepare_retry(policy, attempt, reason).await?;
+        }
         self.finalize().await
     }
 
-    async fn run_invocation(&mut self, prompt: String, kind: InvocationKind) -> Result<()> {
+    /// Records an automatic retry and waits out its backoff. The worker's own pid stands in
+    /// for `codex exec` meanwhile, so the task reads as RUNNING and `stop` can cancel it.
+    async fn prepare_retry(
+        &mut self,
+        policy: RetryPolicy,
+        attempt: u32,
+        reason: Option<String>,
+    ) -> Result<()> {
+        let delay = policy.delay(attempt);
+        if let Some(session) = self.session.as_mut() {
+            let event = json!({
+                "type": RETRY_EVENT,
+                "attempt": attempt,
+                "max_retries": policy.max_retries,
+                "delay_secs": delay.as_secs(),
+                "reason": reason,
+            });
+            session.write_event_line(&event.to_string()).await?;
+            session.paths.write_pid(std::process::id() as i32)?;
+            session.paths.update_metadata(|metadata| {
+                metadata.retry_count += 1;
+                metadata.set_state(TaskState::Running);
+            })?;
+        }
+        tokio::task::spawn_blocking(move || std::thread::sleep(delay))
+            .await
+            .context("failed to wait for retry backoff")
+    }
+
+    async fn run_invocation(
+        &mut self,
+        prompt: String,
+        kind: InvocationKind,
+        retry: bool,
+    ) -> Result<InvocationOutcome> {
         let mut buffered_events: Vec<String> = Vec::new();
+        let mut failures: Vec<String> = Vec::new();
         let mut pending_pid: Option<i32> = None;
         let mut pending_prompt: Option<String> = None;
 
-        if let Some(event) = user_message_event(&prompt) {
+        // A retry belongs to the invocation already in the log, which `prepare_retry` marked;
+        // recording the prompt again would count it as a new invocation. Retries of an initial
+        // invocation that never got a thread id have nothing logged yet.
+        let starts_invocation = !(retry && self.session.is_some());
+        // Snapshotting runs blocking `git` subprocesses; keep them off the async runtime.
+        let snapshot_event = match self.config.working_dir.clone() {
+            Some(dir) if starts_invocation => {
+                tokio::task::spawn_blocking(move || workspace_snapshot_event(&dir))
+                    .await
+                    .context("failed to snapshot the workspace")?
+            }
+            _ => None,
+        };
+        let prompt_event = user_message_event(&prompt).filter(|_| starts_invocation);
+        for event in [prompt_event, snapshot_event].into_iter().flatten() {
             if let Some(session) = self.session.as_mut() {
                 session.write_event_line(&event).await?;
             } else {
-                buffered_events.push(event);
+                buffered_events.push(stamp_log_line(&event, Utc::now()));
             }
         }
 
@@ -185,7 +314,9 @@ impl Worker {
                 .context("failed to update metadata before invocation")?;
         }
 
-        let result_file = NamedTempFile::new_in(&self.config.store_root)
+        let result_file = tempfile::Builder::new()
+            .prefix(&format!("{RESULT_TEMP_PREFIX}{}-", std::process::id()))
+            .tempfile_in(&self.config.store_root)
             .context("failed to create temporary result file")?;
         let result_path = result_file.into_temp_path();
 
@@ -195,6 +326,10 @@ impl Worker {
         command.arg("--json");
         command.arg("--output-last-message");
         command.arg(&result_path);
+        command.args(&self.config.tasks_config.exec_args);
+        let exec = self.config.exec.clone().unwrap_or_default();
+        command.args(&exec.args);
+        command.envs(&exec.env);
 
         if let Some(dir) = &self.config.working_dir {
             command.arg("--cd");
@@ -203,10 +338,17 @@ impl Worker {
         if let Some(home) = &codex_home {
             command.env("CODEX_HOME", home);
         }
+        match &self.session {
+            Some(session) => command.env(PARENT_ENV_VAR, &session.thread_id),
+            None => command.env(PARENT_ENV_VAR, &self.config.launch_id),
+        };
 
         match (&self.session, kind) {
             (None, InvocationKind::Initial) => {
-                command.arg(&prompt);
+                match &self.config.preamble {
+                    Some(preamble) => command.arg(format!("{preamble}{prompt}")),
+                    None => command.arg(&prompt),
+                };
             }
             (Some(session), InvocationKind::Resume) => {
                 command.arg("resume");
@@ -240,23 +382,46 @@ impl Worker {
             .context("failed to capture stderr of `codex exec`")?;
 
         if let Some(session) = self.session.as_mut() {
+            // A marker left by a stop that raced the previous invocation's exit is stale.
+            session.paths.take_stop_request()?;
             session.paths.write_pid(child_pid as i32)?;
             session
                 .paths
                 .update_metadata(|metadata| metadata.set_state(TaskState::Running))?;
+            self.run_start_hooks();
         } else {
             pending_pid = Some(child_pid as i32);
         }
 
         let mut stdout_lines = BufReader::new(stdout).lines();
         let mut stderr_lines = BufReader::new(stderr).lines();
-        let wait_handle = tokio::spawn(async move { child.wait().await });
 
         let mut stdout_done = false;
         let mut stderr_done = false;
+        let timeout = exec.timeout_secs.map(Duration::from_secs);
+        let deadline = tokio::time::sleep(timeout.unwrap_or(Duration::MAX));
+        tokio::pin!(deadline);
+        let mut timed_out = false;
 
-        loop {
+        while !(stdout_done && stderr_done) {
             tokio::select! {
+                _ = &mut deadline, if timeout.is_some() && !timed_out => {
+                    timed_out = true;
+                    let message = format!(
+                        "invocation timed out after {}",
+                        format_duration(timeout.unwrap_or_default())
+                    );
+                    let timeout_event = json!({ "type": "error", "message": message }).to_string();
+                    failures.push(message);
+                    if let Some(session) = self.session.as_mut() {
+                        session.write_event_line(&timeout_event).await?;
+                    } else {
+                        buffered_events.push(stamp_log_line(&timeout_event, Utc::now()));
+                    }
+                    child
+                        .start_kill()
+                        .context("failed to kill timed out `codex exec`")?;
+                }
                 line = stdout_lines.next_line(), if !stdout_done => {
                     match line {
                         Ok(Some(content)) => {
@@ -264,6 +429,7 @@ impl Worker {
                                         &mut buffered_events,
                                         &mut pending_pid,
                                         &mut pending_prompt,
+                                        &mut failures,
                                         &content,
                                     ).await?;
                         }
@@ -278,45 +444,37 @@ impl Worker {
                                 "type": "stderr",
                                 "message": content,
                             }).to_string();
+                            failures.push(content);
                             if let Some(session) = self.session.as_mut() {
                                 session.write_event_line(&stderr_event).await?;
                             } else {
-                                buffered_events.push(stderr_event);
+                                buffered_events.push(stamp_log_line(&stderr_event, Utc::now()));
                             }
                         }
                         Ok(None) => stderr_done = true,
                         Err(err) => return Err(err).context("failed to read stderr from `codex exec`"),
                     }
                 }
-                else => {
-                    if stdout_done && stderr_done {
-                        break;
-                    }
-                }
             }
         }
 
-        let status = wait_handle
+        // Stand in for `codex exec` before reaping it: once it is reaped its pid no longer
+        // exists, and a RUNNING task without a live pid would read as DIED until the final
+        // state is recorded below.
+        if let Some(session) = self.session.as_mut() {
+            session.paths.write_pid(std::process::id() as i32)?;
+        }
+        let status = child
+            .wait()
             .await
-            .context("failed to join exec child task")?
             .context("`codex exec` terminated unexpectedly")?;
 
-        if status.success() {
-            if let Some(session) = self.session.as_mut() {
-                session
-                    .paths
-                    .update_metadata(|metadata| metadata.set_state(TaskState::Stopped))?;
-            }
-        } else if let Some(session) = self.session.as_mut() {
-            session
-                .paths
-                .update_metadata(|metadata| metadata.set_state(TaskState::Died))?;
-        }
-
+        // Record the result first so it is in place once the task reads as finished. The file
+        // is created up front, so it stays empty when `codex exec` was stopped before answering.
         if result_path.exists() {
             let message =
                 fs::read_to_string(&result_path).context("failed to read result output")?;
-            if let Some(session) = self.session.as_mut() {
+            if let Some(session) = self.session.as_mut().filter(|_| !message.is_empty()) {
                 session.record_last_result(&message).await?;
             }
             result_path
@@ -324,7 +482,33 @@ impl Worker {
                 .context("failed to remove temporary result file")?;
         }
 
-        Ok(())
+        // `stop` leaves a marker before it signals `codex exec`; any other signal or failure
+        // means the task died.
+        let stop_requested = match self.session.as_ref() {
+            Some(session) => session.paths.take_stop_request()?,
+            None => false,
+        };
+        let stopped = status.success() || stop_requested;
+        let final_state = if stopped {
+            TaskState::Stopped
+        } else {
+            TaskState::Died
+        };
+        if let Some(session) = self.session.as_mut() {
+            session
+                .paths
+                .update_metadata(|metadata| metadata.set_state(final_state))?;
+        }
+        self.log_hooks(false).await;
+
+        if status.success() {
+            Ok(InvocationOutcome::Succeeded)
+        } else {
+            Ok(InvocationOutcome::Failed {
+                transient: !stop_requested && is_transient_failure(status.signal(), &failures),
+                reason: failures.pop(),
+            })
+        }
     }
 
     async fn handle_stdout_line(
@@ -332,19 +516,25 @@ impl Worker {
         buffered_events: &mut Vec<String>,
         pending_pid: &mut Option<i32>,
         pending_prompt: &mut Option<String>,
+        failures: &mut Vec<String>,
         line: &str,
     ) -> Result<()> {
         let value: Value = match serde_json::from_str(line) {
             Ok(val) => val,
             Err(_) => return Ok(()),
         };
+        failures.extend(failure_message(&value));
 
         if let Some(session) = self.session.as_mut() {
             session.write_event_line(line).await?;
+            if value.get("type").and_then(Value::as_str) == Some("turn.completed") {
+                self.spawn_hooks(HookEvent::OnTurnCompleted);
+            }
+            self.log_hooks(false).await;
             return Ok(());
         }
 
-        buffered_events.push(line.to_string());
+        buffered_events.push(stamp_log_line(line, Utc::now()));
 
         if let Some(thread_id) = extract_thread_id(&value) {
             self.initialize_session(thread_id, buffered_events, pending_pid, pending_prompt)
@@ -376,6 +566,17 @@ impl Worker {
                 meta.initial_prompt = Some(prompt_text.clone());
                 meta.last_prompt = Some(prompt_text.clone());
             }
+            if let Some((forked_from, fork_point)) = self.config.fork.clone() {
+                meta.forked_from = Some(forked_from);
+                meta.fork_point = Some(fork_point);
+            }
+            meta.parent_id = self.config.parent_id.clone();
+            meta.retry_policy = self.config.retry_policy;
+            meta.tags = self.config.tags.clone();
+            meta.launch_id = Some(self.config.launch_id.clone());
+            meta.hooks = self.config.hooks.clone();
+            meta.preset = self.config.preset.clone();
+            meta.exec = self.config.exec.clone();
             meta
         };
         metadata.set_state(TaskState::Running);
@@ -421,11 +622,65 @@ impl Worker {
 
         *pending_prompt = None;
         self.session = Some(session);
+        self.run_start_hooks();
         Ok(())
     }
 
+    fn run_start_hooks(&mut self) {
+        if !self.start_hooks_run {
+            self.start_hooks_run = true;
+            self.spawn_hooks(HookEvent::OnStart);
+        }
+    }
+
+    /// Runs the hooks and webhooks for `event` in the background so slow hooks or endpoints
+    /// never hold up the task.
+    fn spawn_hooks(&mut self, event: HookEvent) {
+        let Some(session) = self.session.as_ref() else {
+            return;
+        };
+        let store = self.store.clone();
+        let paths = session.paths.clone();
+        let tasks_config = self.config.tasks_config.clone();
+        let error = self
+            .last_failure
+            .clone()
+            .filter(|_| event == HookEvent::OnDied);
+        self.pending_hooks
+            .push(tokio::task::spawn_blocking(move || {
+                let runs = run_hooks(&store, &paths, event, &tasks_config);
+                notify_webhooks(&store, &paths, event, error.as_deref(), &tasks_config, true);
+                runs
+            }));
+    }
+
+    /// Writes the outcome of finished hooks to the task log; with `wait`, waits for all of them.
+    async fn log_hooks(&mut self, wait: bool) {
+        for handle in std::mem::take(&mut self.pending_hooks) {
+            if !wait && !handle.is_finished() {
+                self.pending_hooks.push(handle);
+                continue;
+            }
+            let runs = match handle.await {
+                Ok(runs) => runs,
+                Err(err) => {
+                    eprintln!("failed to run hooks: {err}");
+                    continue;
+                }
+            };
+            let Some(session) = self.session.as_mut() else {
+                continue;
+            };
+            for run in runs {
+                if let Err(err) = session.write_event_line(&run.to_event()).await {
+                    eprintln!("failed to log hook for task {}: {err:#}", session.thread_id);
+                }
+            }
+        }
+    }
+
     async fn finalize(mut self) -> Result<()> {
-        if let Some(mut session) = self.session.take() {
+        if let Some(session) = self.session.as_mut() {
             if let Err(err) = session.flush().await {
                 eprintln!(
                     "failed to flush log for task {}: {err:#}",
@@ -438,7 +693,26 @@ impl Worker {
                     session.thread_id
                 );
             }
+            // This worker's slot is free now; start whatever the concurrency limit held back.
+            // Workers that never reported a thread id skip this: whoever launched them may be
+            // holding the queue lock while it waits for that id.
+            let service =
+                TaskService::new(self.store.clone(), self.config.tasks_config.clone(), false);
+            match tokio::task::spawn_blocking(move || service.admit_queued()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => eprintln!("failed to start queued tasks: {err:#}"),
+                Err(err) => eprintln!("failed to start queued tasks: {err}"),
+            }
+
+            // The final hooks run once the task has settled, so they see its final state.
+            match session.paths.read_metadata().map(|metadata| metadata.state) {
+                Ok(TaskState::Stopped) => self.spawn_hooks(HookEvent::OnStopped),
+                Ok(TaskState::Died) => self.spawn_hooks(HookEvent::OnDied),
+                Ok(_) => {}
+                Err(err) => eprintln!("failed to read final state for hooks: {err:#}"),
+            }
         }
+        self.log_hooks(true).await;
         Ok(())
     }
 }
@@ -462,7 +736,9 @@ impl ActiveSession {
         Self::new(thread_id, paths, log)
     }
 
+    /// Appends an event, stamped with the current time unless it was stamped when received.
     async fn write_event_line(&mut self, line: &str) -> io::Result<()> {
+        let line = stamp_log_line(line, Utc::now());
         self.log.write_all(line.as_bytes()).await?;
         self.log.write_all(b"\n").await?;
         self.log.flush().await
@@ -507,6 +783,20 @@ fn user_message_event(prompt: &str) -> Option<String> {
     Some(value.to_string())
 }
 
+/// Records the git state of the working directory so `diff` can show what the invocation changed.
+fn workspace_snapshot_event(dir: &Path) -> Option<String> {
+    let snapshot = match workspace::capture(dir) {
+        Ok(snapshot) => snapshot?,
+        Err(err) => {
+            eprintln!("warning: failed to snapshot {}: {err:#}", dir.display());
+            return None;
+        }
+    };
+    let mut value = serde_json::to_value(snapshot).ok()?;
+    value["type"] = Value::String(WORKSPACE_SNAPSHOT_EVENT.to_string());
+    Some(value.to_string())
+}
+
 #[derive(Debug, Clone, Serialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 enum UserPromptKind {
@@ -531,6 +821,17 @@ impl From<&str> for UserPromptKind {
     }
 }
 
+/// How a `codex exec` invocation ended.
+enum InvocationOutcome {
+    Succeeded,
+    Failed {
+        /// Whether the failure looks temporary enough to retry.
+        transient: bool,
+        /// Last error reported by `codex exec`, if any.
+        reason: Option<String>,
+    },
+}
+
 enum InvocationKind {
     Initial,
     Resume,
@@ -3,7 +3,9 @@ use std::process::{Child, Command, Stdio};
 
 use anyhow::{Context, Result};
 
-use super::child::{PROMPT_ENV_VAR, TITLE_ENV_VAR};
+use super::child::{PREAMBLE_ENV_VAR, PROMPT_ENV_VAR, TITLE_ENV_VAR};
+use crate::tasks::config::CONFIG_ENV_VAR;
+use crate::tasks::{ExecOptions, ForkPoint, HookEvent, Hooks, RetryPolicy, TaskId};
 
 /// Parameters required to spawn a detached worker process.
 #[derive(Debug)]
@@ -12,9 +14,30 @@ pub struct WorkerLaunchRequest {
     pub task_id: Option<String>,
     pub title: Option<String>,
     pub prompt: String,
+    /// Context sent to Codex ahead of the prompt on the initial invocation only; it is not
+    /// recorded as the task's prompt.
+    pub preamble: Option<String>,
     pub executable: Option<PathBuf>,
     pub config_path: Option<PathBuf>,
     pub working_directory: Option<PathBuf>,
+    /// Lineage recorded on the new task when it is created as a fork.
+    pub fork: Option<(TaskId, ForkPoint)>,
+    /// Parent recorded on the new task.
+    pub parent_id: Option<TaskId>,
+    /// Retry policy recorded on the new task.
+    pub retry_policy: Option<RetryPolicy>,
+    /// Labels recorded on the new task.
+    pub tags: Vec<String>,
+    /// Launch id the new task should use, so ids handed out while it was queued keep resolving.
+    pub launch_id: Option<String>,
+    /// Lifecycle hooks recorded on the new task.
+    pub hooks: Option<Hooks>,
+    /// Configuration preset recorded on the new task.
+    pub preset: Option<String>,
+    /// `codex exec` settings recorded on the new task.
+    pub exec: Option<ExecOptions>,
+    /// `codex-tasks` configuration file the worker should read, if any.
+    pub tasks_config: Option<PathBuf>,
 }
 
 impl WorkerLaunchRequest {
@@ -25,9 +48,19 @@ impl WorkerLaunchRequest {
             task_id: None,
             title: None,
             prompt,
+            preamble: None,
             executable: None,
             config_path: None,
             working_directory: None,
+            fork: None,
+            parent_id: None,
+            retry_policy: None,
+            tags: Vec::new(),
+            launch_id: None,
+            hooks: None,
+            preset: None,
+            exec: None,
+            tasks_config: None,
         }
     }
 }
@@ -39,9 +72,19 @@ pub fn spawn_worker(request: WorkerLaunchRequest) -> Result<Child> {
         task_id,
         title,
         prompt,
+        preamble,
         executable,
         config_path,
         working_directory,
+        fork,
+        parent_id,
+        retry_policy,
+        tags,
+        launch_id,
+        hooks,
+        preset,
+        exec,
+        tasks_config,
     } = request;
 
     let exe = match executable {
@@ -63,6 +106,13 @@ pub fn spawn_worker(request: WorkerLaunchRequest) -> Result<Child> {
     }
 
     command.env(PROMPT_ENV_VAR, &prompt);
+    match preamble {
+        Some(preamble) => command.env(PREAMBLE_ENV_VAR, preamble),
+        None => command.env_remove(PREAMBLE_ENV_VAR),
+    };
+    if let Some(path) = &tasks_config {
+        command.env(CONFIG_ENV_VAR, path);
+    }
 
     if let Some(config_path) = config_path {
         command.arg("--config-path");
@@ -74,6 +124,70 @@ pub fn spawn_worker(request: WorkerLaunchRequest) -> Result<Child> {
         command.arg(working_directory);
     }
 
+    if let Some((forked_from, fork_point)) = fork {
+        command.arg("--forked-from");
+        command.arg(forked_from);
+        command.arg("--fork-invocation");
+        command.arg(fork_point.invocation.to_string());
+        command.arg("--fork-time");
+        command.arg(fork_point.at.to_rfc3339());
+    }
+
+    if let Some(parent_id) = parent_id {
+        command.arg("--parent-id");
+        command.arg(parent_id);
+    }
+
+    if let Some(policy) = retry_policy {
+        command.arg("--retries");
+        command.arg(policy.max_retries.to_string());
+        command.arg("--retry-backoff-secs");
+        command.arg(policy.backoff_secs.to_string());
+    }
+
+    for tag in tags {
+        command.arg("--tag");
+        command.arg(tag);
+    }
+
+    if let Some(launch_id) = launch_id {
+        command.arg("--launch-id");
+        command.arg(launch_id);
+    }
+
+    if let Some(hooks) = hooks {
+        for event in HookEvent::ALL {
+            if let Some(hook) = hooks.command(event) {
+                command.arg("--hook");
+                command.arg(format!("{}={hook}", event.as_str()));
+            }
+        }
+        if let Some(timeout_secs) = hooks.timeout_secs {
+            command.arg("--hook-timeout-secs");
+            command.arg(timeout_secs.to_string());
+        }
+    }
+
+    if let Some(preset) = preset {
+        command.arg("--preset");
+        command.arg(preset);
+    }
+
+    if let Some(exec) = exec {
+        for arg in exec.args {
+            // `=` keeps arguments that start with `-` from being read as worker flags.
+            command.arg(format!("--exec-arg={arg}"));
+        }
+        for (name, value) in exec.env {
+            command.arg("--env");
+            command.arg(format!("{name}={value}"));
+        }
+        if let Some(timeout_secs) = exec.timeout_secs {
+            command.arg("--timeout-secs");
+            command.arg(timeout_secs.to_string());
+        }
+    }
+
     command.stdin(Stdio::null());
     command.stdout(Stdio::piped());
     command.stderr(Stdio::null());
@@ -2,6 +2,7 @@ use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::ffi::{CString, OsString};
 use std::fs;
+use std::io::{BufRead, BufReader};
 use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
 use std::process::{Command as StdCommand, Stdio};
@@ -607,6 +608,7 @@ fn send_appends_prompt_to_log() {
         .map(|line| serde_json::from_str(line).expect("valid json line"))
         .collect();
 
+    assert!(events.iter().all(|event| event["timestamp"].is_string()));
     assert!(
         events.iter().any(|event| {
             event["type"] == "user_message" && event["message"] == "second prompt"
@@ -664,6 +666,81 @@ fn stop_all_stops_running_tasks() {
     env.wait_for_condition(&second, |value| value["state"] == "STOPPED");
 }
 
+#[test]
+fn externally_terminated_task_is_reported_as_died() {
+    let env = IntegrationTestEnv::with_delay(5000);
+    let task_id = env.start_task("Terminated", "prompt");
+    let pid = env.wait_for_pid(&task_id);
+    env.wait_for_condition(&task_id, |value| value["state"] == "RUNNING");
+
+    let kill_result = unsafe { libc::kill(pid, libc::SIGTERM) };
+    assert_eq!(kill_result, 0, "failed to send SIGTERM to codex");
+
+    env.wait_for_condition(&task_id, |value| value["state"] == "DIED");
+    env.command()
+        .args(["wait", "--fail-on-died", &task_id])
+        .assert()
+        .code(3);
+    assert!(!env.tasks_root().join(&task_id).join("task.stop").exists());
+}
+
+#[test]
+fn stop_leaves_the_process_launching_a_task_alone() {
+    let tmp = tempdir().expect("tempdir");
+    let task_id = "launch-123";
+    let task_dir = tmp.path().join(".codex").join("tasks").join(task_id);
+    fs::create_dir_all(&task_dir).expect("task dir");
+    let mut launcher = StdCommand::new("sleep")
+        .arg("5")
+        .spawn()
+        .expect("spawn sleep");
+    let metadata = json!({
+        "id": task_id,
+        "launch_id": task_id,
+        "state": "RUNNING",
+        "reserved": {
+            "launcher_pid": launcher.id(),
+            "reserved_at": "2024-05-03T06:07:08Z"
+        },
+        "created_at": "2024-05-03T06:07:08Z",
+        "updated_at": "2024-05-03T06:07:08Z"
+    });
+    fs::write(task_dir.join("task.json"), metadata.to_string()).expect("metadata");
+
+    let stop = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+        cmd.env("HOME", tmp.path()).arg("stop").args(args).assert()
+    };
+    stop(&[task_id]).failure().stderr(predicates::str::contains(
+        "task launch-123 is still starting",
+    ));
+    stop(&["--all"])
+        .success()
+        .stdout(predicates::str::contains("No running tasks to stop."));
+    assert!(
+        launcher.try_wait().expect("query launcher").is_none(),
+        "the launching process was signalled"
+    );
+    let status = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+        let output = cmd
+            .env("HOME", tmp.path())
+            .arg("status")
+            .args(args)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        serde_json::from_slice::<Value>(&output).expect("status json")
+    };
+    assert_eq!(status(&["--json", task_id])["state"], json!("RUNNING"));
+
+    let _ = launcher.kill();
+    let _ = launcher.wait();
+    assert_eq!(status(&["--json", task_id])["state"], json!("DIED"));
+}
+
 #[test]
 fn stop_all_reports_when_no_running_tasks_found() {
     let tmp = tempdir().expect("tempdir");
@@ -1024,6 +1101,65 @@ impl IntegrationTestEnv {
     }
 }
 
+#[test]
+fn wait_and_ls_select_tasks_by_tag_and_title() {
+    let home = tempdir().expect("tempdir");
+    let tasks_dir = home.path().join(".codex").join("tasks");
+    let timestamp = Utc::now().to_rfc3339();
+    for (id, title, state, tags) in [
+        ("task-nightly", "Nightly build", "STOPPED", json!(["ci"])),
+        (
+            "task-broken",
+            "Release build",
+            "DIED",
+            json!(["ci", "release"]),
+        ),
+        ("task-notes", "Nightly notes", "STOPPED", json!([])),
+    ] {
+        fs::create_dir_all(tasks_dir.join(id)).expect("task directory");
+        let payload = json!({
+            "id": id,
+            "title": title,
+            "state": state,
+            "tags": tags,
+            "created_at": timestamp,
+            "updated_at": timestamp,
+        });
+        fs::write(
+            tasks_dir.join(id).join("task.json"),
+            serde_json::to_string_pretty(&payload).expect("serialize metadata"),
+        )
+        .expect("write metadata");
+    }
+    let run = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+        cmd.env("HOME", home.path())
+            .args(args)
+            .timeout(Duration::from_secs(10))
+            .assert()
+    };
+
+    run(&["wait", "--tag", "ci", "--title", "nightly"])
+        .success()
+        .stdout("task-nightly\tSTOPPED\n");
+    run(&["wait", "--fail-on-died", "--tag", "ci"])
+        .code(3)
+        .stdout(predicates::str::contains("task-broken\tDIED"));
+    run(&["wait", "--state", "STOPPED", "--title", "NIGHTLY", "--json"])
+        .success()
+        .stdout(predicates::str::contains("task-notes"));
+    run(&["wait", "--tag", "ci", "task-nightly"]).failure();
+
+    let output = run(&["ls", "--tag", "release", "--format", "jsonl"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let listing = String::from_utf8(output).expect("stdout utf8");
+    assert_eq!(listing.lines().count(), 1);
+    assert!(listing.contains("task-broken"));
+}
+
 fn write_metadata(tasks_dir: &Path, task_id: &str, state: &str) {
     let task_dir = tasks_dir.join(task_id);
     fs::create_dir_all(&task_dir).expect("task directory");
@@ -1681,6 +1817,148 @@ fn ls_supports_iso_time_format() {
     );
 }
 
+#[test]
+fn ls_supports_formats_columns_sort_and_limit() {
+    let home = tempdir().expect("tempdir");
+    let tasks_root = home.path().join(".codex").join("tasks");
+    write_metadata_with_timestamps(
+        &tasks_root,
+        "task-short",
+        "STOPPED",
+        "2024-05-01T12:00:00Z",
+        "2024-05-01T12:05:00Z",
+    );
+    write_metadata_with_timestamps(
+        &tasks_root,
+        "task-long",
+        "DIED",
+        "2024-05-01T10:00:00Z",
+        "2024-05-01T13:00:00Z",
+    );
+    let log_path = tasks_root.join("task-long").join("task.log");
+    fs::write(
+        &log_path,
+        concat!(
+            r#"{"type":"turn.completed","usage":{"input_tokens":100,"output_tokens":20}}"#,
+            "\n"
+        ),
+    )
+    .expect("write log");
+
+    let run = |args: &[&str]| -> String {
+        let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+        let assert = cmd
+            .env("HOME", home.path())
+            .env("TZ", "UTC")
+            .arg("ls")
+            .args(args)
+            .assert()
+            .success();
+        String::from_utf8(assert.get_output().stdout.clone()).expect("stdout utf8")
+    };
+
+    let json_output = run(&[
+        "--format",
+        "json",
+        "--columns",
+        "id,duration,tokens",
+        "--sort",
+        "duration",
+        "--desc",
+    ]);
+    let rows: Value = serde_json::from_str(&json_output).expect("json output");
+    assert_eq!(
+        rows,
+        json!([
+            {"id": "task-long", "duration_secs": 10800, "tokens": 120},
+            {"id": "task-short", "duration_secs": 300, "tokens": null},
+        ])
+    );
+
+    let jsonl_output = run(&["--format", "jsonl", "--sort", "created", "--limit", "1"]);
+    let lines: Vec<&str> = jsonl_output.lines().collect();
+    assert_eq!(lines.len(), 1, "expected one JSONL row:\n{jsonl_output}");
+    let first: Value = serde_json::from_str(lines[0]).expect("jsonl row");
+    assert_eq!(first["id"], "task-long");
+    assert_eq!(first["title"], "Example task");
+
+    let csv_output = run(&["--format", "csv", "--columns", "id,state", "--sort", "id"]);
+    assert_eq!(csv_output, "id,state\ntask-long,DIED\ntask-short,STOPPED\n");
+
+    let table_output = run(&["--columns", "id,duration", "--time-format", "relative"]);
+    assert!(
+        table_output.contains("Duration") && table_output.contains("3h"),
+        "expected duration column in table output:\n{table_output}"
+    );
+
+    let relative_output = run(&["--time-format", "relative"]);
+    assert!(
+        relative_output.contains("d ago"),
+        "expected relative timestamps:\n{relative_output}"
+    );
+}
+
+#[test]
+fn search_matches_transcripts_with_field_and_date_filters() {
+    let home = tempdir().expect("tempdir");
+    let tasks_root = home.path().join(".codex").join("tasks");
+    write_metadata_with_timestamps(
+        &tasks_root,
+        "task-search",
+        "STOPPED",
+        "2024-05-01T12:00:00Z",
+        "2024-05-01T13:00:00Z",
+    );
+    let events = [
+        json!({"type": "user_message", "message": "Why does build.rs fail?"}),
+        json!({"type": "item.completed", "item": {"type": "command_execution", "command": "cat build.rs", "aggregated_output": "fn main() {}", "exit_code": 0}}),
+        json!({"type": "item.completed", "item": {"type": "agent_message", "text": "build.rs is fine."}}),
+    ];
+    let log: Vec<String> = events.iter().map(|event| event.to_string()).collect();
+    fs::write(
+        tasks_root.join("task-search").join("task.log"),
+        log.join("\n"),
+    )
+    .expect("write log");
+
+    let run = |args: &[&str]| -> String {
+        let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+        let assert = cmd
+            .env("HOME", home.path())
+            .arg("search")
+            .args(args)
+            .assert()
+            .success();
+        String::from_utf8(assert.get_output().stdout.clone()).expect("stdout utf8")
+    };
+
+    let human = run(&["-F", "build.rs"]);
+    assert_eq!(
+        human,
+        "task-search #1 [prompts] Why does build.rs fail?\n\
+         task-search #1 [commands] cat build.rs\n\
+         task-search #1 [messages] build.rs is fine.\n"
+    );
+
+    let json_output = run(&["--json", "--in", "commands", "BUILD", "-i"]);
+    let hits: Value = serde_json::from_str(&json_output).expect("json output");
+    assert_eq!(
+        hits,
+        json!([{
+            "task_id": "task-search",
+            "title": "Example task",
+            "archived": false,
+            "invocation": 1,
+            "field": "commands",
+            "log_line": 2,
+            "text": "cat build.rs",
+        }])
+    );
+
+    let filtered = run(&["--since", "2024-06-01", "build"]);
+    assert_eq!(filtered, "No matches found.\n");
+}
+
 #[test]
 fn status_formats_timestamps_in_unix_style() {
     let temp = TempDir::new().expect("temp dir");
@@ -1828,3 +2106,1915 @@ fn log_forever_flag_waits_for_manual_interrupt() {
     child.kill().expect("kill log --forever");
     let _ = child.wait();
 }
+
+#[test]
+fn migrate_upgrades_legacy_tasks_in_store() {
+    let home = tempdir().expect("tempdir");
+    let task_root = home.path().join(".codex").join("tasks");
+    fs::create_dir_all(&task_root).expect("layout");
+
+    write_metadata(&task_root, "task-legacy", "IDLE");
+    fs::write(task_root.join("task-legacy").join("task.pipe"), "").expect("write pipe");
+
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    cmd.env("HOME", home.path())
+        .arg("migrate")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "Upgraded task task-legacy from schema v0 to v2.",
+        ))
+        .stdout(predicates::str::contains(
+            "Migrated 1 task(s); 0 already current.",
+        ));
+
+    let metadata: Value = serde_json::from_str(
+        &fs::read_to_string(task_root.join("task-legacy").join("task.json")).expect("read"),
+    )
+    .expect("metadata json");
+    assert_eq!(metadata["schema_version"], json!(2));
+    assert_eq!(metadata["state"], json!("STOPPED"));
+    assert!(!task_root.join("task-legacy").join("task.pipe").exists());
+
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    cmd.env("HOME", home.path())
+        .arg("migrate")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "Migrated 0 task(s); 1 already current.",
+        ));
+}
+
+#[test]
+fn doctor_quarantines_corrupt_tasks_and_listing_skips_them() {
+    let home = tempdir().expect("tempdir");
+    let task_root = home.path().join(".codex").join("tasks");
+    fs::create_dir_all(&task_root).expect("layout");
+
+    write_metadata(&task_root, "task-healthy", "STOPPED");
+    let corrupt_dir = task_root.join("task-corrupt");
+    fs::create_dir_all(&corrupt_dir).expect("task directory");
+    fs::write(corrupt_dir.join("task.json"), "{ truncated").expect("write metadata");
+
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    cmd.env("HOME", home.path())
+        .arg("ls")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("task-healthy"))
+        .stderr(predicates::str::contains("codex-tasks doctor"));
+
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    cmd.env("HOME", home.path())
+        .arg("doctor")
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("[corrupt-metadata]"))
+        .stderr(predicates::str::contains("rerun with --fix"));
+    assert!(corrupt_dir.exists());
+
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    cmd.env("HOME", home.path())
+        .args(["doctor", "--fix"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("quarantined to"));
+    assert!(!corrupt_dir.exists());
+    assert_eq!(
+        fs::read_dir(task_root.join("quarantine"))
+            .expect("quarantine")
+            .count(),
+        1
+    );
+
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    cmd.env("HOME", home.path())
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("No problems found in 1 task(s)."));
+}
+
+#[test]
+fn fork_copies_codex_session_and_records_lineage() {
+    let env = IntegrationTestEnv::new();
+    let source_id = "11111111-2222-3333-4444-555555555555";
+    write_metadata(&env.tasks_root(), source_id, "STOPPED");
+    fs::write(
+        env.tasks_root().join(source_id).join("task.log"),
+        format!(
+            "{}\n{}\n",
+            json!({"type": "user_message", "message": "first"}),
+            json!({"type": "user_message", "message": "second"}),
+        ),
+    )
+    .expect("write log");
+
+    let sessions = env
+        .home
+        .path()
+        .join(".codex")
+        .join("sessions")
+        .join("2025")
+        .join("01")
+        .join("02");
+    fs::create_dir_all(&sessions).expect("sessions dir");
+    fs::write(
+        sessions.join(format!("rollout-2025-01-02T03-04-05-{source_id}.jsonl")),
+        format!(
+            "{}\n",
+            json!({"type": "session_meta", "payload": {"id": source_id}})
+        ),
+    )
+    .expect("write rollout");
+
+    let assert = env
+        .command()
+        .args(["fork", "-t", "Alternative", source_id])
+        .assert()
+        .success();
+    let fork_id = String::from_utf8(assert.get_output().stdout.clone())
+        .expect("stdout utf8")
+        .trim()
+        .to_string();
+    assert_ne!(fork_id, source_id);
+
+    let copied =
+        fs::read_to_string(sessions.join(format!("rollout-2025-01-02T03-04-05-{fork_id}.jsonl")))
+            .expect("forked rollout");
+    assert!(copied.contains(&fork_id));
+
+    let status = env.status_json(&fork_id);
+    assert_eq!(status["state"], json!("STOPPED"));
+    assert_eq!(status["title"], json!("Alternative"));
+    assert_eq!(status["forked_from"], json!(source_id));
+    assert_eq!(status["fork_point"]["invocation"], json!(2));
+
+    env.command()
+        .args(["status", &fork_id])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!(
+            "Forked From: {source_id} (after prompt 2"
+        )));
+}
+
+#[test]
+fn fork_falls_back_to_transcript_preamble() {
+    let env = IntegrationTestEnv::new();
+    let source_id = env.start_task("Source", "original question");
+    env.wait_for_condition(&source_id, |value| value["state"] == json!("STOPPED"));
+
+    env.command()
+        .args(["fork", &source_id])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("provide a prompt"));
+
+    let assert = env
+        .command()
+        .args(["fork", &source_id, "what else?"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("task transcript"));
+    let fork_id = String::from_utf8(assert.get_output().stdout.clone())
+        .expect("stdout utf8")
+        .trim()
+        .to_string();
+    assert_ne!(fork_id, source_id);
+
+    let status = env.wait_for_condition(&fork_id, |value| value["state"] == json!("STOPPED"));
+    assert_eq!(status["title"], json!("Source"));
+    assert_eq!(status["forked_from"], json!(source_id));
+    assert_eq!(status["fork_point"]["invocation"], json!(1));
+    assert_eq!(status["last_prompt"], json!("what else?"));
+    let metadata: Value = serde_json::from_str(
+        &fs::read_to_string(env.tasks_root().join(&fork_id).join("task.json")).expect("metadata"),
+    )
+    .expect("metadata json");
+    assert_eq!(metadata["initial_prompt"], json!("what else?"));
+    let answer = status["last_result"].as_str().expect("last result");
+    assert!(answer.contains("response 1: original question"));
+    assert!(answer.ends_with("what else?"));
+}
+
+#[test]
+fn parent_links_drive_tree_listing_and_recursive_archive() {
+    let env = IntegrationTestEnv::new();
+    let parent = env.start_task("Parent", "plan the work");
+    env.wait_for_condition(&parent, |value| value["state"] == json!("STOPPED"));
+
+    let metadata: Value = serde_json::from_str(
+        &fs::read_to_string(env.tasks_root().join(&parent).join("task.json")).expect("metadata"),
+    )
+    .expect("metadata json");
+    let launch_id = metadata["launch_id"]
+        .as_str()
+        .expect("launch id")
+        .to_string();
+
+    env.command()
+        .args(["send", &parent, "keep going"])
+        .assert()
+        .success();
+    let record = env.home.path().join(format!("{parent}.parent-env"));
+    let start = Instant::now();
+    let exported = loop {
+        let contents = fs::read_to_string(&record).expect("parent env record");
+        if contents.lines().count() >= 2 || start.elapsed() > Duration::from_secs(10) {
+            break contents;
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    env.wait_for_condition(&parent, |value| value["state"] == json!("STOPPED"));
+    assert_eq!(
+        exported.lines().collect::<Vec<_>>(),
+        vec![launch_id.as_str(), parent.as_str()]
+    );
+
+    let mut cmd = env.command();
+    cmd.env("CODEX_TASKS_PARENT_ID", &launch_id)
+        .args(["start", "--title", "Child", "subtask"]);
+    let child = String::from_utf8(cmd.assert().success().get_output().stdout.clone())
+        .expect("stdout utf8")
+        .trim()
+        .to_string();
+    let status = env.wait_for_condition(&child, |value| value["state"] == json!("STOPPED"));
+    assert_eq!(status["parent_id"], json!(parent));
+
+    let mut cmd = env.command();
+    cmd.args(["start", "--parent", &child, "--title", "Grandchild", "leaf"]);
+    let grandchild = String::from_utf8(cmd.assert().success().get_output().stdout.clone())
+        .expect("stdout utf8")
+        .trim()
+        .to_string();
+    env.wait_for_condition(&grandchild, |value| value["state"] == json!("STOPPED"));
+
+    let output = env
+        .command()
+        .args(["ls", "--tree"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let listing = String::from_utf8(output).expect("stdout utf8");
+    let lines: Vec<&str> = listing.lines().collect();
+    assert!(
+        lines[1].starts_with(&parent),
+        "unexpected listing:\n{listing}"
+    );
+    assert!(lines[2].starts_with(&format!("└─ {child}")));
+    assert!(lines[3].starts_with(&format!("   └─ {grandchild}")));
+
+    env.command()
+        .args(["stop", "--recursive", &launch_id])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!(
+            "Stopped 0 of 3 task(s) in the tree of {launch_id}."
+        )));
+
+    env.command()
+        .args(["archive", "--recursive", &launch_id])
+        .assert()
+        .success();
+    for id in [&parent, &child, &grandchild] {
+        assert_eq!(env.status_json(id)["state"], json!("ARCHIVED"));
+        assert!(!env.tasks_root().join(id).exists());
+    }
+}
+
+#[test]
+fn watch_once_prints_task_table_with_log_summary() {
+    let home = tempdir().expect("tempdir");
+    let task_root = home.path().join(".codex").join("tasks");
+    fs::create_dir_all(&task_root).expect("layout");
+    write_metadata_with_timestamps(
+        &task_root,
+        "task-watch",
+        "STOPPED",
+        "2024-01-01T00:00:00Z",
+        "2024-01-01T00:05:00Z",
+    );
+    fs::write(
+        task_root.join("task-watch").join("task.log"),
+        format!(
+            "{}\n{}\n{}\n",
+            json!({"type": "user_message", "message": "hello"}),
+            json!({"type": "item.completed", "item": {"type": "agent_message", "text": "all done"}}),
+            json!({"type": "turn.completed", "usage": {"input_tokens": 40, "output_tokens": 2}}),
+        ),
+    )
+    .expect("write log");
+
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    let output = cmd
+        .env("HOME", home.path())
+        .args(["top", "--once"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let text = String::from_utf8(output).expect("stdout utf8");
+    let row = text
+        .lines()
+        .find(|line| line.starts_with("task-watch"))
+        .unwrap_or_else(|| panic!("missing task row:\n{text}"));
+    for expected in ["Example task", "STOPPED", "idle", "42", "all done"] {
+        assert!(row.contains(expected), "row {row:?} lacks {expected}");
+    }
+}
+
+#[test]
+fn diff_reports_workspace_changes_per_invocation() {
+    let env = IntegrationTestEnv::new();
+    let repo = env.home.path().join("repo");
+    fs::create_dir_all(&repo).expect("repo dir");
+    let git = |args: &[&str]| {
+        let status = StdCommand::new("git")
+            .arg("-C")
+            .arg(&repo)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()
+            .expect("run git");
+        assert!(status.success(), "git {args:?} failed");
+    };
+    git(&["init", "--quiet"]);
+    fs::write(repo.join("tracked.txt"), "one\n").expect("write tracked file");
+    git(&["add", "tracked.txt"]);
+    git(&["commit", "--quiet", "-m", "initial"]);
+
+    let mut cmd = env.command();
+    cmd.args(["start", "--title", "Diff", "--working-dir"])
+        .arg(&repo)
+        .arg("edit the file");
+    let task_id = String::from_utf8(cmd.assert().success().get_output().stdout.clone())
+        .expect("stdout utf8")
+        .trim()
+        .to_string();
+    env.wait_for_condition(&task_id, |value| value["state"] == json!("STOPPED"));
+    fs::write(repo.join("tracked.txt"), "two\n").expect("edit tracked file");
+
+    let diff = |args: &[&str]| -> String {
+        let output = env
+            .command()
+            .arg("diff")
+            .args(args)
+            .arg(&task_id)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(output).expect("stdout utf8")
+    };
+    let patch = diff(&[]);
+    assert!(patch.contains("-one\n+two"), "unexpected patch:\n{patch}");
+
+    env.command()
+        .args(["send", &task_id, "add a file"])
+        .assert()
+        .success();
+    let log_path = env.tasks_root().join(&task_id).join("task.log");
+    let start = Instant::now();
+    while fs::read_to_string(&log_path)
+        .expect("task log")
+        .matches("\"workspace_snapshot\"")
+        .count()
+        < 2
+    {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "second snapshot was not recorded"
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+    env.wait_for_condition(&task_id, |value| value["state"] == json!("STOPPED"));
+    fs::write(repo.join("new.txt"), "fresh\n").expect("write new file");
+
+    let first = diff(&["--invocation", "1"]);
+    assert!(
+        first.contains("tracked.txt") && !first.contains("new.txt"),
+        "invocation 1:\n{first}"
+    );
+    let second = diff(&["--invocation", "2"]);
+    assert!(
+        second.contains("+++ b/new.txt") && !second.contains("tracked.txt"),
+        "invocation 2:\n{second}"
+    );
+    let all = diff(&[]);
+    assert!(
+        all.contains("tracked.txt") && all.contains("new.txt"),
+        "full diff:\n{all}"
+    );
+
+    env.command()
+        .args(["diff", "--invocation", "3", &task_id])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("between 1 and 2"));
+}
+
+#[test]
+fn result_prints_answers_code_blocks_and_json_payloads() {
+    let env = IntegrationTestEnv::new();
+    let task_id = env.start_task("Result", "\n```rust\nfn answer() {}\n```");
+
+    let result = |args: &[&str]| {
+        env.command()
+            .arg("result")
+            .args(args)
+            .arg(&task_id)
+            .assert()
+    };
+    let output = result(&["--wait"]).success().get_output().stdout.clone();
+    assert_eq!(
+        String::from_utf8(output).expect("stdout utf8"),
+        "response 1: \n```rust\nfn answer() {}\n```\n"
+    );
+    result(&["--code-blocks", "--lang", "rust"])
+        .success()
+        .stdout("fn answer() {}\n");
+    result(&["--code-blocks", "--lang", "python"])
+        .failure()
+        .stderr(predicates::str::contains("no `python` code blocks"));
+
+    env.command()
+        .args(["send", &task_id, "\n```json\n{\"ok\": true}\n```"])
+        .assert()
+        .success();
+    env.wait_for_condition(&task_id, |value| {
+        value["state"] == json!("STOPPED")
+            && value["last_result"]
+                .as_str()
+                .is_some_and(|text| text.starts_with("response 2"))
+    });
+
+    let payload: Value =
+        serde_json::from_slice(&result(&["--json"]).success().get_output().stdout.clone())
+            .expect("json payload");
+    assert_eq!(payload, json!({"ok": true}));
+    result(&["--invocation", "1", "--code-blocks"])
+        .success()
+        .stdout("fn answer() {}\n");
+    result(&["--invocation", "3"])
+        .failure()
+        .stderr(predicates::str::contains("between 1 and 2"));
+}
+
+#[test]
+fn attach_sends_prompts_and_runs_slash_commands() {
+    let env = IntegrationTestEnv::new();
+    let task_id = env.start_task("Attach", "first question");
+    env.wait_for_condition(&task_id, |value| value["state"] == json!("STOPPED"));
+
+    let assert = env
+        .command()
+        .args(["attach", &task_id])
+        .write_stdin("\"\"\"\nsecond\nquestion\n\"\"\"\n/result\n/bogus\n")
+        .timeout(Duration::from_secs(30))
+        .assert()
+        .success();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).expect("stdout utf8");
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).expect("stderr utf8");
+
+    assert!(
+        stdout.contains("response 1: first question"),
+        "expected replayed transcript:\n{stdout}"
+    );
+    assert!(
+        stdout.contains("response 2: second\nquestion"),
+        "expected streamed answer to the multi-line prompt:\n{stdout}"
+    );
+    assert_eq!(
+        stdout.matches("response 2: second\nquestion").count(),
+        2,
+        "expected /result to print the latest answer:\n{stdout}"
+    );
+    assert!(
+        stderr.contains("Unknown command /bogus"),
+        "stderr:\n{stderr}"
+    );
+    assert!(
+        stderr.contains(&format!("Detached from task {task_id}.")),
+        "stderr:\n{stderr}"
+    );
+
+    let status = env.wait_for_condition(&task_id, |value| value["state"] == json!("STOPPED"));
+    assert_eq!(status["last_prompt"], json!("second\nquestion"));
+}
+
+#[test]
+fn wait_exit_codes_reflect_how_tasks_ended() {
+    let env = IntegrationTestEnv::with_delay(3_000);
+    let task_id = env.start_task("Wait", "slow work");
+
+    env.command()
+        .args(["wait", "--timeout", "1s", &task_id])
+        .assert()
+        .code(4)
+        .stderr(predicates::str::contains("Timed out"));
+
+    let output = env
+        .command()
+        .args(["wait", "--until", "stopped", "--json", &task_id])
+        .timeout(Duration::from_secs(30))
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: Value = serde_json::from_slice(&output).expect("wait json");
+    assert_eq!(report["outcome"], json!("reached"));
+    assert_eq!(report["tasks"][0]["state"], json!("STOPPED"));
+
+    write_metadata_with_timestamps(
+        &env.tasks_root(),
+        "dead-task",
+        "RUNNING",
+        "2024-01-01T00:00:00Z",
+        "2024-01-01T00:00:00Z",
+    );
+    let wait = |args: &[&str]| env.command().arg("wait").args(args).assert();
+    wait(&[&task_id, "dead-task"])
+        .success()
+        .stdout(predicates::str::contains("dead-task\tDIED"));
+    wait(&["--fail-on-died", "dead-task"]).code(3);
+    wait(&["--until", "STOPPED", &task_id, "dead-task"]).code(3);
+    wait(&["--until", "DIED", "dead-task"]).success();
+    wait(&["--until", "DIED", &task_id]).code(6);
+    wait(&["--any", "--until", "STOPPED", &task_id, "dead-task"]).success();
+    wait(&["missing-task"])
+        .code(5)
+        .stdout(predicates::str::contains("missing-task\tNOT FOUND"));
+}
+
+#[test]
+fn retry_policy_and_retry_command_recover_died_tasks() {
+    let env = IntegrationTestEnv::new();
+    let start = |title: &str, message: &str, prompt: &str| {
+        let assert = env
+            .command()
+            .env("FAKE_CODEX_FAIL_TIMES", "2")
+            .env("FAKE_CODEX_FAIL_MESSAGE", message)
+            .args([
+                "start",
+                "-t",
+                title,
+                "--retries",
+                "3",
+                "--retry-backoff",
+                "0s",
+            ])
+            .arg(prompt)
+            .assert()
+            .success();
+        String::from_utf8(assert.get_output().stdout.clone())
+            .expect("stdout utf8")
+            .trim()
+            .to_string()
+    };
+
+    let flaky = start("Flaky", "stream error: 429 Too Many Requests", "flaky");
+    let status = env.wait_for_condition(&flaky, |value| {
+        value["state"] == json!("STOPPED") && value["retry_count"] == json!(2)
+    });
+    assert_eq!(status["last_result"], json!("response 1: flaky"));
+    assert_eq!(status["retry_policy"]["max_retries"], json!(3));
+    env.command()
+        .args(["log", &flaky])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("RETRY: attempt 2 of 3 in 0s"));
+    env.command()
+        .args(["result", "--invocation", "2", &flaky])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("has 1 invocation(s)"));
+
+    fs::remove_file(env.home.path().join("failures.count")).expect("reset failure counter");
+    let broken = start("Broken", "error: unknown model 'gpt-9'", "fragile");
+    let status = env.wait_for_condition(&broken, |value| value["state"] == json!("DIED"));
+    assert_eq!(status["retry_count"], json!(0));
+
+    env.command().args(["retry", &broken]).assert().success();
+    let status = env.wait_for_condition(&broken, |value| value["state"] == json!("STOPPED"));
+    assert_eq!(status["last_result"], json!("response 1: fragile"));
+    assert_eq!(status["retry_count"], json!(1));
+
+    env.command()
+        .args(["retry", &broken])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("only DIED tasks can be retried"));
+
+    let timestamp = Utc::now().to_rfc3339();
+    let placeholder = env.tasks_root().join("launch-placeholder");
+    fs::create_dir_all(&placeholder).expect("placeholder directory");
+    fs::write(
+        placeholder.join("task.json"),
+        json!({
+            "id": "launch-placeholder",
+            "state": "DIED",
+            "launch_id": "launch-placeholder",
+            "last_prompt": "never ran",
+            "created_at": timestamp,
+            "updated_at": timestamp,
+        })
+        .to_string(),
+    )
+    .expect("write placeholder metadata");
+    env.command()
+        .args(["retry", "launch-placeholder"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "task launch-placeholder never started a thread; use `start` again",
+        ));
+}
+
+#[test]
+fn completions_offer_task_ids_per_subcommand() {
+    let env = IntegrationTestEnv::new();
+    let tasks_root = env.tasks_root();
+    let created_at = "2024-01-01T00:00:00Z";
+    write_metadata_with_timestamps(
+        &tasks_root,
+        "task-stopped",
+        "STOPPED",
+        created_at,
+        created_at,
+    );
+    write_metadata_with_timestamps(&tasks_root, "task-died", "DIED", created_at, created_at);
+    write_metadata_with_timestamps(&tasks_root, "task-old", "STOPPED", created_at, created_at);
+    env.command()
+        .args(["archive", "task-old"])
+        .assert()
+        .success();
+
+    let complete = |words: &[&str]| -> Vec<String> {
+        let assert = env
+            .command()
+            .arg("complete-task-ids")
+            .arg("codex-tasks")
+            .args(words)
+            .assert()
+            .success();
+        let mut ids: Vec<String> = String::from_utf8(assert.get_output().stdout.clone())
+            .expect("stdout utf8")
+            .lines()
+            .map(|line| line.split('\t').next().unwrap_or_default().to_string())
+            .collect();
+        ids.sort();
+        ids
+    };
+
+    assert_eq!(
+        complete(&["archive", ""]),
+        vec!["task-died", "task-stopped"]
+    );
+    assert_eq!(complete(&["send", "task-"]), vec!["task-stopped"]);
+    assert_eq!(complete(&["stop", ""]), Vec::<String>::new());
+    assert_eq!(
+        complete(&["log", "-n", "5", ""]),
+        vec!["task-died", "task-old", "task-stopped"]
+    );
+    assert_eq!(
+        complete(&["send", "task-stopped", ""]),
+        Vec::<String>::new()
+    );
+    assert_eq!(complete(&["status", "--j"]), Vec::<String>::new());
+
+    for shell in ["bash", "zsh", "fish"] {
+        env.command()
+            .args(["completions", shell])
+            .assert()
+            .success()
+            .stdout(predicates::str::contains("complete-task-ids"));
+    }
+}
+
+#[test]
+fn start_manifest_validates_and_launches_matrix_entries() {
+    let env = IntegrationTestEnv::new();
+    let manifest = env.home.path().join("tasks.toml");
+    fs::write(env.home.path().join("review.md"), "review {{area}}").expect("write template");
+    fs::write(
+        &manifest,
+        r#"
+[defaults]
+tags = ["batch"]
+
+[[task]]
+name = "review"
+title = "Review {{area}}"
+template = "review.md"
+working_dir = "work/{{area}}"
+tags = ["{{area}}"]
+matrix.area = ["api", "web"]
+
+[[task]]
+name = "summary"
+prompt = "summarize"
+"#,
+    )
+    .expect("write manifest");
+
+    let assert = env
+        .command()
+        .args(["start", "--validate", "--json", "--manifest"])
+        .arg(&manifest)
+        .assert()
+        .success();
+    let plan: Value = serde_json::from_slice(&assert.get_output().stdout).expect("plan json");
+    assert_eq!(plan["review[web]"]["prompt"], json!("review web"));
+    assert_eq!(plan["review[web]"]["tags"], json!(["batch", "web"]));
+    assert!(fs::read_dir(env.tasks_root()).map_or(true, |mut entries| entries.next().is_none()));
+
+    let assert = env
+        .command()
+        .args(["start", "--json", "--manifest"])
+        .arg(&manifest)
+        .assert()
+        .success();
+    let started: Value = serde_json::from_slice(&assert.get_output().stdout).expect("start json");
+    let ids = started.as_object().expect("map of entries");
+    assert_eq!(
+        ids.keys().collect::<Vec<_>>(),
+        ["review[api]", "review[web]", "summary"]
+    );
+
+    let api = ids["review[api]"].as_str().expect("task id");
+    let status = env.wait_for_condition(api, |value| value["state"] == json!("STOPPED"));
+    assert_eq!(status["title"], json!("Review api"));
+    assert_eq!(status["last_result"], json!("response 1: review api"));
+    assert_eq!(status["tags"], json!(["batch", "api"]));
+    assert!(
+        status["working_dir"]
+            .as_str()
+            .is_some_and(|dir| dir.ends_with("work/api"))
+    );
+
+    fs::write(&manifest, "[[task]]\nprompt = \"fix {{target}}\"\n").expect("rewrite manifest");
+    env.command()
+        .args(["start", "--validate", "--manifest"])
+        .arg(&manifest)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "unknown placeholder `{{target}}`",
+        ));
+}
+
+#[test]
+fn queue_limits_running_tasks_and_admits_by_priority() {
+    let env = IntegrationTestEnv::with_delay(1000);
+    env.command()
+        .args(["queue", "--max-running", "1"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Running: 0 of 1"));
+
+    let first = env.start_task("first", "one");
+    let second = env.start_task("second", "two");
+    let urgent = {
+        let assert = env
+            .command()
+            .args(["start", "--priority", "5", "--title", "urgent", "three"])
+            .assert()
+            .success()
+            .stderr(predicates::str::contains("QUEUED"));
+        String::from_utf8(assert.get_output().stdout.clone())
+            .expect("stdout utf8")
+            .trim()
+            .to_string()
+    };
+    let cancelled = env.start_task("cancelled", "four");
+    assert_eq!(env.status_json(&second)["state"], json!("QUEUED"));
+
+    let assert = env.command().args(["queue", "--json"]).assert().success();
+    let queue: Value = serde_json::from_slice(&assert.get_output().stdout).expect("queue json");
+    assert_eq!(queue["max_running"], json!(1));
+    assert_eq!(queue["running"], json!(1));
+    let order: Vec<&str> = queue["queued"]
+        .as_array()
+        .expect("queued array")
+        .iter()
+        .map(|entry| entry["id"].as_str().expect("id"))
+        .collect();
+    assert_eq!(order, [&urgent, &second, &cancelled]);
+
+    env.command()
+        .args(["stop", &cancelled])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("pending prompt was cancelled"));
+    env.command()
+        .args(["status", &cancelled])
+        .assert()
+        .failure();
+
+    env.wait_for_condition(&first, |value| value["state"] == json!("STOPPED"));
+    let urgent_status = env.wait_for_condition(&urgent, |value| value["state"] == json!("STOPPED"));
+    let second_status = env.wait_for_condition(&second, |value| value["state"] == json!("STOPPED"));
+    assert_eq!(urgent_status["last_result"], json!("response 1: three"));
+    assert_eq!(second_status["last_result"], json!("response 1: two"));
+    assert_ne!(second_status["id"], json!(second));
+    assert!(
+        urgent_status["created_at"].as_str().expect("created_at")
+            < second_status["created_at"].as_str().expect("created_at")
+    );
+
+    env.command()
+        .args(["queue"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("No queued tasks."));
+}
+
+#[test]
+fn queue_admits_work_left_behind_by_a_dead_worker() {
+    let env = IntegrationTestEnv::with_delay(3000);
+    env.command()
+        .args(["queue", "--max-running", "1"])
+        .assert()
+        .success();
+
+    let crashed = env.start_task("crashed", "one");
+    let waiting = env.start_task("waiting", "two");
+    assert_eq!(env.status_json(&waiting)["state"], json!("QUEUED"));
+
+    let pid = env.wait_for_pid(&crashed);
+    let kill_result = unsafe { libc::kill(pid, libc::SIGKILL) };
+    assert_eq!(kill_result, 0, "failed to send SIGKILL to worker");
+    let pid_path = env.tasks_root().join(&crashed).join("task.pid");
+    fs::remove_file(&pid_path).expect("remove pid after crash");
+    env.wait_for_condition(&crashed, |value| value["state"] == json!("DIED"));
+
+    env.command()
+        .args(["queue"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("No queued tasks."));
+    let status = env.wait_for_condition(&waiting, |value| value["state"] == json!("STOPPED"));
+    assert_eq!(status["last_result"], json!("response 1: two"));
+}
+
+#[test]
+fn scheduler_runs_due_schedules_and_skips_busy_tasks() {
+    let env = IntegrationTestEnv::with_delay(1500);
+    let task_id = env.start_task("long-lived", "initial");
+
+    let add = |args: &[&str]| -> String {
+        let assert = env
+            .command()
+            .args(["schedule", "add"])
+            .args(args)
+            .assert()
+            .success();
+        String::from_utf8(assert.get_output().stdout.clone())
+            .expect("stdout utf8")
+            .trim()
+            .to_string()
+    };
+    let audit = add(&["--cron", "0 2 * * *", "--task", &task_id, "audit deps"]);
+    let fresh = add(&[
+        "--cron",
+        "*/5 * * * *",
+        "--new",
+        "--title",
+        "nightly",
+        "--tag",
+        "audit",
+        "fresh audit",
+    ]);
+    env.command()
+        .args(["schedule", "add", "--cron", "61 * * * *", "--new", "bad"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("minute value `61`"));
+
+    let schedules_path = env.tasks_root().join("schedules.json");
+    let make_due = || {
+        let mut file: Value =
+            serde_json::from_str(&fs::read_to_string(&schedules_path).expect("read schedules"))
+                .expect("schedules json");
+        for schedule in file["schedules"].as_array_mut().expect("schedules") {
+            schedule["next_run"] = json!("2000-01-01T00:00:00Z");
+        }
+        fs::write(&schedules_path, file.to_string()).expect("write schedules");
+    };
+
+    make_due();
+    env.command()
+        .args(["scheduler", "run", "--once"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!(
+            "schedule {audit}: skipped"
+        )))
+        .stdout(predicates::str::contains(format!(
+            "schedule {fresh}: started"
+        )));
+
+    env.wait_for_condition(&task_id, |value| value["state"] == json!("STOPPED"));
+    make_due();
+    env.command()
+        .args(["scheduler", "run", "--once"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!(
+            "schedule {audit}: started"
+        )));
+    let status = env.wait_for_condition(&task_id, |value| {
+        value["last_result"] == json!("response 2: audit deps")
+    });
+    assert_eq!(status["state"], json!("STOPPED"));
+
+    let assert = env
+        .command()
+        .args(["schedule", "ls", "--json"])
+        .assert()
+        .success();
+    let schedules: Value = serde_json::from_slice(&assert.get_output().stdout).expect("ls json");
+    let audit_entry = schedules
+        .as_array()
+        .expect("schedule array")
+        .iter()
+        .find(|schedule| schedule["id"] == json!(audit))
+        .expect("audit schedule");
+    let outcomes: Vec<&Value> = audit_entry["history"]
+        .as_array()
+        .expect("history")
+        .iter()
+        .map(|run| &run["outcome"])
+        .collect();
+    assert_eq!(outcomes, [&json!("skipped"), &json!("started")]);
+    assert_ne!(audit_entry["next_run"], json!("2000-01-01T00:00:00Z"));
+
+    let fresh_entry = schedules
+        .as_array()
+        .expect("schedule array")
+        .iter()
+        .find(|schedule| schedule["id"] == json!(fresh))
+        .expect("fresh schedule");
+    let started = fresh_entry["history"][0]["task_id"]
+        .as_str()
+        .expect("started task id");
+    let started = env.wait_for_condition(started, |value| value["state"] == json!("STOPPED"));
+    assert_eq!(started["title"], json!("nightly"));
+    assert_eq!(started["tags"], json!(["audit"]));
+
+    env.command()
+        .args(["schedule", "rm", &fresh[..4]])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!(
+            "Schedule {fresh} removed."
+        )));
+    env.command()
+        .args(["schedule", "ls"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(&audit))
+        .stdout(predicates::str::contains(&fresh).not());
+}
+
+#[test]
+fn scheduler_disables_schedules_that_never_match_and_keeps_going() {
+    let env = IntegrationTestEnv::new();
+    let add = |prompt: &str| -> String {
+        let assert = env
+            .command()
+            .args(["schedule", "add", "--cron", "0 2 * * *", "--new", prompt])
+            .assert()
+            .success();
+        String::from_utf8(assert.get_output().stdout.clone())
+            .expect("stdout utf8")
+            .trim()
+            .to_string()
+    };
+    let broken = add("never");
+    let healthy = add("nightly");
+
+    let schedules_path = env.tasks_root().join("schedules.json");
+    let mut file: Value =
+        serde_json::from_str(&fs::read_to_string(&schedules_path).expect("read schedules"))
+            .expect("schedules json");
+    for schedule in file["schedules"].as_array_mut().expect("schedules") {
+        schedule["next_run"] = json!("2000-01-01T00:00:00Z");
+        if schedule["id"] == json!(broken) {
+            schedule["cron"] = json!("0 0 31 2 *");
+        }
+    }
+    fs::write(&schedules_path, file.to_string()).expect("write schedules");
+
+    let run_once = || {
+        env.command()
+            .args(["scheduler", "run", "--once"])
+            .assert()
+            .success()
+    };
+    run_once()
+        .stdout(predicates::str::contains(format!(
+            "schedule {broken}: failed: schedule {broken} never matches again; schedule disabled"
+        )))
+        .stdout(predicates::str::contains(format!(
+            "schedule {healthy}: started"
+        )));
+    run_once().stdout(predicates::str::contains(&broken).not());
+
+    env.command()
+        .args(["schedule", "ls"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("disabled"));
+}
+#[test]
+fn pipeline_runs_steps_after_dependencies_and_aborts_on_died() {
+    let env = IntegrationTestEnv::new();
+    let pipeline = env.home.path().join("pipeline.toml");
+    fs::write(
+        &pipeline,
+        r#"
+[[task]]
+name = "investigate"
+prompt = "find bug"
+
+[[task]]
+name = "implement"
+after = ["investigate"]
+prompt = "fix {{investigate.result}}"
+"#,
+    )
+    .expect("write pipeline");
+
+    env.command()
+        .args(["pipeline", "run", "--validate"])
+        .arg(&pipeline)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("implement (after investigate)"));
+    env.command()
+        .args(["start", "--manifest"])
+        .arg(&pipeline)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("codex-tasks pipeline run"));
+
+    let assert = env
+        .command()
+        .args(["pipeline", "run", "--json"])
+        .arg(&pipeline)
+        .assert()
+        .success();
+    let report: Value = serde_json::from_slice(&assert.get_output().stdout).expect("report json");
+    assert_eq!(report["status"], json!("succeeded"));
+    let steps = report["steps"].as_array().expect("steps");
+    assert_eq!(steps[1]["key"], json!("implement"));
+    assert_eq!(steps[1]["state"], json!("stopped"));
+    let implement = steps[1]["task_id"].as_str().expect("implement task id");
+    let status = env.status_json(implement);
+    assert_eq!(
+        status["last_result"],
+        json!("response 1: fix response 1: find bug")
+    );
+
+    fs::write(
+        &pipeline,
+        "[[task]]\nname = \"build\"\nprompt = \"build\"\n\
+         [[task]]\nname = \"deploy\"\nafter = [\"build\"]\nprompt = \"deploy\"\n",
+    )
+    .expect("rewrite pipeline");
+    env.command()
+        .env("FAKE_CODEX_FAIL_TIMES", "1")
+        .env(
+            "FAKE_CODEX_FAIL_MESSAGE",
+            "unexpected status 400 Bad Request",
+        )
+        .args(["pipeline", "run"])
+        .arg(&pipeline)
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("ABORTED"))
+        .stdout(predicates::str::contains("`build` is DIED"))
+        .stderr(predicates::str::contains(
+            "pipeline failed: 2 of 2 step(s) did not finish",
+        ));
+
+    fs::write(
+        &pipeline,
+        "[[task]]\nname = \"a\"\nafter = [\"b\"]\nprompt = \"x\"\n\
+         [[task]]\nname = \"b\"\nafter = [\"a\"]\nprompt = \"y\"\n",
+    )
+    .expect("rewrite pipeline");
+    env.command()
+        .args(["pipeline", "run", "--validate"])
+        .arg(&pipeline)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("dependency cycle between a, b"));
+}
+
+#[test]
+fn pipeline_aborts_steps_after_a_dependency_stopped_before_answering() {
+    let env = IntegrationTestEnv::with_delay(5000);
+    let pipeline = env.home.path().join("pipeline.toml");
+    fs::write(
+        &pipeline,
+        "[[task]]\nname = \"build\"\nprompt = \"build\"\n\
+         [[task]]\nname = \"deploy\"\nafter = [\"build\"]\nprompt = \"deploy {{build.result}}\"\n",
+    )
+    .expect("write pipeline");
+
+    let mut child = StdCommand::new(cargo_bin(BIN))
+        .env("HOME", env.home.path())
+        .env("PATH", &env.path)
+        .envs(env.extra_envs.iter().map(|(key, value)| (key, value)))
+        .args(["pipeline", "run"])
+        .arg(&pipeline)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn pipeline run");
+
+    let mut progress = BufReader::new(child.stderr.take().expect("pipeline stderr"));
+    let mut line = String::new();
+    progress.read_line(&mut line).expect("read progress");
+    let build = line
+        .trim()
+        .strip_prefix("[build] RUNNING ")
+        .unwrap_or_else(|| panic!("unexpected progress line: {line}"))
+        .to_string();
+    env.command().args(["stop", &build]).assert().success();
+
+    let output = child.wait_with_output().expect("wait for pipeline");
+    assert!(!output.status.success(), "pipeline should fail");
+    let table = String::from_utf8(output.stdout).expect("stdout utf8");
+    assert!(
+        table.lines().any(|row| row.starts_with("build")
+            && row.contains("CANCELLED")
+            && row.contains(&build)),
+        "unexpected report:\n{table}"
+    );
+    assert!(
+        table.contains("`build` is CANCELLED"),
+        "unexpected report:\n{table}"
+    );
+}
+#[test]
+fn lifecycle_hooks_run_with_task_environment_and_never_break_the_task() {
+    let env = IntegrationTestEnv::new();
+    let record = env.home.path().join("hooks.txt");
+    let append = |line: &str| format!("echo \"{line}\" >> '{}'", record.display());
+    let wait_for_record = |expected: &str| {
+        let start = Instant::now();
+        loop {
+            let contents = fs::read_to_string(&record).unwrap_or_default();
+            if contents.contains(expected) {
+                return contents;
+            }
+            if start.elapsed() > Duration::from_secs(10) {
+                panic!("timed out waiting for `{expected}` in hook record:\n{contents}");
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    };
+
+    env.command()
+        .args(["hooks", "--set"])
+        .arg(format!(
+            "on_turn_completed={}",
+            append("turn $CODEX_TASK_ID $CODEX_TASK_STATE")
+        ))
+        .args(["--set", "on_died=exit 1", "--timeout", "5s"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("on_turn_completed"))
+        .stdout(predicates::str::contains("Timeout: 5s"));
+    env.command()
+        .args(["hooks", "--unset", "on_died", "--json"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("\"timeout_secs\": 5"))
+        .stdout(predicates::str::contains("on_died").not());
+
+    let assert = env
+        .command()
+        .args(["start", "-t", "Hooked", "--hook"])
+        .arg(format!(
+            "on_stopped={}; echo notified; exit 7",
+            append(
+                "stopped $CODEX_TASK_TITLE $CODEX_TASK_STATE $(cat \"$CODEX_TASK_RESULT_PATH\")"
+            )
+        ))
+        .args(["--hook"])
+        .arg(format!("on_archived={}", append("archived $CODEX_TASK_ID")))
+        .arg("build it")
+        .assert()
+        .success();
+    let task_id = String::from_utf8(assert.get_output().stdout.clone())
+        .expect("stdout utf8")
+        .trim()
+        .to_string();
+
+    let contents = wait_for_record("stopped Hooked STOPPED response 1: build it");
+    assert!(contents.contains(&format!("turn {task_id} RUNNING")));
+    let status = env.wait_for_condition(&task_id, |value| value["state"] == json!("STOPPED"));
+    assert_eq!(status["last_result"], json!("response 1: build it"));
+    assert!(status["hooks"]["on_stopped"].is_string());
+
+    let start = Instant::now();
+    loop {
+        let log = fs::read_to_string(env.tasks_root().join(&task_id).join("task.log"))
+            .unwrap_or_default();
+        if log.contains("\"event\":\"on_stopped\"") {
+            break;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "hook outcome was not logged"
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+    env.command()
+        .args(["log", &task_id])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("HOOK on_stopped:"))
+        .stdout(predicates::str::contains("(exited 7)"))
+        .stdout(predicates::str::contains("  notified"));
+
+    env.command().args(["archive", &task_id]).assert().success();
+    wait_for_record(&format!("archived {task_id}"));
+}
+
+fn serve_webhook_requests(
+    listener: std::net::TcpListener,
+    sender: std::sync::mpsc::Sender<String>,
+) {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { break };
+        let mut reader = BufReader::new(stream.try_clone().expect("clone stream"));
+        let mut request = String::new();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                break;
+            }
+            if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+            request.push_str(&line);
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).expect("read body");
+        request.push_str("\r\n");
+        request.push_str(&String::from_utf8_lossy(&body));
+        let _ = stream.write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n");
+        if sender.send(request).is_err() {
+            break;
+        }
+    }
+}
+
+#[test]
+fn webhooks_deliver_signed_task_events() {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    let env = IntegrationTestEnv::new();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind listener");
+    let url = format!("http://{}/hook", listener.local_addr().expect("local addr"));
+    let (sender, receiver) = std::sync::mpsc::channel();
+    thread::spawn(move || serve_webhook_requests(listener, sender));
+
+    env.command()
+        .args(["webhook", "add", "ftp://example.com"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("http"));
+    let assert = env
+        .command()
+        .args([
+            "webhook", "add", &url, "--event", "result", "--secret", "s3cret",
+        ])
+        .assert()
+        .success();
+    let webhook_id = String::from_utf8(assert.get_output().stdout.clone())
+        .expect("stdout utf8")
+        .trim()
+        .to_string();
+    env.command()
+        .args(["webhook", "ls"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(&webhook_id))
+        .stdout(predicates::str::contains("result"))
+        .stdout(predicates::str::contains("yes"));
+    env.command()
+        .args(["webhook", "ls", "--json"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("s3cret").not());
+
+    let task_id = env.start_task("Notify", "ship it");
+    let request = receiver
+        .recv_timeout(Duration::from_secs(10))
+        .expect("webhook request");
+    let (head, body) = request.split_once("\r\n\r\n").expect("request body");
+    assert!(head.starts_with("POST /hook "));
+    let head = head.to_ascii_lowercase();
+    assert!(head.contains("x-codex-tasks-event: result"));
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").expect("hmac key");
+    mac.update(body.as_bytes());
+    let expected: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    assert!(head.contains(&format!("x-codex-tasks-signature: sha256={expected}")));
+    let payload: Value = from_str(body).expect("payload json");
+    assert_eq!(payload["event"], json!("result"));
+    assert_eq!(payload["task_id"], json!(task_id));
+    assert_eq!(payload["state"], json!("STOPPED"));
+    assert_eq!(payload["result"], json!("response 1: ship it"));
+
+    let start = Instant::now();
+    let deliveries = loop {
+        let output = env
+            .command()
+            .args(["webhook", "log", "--json"])
+            .output()
+            .expect("webhook log");
+        let deliveries: Value =
+            from_str(&String::from_utf8_lossy(&output.stdout)).expect("deliveries json");
+        if !deliveries.as_array().expect("array").is_empty() {
+            break deliveries;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "delivery was not logged"
+        );
+        thread::sleep(Duration::from_millis(50));
+    };
+    assert_eq!(deliveries[0]["webhook_id"], json!(webhook_id));
+    assert_eq!(deliveries[0]["delivered"], json!(true));
+    assert_eq!(deliveries[0]["status"], json!(204));
+
+    env.command()
+        .args(["webhook", "rm", &webhook_id[..4]])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("removed"));
+    env.command()
+        .args(["webhook", "ls"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("No webhooks."));
+}
+
+#[test]
+fn config_file_supplies_defaults_to_cli_and_workers() {
+    let env = IntegrationTestEnv::new();
+    env.command()
+        .args(["config", "show"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("No configuration file"))
+        .stdout(predicates::str::contains("stop = \"10s\""));
+
+    let default_config = env.tasks_root().join("config.toml");
+    fs::create_dir_all(env.tasks_root()).expect("create store");
+    fs::write(&default_config, "[limits]\nmax_running = 0\n").expect("write config");
+    env.command()
+        .args(["ls"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "`limits.max_running` must be at least 1",
+        ));
+    fs::write(&default_config, "[exec]\nflags = []\n").expect("write config");
+    env.command()
+        .args(["ls"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("unknown field `flags`"));
+    fs::remove_file(&default_config).expect("remove config");
+
+    let record = env.home.path().join("stopped.txt");
+    let config_path = env.home.path().join("tasks.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[exec]\nargs = [\"--model\", \"o3\"]\n\n[timeouts]\nhook = \"45s\"\n\n\
+             [limits]\nmax_running = 2\n\n[hooks]\non_stopped = \"echo $CODEX_TASK_ID >> '{}'\"\n\n\
+             [retention]\narchive_after = \"1s\"\n",
+            record.display()
+        ),
+    )
+    .expect("write config");
+
+    let output = env
+        .command()
+        .args(["config", "show", "--json", "--config"])
+        .arg(&config_path)
+        .output()
+        .expect("config show");
+    assert!(output.status.success());
+    let shown: Value = from_str(&String::from_utf8_lossy(&output.stdout)).expect("config json");
+    assert_eq!(shown["path"], json!(config_path.to_string_lossy()));
+    assert_eq!(shown["limits"]["max_running"], json!(2));
+    assert_eq!(
+        shown["hooks"]["on_stopped"],
+        json!(format!("echo $CODEX_TASK_ID >> '{}'", record.display()))
+    );
+    assert_eq!(shown["timeouts"]["hook"], json!("45s"));
+    assert_eq!(shown["exec"]["args"], json!(["--model", "o3"]));
+    env.command()
+        .args(["queue", "--config"])
+        .arg(&config_path)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("of 2"));
+
+    let assert = env
+        .command()
+        .env("CODEX_TASKS_CONFIG", &config_path)
+        .args(["start", "-t", "Configured", "configure it"])
+        .assert()
+        .success();
+    let first_id = String::from_utf8(assert.get_output().stdout.clone())
+        .expect("stdout utf8")
+        .trim()
+        .to_string();
+    env.wait_for_condition(&first_id, |value| value["state"] == json!("STOPPED"));
+    let start = Instant::now();
+    while !fs::read_to_string(&record)
+        .unwrap_or_default()
+        .contains(&first_id)
+    {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "worker did not run the configured hook"
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    // Retention only runs when asked: starting another task archives nothing.
+    thread::sleep(Duration::from_millis(1_500));
+    let assert = env
+        .command()
+        .env("CODEX_TASKS_CONFIG", &config_path)
+        .args(["start", "-t", "Second", "again"])
+        .assert()
+        .success();
+    let second_id = String::from_utf8(assert.get_output().stdout.clone())
+        .expect("stdout utf8")
+        .trim()
+        .to_string();
+    assert!(env.tasks_root().join(&first_id).exists());
+    env.command()
+        .args(["archive", "--expired"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("no retention is configured"));
+    env.command()
+        .env("CODEX_TASKS_CONFIG", &config_path)
+        .args(["archive", "--expired"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!(
+            "Task {first_id} archived"
+        )));
+    env.command()
+        .args(["status", &first_id])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("ARCHIVED"));
+
+    env.wait_for_condition(&second_id, |value| value["state"] == json!("STOPPED"));
+    thread::sleep(Duration::from_millis(1_500));
+    env.command()
+        .env("CODEX_TASKS_CONFIG", &config_path)
+        .args(["scheduler", "run", "--once"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!(
+            "retention: archived {second_id}"
+        )));
+    assert!(!env.tasks_root().join(&second_id).exists());
+
+    // Store settings take precedence over the file; its hooks fill the events they leave unset.
+    env.command()
+        .args(["queue", "--max-running", "5"])
+        .assert()
+        .success();
+    env.command()
+        .args(["hooks", "--set", "on_died=true"])
+        .assert()
+        .success();
+    let output = env
+        .command()
+        .args(["config", "show", "--json", "--config"])
+        .arg(&config_path)
+        .output()
+        .expect("config show");
+    assert!(output.status.success());
+    let shown: Value = from_str(&String::from_utf8_lossy(&output.stdout)).expect("config json");
+    assert_eq!(shown["limits"]["max_running"], json!(5));
+    assert_eq!(shown["hooks"]["on_died"], json!("true"));
+    assert!(shown["hooks"]["on_stopped"].is_string());
+}
+
+#[test]
+fn start_preset_applies_config_defaults_and_send_reuses_them() {
+    let env = IntegrationTestEnv::new();
+    let work_root = env.home.path().join("work");
+    fs::create_dir_all(env.tasks_root()).expect("create store");
+    fs::write(
+        env.tasks_root().join("config.toml"),
+        format!(
+            "[presets.slow]\nworking_dir = \"{}/{{{{preset}}}}/{{{{title}}}}\"\ntags = [\"slow\"]\n\
+             env = {{ FAKE_CODEX_DELAY_MS = \"3000\" }}\ntimeout = \"1s\"\n",
+            work_root.display()
+        ),
+    )
+    .expect("write config");
+
+    env.command()
+        .args(["start", "--preset", "fast", "hello"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "unknown preset `fast` (configured: slow)",
+        ));
+
+    let assert = env
+        .command()
+        .args([
+            "start",
+            "--preset",
+            "slow",
+            "-t",
+            "Slow Job",
+            "take your time",
+        ])
+        .assert()
+        .success();
+    let task_id = String::from_utf8(assert.get_output().stdout.clone())
+        .expect("stdout utf8")
+        .trim()
+        .to_string();
+    let status = env.wait_for_condition(&task_id, |value| value["state"] == json!("DIED"));
+    assert_eq!(status["preset"], json!("slow"));
+    assert_eq!(status["tags"], json!(["slow"]));
+    assert_eq!(status["exec"]["timeout_secs"], json!(1));
+    assert_eq!(
+        status["working_dir"],
+        json!(work_root.join("slow").join("slow-job").to_string_lossy())
+    );
+    env.command()
+        .args(["log", &task_id])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("invocation timed out after 1s"));
+
+    // Prompts sent later run with the settings recorded on the task.
+    env.command().args(["retry", &task_id]).assert().success();
+    env.wait_for_condition(&task_id, |value| {
+        value["state"] == json!("DIED") && value["last_prompt"] == json!("take your time")
+    });
+    let start = Instant::now();
+    loop {
+        let log = fs::read_to_string(env.tasks_root().join(&task_id).join("task.log"))
+            .unwrap_or_default();
+        if log.matches("invocation timed out").count() == 2 {
+            break;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "retry did not reuse the preset timeout"
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let assert = env
+        .command()
+        .args([
+            "start",
+            "--preset",
+            "slow",
+            "-t",
+            "Patient",
+            "--timeout",
+            "30s",
+            "--tag",
+            "mine",
+            "--env",
+            "FAKE_CODEX_DELAY_MS=0",
+            "done soon",
+        ])
+        .assert()
+        .success();
+    let task_id = String::from_utf8(assert.get_output().stdout.clone())
+        .expect("stdout utf8")
+        .trim()
+        .to_string();
+    let status = env.wait_for_condition(&task_id, |value| value["state"] == json!("STOPPED"));
+    assert_eq!(status["tags"], json!(["mine"]));
+    assert_eq!(status["exec"]["timeout_secs"], json!(30));
+    assert_eq!(status["exec"]["env"]["FAKE_CODEX_DELAY_MS"], json!("0"));
+    assert_eq!(status["last_result"], json!("response 1: done soon"));
+}
+
+#[test]
+fn log_filters_categories_and_formats_events() {
+    let home = tempdir().expect("tempdir");
+    let task_dir = home.path().join(".codex").join("tasks").join("task-render");
+    fs::create_dir_all(&task_dir).expect("create dirs");
+    let events = [
+        json!({"timestamp": "2024-05-01T12:00:00.000Z", "type": "user_message", "message": "Fix the build"}),
+        json!({"type": "item.completed", "item": {"type": "reasoning", "text": "looking at cargo"}}),
+        json!({"type": "item.started", "item": {"type": "todo_list", "items": [{"text": "read logs", "completed": true}, {"text": "patch", "completed": false}]}}),
+        json!({"type": "item.started", "item": {"type": "command_execution", "command": "cargo build"}}),
+        json!({"type": "item.completed", "item": {"type": "command_execution", "command": "cargo build", "aggregated_output": "one\ntwo\nthree\nfour", "exit_code": 1, "status": "failed"}}),
+        json!({"type": "item.completed", "item": {"type": "agent_message", "text": "Build fixed."}}),
+        json!({"type": "item.completed", "item": {"type": "mystery", "payload": 7}}),
+        json!({"type": "turn.completed", "usage": {"output_tokens": 12}}),
+    ];
+    let log: Vec<String> = events.iter().map(|event| event.to_string()).collect();
+    fs::write(task_dir.join("task.log"), log.join("\n") + "\n").expect("write log");
+
+    let run = |args: &[&str]| -> String {
+        let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+        let assert = cmd
+            .env("HOME", home.path())
+            .env("TZ", "UTC")
+            .arg("log")
+            .args(args)
+            .arg("task-render")
+            .assert()
+            .success();
+        String::from_utf8(assert.get_output().stdout.clone()).expect("stdout utf8")
+    };
+
+    let full = run(&[]);
+    assert!(
+        !full.contains('\u{1b}'),
+        "colors are off when piped:\n{full}"
+    );
+    for expected in [
+        "thinking\nlooking at cargo",
+        "plan\n[x] read logs\n[ ] patch",
+        "exec\ncargo build\nrunning",
+        "exited 1 (failed)\none\ntwo\nthree\nfour",
+        "codex\nBuild fixed.",
+        "tokens used\n12",
+    ] {
+        assert!(full.contains(expected), "missing {expected:?} in:\n{full}");
+    }
+    assert!(!full.contains("mystery"));
+
+    let filtered = run(&["--only", "messages,commands", "--max-output-lines", "2"]);
+    assert!(filtered.contains("user\nFix the build"));
+    assert!(filtered.contains("exited 1 (failed)\none\ntwo\n… 2 more lines"));
+    for hidden in ["thinking", "plan", "tokens used"] {
+        assert!(!filtered.contains(hidden), "{hidden} shown in:\n{filtered}");
+    }
+
+    let without_reasoning = run(&["--no-reasoning", "--exclude", "usage"]);
+    assert!(!without_reasoning.contains("thinking"));
+    assert!(!without_reasoning.contains("tokens used"));
+    assert!(without_reasoning.contains("codex\nBuild fixed."));
+
+    let stamped = run(&["--timestamps", "--time-format", "iso", "--verbose"]);
+    assert!(
+        stamped.starts_with("[2024-05-01T12:00:00"),
+        "missing timestamp prefix:\n{stamped}"
+    );
+    assert!(stamped.contains(r#""payload":7"#));
+
+    let json = run(&["--json", "--only", "commands"]);
+    let lines: Vec<Value> = json
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("json line"))
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert!(
+        lines
+            .iter()
+            .all(|event| event["item"]["type"] == "command_execution")
+    );
+}
+
+#[test]
+fn log_export_renders_markdown_and_html_reports() {
+    let home = tempdir().expect("tempdir");
+    let tasks_root = home.path().join(".codex").join("tasks");
+    write_metadata_with_timestamps(
+        &tasks_root,
+        "task-report",
+        "STOPPED",
+        "2024-05-01T12:00:00Z",
+        "2024-05-01T13:00:00Z",
+    );
+    let events = [
+        json!({"timestamp": "2024-05-01T12:00:00.000Z", "type": "user_message", "message": "Why does the build fail?"}),
+        json!({"type": "item.completed", "item": {"type": "reasoning", "text": "check build.rs"}}),
+        json!({"type": "item.started", "item": {"type": "command_execution", "command": "cargo build"}}),
+        json!({"type": "item.completed", "item": {"type": "command_execution", "command": "cargo build", "aggregated_output": "error[E0425]: x < y", "exit_code": 101, "status": "failed"}}),
+        json!({"type": "item.completed", "item": {"type": "file_change", "changes": [{"path": "build.rs", "kind": "update"}]}}),
+        json!({"type": "item.completed", "item": {"type": "agent_message", "text": "Fixed `build.rs`."}}),
+        json!({"type": "turn.completed", "usage": {"input_tokens": 1200, "output_tokens": 34}}),
+        json!({"type": "user_message", "message": "Thanks"}),
+        json!({"type": "item.completed", "item": {"type": "agent_message", "text": "You're welcome."}}),
+        json!({"type": "turn.completed", "usage": {"output_tokens": 5}}),
+    ];
+    let log: Vec<String> = events.iter().map(|event| event.to_string()).collect();
+    fs::write(
+        tasks_root.join("task-report").join("task.log"),
+        log.join("\n") + "\n",
+    )
+    .expect("write log");
+
+    let output = home.path().join("report.md");
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    cmd.env("HOME", home.path())
+        .args(["log", "--export", "md", "-o"])
+        .arg(&output)
+        .arg("task-report")
+        .assert()
+        .success()
+        .stdout("");
+    let markdown = fs::read_to_string(&output).expect("read report");
+    for expected in [
+        "# Example task\n",
+        "| State | STOPPED |",
+        "| Invocations | 2 |",
+        "| Tokens used | 1,239 |",
+        "## Invocation 1\n",
+        "> Why does the build fail?",
+        "<details>\n<summary>Reasoning</summary>\n\ncheck build.rs\n\n</details>",
+        "**Command** (exit 101, failed)\n\n```sh\ncargo build\n```\n\n```text\nerror[E0425]: x < y\n```",
+        "**Files changed** (completed)\n\n- M `build.rs`",
+        "**Codex**\n\nFixed `build.rs`.",
+        "_Tokens used: 1,234_",
+        "## Invocation 2\n",
+        "> Thanks",
+    ] {
+        assert!(
+            markdown.contains(expected),
+            "missing {expected:?} in:\n{markdown}"
+        );
+    }
+    assert_eq!(markdown.matches("cargo build").count(), 1);
+
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    let assert = cmd
+        .env("HOME", home.path())
+        .args(["log", "--export", "html", "--no-reasoning", "task-report"])
+        .assert()
+        .success();
+    let html = String::from_utf8(assert.get_output().stdout.clone()).expect("stdout utf8");
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Example task</title>"));
+    assert!(html.contains("error[E0425]: x &lt; y"));
+    assert!(html.contains("You&#39;re welcome."));
+    assert!(!html.contains("check build.rs"));
+
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    cmd.env("HOME", home.path())
+        .args(["log", "--export", "md", "--json", "task-report"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn log_slices_by_time_and_invocation() {
+    let home = tempdir().expect("tempdir");
+    let task_dir = home.path().join(".codex").join("tasks").join("task-slice");
+    fs::create_dir_all(&task_dir).expect("create dirs");
+    let events = [
+        json!({"timestamp": "2024-05-01T12:00:00.000Z", "type": "user_message", "message": "First prompt"}),
+        json!({"timestamp": "2024-05-01T12:00:05.000Z", "type": "item.started", "item": {"type": "command_execution", "command": "ls"}}),
+        json!({"timestamp": "2024-05-01T12:00:06.000Z", "type": "item.completed", "item": {"type": "command_execution", "command": "ls", "aggregated_output": "", "exit_code": 0, "status": "completed"}}),
+        json!({"timestamp": "2024-05-01T12:01:00.000Z", "type": "item.completed", "item": {"type": "agent_message", "text": "First answer"}}),
+        json!({"timestamp": "2024-05-01T13:00:00.000Z", "type": "user_message", "message": "Second prompt"}),
+        json!({"type": "item.completed", "item": {"type": "reasoning", "text": "unstamped thought"}}),
+        json!({"timestamp": "2024-05-01T13:02:00.000Z", "type": "item.completed", "item": {"type": "agent_message", "text": "Second answer"}}),
+        json!({"timestamp": "2024-05-01T13:02:01.000Z", "type": "item.completed", "item": {"type": "mystery", "payload": 7}}),
+    ];
+    let log: Vec<String> = events.iter().map(|event| event.to_string()).collect();
+    fs::write(task_dir.join("task.log"), log.join("\n") + "\n").expect("write log");
+
+    let run = |args: &[&str]| -> String {
+        let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+        let assert = cmd
+            .env("HOME", home.path())
+            .arg("log")
+            .args(args)
+            .arg("task-slice")
+            .assert()
+            .success();
+        String::from_utf8(assert.get_output().stdout.clone()).expect("stdout utf8")
+    };
+
+    let last = run(&["--invocation", "last"]);
+    assert!(last.contains("Second prompt") && last.contains("unstamped thought"));
+    assert!(
+        !last.contains("First"),
+        "first invocation shown in:\n{last}"
+    );
+    assert_eq!(run(&["--invocation", "2"]), last);
+
+    let first = run(&["--invocation", "1"]);
+    assert!(first.contains("First answer"));
+    assert!(!first.contains("Second"));
+
+    let window = run(&[
+        "--since",
+        "2024-05-01T12:00:30Z",
+        "--until",
+        "2024-05-01T13:00:00Z",
+    ]);
+    assert!(window.contains("First answer") && window.contains("Second prompt"));
+    assert!(!window.contains("First prompt"));
+    assert!(
+        window.contains("unstamped thought"),
+        "unstamped events inherit the previous time:\n{window}"
+    );
+    assert!(!window.contains("Second answer"));
+
+    assert!(run(&["--since", "10m"]).is_empty());
+
+    // The last raw line has no human rendering, so it must not count towards `-n`.
+    let tail = run(&["-n", "2"]);
+    assert!(tail.contains("unstamped thought") && tail.contains("Second answer"));
+    assert!(
+        !tail.contains("Second prompt"),
+        "too many events in:\n{tail}"
+    );
+
+    let json = run(&["--json", "--invocation", "1", "-n", "1"]);
+    let lines: Vec<Value> = json
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("json line"))
+        .collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["item"]["text"], "First answer");
+
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    cmd.env("HOME", home.path())
+        .args(["log", "--invocation", "3", "task-slice"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "task task-slice has 2 invocation(s); --invocation must be between 1 and 2",
+        ));
+}
+
+#[test]
+fn result_wait_gives_up_after_timeout() {
+    let home = tempdir().expect("tempdir");
+    write_metadata(
+        &home.path().join(".codex").join("tasks"),
+        "task-queued",
+        "QUEUED",
+    );
+
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    cmd.env("HOME", home.path())
+        .args(["result", "--wait", "--timeout", "1", "task-queued"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "task task-queued is still QUEUED after waiting 1s",
+        ));
+}
+
+#[test]
+fn wait_any_returns_immediately_when_nothing_is_running() {
+    let home = tempdir().expect("tempdir");
+    write_metadata(
+        &home.path().join(".codex").join("tasks"),
+        "task-done",
+        "STOPPED",
+    );
+
+    let mut cmd = Command::cargo_bin(BIN).expect("binary should build");
+    cmd.env("HOME", home.path())
+        .args(["wait", "--all-running", "--any"])
+        .timeout(Duration::from_secs(10))
+        .assert()
+        .success()
+        .stdout("");
+}
@@ -11,6 +11,8 @@ import uuid
 
 ROOT = os.path.abspath(os.environ.get("FAKE_CODEX_ROOT", "."))
 DELAY_MS = int(os.environ.get("FAKE_CODEX_DELAY_MS", "0"))
+FAIL_TIMES = int(os.environ.get("FAKE_CODEX_FAIL_TIMES", "0"))
+FAIL_MESSAGE = os.environ.get("FAKE_CODEX_FAIL_MESSAGE", "stream error: 429 Too Many Requests")
 
 
 def parse_args(args):
@@ -87,11 +89,28 @@ def main():
 
     count = load_counter(thread_id) + 1
     store_counter(thread_id, count)
+    parent_env = os.environ.get("CODEX_TASKS_PARENT_ID")
+    if parent_env:
+        with open(os.path.join(ROOT, f"{thread_id}.parent-env"), "a", encoding="utf-8") as handle:
+            handle.write(parent_env + "\n")
     message = f"response {count}: {prompt}"
 
     emit({"type": "thread.started", "thread_id": thread_id})
     emit({"type": "turn.started"})
 
+    if FAIL_TIMES > 0:
+        failures_path = os.path.join(ROOT, "failures.count")
+        failures = 0
+        if os.path.exists(failures_path):
+            with open(failures_path, "r", encoding="utf-8") as handle:
+                failures = int(handle.read() or "0")
+        if failures < FAIL_TIMES:
+            with open(failures_path, "w", encoding="utf-8") as handle:
+                handle.write(str(failures + 1))
+            store_counter(thread_id, count - 1)
+            emit({"type": "turn.failed", "error": {"message": FAIL_MESSAGE}})
+            return 1
+
     if DELAY_MS > 0:
         time.sleep(DELAY_MS / 1000.0)
 